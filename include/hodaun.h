@@ -0,0 +1,103 @@
+/*
+ * C API for hodaun
+ *
+ * Build a crate with `crate-type = ["cdylib"]` or `["staticlib"]` that depends on hodaun
+ * with the `ffi` feature enabled, and link against it.
+ *
+ * Every object is created and destroyed through this API and is passed around as an opaque pointer.
+ * Objects must only be freed once, with the matching `_free` function.
+ * Passing a null pointer to a `_free` function does nothing.
+ *
+ * Functions that can fail return null, and the reason can be read with `hodaun_last_error`.
+ */
+
+#ifndef HODAUN_H
+#define HODAUN_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* An output device that mixes sounds in stereo */
+typedef struct HodaunOutput HodaunOutput;
+
+/* A buffer of stereo audio that can be played any number of times */
+typedef struct HodaunBuffer HodaunBuffer;
+
+/* A parameter that can be changed from any thread while sounds that use it play */
+typedef struct HodaunParam HodaunParam;
+
+/*
+ * Get a message describing the last error that occurred on this thread
+ *
+ * Returns null if there has been no error.
+ * The string is owned by hodaun and is valid until the next error on this thread.
+ */
+const char *hodaun_last_error(void);
+
+/*
+ * Open the default output device and start it playing
+ *
+ * Returns null if the device could not be opened.
+ */
+HodaunOutput *hodaun_output_new(void);
+
+/* Stop and close an output */
+void hodaun_output_free(HodaunOutput *output);
+
+/* Get the sample rate of an output */
+double hodaun_output_sample_rate(const HodaunOutput *output);
+
+/*
+ * Create a buffer by copying interleaved samples
+ *
+ * `frames` is the number of frames, so `samples` must point to `frames * channels` samples.
+ * Mono buffers are played on both channels, and channels past the second are ignored.
+ * Returns null if `channels` is zero, if `samples` is null, or if `frames * channels` is too large.
+ */
+HodaunBuffer *hodaun_buffer_new(const float *samples, size_t frames, uint32_t channels, double sample_rate);
+
+/*
+ * Free a buffer
+ *
+ * Sounds already playing from the buffer keep playing.
+ */
+void hodaun_buffer_free(HodaunBuffer *buffer);
+
+/* Get the length of a buffer in seconds */
+double hodaun_buffer_duration(const HodaunBuffer *buffer);
+
+/* Create a parameter with an initial value */
+HodaunParam *hodaun_param_new(double value);
+
+/* Set the value of a parameter */
+void hodaun_param_set(const HodaunParam *param, double value);
+
+/* Get the value of a parameter */
+double hodaun_param_get(const HodaunParam *param);
+
+/*
+ * Free a parameter
+ *
+ * Sounds already using the parameter keep its last value.
+ */
+void hodaun_param_free(HodaunParam *param);
+
+/*
+ * Play a buffer once on an output
+ *
+ * `gain` may be null, in which case the gain is 1.0.
+ * `pan` may be null, in which case the buffer plays unpanned.
+ * Panned buffers are mixed to mono first. Pan goes from -1.0 for left to 1.0 for right.
+ * Changes to the parameters are heard while the sound plays.
+ */
+void hodaun_play_oneshot(const HodaunOutput *output, const HodaunBuffer *buffer, const HodaunParam *gain, const HodaunParam *pan);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,317 @@
+//! First-order ambisonics
+//!
+//! Ambisonics represents a full sphere of sound in a [`BFormat`] frame, independent of any speaker layout.
+//! Sources are positioned with [`encode`], the whole sound field can be turned with [`rotate`],
+//! and the result is played back with [`decode_stereo`] or [`decode_binaural`].
+//!
+//! Frames use the AmbiX convention: ACN channel order (W, Y, Z, X) with SN3D normalization.
+//! Azimuth is measured in radians counterclockwise from the front, so positive azimuths are to the left.
+//! Elevation is measured in radians upward from the horizon.
+//!
+//! This module is only available when the `ambisonics` feature is enabled.
+
+use core::f64::consts::TAU;
+
+use crate::{Automation, Frame, Source, Stereo};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// A first-order ambisonic [`Frame`]
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct BFormat {
+    /// The omnidirectional component
+    pub w: f64,
+    /// The left-right component
+    pub y: f64,
+    /// The up-down component
+    pub z: f64,
+    /// The front-back component
+    pub x: f64,
+}
+
+impl BFormat {
+    /// Create a new B-format frame
+    pub const fn new(w: f64, y: f64, z: f64, x: f64) -> Self {
+        BFormat { w, y, z, x }
+    }
+    /// Encode a mono sample arriving from a direction
+    pub fn encode(sample: f64, azimuth: f64, elevation: f64) -> Self {
+        let (sin_az, cos_az) = azimuth.sin_cos();
+        let (sin_el, cos_el) = elevation.sin_cos();
+        BFormat {
+            w: sample,
+            y: sample * sin_az * cos_el,
+            z: sample * sin_el,
+            x: sample * cos_az * cos_el,
+        }
+    }
+    /// Rotate the sound field
+    ///
+    /// The rotation is applied as roll around the front axis, then pitch around the left axis,
+    /// then yaw around the vertical axis.
+    /// To compensate for a listener's head orientation, rotate by the negated angles in reverse order.
+    pub fn rotate(self, yaw: f64, pitch: f64, roll: f64) -> Self {
+        let (sin_r, cos_r) = roll.sin_cos();
+        let (sin_p, cos_p) = pitch.sin_cos();
+        let (sin_y, cos_y) = yaw.sin_cos();
+        let (x, y, z) = (self.x, self.y, self.z);
+        // Roll around X
+        let (y, z) = (y * cos_r - z * sin_r, y * sin_r + z * cos_r);
+        // Pitch around Y, with positive pitch tilting the front upward
+        let (x, z) = (x * cos_p - z * sin_p, x * sin_p + z * cos_p);
+        // Yaw around Z
+        let (x, y) = (x * cos_y - y * sin_y, x * sin_y + y * cos_y);
+        BFormat { w: self.w, y, z, x }
+    }
+    /// Get the signal picked up by a virtual microphone pointing in a direction
+    ///
+    /// `pattern` goes from `0.0` for omnidirectional to `1.0` for figure-of-eight.
+    /// A pattern of `0.5` is a cardioid.
+    pub fn virtual_mic(&self, azimuth: f64, elevation: f64, pattern: f64) -> f64 {
+        let (sin_az, cos_az) = azimuth.sin_cos();
+        let (sin_el, cos_el) = elevation.sin_cos();
+        let directional = self.x * cos_az * cos_el + self.y * sin_az * cos_el + self.z * sin_el;
+        (1.0 - pattern) * self.w + pattern * directional
+    }
+}
+
+impl Frame for BFormat {
+    const CHANNELS: usize = 4;
+    fn uniform(amplitude: f64) -> Self {
+        BFormat::new(amplitude, amplitude, amplitude, amplitude)
+    }
+    fn get_channel(&self, index: usize) -> f64 {
+        match index {
+            0 => self.w,
+            1 => self.y,
+            2 => self.z,
+            3 => self.x,
+            _ => 0.0,
+        }
+    }
+    fn set_channel(&mut self, index: usize, amplitude: f64) {
+        match index {
+            0 => self.w = amplitude,
+            1 => self.y = amplitude,
+            2 => self.z = amplitude,
+            3 => self.x = amplitude,
+            _ => {}
+        }
+    }
+    fn map(self, f: impl Fn(f64) -> f64) -> Self {
+        BFormat::new(f(self.w), f(self.y), f(self.z), f(self.x))
+    }
+    fn merge(&mut self, other: Self, f: impl Fn(f64, f64) -> f64) {
+        *self = BFormat::new(
+            f(self.w, other.w),
+            f(self.y, other.y),
+            f(self.z, other.z),
+            f(self.x, other.x),
+        );
+    }
+    fn avg(&self) -> f64 {
+        self.w
+    }
+}
+
+/// Position a source in the sound field
+///
+/// The source is mixed to mono first.
+pub fn encode<S, A, E>(source: S, azimuth: A, elevation: E) -> Encode<S, A, E>
+where
+    S: Source,
+    A: Automation,
+    E: Automation,
+{
+    Encode {
+        source,
+        azimuth,
+        elevation,
+    }
+}
+
+/// Source returned from [`encode`]
+#[derive(Debug, Clone)]
+pub struct Encode<S, A, E> {
+    source: S,
+    azimuth: A,
+    elevation: E,
+}
+
+impl<S, A, E> Source for Encode<S, A, E>
+where
+    S: Source,
+    A: Automation,
+    E: Automation,
+{
+    type Frame = BFormat;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let azimuth = self.azimuth.next_value(sample_rate)?;
+        let elevation = self.elevation.next_value(sample_rate)?;
+        let frame = self.source.next(sample_rate)?;
+        Some(BFormat::encode(frame.avg(), azimuth, elevation))
+    }
+}
+
+/// Rotate the sound field of a B-format source
+///
+/// See [`BFormat::rotate`] for the order the rotations are applied in.
+pub fn rotate<S, Y, P, R>(source: S, yaw: Y, pitch: P, roll: R) -> Rotate<S, Y, P, R>
+where
+    S: Source<Frame = BFormat>,
+    Y: Automation,
+    P: Automation,
+    R: Automation,
+{
+    Rotate {
+        source,
+        yaw,
+        pitch,
+        roll,
+    }
+}
+
+/// Source returned from [`rotate`]
+#[derive(Debug, Clone)]
+pub struct Rotate<S, Y, P, R> {
+    source: S,
+    yaw: Y,
+    pitch: P,
+    roll: R,
+}
+
+impl<S, Y, P, R> Source for Rotate<S, Y, P, R>
+where
+    S: Source<Frame = BFormat>,
+    Y: Automation,
+    P: Automation,
+    R: Automation,
+{
+    type Frame = BFormat;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let yaw = self.yaw.next_value(sample_rate)?;
+        let pitch = self.pitch.next_value(sample_rate)?;
+        let roll = self.roll.next_value(sample_rate)?;
+        let frame = self.source.next(sample_rate)?;
+        Some(frame.rotate(yaw, pitch, roll))
+    }
+}
+
+/// Decode a B-format source to stereo speakers
+///
+/// Each channel is a cardioid virtual microphone pointing 90 degrees to its side.
+pub fn decode_stereo<S>(source: S) -> DecodeStereo<S>
+where
+    S: Source<Frame = BFormat>,
+{
+    DecodeStereo { source }
+}
+
+/// Source returned from [`decode_stereo`]
+#[derive(Debug, Clone)]
+pub struct DecodeStereo<S> {
+    source: S,
+}
+
+impl<S> Source for DecodeStereo<S>
+where
+    S: Source<Frame = BFormat>,
+{
+    type Frame = Stereo;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let frame = self.source.next(sample_rate)?;
+        let quarter = TAU / 4.0;
+        Some(Stereo::new(
+            frame.virtual_mic(quarter, 0.0, 0.5),
+            frame.virtual_mic(-quarter, 0.0, 0.5),
+        ))
+    }
+}
+
+/// The number of virtual speakers used by [`decode_binaural`]
+const VIRTUAL_SPEAKERS: usize = 8;
+/// The radius of the modeled head in meters
+const HEAD_RADIUS: f64 = 0.0875;
+/// The speed of sound in meters per second
+const SPEED_OF_SOUND: f64 = 343.0;
+
+/// Decode a B-format source for headphones
+///
+/// The sound field is decoded to a ring of virtual speakers,
+/// each of which is rendered to the ears with a simple spherical head model
+/// of interaural time and level differences.
+pub fn decode_binaural<S>(source: S) -> DecodeBinaural<S>
+where
+    S: Source<Frame = BFormat>,
+{
+    DecodeBinaural {
+        source,
+        history: vec![[0.0; VIRTUAL_SPEAKERS]; 64],
+        pos: 0,
+        delays: [(0, 0); VIRTUAL_SPEAKERS],
+        sample_rate: 0.0,
+    }
+}
+
+/// Source returned from [`decode_binaural`]
+#[derive(Debug, Clone)]
+pub struct DecodeBinaural<S> {
+    source: S,
+    history: Vec<[f64; VIRTUAL_SPEAKERS]>,
+    pos: usize,
+    delays: [(usize, usize); VIRTUAL_SPEAKERS],
+    sample_rate: f64,
+}
+
+/// Get the azimuth of a virtual speaker
+fn speaker_azimuth(i: usize) -> f64 {
+    TAU * i as f64 / VIRTUAL_SPEAKERS as f64
+}
+
+impl<S> Source for DecodeBinaural<S>
+where
+    S: Source<Frame = BFormat>,
+{
+    type Frame = Stereo;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let frame = self.source.next(sample_rate)?;
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            let mut max_delay = 0;
+            for (i, delays) in self.delays.iter_mut().enumerate() {
+                // Woodworth's formula for the interaural time difference
+                let lateral = speaker_azimuth(i).sin().asin();
+                let itd = HEAD_RADIUS / SPEED_OF_SOUND * (lateral.abs() + lateral.abs().sin());
+                let delay = (itd * sample_rate).round() as usize;
+                max_delay = max_delay.max(delay);
+                // The ear facing away from the speaker hears it later
+                *delays = if lateral >= 0.0 {
+                    (0, delay)
+                } else {
+                    (delay, 0)
+                };
+            }
+            if self.history.len() <= max_delay {
+                self.history.resize(max_delay + 1, [0.0; VIRTUAL_SPEAKERS]);
+            }
+        }
+        let len = self.history.len();
+        self.pos = (self.pos + 1) % len;
+        let speakers = &mut self.history[self.pos];
+        for (i, speaker) in speakers.iter_mut().enumerate() {
+            *speaker =
+                frame.virtual_mic(speaker_azimuth(i), 0.0, 0.5) * 2.0 / VIRTUAL_SPEAKERS as f64;
+        }
+        let mut out = Stereo::default();
+        for (i, &(left_delay, right_delay)) in self.delays.iter().enumerate() {
+            let side = speaker_azimuth(i).sin();
+            // Head shadow attenuates the far ear
+            let left_gain = 1.0 - 0.4 * (-side).max(0.0);
+            let right_gain = 1.0 - 0.4 * side.max(0.0);
+            out.left += self.history[(self.pos + len - left_delay) % len][i] * left_gain;
+            out.right += self.history[(self.pos + len - right_delay) % len][i] * right_gain;
+        }
+        Some(out)
+    }
+}
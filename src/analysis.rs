@@ -0,0 +1,1244 @@
+use std::{
+    collections::VecDeque,
+    f64::consts::TAU,
+    fmt, iter,
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::{
+    sync::{ring, Mutex, RingReceiver, RingSender},
+    FiniteSource, Frame, Shared, Source, ToDuration,
+};
+
+/// Compute the in-place FFT of a buffer of `(re, im)` pairs
+///
+/// The buffer length must be a power of two.
+pub(crate) fn fft(buf: &mut [(f64, f64)]) {
+    let n = buf.len();
+    debug_assert!(n.is_power_of_two());
+    // Bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            buf.swap(i, j);
+        }
+    }
+    // Butterflies
+    let mut len = 2;
+    while len <= n {
+        let angle = -TAU / len as f64;
+        let (w_im, w_re) = angle.sin_cos();
+        for start in (0..n).step_by(len) {
+            let (mut re, mut im) = (1.0, 0.0);
+            for k in 0..len / 2 {
+                let (a_re, a_im) = buf[start + k];
+                let (b_re, b_im) = buf[start + k + len / 2];
+                let t_re = b_re * re - b_im * im;
+                let t_im = b_re * im + b_im * re;
+                buf[start + k] = (a_re + t_re, a_im + t_im);
+                buf[start + k + len / 2] = (a_re - t_re, a_im - t_im);
+                (re, im) = (re * w_re - im * w_im, re * w_im + im * w_re);
+            }
+        }
+        len <<= 1;
+    }
+}
+
+/// Compute the in-place inverse FFT of a buffer of `(re, im)` pairs
+///
+/// The buffer length must be a power of two.
+pub(crate) fn ifft(buf: &mut [(f64, f64)]) {
+    for (_, im) in buf.iter_mut() {
+        *im = -*im;
+    }
+    fft(buf);
+    let n = buf.len() as f64;
+    for (re, im) in buf.iter_mut() {
+        *re /= n;
+        *im = -*im / n;
+    }
+}
+
+/// Recover the impulse response of a system from its response to a known excitation signal
+///
+/// `excitation` is the signal that was played into the system, like a [`SineSweep`](crate::SineSweep),
+/// and `response` is what was recorded from it at the same sample rate.
+/// The response should continue for at least as long as the impulse response after the excitation ends.
+///
+/// The impulse response is found by dividing the spectrum of the response by that of the excitation.
+/// Frequencies the excitation does not contain are suppressed rather than amplified.
+/// The returned impulse response is as long as the part of the response after the excitation.
+pub fn deconvolve(excitation: &[f64], response: &[f64]) -> Vec<f64> {
+    let ir_len = response.len().saturating_sub(excitation.len()).max(1);
+    let n = (excitation.len() + response.len()).next_power_of_two();
+    let spectrum = |signal: &[f64]| {
+        let mut buf = vec![(0.0, 0.0); n];
+        for (b, &x) in buf.iter_mut().zip(signal) {
+            b.0 = x;
+        }
+        fft(&mut buf);
+        buf
+    };
+    let exc = spectrum(excitation);
+    let mut res = spectrum(response);
+    let max_power = exc
+        .iter()
+        .map(|(re, im)| re * re + im * im)
+        .fold(0.0, f64::max);
+    // Regularize the division so that frequencies missing from the excitation are not blown up
+    let epsilon = (max_power * 1e-6).max(f64::MIN_POSITIVE);
+    for ((r_re, r_im), (e_re, e_im)) in res.iter_mut().zip(&exc) {
+        let power = e_re * e_re + e_im * e_im + epsilon;
+        // Multiply by the conjugate of the excitation and divide by its power
+        let re = (*r_re * e_re + *r_im * e_im) / power;
+        let im = (*r_im * e_re - *r_re * e_im) / power;
+        (*r_re, *r_im) = (re, im);
+    }
+    ifft(&mut res);
+    res.into_iter().take(ir_len).map(|(re, _)| re).collect()
+}
+
+/// Collects mono samples into overlapping windows and computes their magnitude spectra
+#[derive(Debug, Clone)]
+pub(crate) struct Spectrum {
+    window: Vec<f64>,
+    input: VecDeque<f64>,
+    hop: usize,
+    until_hop: usize,
+    buf: Vec<(f64, f64)>,
+    magnitudes: Vec<f64>,
+}
+
+impl Spectrum {
+    /// Create a new spectrum with the given power-of-two window size and hop size
+    pub fn new(size: usize, hop: usize) -> Self {
+        let window = (0..size)
+            .map(|i| 0.5 - 0.5 * (TAU * i as f64 / size as f64).cos())
+            .collect();
+        Spectrum {
+            window,
+            input: VecDeque::from(vec![0.0; size]),
+            hop,
+            until_hop: hop,
+            buf: vec![(0.0, 0.0); size],
+            magnitudes: vec![0.0; size / 2],
+        }
+    }
+    /// Push a sample, returning the magnitude spectrum if a new window is complete
+    pub fn push(&mut self, sample: f64) -> Option<&[f64]> {
+        self.input.pop_front();
+        self.input.push_back(sample);
+        self.until_hop -= 1;
+        if self.until_hop > 0 {
+            return None;
+        }
+        self.until_hop = self.hop;
+        for ((out, &x), &w) in self.buf.iter_mut().zip(&self.input).zip(&self.window) {
+            *out = (x * w, 0.0);
+        }
+        fft(&mut self.buf);
+        let scale = 2.0 / self.window.len() as f64;
+        for (mag, &(re, im)) in self.magnitudes.iter_mut().zip(&self.buf) {
+            *mag = re.hypot(im) * scale;
+        }
+        Some(&self.magnitudes)
+    }
+}
+
+/// The window size used for onset detection
+const ONSET_WINDOW: usize = 1024;
+/// The hop size used for onset detection
+const ONSET_HOP: usize = 512;
+/// The number of seconds of onset strength used to estimate the tempo
+const TEMPO_HISTORY: f64 = 6.0;
+/// The range of tempos that can be estimated
+const TEMPO_RANGE: (f64, f64) = (60.0, 200.0);
+/// The highest sample rate the onset history is preallocated for
+const ONSET_MAX_RATE: f64 = 192_000.0;
+/// The number of onsets an [`OnsetTracker`] can fall behind before new ones are dropped
+const ONSET_CAPACITY: usize = 1024;
+
+/// An onset detected by an [`OnsetDetector`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Onset {
+    /// The time of the onset, relative to when the source started
+    pub time: Duration,
+    /// The strength of the onset
+    pub strength: f64,
+}
+
+/// Receives [`Onset`]s detected by an [`OnsetDetector`]
+///
+/// Created with [`Source::onsets`].
+/// Up to 1024 onsets are held until they are taken. Onsets detected after that are dropped.
+pub struct OnsetTracker {
+    recv: Mutex<RingReceiver<Onset>>,
+    bpm: Shared<Option<f64>>,
+}
+
+impl fmt::Debug for OnsetTracker {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OnsetTracker")
+            .field("bpm", &self.bpm)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OnsetTracker {
+    /// Take the next detected onset if there is one
+    pub fn try_next(&self) -> Option<Onset> {
+        self.recv.lock().pop()
+    }
+    /// Iterate over all onsets detected so far
+    pub fn drain(&self) -> impl Iterator<Item = Onset> + '_ {
+        iter::from_fn(|| self.try_next())
+    }
+    /// Get the estimated tempo in beats per minute
+    ///
+    /// Returns [`None`] until enough audio has been analyzed.
+    pub fn bpm(&self) -> Option<f64> {
+        self.bpm.get()
+    }
+    /// Get a shared handle to the estimated tempo
+    pub fn bpm_handle(&self) -> Shared<Option<f64>> {
+        self.bpm.clone()
+    }
+}
+
+/// Source returned from [`Source::onsets`]
+///
+/// It detects onsets using spectral flux, the sum of increases in
+/// each frequency bin's magnitude between consecutive windows.
+pub struct OnsetDetector<S> {
+    source: S,
+    spectrum: Spectrum,
+    prev: Vec<f64>,
+    flux: VecDeque<f64>,
+    /// Scratch space for tempo estimation
+    env: Vec<f64>,
+    frames: u64,
+    last_onset: Option<u64>,
+    hops_since_tempo: usize,
+    send: RingSender<Onset>,
+    bpm: Shared<Option<f64>>,
+    sensitivity: f64,
+}
+
+impl<S> OnsetDetector<S> {
+    pub(crate) fn new(source: S) -> (OnsetTracker, Self) {
+        let (send, recv) = ring(ONSET_CAPACITY);
+        let bpm = Shared::new(None);
+        let history = (TEMPO_HISTORY * ONSET_MAX_RATE / ONSET_HOP as f64) as usize;
+        (
+            OnsetTracker {
+                recv: Mutex::new(recv),
+                bpm: bpm.clone(),
+            },
+            OnsetDetector {
+                source,
+                spectrum: Spectrum::new(ONSET_WINDOW, ONSET_HOP),
+                prev: vec![0.0; ONSET_WINDOW / 2],
+                flux: VecDeque::with_capacity(history),
+                env: Vec::with_capacity(history),
+                frames: 0,
+                last_onset: None,
+                hops_since_tempo: 0,
+                send,
+                bpm,
+                sensitivity: 1.5,
+            },
+        )
+    }
+    /// Set how far above the recent average the spectral flux must rise to count as an onset
+    ///
+    /// Higher values detect fewer onsets. The default is `1.5`.
+    pub fn sensitivity(self, sensitivity: f64) -> Self {
+        OnsetDetector {
+            sensitivity,
+            ..self
+        }
+    }
+    fn analyze_hop(&mut self, sample_rate: f64) {
+        let hop_rate = sample_rate / ONSET_HOP as f64;
+        let history = (TEMPO_HISTORY * hop_rate) as usize;
+        // Compute the spectral flux of the new window
+        let magnitudes = self.spectrum.magnitudes.iter();
+        let mut flux = 0.0;
+        for (prev, &mag) in self.prev.iter_mut().zip(magnitudes) {
+            let mag = (1.0 + 100.0 * mag).ln();
+            flux += (mag - *prev).max(0.0);
+            *prev = mag;
+        }
+        self.flux.push_back(flux);
+        while self.flux.len() > history.max(3) {
+            self.flux.pop_front();
+        }
+        // Pick peaks above an adaptive threshold
+        let n = self.flux.len();
+        if n >= 3 {
+            let candidate = self.flux[n - 2];
+            let recent = (hop_rate * 0.5) as usize;
+            let mean =
+                self.flux.iter().rev().take(recent.max(1)).sum::<f64>() / recent.clamp(1, n) as f64;
+            let min_gap = (0.05 * sample_rate) as u64;
+            let frame = self
+                .frames
+                .saturating_sub(ONSET_HOP as u64 + ONSET_WINDOW as u64 / 2);
+            if candidate > self.flux[n - 3]
+                && candidate >= self.flux[n - 1]
+                && candidate > mean * self.sensitivity
+                && candidate > 1e-3
+                && self.last_onset.is_none_or(|last| frame >= last + min_gap)
+            {
+                self.last_onset = Some(frame);
+                let _ = self.send.push(Onset {
+                    time: Duration::from_secs_f64(frame as f64 / sample_rate),
+                    strength: candidate,
+                });
+            }
+        }
+        // Periodically re-estimate the tempo
+        self.hops_since_tempo += 1;
+        if self.hops_since_tempo as f64 >= hop_rate && self.flux.len() >= history / 2 {
+            self.hops_since_tempo = 0;
+            self.bpm
+                .set(estimate_tempo(&self.flux, &mut self.env, hop_rate));
+        }
+    }
+}
+
+/// Estimate the tempo of an onset strength envelope using autocorrelation
+///
+/// `env` is cleared and used as scratch space.
+fn estimate_tempo(flux: &VecDeque<f64>, env: &mut Vec<f64>, hop_rate: f64) -> Option<f64> {
+    let mean = flux.iter().sum::<f64>() / flux.len() as f64;
+    env.clear();
+    env.extend(flux.iter().map(|f| f - mean));
+    let env = &*env;
+    let min_lag = (60.0 / TEMPO_RANGE.1 * hop_rate).floor().max(1.0) as usize;
+    let max_lag = (60.0 / TEMPO_RANGE.0 * hop_rate).ceil() as usize;
+    let energy: f64 = env.iter().map(|x| x * x).sum();
+    if energy <= 0.0 || max_lag >= env.len() {
+        return None;
+    }
+    let (lag, corr) = (min_lag..=max_lag)
+        .map(|lag| {
+            let corr: f64 = env.iter().zip(&env[lag..]).map(|(a, b)| a * b).sum();
+            (lag, corr)
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))?;
+    if corr <= 0.0 {
+        return None;
+    }
+    // Refine the peak with parabolic interpolation
+    let at = |lag: usize| -> f64 { env.iter().zip(&env[lag..]).map(|(a, b)| a * b).sum() };
+    let (l, r) = (at(lag - 1), at(lag + 1));
+    let denom = l - 2.0 * corr + r;
+    let offset = if denom != 0.0 {
+        (0.5 * (l - r) / denom).clamp(-0.5, 0.5)
+    } else {
+        0.0
+    };
+    Some(60.0 * hop_rate / (lag as f64 + offset))
+}
+
+impl<S> Source for OnsetDetector<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let frame = self.source.next(sample_rate)?;
+        self.frames += 1;
+        if self.spectrum.push(frame.avg()).is_some() {
+            self.analyze_hop(sample_rate);
+        }
+        Some(frame)
+    }
+}
+
+impl<S> FiniteSource for OnsetDetector<S>
+where
+    S: FiniteSource,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+/// The window size used for band analysis
+const BAND_WINDOW: usize = 2048;
+/// The hop size used for band analysis
+const BAND_HOP: usize = 512;
+
+/// A snapshot of the energy levels measured by a [`BandAnalyzer`]
+///
+/// Created with [`Source::bands`].
+/// Reading the levels never blocks the audio thread.
+#[derive(Debug, Clone)]
+pub struct BandLevels {
+    levels: Arc<[AtomicU64]>,
+}
+
+impl BandLevels {
+    /// Get the number of bands
+    pub fn len(&self) -> usize {
+        self.levels.len()
+    }
+    /// Check if there are no bands
+    pub fn is_empty(&self) -> bool {
+        self.levels.is_empty()
+    }
+    /// Get the level of a single band
+    ///
+    /// Bands are ordered from lowest to highest frequency.
+    pub fn get(&self, band: usize) -> Option<f64> {
+        let level = self.levels.get(band)?;
+        Some(f64::from_bits(level.load(Ordering::Relaxed)))
+    }
+    /// Get the levels of all bands
+    pub fn levels(&self) -> Vec<f64> {
+        let mut levels = vec![0.0; self.len()];
+        self.read_into(&mut levels);
+        levels
+    }
+    /// Write the levels of all bands into a slice
+    ///
+    /// This does not allocate, so it can be called every frame of a visualizer.
+    pub fn read_into(&self, levels: &mut [f64]) {
+        for (out, level) in levels.iter_mut().zip(self.levels.iter()) {
+            *out = f64::from_bits(level.load(Ordering::Relaxed));
+        }
+    }
+}
+
+/// Source returned from [`Source::bands`]
+///
+/// It measures the energy in log-spaced frequency bands.
+/// Levels rise immediately and fall smoothly.
+pub struct BandAnalyzer<S> {
+    source: S,
+    spectrum: Spectrum,
+    levels: Arc<[AtomicU64]>,
+    smoothed: Vec<f64>,
+    bins: Vec<Range<usize>>,
+    low: f64,
+    high: f64,
+    release: f64,
+    sample_rate: f64,
+}
+
+impl<S> BandAnalyzer<S> {
+    pub(crate) fn new(source: S, bands: usize) -> (BandLevels, Self) {
+        let levels: Arc<[AtomicU64]> = (0..bands).map(|_| AtomicU64::new(0)).collect();
+        (
+            BandLevels {
+                levels: levels.clone(),
+            },
+            BandAnalyzer {
+                source,
+                spectrum: Spectrum::new(BAND_WINDOW, BAND_HOP),
+                levels,
+                smoothed: vec![0.0; bands],
+                bins: Vec::new(),
+                low: 40.0,
+                high: 16000.0,
+                release: 0.15,
+                sample_rate: 0.0,
+            },
+        )
+    }
+    /// Set the frequency range covered by the bands
+    ///
+    /// The default is 40 Hz to 16 kHz.
+    pub fn range(self, low: f64, high: f64) -> Self {
+        BandAnalyzer {
+            low,
+            high,
+            sample_rate: 0.0,
+            ..self
+        }
+    }
+    /// Set the time in seconds it takes for a level to fall most of the way to silence
+    ///
+    /// The default is `0.15`.
+    pub fn release(self, release: f64) -> Self {
+        BandAnalyzer { release, ..self }
+    }
+    fn compute_bins(&mut self, sample_rate: f64) {
+        self.sample_rate = sample_rate;
+        let bin_width = sample_rate / BAND_WINDOW as f64;
+        let max_bin = BAND_WINDOW / 2;
+        let low = self.low.max(bin_width);
+        let high = self.high.clamp(low, sample_rate / 2.0);
+        let count = self.smoothed.len();
+        let edge = |i: usize| {
+            let freq = low * (high / low).powf(i as f64 / count as f64);
+            ((freq / bin_width).round() as usize).min(max_bin)
+        };
+        self.bins = (0..count)
+            .map(|i| {
+                let start = edge(i).min(max_bin - 1);
+                start..edge(i + 1).max(start + 1)
+            })
+            .collect();
+    }
+}
+
+impl<S> Source for BandAnalyzer<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let frame = self.source.next(sample_rate)?;
+        if self.sample_rate != sample_rate {
+            self.compute_bins(sample_rate);
+        }
+        if let Some(magnitudes) = self.spectrum.push(frame.avg()) {
+            let hop_time = BAND_HOP as f64 / sample_rate;
+            let decay = if self.release > 0.0 {
+                (-hop_time * 5.0 / self.release).exp()
+            } else {
+                0.0
+            };
+            for ((bins, smoothed), level) in self
+                .bins
+                .iter()
+                .zip(&mut self.smoothed)
+                .zip(self.levels.iter())
+            {
+                let power: f64 = magnitudes[bins.clone()].iter().map(|m| m * m).sum();
+                let energy = power.sqrt();
+                *smoothed = energy.max(*smoothed * decay);
+                level.store(smoothed.to_bits(), Ordering::Relaxed);
+            }
+        }
+        Some(frame)
+    }
+}
+
+impl<S> FiniteSource for BandAnalyzer<S>
+where
+    S: FiniteSource,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+/// A snapshot of the stereo image measured by a [`Goniometer`]
+///
+/// Created with [`Source::goniometer`].
+/// Reading never blocks the audio thread.
+#[derive(Debug, Clone)]
+pub struct StereoScope {
+    correlation: Arc<AtomicU64>,
+    points: Arc<[[AtomicU64; 2]]>,
+    write: Arc<AtomicUsize>,
+}
+
+impl StereoScope {
+    /// Get the correlation between the left and right channels
+    ///
+    /// This ranges from `1.0` for mono, through `0.0` for unrelated channels,
+    /// to `-1.0` for channels that are out of phase and will cancel when summed to mono.
+    /// Silence has a correlation of `0.0`.
+    pub fn correlation(&self) -> f64 {
+        f64::from_bits(self.correlation.load(Ordering::Relaxed))
+    }
+    /// Get the number of points kept for the sample cloud
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+    /// Check if no points are kept
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+    /// Get the most recent `(left, right)` sample pairs, oldest first
+    pub fn points(&self) -> Vec<(f64, f64)> {
+        let mut points = vec![(0.0, 0.0); self.len()];
+        self.read_into(&mut points);
+        points
+    }
+    /// Write the most recent `(left, right)` sample pairs into a slice, oldest first
+    ///
+    /// This does not allocate, so it can be called every frame of a vectorscope.
+    /// For the usual vectorscope orientation, plot `right - left` horizontally and `left + right` vertically.
+    pub fn read_into(&self, points: &mut [(f64, f64)]) {
+        let len = self.points.len();
+        let start = self.write.load(Ordering::Relaxed);
+        for (i, out) in points.iter_mut().take(len).enumerate() {
+            let [left, right] = &self.points[(start + i) % len];
+            *out = (
+                f64::from_bits(left.load(Ordering::Relaxed)),
+                f64::from_bits(right.load(Ordering::Relaxed)),
+            );
+        }
+    }
+}
+
+/// Source returned from [`Source::goniometer`]
+///
+/// It measures the correlation between the first two channels of a source
+/// and keeps their most recent samples for drawing.
+/// Mono sources are treated as having identical channels.
+pub struct Goniometer<S> {
+    source: S,
+    scope: StereoScope,
+    window: f64,
+    sums: [f64; 3],
+}
+
+impl<S> Goniometer<S> {
+    pub(crate) fn new(source: S, points: usize) -> (StereoScope, Self) {
+        let scope = StereoScope {
+            correlation: Arc::new(AtomicU64::new(0.0f64.to_bits())),
+            points: (0..points)
+                .map(|_| [AtomicU64::new(0), AtomicU64::new(0)])
+                .collect(),
+            write: Arc::new(AtomicUsize::new(0)),
+        };
+        (
+            scope.clone(),
+            Goniometer {
+                source,
+                scope,
+                window: 0.3,
+                sums: [0.0; 3],
+            },
+        )
+    }
+    /// Set the time in seconds over which the correlation is averaged
+    ///
+    /// The default is `0.3`.
+    pub fn window(self, window: f64) -> Self {
+        Goniometer { window, ..self }
+    }
+}
+
+impl<S> Source for Goniometer<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let frame = self.source.next(sample_rate)?;
+        let left = frame.get_channel(0);
+        let right = if S::Frame::CHANNELS > 1 {
+            frame.get_channel(1)
+        } else {
+            left
+        };
+        let t = if self.window > 0.0 {
+            1.0 - (-1.0 / (self.window * sample_rate)).exp()
+        } else {
+            1.0
+        };
+        let [lr, ll, rr] = &mut self.sums;
+        *lr += (left * right - *lr) * t;
+        *ll += (left * left - *ll) * t;
+        *rr += (right * right - *rr) * t;
+        let power = (*ll * *rr).sqrt();
+        let correlation = if power > 1e-12 {
+            (*lr / power).clamp(-1.0, 1.0)
+        } else {
+            0.0
+        };
+        let scope = &self.scope;
+        scope
+            .correlation
+            .store(correlation.to_bits(), Ordering::Relaxed);
+        let len = scope.points.len();
+        if len > 0 {
+            let i = scope.write.load(Ordering::Relaxed);
+            scope.points[i][0].store(left.to_bits(), Ordering::Relaxed);
+            scope.points[i][1].store(right.to_bits(), Ordering::Relaxed);
+            scope.write.store((i + 1) % len, Ordering::Relaxed);
+        }
+        Some(frame)
+    }
+}
+
+impl<S> FiniteSource for Goniometer<S>
+where
+    S: FiniteSource,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+/// Minimum and maximum amplitudes of a finite source, for drawing waveform overviews
+///
+/// Each channel is divided into buckets of a fixed number of frames.
+/// Buckets can be combined to draw the waveform at any zoom level coarser than the bucket size.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Peaks {
+    sample_rate: f64,
+    bucket_len: usize,
+    channels: usize,
+    /// Min/max pairs, interleaved by channel
+    data: Vec<(f64, f64)>,
+}
+
+/// The first bytes of a file written by [`Peaks::save`]
+const PEAKS_MAGIC: &[u8; 8] = b"HODPEAK1";
+
+impl Peaks {
+    /// Scan a source and record the peaks of every `bucket_len` frames
+    ///
+    /// The source must be finite.
+    pub fn scan<S>(mut source: S, sample_rate: f64, bucket_len: usize) -> Self
+    where
+        S: Source,
+    {
+        let bucket_len = bucket_len.max(1);
+        let channels = S::Frame::CHANNELS;
+        let mut data = Vec::new();
+        let mut filled = 0;
+        while let Some(frame) = source.next(sample_rate) {
+            if filled == 0 {
+                data.extend((0..channels).map(|_| (f64::INFINITY, f64::NEG_INFINITY)));
+            }
+            let start = data.len() - channels;
+            let bucket = &mut data[start..];
+            for (c, (min, max)) in bucket.iter_mut().enumerate() {
+                let amp = frame.get_channel(c);
+                *min = min.min(amp);
+                *max = max.max(amp);
+            }
+            filled = (filled + 1) % bucket_len;
+        }
+        Peaks {
+            sample_rate,
+            bucket_len,
+            channels,
+            data,
+        }
+    }
+    /// Decode a WAV file and record the peaks of every `bucket_len` frames
+    ///
+    /// The peaks are measured at the file's own sample rate.
+    #[cfg(feature = "wav")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "wav")))]
+    pub fn scan_wav(
+        path: impl AsRef<std::path::Path>,
+        bucket_len: usize,
+    ) -> Result<Self, crate::wav::WaveError> {
+        use crate::UnrolledSource;
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let source = crate::wav::WavSource::new(file)?;
+        let sample_rate = source.sample_rate();
+        Ok(match source.channels() {
+            1 => Peaks::scan(source.resample::<f64>(), sample_rate, bucket_len),
+            _ => Peaks::scan(source.resample::<crate::Stereo>(), sample_rate, bucket_len),
+        })
+    }
+    /// Get the sample rate the peaks were measured at
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+    /// Get the number of frames in each bucket
+    pub fn bucket_len(&self) -> usize {
+        self.bucket_len
+    }
+    /// Get the number of channels
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+    /// Get the number of buckets in each channel
+    pub fn len(&self) -> usize {
+        self.data.len().checked_div(self.channels).unwrap_or(0)
+    }
+    /// Check if there are no buckets
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+    /// Get the duration of the scanned source
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64((self.len() * self.bucket_len) as f64 / self.sample_rate)
+    }
+    /// Get the minimum and maximum amplitudes of a single bucket
+    pub fn get(&self, channel: usize, bucket: usize) -> Option<(f64, f64)> {
+        if channel >= self.channels {
+            return None;
+        }
+        self.data.get(bucket * self.channels + channel).copied()
+    }
+    /// Get the minimum and maximum amplitudes of a channel over a time range
+    ///
+    /// Returns [`None`] if the range contains no buckets.
+    pub fn range(&self, channel: usize, start: Duration, end: Duration) -> Option<(f64, f64)> {
+        let bucket = |time: Duration| {
+            ((time.as_secs_f64() * self.sample_rate / self.bucket_len as f64) as usize)
+                .min(self.len())
+        };
+        let (start, end) = (bucket(start), bucket(end).max(bucket(start) + 1));
+        (start..end.min(self.len()))
+            .filter_map(|i| self.get(channel, i))
+            .reduce(|(a_min, a_max), (b_min, b_max)| (a_min.min(b_min), a_max.max(b_max)))
+    }
+    /// Get `width` evenly spaced min/max pairs covering the whole channel
+    ///
+    /// This is usually called with the width of the waveform display in pixels.
+    pub fn overview(&self, channel: usize, width: usize) -> Vec<(f64, f64)> {
+        let len = self.len();
+        (0..width)
+            .map(|x| {
+                let start = x * len / width;
+                let end = ((x + 1) * len / width).max(start + 1).min(len);
+                (start..end)
+                    .filter_map(|i| self.get(channel, i))
+                    .reduce(|(a_min, a_max), (b_min, b_max)| (a_min.min(b_min), a_max.max(b_max)))
+                    .unwrap_or((0.0, 0.0))
+            })
+            .collect()
+    }
+    /// Write the peaks to a file so they do not need to be scanned again
+    pub fn save(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let mut bytes = Vec::with_capacity(32 + self.data.len() * 8);
+        bytes.extend_from_slice(PEAKS_MAGIC);
+        bytes.extend_from_slice(&self.sample_rate.to_le_bytes());
+        bytes.extend_from_slice(&(self.bucket_len as u64).to_le_bytes());
+        bytes.extend_from_slice(&(self.channels as u64).to_le_bytes());
+        for &(min, max) in &self.data {
+            bytes.extend_from_slice(&(min as f32).to_le_bytes());
+            bytes.extend_from_slice(&(max as f32).to_le_bytes());
+        }
+        std::fs::write(path, bytes)
+    }
+    /// Read peaks from a file written by [`Peaks::save`]
+    pub fn load(path: impl AsRef<std::path::Path>) -> std::io::Result<Self> {
+        use std::io::{Error, ErrorKind};
+        let bytes = std::fs::read(path)?;
+        let invalid = || Error::new(ErrorKind::InvalidData, "invalid peak file");
+        let (header, body) = bytes.split_at_checked(32).ok_or_else(invalid)?;
+        if &header[..8] != PEAKS_MAGIC {
+            return Err(invalid());
+        }
+        let word = |i: usize| -> [u8; 8] { header[i..i + 8].try_into().unwrap() };
+        let sample_rate = f64::from_le_bytes(word(8));
+        let size = |i: usize| usize::try_from(u64::from_le_bytes(word(i))).map_err(|_| invalid());
+        let bucket_len = size(16)?;
+        let channels = size(24)?;
+        // Each bucket holds a min and max `f32` for each channel
+        let bucket_bytes = channels.checked_mul(8).ok_or_else(invalid)?;
+        if channels == 0 || bucket_len == 0 || body.len() % bucket_bytes != 0 {
+            return Err(invalid());
+        }
+        let data = body
+            .chunks_exact(8)
+            .map(|pair| {
+                let min = f32::from_le_bytes(pair[..4].try_into().unwrap());
+                let max = f32::from_le_bytes(pair[4..].try_into().unwrap());
+                (min as f64, max as f64)
+            })
+            .collect();
+        Ok(Peaks {
+            sample_rate,
+            bucket_len,
+            channels,
+            data,
+        })
+    }
+}
+
+/// Get the largest absolute amplitude of any channel in a frame
+fn frame_peak<F: Frame>(frame: &F) -> f64 {
+    (0..F::CHANNELS).fold(0.0, |peak: f64, i| peak.max(frame.get_channel(i).abs()))
+}
+
+/// Find the silent parts of a source
+///
+/// A frame is silent if the amplitudes of all of its channels are at most `threshold`.
+/// Only runs of silence at least `min_len` long are returned.
+/// The source must be finite.
+pub fn detect_silence<S>(
+    mut source: S,
+    sample_rate: f64,
+    threshold: f64,
+    min_len: impl ToDuration,
+) -> Vec<Range<Duration>>
+where
+    S: Source,
+{
+    let min_len = (min_len.to_duration().as_secs_f64() * sample_rate).ceil() as u64;
+    let time = |frame: u64| Duration::from_secs_f64(frame as f64 / sample_rate);
+    let mut ranges = Vec::new();
+    let mut start = None;
+    let mut frames = 0;
+    while let Some(frame) = source.next(sample_rate) {
+        if frame_peak(&frame) <= threshold {
+            start.get_or_insert(frames);
+        } else if let Some(start) = start.take() {
+            if frames - start >= min_len {
+                ranges.push(time(start)..time(frames));
+            }
+        }
+        frames += 1;
+    }
+    if let Some(start) = start {
+        if frames - start >= min_len {
+            ranges.push(time(start)..time(frames));
+        }
+    }
+    ranges
+}
+
+/// The longest silence in seconds a [`TrimSilence`] holds back before playing it
+const TRIM_MAX_GAP: f64 = 10.0;
+
+/// Source returned from [`Source::trim_silence`]
+pub struct TrimSilence<S: Source> {
+    source: S,
+    threshold: f64,
+    started: bool,
+    pending: VecDeque<S::Frame>,
+    resume: Option<S::Frame>,
+}
+
+impl<S: Source> TrimSilence<S> {
+    pub(crate) fn new(source: S, threshold: f64) -> Self {
+        TrimSilence {
+            source,
+            threshold,
+            started: false,
+            pending: VecDeque::new(),
+            resume: None,
+        }
+    }
+}
+
+impl<S> Source for TrimSilence<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        // Silence that was followed by sound is played back first
+        if let Some(resume) = &self.resume {
+            if let Some(frame) = self.pending.pop_front() {
+                return Some(frame);
+            }
+            let frame = resume.clone();
+            self.resume = None;
+            return Some(frame);
+        }
+        loop {
+            let frame = self.source.next(sample_rate)?;
+            let loud = frame_peak(&frame) > self.threshold;
+            if !self.started {
+                if loud {
+                    self.started = true;
+                    return Some(frame);
+                }
+            } else if loud {
+                if self.pending.is_empty() {
+                    return Some(frame);
+                }
+                self.resume = Some(frame);
+                return self.pending.pop_front();
+            } else {
+                self.pending.push_back(frame);
+                // Silence this long is kept, so it does not need to be held back
+                if self.pending.len() as f64 > TRIM_MAX_GAP * sample_rate {
+                    return self.pending.pop_front();
+                }
+            }
+        }
+    }
+}
+
+/// The length in seconds of the blocks a [`Vad`] classifies
+const VAD_BLOCK: f64 = 0.02;
+/// The zero crossing rate above which a block is considered noise rather than speech
+const VAD_MAX_ZCR: f64 = 0.35;
+/// The amplitude below which a block is never considered speech
+const VAD_MIN_LEVEL: f64 = 1e-4;
+/// The number of segments a [`VoiceActivity`] can fall behind before new ones are dropped
+const VAD_CAPACITY: usize = 256;
+
+/// Reports the speech detected by a [`Vad`]
+///
+/// Created with [`Source::vad`]
+///
+/// Up to 256 segments are held until they are taken. Segments completed after that are dropped.
+pub struct VoiceActivity {
+    speaking: Arc<AtomicBool>,
+    recv: Mutex<RingReceiver<Range<Duration>>>,
+}
+
+impl fmt::Debug for VoiceActivity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VoiceActivity")
+            .field("speaking", &self.is_speaking())
+            .finish_non_exhaustive()
+    }
+}
+
+impl VoiceActivity {
+    /// Check if speech is currently detected
+    pub fn is_speaking(&self) -> bool {
+        self.speaking.load(Ordering::Relaxed)
+    }
+    /// Take the next completed speech segment if there is one
+    ///
+    /// Segment times are relative to when the source started.
+    pub fn try_next(&self) -> Option<Range<Duration>> {
+        self.recv.lock().pop()
+    }
+    /// Iterate over all speech segments completed so far
+    pub fn drain(&self) -> impl Iterator<Item = Range<Duration>> + '_ {
+        iter::from_fn(|| self.try_next())
+    }
+}
+
+/// Source returned from [`Source::vad`]
+///
+/// It classifies short blocks of audio as speech when their level is well above
+/// the tracked noise floor and their zero crossing rate is not noise-like.
+pub struct Vad<S> {
+    source: S,
+    speaking: Arc<AtomicBool>,
+    send: RingSender<Range<Duration>>,
+    sensitivity: f64,
+    hangover: f64,
+    gate: bool,
+    noise_floor: Option<f64>,
+    block_sum: f64,
+    block_crossings: usize,
+    block_len: usize,
+    prev: f64,
+    frames: u64,
+    segment_start: Option<u64>,
+    last_speech: u64,
+}
+
+impl<S> Vad<S> {
+    pub(crate) fn new(source: S) -> (VoiceActivity, Self) {
+        let speaking = Arc::new(AtomicBool::new(false));
+        let (send, recv) = ring(VAD_CAPACITY);
+        (
+            VoiceActivity {
+                speaking: speaking.clone(),
+                recv: Mutex::new(recv),
+            },
+            Vad {
+                source,
+                speaking,
+                send,
+                sensitivity: 3.0,
+                hangover: 0.3,
+                gate: false,
+                noise_floor: None,
+                block_sum: 0.0,
+                block_crossings: 0,
+                block_len: 0,
+                prev: 0.0,
+                frames: 0,
+                segment_start: None,
+                last_speech: 0,
+            },
+        )
+    }
+    /// Set how many times louder than the noise floor speech must be
+    ///
+    /// Lower values detect quieter speech but may mistake noise for speech.
+    /// The default is `3.0`.
+    pub fn sensitivity(self, sensitivity: f64) -> Self {
+        Vad {
+            sensitivity,
+            ..self
+        }
+    }
+    /// Set how long speech is still considered active after it stops being detected
+    ///
+    /// This keeps short pauses between words from splitting segments.
+    /// The default is 300 milliseconds.
+    pub fn hangover(self, hangover: impl ToDuration) -> Self {
+        Vad {
+            hangover: hangover.to_duration().as_secs_f64(),
+            ..self
+        }
+    }
+    /// Silence the source whenever speech is not detected
+    ///
+    /// Speech is detected in blocks of 20 milliseconds,
+    /// so the very start of a segment may be cut off.
+    pub fn gate(self) -> Self {
+        Vad { gate: true, ..self }
+    }
+    fn classify_block(&mut self, sample_rate: f64) {
+        let level = (self.block_sum / self.block_len as f64).sqrt();
+        let zcr = self.block_crossings as f64 / self.block_len as f64;
+        self.block_sum = 0.0;
+        self.block_crossings = 0;
+        self.block_len = 0;
+        // Track the noise floor, falling quickly and rising slowly
+        let floor = self.noise_floor.get_or_insert(level);
+        *floor = if level < *floor {
+            level
+        } else {
+            *floor * (1.0 + VAD_BLOCK * 0.5) + 1e-7
+        };
+        let floor = *floor;
+        let speech = level > VAD_MIN_LEVEL && level > floor * self.sensitivity && zcr < VAD_MAX_ZCR;
+        let time = |frame: u64| Duration::from_secs_f64(frame as f64 / sample_rate);
+        if speech {
+            self.last_speech = self.frames;
+            if self.segment_start.is_none() {
+                let block = (VAD_BLOCK * sample_rate) as u64;
+                self.segment_start = Some(self.frames.saturating_sub(block));
+                self.speaking.store(true, Ordering::Relaxed);
+            }
+        } else if let Some(start) = self.segment_start {
+            if (self.frames - self.last_speech) as f64 / sample_rate >= self.hangover {
+                self.segment_start = None;
+                self.speaking.store(false, Ordering::Relaxed);
+                let _ = self.send.push(time(start)..time(self.last_speech));
+            }
+        }
+    }
+}
+
+impl<S> Source for Vad<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let Some(frame) = self.source.next(sample_rate) else {
+            // Finish any segment that is still in progress
+            if let Some(start) = self.segment_start.take() {
+                self.speaking.store(false, Ordering::Relaxed);
+                let time = |frame: u64| Duration::from_secs_f64(frame as f64 / sample_rate);
+                let _ = self.send.push(time(start)..time(self.frames));
+            }
+            return None;
+        };
+        let sample = frame.avg();
+        self.block_sum += sample * sample;
+        if (sample >= 0.0) != (self.prev >= 0.0) {
+            self.block_crossings += 1;
+        }
+        self.prev = sample;
+        self.block_len += 1;
+        self.frames += 1;
+        if self.block_len as f64 >= VAD_BLOCK * sample_rate {
+            self.classify_block(sample_rate);
+        }
+        if self.gate && self.segment_start.is_none() {
+            Some(S::Frame::uniform(0.0))
+        } else {
+            Some(frame)
+        }
+    }
+}
+
+impl<S> FiniteSource for Vad<S>
+where
+    S: FiniteSource,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{gen::Mls, test_util::StepSource, Stereo};
+
+    #[test]
+    fn deconvolve_recovers_the_impulse_response() {
+        let ir = [0.0, 1.0, 0.0, 0.5, -0.25, 0.0, 0.0, 0.1];
+        let mut mls = Mls::new(10);
+        let excitation: Vec<f64> = (0..mls.period()).map_while(|_| mls.next(1.0)).collect();
+        let mut response = vec![0.0; excitation.len() + ir.len()];
+        for (i, x) in excitation.iter().enumerate() {
+            for (j, h) in ir.iter().enumerate() {
+                response[i + j] += x * h;
+            }
+        }
+        let recovered = deconvolve(&excitation, &response);
+        assert_eq!(recovered.len(), ir.len());
+        for (i, (a, b)) in recovered.iter().zip(ir).enumerate() {
+            assert!((a - b).abs() < 1e-3, "sample {i} is {a}, expected {b}");
+        }
+    }
+
+    #[test]
+    fn peaks_scan_buckets() {
+        let source = StepSource::new(0.0, 0.25).take(2.5);
+        let peaks = Peaks::scan(source.map(|a| Stereo::new(a, -a)), 4.0, 4);
+        assert_eq!(peaks.channels(), 2);
+        assert_eq!(peaks.len(), 3);
+        assert_eq!(peaks.get(0, 0), Some((0.0, 0.75)));
+        assert_eq!(peaks.get(1, 1), Some((-1.75, -1.0)));
+        // The last bucket is only partly filled
+        assert_eq!(peaks.get(0, 2), Some((2.0, 2.25)));
+        assert_eq!(peaks.get(2, 0), None);
+    }
+
+    #[test]
+    fn peaks_save_and_load() {
+        let path = std::env::temp_dir().join(format!("hodaun-peaks-{}", std::process::id()));
+        let source = StepSource::new(-1.0, 0.125).take(1.0);
+        let peaks = Peaks::scan(source.map(|a| Stereo::new(a, a * 0.5)), 64.0, 8);
+        peaks.save(&path).unwrap();
+        let loaded = Peaks::load(&path);
+        // A file that is cut off is rejected
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+        let truncated = Peaks::load(&path);
+        std::fs::write(&path, b"not a peak file at all, just text").unwrap();
+        let garbage = Peaks::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.unwrap(), peaks);
+        assert!(truncated.is_err());
+        assert!(garbage.is_err());
+    }
+
+    #[test]
+    fn trim_silence_bounds_its_buffer() {
+        let sample_rate = 10.0;
+        let gap = (TRIM_MAX_GAP * sample_rate) as usize;
+        // A click, a gap longer than the limit, another click, then the same gap again
+        let clicks = StepSource::new(0.0, 1.0)
+            .map(move |i| (i % (gap + 6) as f64 == 0.0) as u8 as f64)
+            .take((2 * gap + 12) as f64 / sample_rate);
+        let mut trimmed = clicks.trim_silence(0.5);
+        let mut frames = Vec::new();
+        while let Some(frame) = trimmed.next(sample_rate) {
+            assert!(trimmed.pending.len() <= gap);
+            frames.push(frame);
+        }
+        // The middle gap is kept whole, and only the last `gap` frames of the trailing one are trimmed
+        assert_eq!(frames.len(), 2 * gap + 12 - gap);
+        assert_eq!(frames[gap + 6], 1.0);
+    }
+}
@@ -0,0 +1,84 @@
+use std::collections::HashMap;
+
+use crate::{
+    source::{Buffered, Empty},
+    Frame, Source,
+};
+
+/// A sound bank buffer source
+pub type BankSound<F> = Buffered<Empty<F>>;
+
+/// A collection of preloaded sounds keyed by name
+///
+/// Sounds are decoded once when they are added to the bank.
+/// Retrieving a sound with [`SoundBank::get`] is cheap, and all
+/// of the returned sources share the same underlying buffer.
+///
+/// Because sounds are decoded ahead of time, the bank must know the
+/// sample rate they will be played at.
+pub struct SoundBank<F: Frame> {
+    sample_rate: f64,
+    sounds: HashMap<String, BankSound<F>>,
+}
+
+impl<F: Frame> SoundBank<F> {
+    /// Create a new sound bank for sounds that will be played at the given sample rate
+    pub fn new(sample_rate: f64) -> Self {
+        SoundBank {
+            sample_rate,
+            sounds: HashMap::new(),
+        }
+    }
+    /// Get the sample rate sounds are decoded at
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+    /// Render a source and add it to the bank
+    ///
+    /// The source must be finite.
+    /// If a sound with the same name already exists, it is replaced.
+    pub fn insert<S>(&mut self, name: impl Into<String>, mut source: S)
+    where
+        S: Source<Frame = F>,
+    {
+        let mut buffer = Vec::new();
+        while let Some(frame) = source.next(self.sample_rate) {
+            buffer.push(frame);
+        }
+        self.sounds.insert(name.into(), buffer.into());
+    }
+    /// Load a WAV file and add it to the bank
+    ///
+    /// If a sound with the same name already exists, it is replaced.
+    #[cfg(feature = "wav")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "wav")))]
+    pub fn load_wav(
+        &mut self,
+        name: impl Into<String>,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<(), crate::wav::WaveError> {
+        use crate::UnrolledSource;
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let source = crate::wav::WavSource::new(file)?;
+        self.insert(name, source.resample());
+        Ok(())
+    }
+    /// Get a playable instance of a sound
+    pub fn get(&self, name: &str) -> Option<BankSound<F>> {
+        self.sounds.get(name).cloned()
+    }
+    /// Check if the bank contains a sound
+    pub fn contains(&self, name: &str) -> bool {
+        self.sounds.contains_key(name)
+    }
+    /// Remove a sound from the bank
+    ///
+    /// Instances of the sound that are already playing are not affected.
+    pub fn remove(&mut self, name: &str) -> bool {
+        self.sounds.remove(name).is_some()
+    }
+    /// Iterate over the names of the sounds in the bank
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.sounds.keys().map(String::as_str)
+    }
+}
@@ -0,0 +1,240 @@
+use core::time::Duration;
+
+use crate::{source::Take, Frame, Pitch, Source, Tempo, ToDuration};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// A note in a [`Clip`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClipNote {
+    /// The time at which the note starts, relative to the start of the clip
+    ///
+    /// This is in seconds, or in beats when the clip follows a [`Tempo`].
+    pub start: f64,
+    /// The length of the note
+    ///
+    /// This is in seconds, or in beats when the clip follows a [`Tempo`].
+    pub duration: f64,
+    /// The pitch of the note
+    pub pitch: Pitch,
+    /// The velocity of the note, in the range [0, 1]
+    pub velocity: f64,
+    /// The channel of the note
+    ///
+    /// Channels can be used to route notes to different instruments.
+    pub channel: u8,
+}
+
+impl ClipNote {
+    /// Create a new note with full velocity on channel 0
+    pub fn new(start: impl ToDuration, duration: impl ToDuration, pitch: impl Into<Pitch>) -> Self {
+        ClipNote {
+            start: start.to_duration().as_secs_f64(),
+            duration: duration.to_duration().as_secs_f64(),
+            pitch: pitch.into(),
+            velocity: 1.0,
+            channel: 0,
+        }
+    }
+    /// Set the velocity
+    pub fn velocity(self, velocity: f64) -> Self {
+        ClipNote { velocity, ..self }
+    }
+    /// Set the channel
+    pub fn channel(self, channel: u8) -> Self {
+        ClipNote { channel, ..self }
+    }
+    /// Get the time at which the note ends
+    pub fn end(&self) -> f64 {
+        self.start + self.duration
+    }
+}
+
+/// A piano-roll style collection of timed notes
+///
+/// Unlike a step sequence, notes can start at any time and overlap freely.
+///
+/// # Example
+/// ```
+/// use hodaun::*;
+/// use Letter::*;
+///
+/// let clip = Clip::new()
+///     .with_note(ClipNote::new(0.0, 1.0, (C, 4)))
+///     .with_note(ClipNote::new(0.5, 1.0, (E, 4)).velocity(0.5));
+/// assert_eq!(clip.duration(), std::time::Duration::from_secs_f64(1.5));
+/// let source = clip.play(|note| SineWave::new(note.pitch).amplify(note.velocity));
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Clip {
+    notes: Vec<ClipNote>,
+}
+
+impl Clip {
+    /// Create an empty clip
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Add a note
+    pub fn add(&mut self, note: ClipNote) {
+        let i = self.notes.partition_point(|n| n.start <= note.start);
+        self.notes.insert(i, note);
+    }
+    /// Add a note and return the clip
+    pub fn with_note(mut self, note: ClipNote) -> Self {
+        self.add(note);
+        self
+    }
+    /// Get the notes, ordered by start time
+    pub fn notes(&self) -> &[ClipNote] {
+        &self.notes
+    }
+    /// Remove all notes that match a predicate
+    pub fn remove_where(&mut self, mut f: impl FnMut(&ClipNote) -> bool) {
+        self.notes.retain(|note| !f(note));
+    }
+    /// Get the time at which the last note ends
+    pub fn duration(&self) -> Duration {
+        let end = self.notes.iter().map(ClipNote::end).fold(0.0, f64::max);
+        Duration::from_secs_f64(end)
+    }
+    /// Shift the pitch of every note by some number of half-steps
+    pub fn transpose(mut self, half_steps: i16) -> Self {
+        for note in &mut self.notes {
+            note.pitch = Pitch::from_half_steps(note.pitch.to_half_steps() + half_steps);
+        }
+        self
+    }
+    /// Play the clip, creating a voice for each note with the given function
+    ///
+    /// When its note ends, each voice fades out over a short release rather than being cut off.
+    /// The release can be changed with [`ClipPlayer::release`].
+    /// Note times are in seconds unless the player follows a [`Tempo`] with [`ClipPlayer::tempo`].
+    pub fn play<V, F>(&self, voice: F) -> ClipPlayer<V, F>
+    where
+        V: Source,
+        F: FnMut(&ClipNote) -> V,
+    {
+        ClipPlayer {
+            notes: self.notes.clone(),
+            voice,
+            next_note: 0,
+            voices: Vec::new(),
+            release: DEFAULT_RELEASE,
+            tempo: None,
+            position: 0.0,
+        }
+        .reserve_voices()
+    }
+}
+
+impl FromIterator<ClipNote> for Clip {
+    fn from_iter<T: IntoIterator<Item = ClipNote>>(iter: T) -> Self {
+        let mut notes: Vec<ClipNote> = iter.into_iter().collect();
+        notes.sort_by(|a, b| a.start.total_cmp(&b.start));
+        Clip { notes }
+    }
+}
+
+/// The default release of a [`ClipPlayer`]'s voices, long enough to avoid clicks
+const DEFAULT_RELEASE: f64 = 0.005;
+
+/// Get the most notes that sound at once, with each note's end extended by a release
+fn max_overlap(notes: &[ClipNote], release: f64) -> usize {
+    let mut ends: Vec<f64> = Vec::new();
+    let mut max = 0;
+    for note in notes {
+        ends.retain(|&end| end > note.start);
+        ends.push(note.end().max(note.start) + release);
+        max = max.max(ends.len());
+    }
+    max
+}
+
+/// Source returned from [`Clip::play`]
+///
+/// Room for as many voices as the clip's notes overlap is allocated up front,
+/// so starting notes does not allocate unless a slower tempo makes them overlap more.
+pub struct ClipPlayer<V, F> {
+    notes: Vec<ClipNote>,
+    voice: F,
+    next_note: usize,
+    voices: Vec<Take<V, f64>>,
+    release: f64,
+    tempo: Option<Tempo>,
+    /// The playback position in seconds, or in beats when following a tempo
+    position: f64,
+}
+
+impl<V, F> ClipPlayer<V, F> {
+    /// Set how long each voice takes to fade out after its note ends
+    ///
+    /// The default is 5 milliseconds.
+    pub fn release(self, release: impl ToDuration) -> Self {
+        ClipPlayer {
+            release: release.to_duration().as_secs_f64(),
+            ..self
+        }
+        .reserve_voices()
+    }
+    /// Follow a tempo, so that note times are in beats instead of seconds
+    ///
+    /// Changes to the tempo move the playback position faster or slower.
+    /// Each note's length is converted to seconds when it starts.
+    pub fn tempo(self, tempo: &Tempo) -> Self {
+        ClipPlayer {
+            tempo: Some(tempo.clone()),
+            ..self
+        }
+        .reserve_voices()
+    }
+    fn reserve_voices(mut self) -> Self {
+        let release = match &self.tempo {
+            Some(tempo) => self.release / tempo.beat_secs(),
+            None => self.release,
+        };
+        let overlap = max_overlap(&self.notes, release);
+        self.voices
+            .reserve(overlap.saturating_sub(self.voices.len()));
+        self
+    }
+    /// Get the number of voices currently playing
+    pub fn playing(&self) -> usize {
+        self.voices.len()
+    }
+}
+
+impl<V, F> Source for ClipPlayer<V, F>
+where
+    V: Source,
+    F: FnMut(&ClipNote) -> V,
+{
+    type Frame = V::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let time = self.position;
+        let beat_secs = self.tempo.as_ref().map_or(1.0, Tempo::beat_secs);
+        while let Some(note) = self.notes.get(self.next_note).filter(|n| n.start <= time) {
+            let duration = note.duration.max(0.0) * beat_secs + self.release;
+            let voice = (self.voice)(note).take_release(duration, self.release);
+            self.voices.push(voice);
+            self.next_note += 1;
+        }
+        if self.voices.is_empty() && self.next_note == self.notes.len() {
+            return None;
+        }
+        let mut frame = V::Frame::uniform(0.0);
+        self.voices
+            .retain_mut(|voice| match voice.next(sample_rate) {
+                Some(next) => {
+                    frame.merge(next, |a, b| a + b);
+                    true
+                }
+                None => false,
+            });
+        self.position += 1.0 / (beat_secs * sample_rate);
+        Some(frame)
+    }
+}
@@ -0,0 +1,135 @@
+use alloc::sync::Arc;
+use core::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Duration,
+};
+
+use crate::{sync::Mutex, FiniteSource, Frame, Source};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+type NamedCounts = Vec<(String, Arc<AtomicU64>)>;
+
+/// Counts samples that go beyond `±1.0`, so clipping can be traced to the sources that cause it
+///
+/// Sources are counted by wrapping them with [`ClipDetector::monitor`].
+/// The final output is counted by passing the detector to [`DeviceIoBuilder::clip_detector`](crate::DeviceIoBuilder::clip_detector).
+/// Samples are counted before they are converted to the device's format, which is where they would clip.
+///
+/// Cloning a detector is cheap, and clones share the same counts.
+///
+/// # Example
+/// ```
+/// use hodaun::*;
+///
+/// let detector = ClipDetector::new();
+/// let mut loud = detector.monitor("loud", SineWave::new(440.0).amplify(2.0));
+/// let mut quiet = detector.monitor("quiet", SineWave::new(440.0).amplify(0.5));
+/// for _ in 0..100 {
+///     loud.next(44100.0);
+///     quiet.next(44100.0);
+/// }
+/// assert!(detector.count("loud").unwrap() > 0);
+/// assert_eq!(detector.count("quiet"), Some(0));
+/// ```
+#[derive(Clone, Default)]
+pub struct ClipDetector {
+    master: Arc<AtomicU64>,
+    sources: Arc<Mutex<NamedCounts>>,
+}
+
+impl ClipDetector {
+    /// Create a new detector with no monitored sources
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Count the clipped samples of a source under a name
+    ///
+    /// The source passes through unchanged.
+    /// Sources monitored under the same name share a count.
+    pub fn monitor<S>(&self, name: impl Into<String>, source: S) -> ClipMonitor<S>
+    where
+        S: Source,
+    {
+        let name = name.into();
+        let mut sources = self.sources.lock();
+        let count = if let Some((_, count)) = sources.iter().find(|(n, _)| *n == name) {
+            count.clone()
+        } else {
+            let count = Arc::new(AtomicU64::new(0));
+            sources.push((name, count.clone()));
+            count
+        };
+        ClipMonitor { source, count }
+    }
+    /// Get the number of clipped samples on the output
+    ///
+    /// This is only counted if the detector was passed to [`DeviceIoBuilder::clip_detector`](crate::DeviceIoBuilder::clip_detector).
+    pub fn master(&self) -> u64 {
+        self.master.load(Ordering::Relaxed)
+    }
+    /// Get the number of clipped samples of the sources monitored under a name
+    pub fn count(&self, name: &str) -> Option<u64> {
+        let sources = self.sources.lock();
+        let (_, count) = sources.iter().find(|(n, _)| n == name)?;
+        Some(count.load(Ordering::Relaxed))
+    }
+    /// Get the names and clipped sample counts of all monitored sources, in the order they were first monitored
+    pub fn counts(&self) -> Vec<(String, u64)> {
+        (self.sources.lock().iter())
+            .map(|(name, count)| (name.clone(), count.load(Ordering::Relaxed)))
+            .collect()
+    }
+    /// Reset all counts to zero
+    pub fn reset(&self) {
+        self.master.store(0, Ordering::Relaxed);
+        for (_, count) in self.sources.lock().iter() {
+            count.store(0, Ordering::Relaxed);
+        }
+    }
+    #[cfg(feature = "output")]
+    pub(crate) fn count_master(&self, samples: &[f64]) {
+        let clipped = count_clipped(samples.iter().copied());
+        if clipped > 0 {
+            self.master.fetch_add(clipped, Ordering::Relaxed);
+        }
+    }
+}
+
+fn count_clipped(samples: impl Iterator<Item = f64>) -> u64 {
+    samples.filter(|s| s.abs() > 1.0).count() as u64
+}
+
+/// Source returned from [`ClipDetector::monitor`]
+pub struct ClipMonitor<S> {
+    source: S,
+    count: Arc<AtomicU64>,
+}
+
+impl<S> Source for ClipMonitor<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let frame = self.source.next(sample_rate)?;
+        let clipped = count_clipped((0..S::Frame::CHANNELS).map(|i| frame.get_channel(i)));
+        if clipped > 0 {
+            self.count.fetch_add(clipped, Ordering::Relaxed);
+        }
+        Some(frame)
+    }
+}
+
+impl<S> FiniteSource for ClipMonitor<S>
+where
+    S: FiniteSource,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
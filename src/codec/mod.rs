@@ -1,3 +1,91 @@
+//! Audio file decoding and encoding
+//!
+//! The codec modules are also re-exported at the crate root.
+
+#[cfg(feature = "ogg")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ogg")))]
+pub mod ogg;
+#[cfg(feature = "opus")]
+#[cfg_attr(docsrs, doc(cfg(feature = "opus")))]
+pub mod opus;
 #[cfg(feature = "wav")]
 #[cfg_attr(docsrs, doc(cfg(feature = "wav")))]
 pub mod wav;
+
+use std::{collections::VecDeque, sync::Arc};
+
+use parking_lot::Mutex;
+
+/// What a decoding source should do when it encounters an error
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum DecodeErrorPolicy {
+    /// Record the error and end the source
+    #[default]
+    End,
+    /// Record the error and skip the sample or packet that could not be decoded
+    Skip,
+    /// Panic with the error
+    Panic,
+}
+
+/// A handle to the errors encountered by a decoding source
+///
+/// This allows the end of a file to be distinguished from a decoding failure,
+/// even after the source has been added to a mixer.
+#[derive(Debug)]
+pub struct DecodeErrors<E> {
+    errors: Arc<Mutex<VecDeque<E>>>,
+}
+
+impl<E> Default for DecodeErrors<E> {
+    fn default() -> Self {
+        DecodeErrors {
+            errors: Arc::new(Mutex::new(VecDeque::new())),
+        }
+    }
+}
+
+impl<E> Clone for DecodeErrors<E> {
+    fn clone(&self) -> Self {
+        DecodeErrors {
+            errors: self.errors.clone(),
+        }
+    }
+}
+
+impl<E> DecodeErrors<E> {
+    /// Check if any errors have been encountered that have not been taken
+    pub fn has_errors(&self) -> bool {
+        !self.errors.lock().is_empty()
+    }
+    /// Take the oldest error that has not yet been taken
+    pub fn take(&self) -> Option<E> {
+        self.errors.lock().pop_front()
+    }
+    /// Take all errors that have not yet been taken
+    pub fn take_all(&self) -> Vec<E> {
+        self.errors.lock().drain(..).collect()
+    }
+}
+
+impl<E> DecodeErrors<E>
+where
+    E: std::fmt::Display,
+{
+    /// Handle an error according to the policy
+    ///
+    /// Returns whether decoding should continue
+    pub(crate) fn report(&self, error: E, policy: DecodeErrorPolicy) -> bool {
+        match policy {
+            DecodeErrorPolicy::End => {
+                self.errors.lock().push_back(error);
+                false
+            }
+            DecodeErrorPolicy::Skip => {
+                self.errors.lock().push_back(error);
+                true
+            }
+            DecodeErrorPolicy::Panic => panic!("{error}"),
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Read ogg vorbis files
+
+use std::{
+    collections::VecDeque,
+    io::{Read, Seek},
+};
+
+use lewton::inside_ogg::OggStreamReader;
+
+use crate::{DecodeErrorPolicy, DecodeErrors, UnrolledSource};
+
+pub use lewton::VorbisError as OggError;
+
+/// A source that reads from an ogg vorbis file
+pub struct OggSource<R: Read + Seek> {
+    reader: OggStreamReader<R>,
+    buffer: VecDeque<f64>,
+    policy: DecodeErrorPolicy,
+    errors: DecodeErrors<OggError>,
+    failures: usize,
+    ended: bool,
+}
+
+/// The number of consecutive packets that may fail to decode before the source ends,
+/// even when skipping errors
+const MAX_CONSECUTIVE_FAILURES: usize = 8;
+
+impl<R> OggSource<R>
+where
+    R: Read + Seek,
+{
+    /// Create a new ogg source from a reader
+    pub fn new(reader: R) -> Result<Self, OggError> {
+        Ok(OggSource {
+            reader: OggStreamReader::new(reader)?,
+            buffer: VecDeque::new(),
+            policy: DecodeErrorPolicy::default(),
+            errors: DecodeErrors::default(),
+            failures: 0,
+            ended: false,
+        })
+    }
+    /// Set what happens when a packet fails to decode
+    ///
+    /// The default is [`DecodeErrorPolicy::End`]
+    pub fn on_error(self, policy: DecodeErrorPolicy) -> Self {
+        OggSource { policy, ..self }
+    }
+    /// Get a handle to the errors encountered while decoding
+    pub fn errors(&self) -> DecodeErrors<OggError> {
+        self.errors.clone()
+    }
+}
+
+impl<R> Iterator for OggSource<R>
+where
+    R: Read + Seek,
+{
+    type Item = f64;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(sample) = self.buffer.pop_front() {
+                return Some(sample);
+            }
+            if self.ended {
+                return None;
+            }
+            match self.reader.read_dec_packet_itl() {
+                Ok(Some(packet)) => {
+                    self.failures = 0;
+                    self.buffer
+                        .extend(packet.into_iter().map(|s| s as f64 / i16::MAX as f64));
+                }
+                Ok(None) => self.ended = true,
+                Err(e) => {
+                    self.failures += 1;
+                    self.ended = !self.errors.report(e, self.policy)
+                        || self.failures >= MAX_CONSECUTIVE_FAILURES;
+                }
+            }
+        }
+    }
+}
+
+impl<R> UnrolledSource for OggSource<R>
+where
+    R: Read + Seek,
+{
+    fn channels(&self) -> usize {
+        self.reader.ident_hdr.audio_channels as usize
+    }
+    fn sample_rate(&self) -> f64 {
+        self.reader.ident_hdr.audio_sample_rate as f64
+    }
+}
@@ -0,0 +1,153 @@
+//! Read ogg opus files
+
+use std::{
+    collections::VecDeque,
+    io::{Read, Seek},
+};
+
+use audiopus::{coder::Decoder, packet::Packet, Channels, MutSignals, SampleRate};
+use ogg::{OggReadError, PacketReader};
+
+use crate::{DecodeErrorPolicy, DecodeErrors, UnrolledSource};
+
+/// An error encountered when reading an opus file
+#[derive(Debug, thiserror::Error)]
+pub enum OpusError {
+    /// An error reading the ogg container
+    #[error("{0}")]
+    Ogg(#[from] OggReadError),
+    /// An error decoding an opus packet
+    #[error("{0}")]
+    Opus(#[from] audiopus::Error),
+    /// The stream does not begin with a valid opus header
+    #[error("Invalid opus header")]
+    InvalidHeader,
+    /// The stream has a channel count that is not supported
+    #[error("Unsupported channel count: {0}")]
+    UnsupportedChannels(u8),
+}
+
+/// Opus always decodes at 48kHz
+const OPUS_SAMPLE_RATE: u32 = 48000;
+/// The maximum duration of an opus packet is 120ms
+const MAX_PACKET_FRAMES: usize = OPUS_SAMPLE_RATE as usize * 120 / 1000;
+/// The number of consecutive packets that may fail to decode before the source ends,
+/// even when skipping errors
+const MAX_CONSECUTIVE_FAILURES: usize = 8;
+
+/// A source that reads from an ogg opus file
+///
+/// Only mono and stereo streams are supported.
+pub struct OpusSource<R: Read + Seek> {
+    reader: PacketReader<R>,
+    decoder: Decoder,
+    channels: usize,
+    pre_skip: usize,
+    decoded: Vec<f32>,
+    buffer: VecDeque<f64>,
+    policy: DecodeErrorPolicy,
+    errors: DecodeErrors<OpusError>,
+    failures: usize,
+    ended: bool,
+}
+
+impl<R> OpusSource<R>
+where
+    R: Read + Seek,
+{
+    /// Create a new opus source from a reader
+    pub fn new(reader: R) -> Result<Self, OpusError> {
+        let mut reader = PacketReader::new(reader);
+        // Identification header
+        let head = reader.read_packet_expected()?.data;
+        if head.len() < 19 || &head[..8] != b"OpusHead" {
+            return Err(OpusError::InvalidHeader);
+        }
+        let channel_count = head[9];
+        let pre_skip = u16::from_le_bytes([head[10], head[11]]) as usize;
+        let channels = match channel_count {
+            1 => Channels::Mono,
+            2 => Channels::Stereo,
+            n => return Err(OpusError::UnsupportedChannels(n)),
+        };
+        // Comment header
+        let tags = reader.read_packet_expected()?.data;
+        if !tags.starts_with(b"OpusTags") {
+            return Err(OpusError::InvalidHeader);
+        }
+        Ok(OpusSource {
+            reader,
+            decoder: Decoder::new(SampleRate::Hz48000, channels)?,
+            channels: channel_count as usize,
+            pre_skip: pre_skip * channel_count as usize,
+            decoded: vec![0.0; MAX_PACKET_FRAMES * channel_count as usize],
+            buffer: VecDeque::new(),
+            policy: DecodeErrorPolicy::default(),
+            errors: DecodeErrors::default(),
+            failures: 0,
+            ended: false,
+        })
+    }
+    /// Set what happens when a packet fails to decode
+    ///
+    /// The default is [`DecodeErrorPolicy::End`]
+    pub fn on_error(self, policy: DecodeErrorPolicy) -> Self {
+        OpusSource { policy, ..self }
+    }
+    /// Get a handle to the errors encountered while decoding
+    pub fn errors(&self) -> DecodeErrors<OpusError> {
+        self.errors.clone()
+    }
+    fn decode_packet(&mut self) -> Result<bool, OpusError> {
+        let Some(packet) = self.reader.read_packet()? else {
+            return Ok(false);
+        };
+        let input = Packet::try_from(packet.data.as_slice())?;
+        let output = MutSignals::try_from(self.decoded.as_mut_slice())?;
+        let frames = self.decoder.decode_float(Some(input), output, false)?;
+        let samples = &self.decoded[..frames * self.channels];
+        let skip = self.pre_skip.min(samples.len());
+        self.pre_skip -= skip;
+        self.buffer
+            .extend(samples[skip..].iter().map(|&s| s as f64));
+        Ok(true)
+    }
+}
+
+impl<R> Iterator for OpusSource<R>
+where
+    R: Read + Seek,
+{
+    type Item = f64;
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(sample) = self.buffer.pop_front() {
+                return Some(sample);
+            }
+            if self.ended {
+                return None;
+            }
+            match self.decode_packet() {
+                Ok(true) => self.failures = 0,
+                Ok(false) => self.ended = true,
+                Err(e) => {
+                    self.failures += 1;
+                    self.ended = !self.errors.report(e, self.policy)
+                        || self.failures >= MAX_CONSECUTIVE_FAILURES;
+                }
+            }
+        }
+    }
+}
+
+impl<R> UnrolledSource for OpusSource<R>
+where
+    R: Read + Seek,
+{
+    fn channels(&self) -> usize {
+        self.channels
+    }
+    fn sample_rate(&self) -> f64 {
+        OPUS_SAMPLE_RATE as f64
+    }
+}
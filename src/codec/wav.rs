@@ -1,10 +1,20 @@
 //! Read and write wave files
 
-use std::io::{Read, Seek, Write};
+use std::{
+    io::{Read, Seek, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
 
 use hound::{SampleFormat, WavIntoSamples, WavReader, WavSpec, WavWriter};
 
-use crate::{Frame, Source, UnrolledSource};
+use crate::{
+    source::Loop, DecodeErrorPolicy, DecodeErrors, FiniteSource, Frame, Shared, Source,
+    UnrolledSource,
+};
 
 pub use hound::Error as WaveError;
 
@@ -13,12 +23,15 @@ pub struct WavSource<R> {
     sample_rate: u32,
     channels: u16,
     samples: GenericWaveSamples<R>,
+    policy: DecodeErrorPolicy,
+    errors: DecodeErrors<WaveError>,
+    ended: bool,
 }
 
 enum GenericWaveSamples<R> {
-    I16(WavIntoSamples<R, i16>),
-    I32(WavIntoSamples<R, i32>),
-    F32(WavIntoSamples<R, f32>),
+    /// Integer samples, along with the amplitude that maps to 1.0
+    Int(WavIntoSamples<R, i32>, f64),
+    Float(WavIntoSamples<R, f32>),
 }
 
 impl<R> WavSource<R>
@@ -26,6 +39,8 @@ where
     R: Read,
 {
     /// Create a new WAV source from a reader
+    ///
+    /// 8, 16, 24, and 32-bit integer and 32-bit float samples are supported.
     pub fn new(reader: R) -> Result<Self, WaveError> {
         let reader = WavReader::new(reader)?;
         Ok(Self {
@@ -33,14 +48,29 @@ where
             channels: reader.spec().channels,
             samples: match reader.spec().sample_format {
                 SampleFormat::Int => match reader.spec().bits_per_sample {
-                    16 => GenericWaveSamples::I16(reader.into_samples::<i16>()),
-                    32 => GenericWaveSamples::I32(reader.into_samples::<i32>()),
+                    bits @ (8 | 16 | 24 | 32) => {
+                        let scale = (1u64 << (bits - 1)) as f64;
+                        GenericWaveSamples::Int(reader.into_samples::<i32>(), scale)
+                    }
                     _ => return Err(WaveError::Unsupported),
                 },
-                SampleFormat::Float => GenericWaveSamples::F32(reader.into_samples::<f32>()),
+                SampleFormat::Float => GenericWaveSamples::Float(reader.into_samples::<f32>()),
             },
+            policy: DecodeErrorPolicy::default(),
+            errors: DecodeErrors::default(),
+            ended: false,
         })
     }
+    /// Set what happens when a sample fails to decode
+    ///
+    /// The default is [`DecodeErrorPolicy::End`]
+    pub fn on_error(self, policy: DecodeErrorPolicy) -> Self {
+        WavSource { policy, ..self }
+    }
+    /// Get a handle to the errors encountered while decoding
+    pub fn errors(&self) -> DecodeErrors<WaveError> {
+        self.errors.clone()
+    }
 }
 
 impl<R> Iterator for WavSource<R>
@@ -49,17 +79,35 @@ where
 {
     type Item = f64;
     fn next(&mut self) -> Option<Self::Item> {
-        Some(match &mut self.samples {
-            GenericWaveSamples::I16(samples) => {
-                samples.next()?.unwrap_or_else(|e| panic!("{e}")) as f64 / i16::MAX as f64
-            }
-            GenericWaveSamples::I32(samples) => {
-                samples.next()?.unwrap_or_else(|e| panic!("{e}")) as f64 / i32::MAX as f64
+        while !self.ended {
+            let sample = match &mut self.samples {
+                GenericWaveSamples::Int(samples, scale) => {
+                    samples.next()?.map(|s| s as f64 / *scale)
+                }
+                GenericWaveSamples::Float(samples) => samples.next()?.map(|s| s as f64),
+            };
+            match sample {
+                Ok(sample) => return Some(sample),
+                Err(e) => self.ended = !self.errors.report(e, self.policy),
             }
-            GenericWaveSamples::F32(samples) => {
-                samples.next()?.unwrap_or_else(|e| panic!("{e}")) as f64
-            }
-        })
+        }
+        None
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<R> ExactSizeIterator for WavSource<R>
+where
+    R: Read,
+{
+    fn len(&self) -> usize {
+        match &self.samples {
+            GenericWaveSamples::Int(samples, _) => samples.len(),
+            GenericWaveSamples::Float(samples) => samples.len(),
+        }
     }
 }
 
@@ -75,6 +123,19 @@ where
     }
 }
 
+/// Decode a WAV file into a gapless [`Loop`]
+///
+/// The file is decoded at the given sample rate.
+/// Use [`Loop::crossfade`] to smooth over the seam.
+pub fn loop_file<F: Frame>(
+    path: impl AsRef<std::path::Path>,
+    sample_rate: f64,
+) -> Result<Loop<F>, WaveError> {
+    let file = std::io::BufReader::new(std::fs::File::open(path)?);
+    let source = WavSource::new(file)?;
+    Ok(Loop::new(source.resample(), sample_rate))
+}
+
 /// Write a source to a WAV file
 pub fn write_source<W, S>(writer: W, mut source: S, sample_rate: u32) -> Result<(), WaveError>
 where
@@ -95,3 +156,123 @@ where
     }
     Ok(())
 }
+
+/// A WAV export running on background threads
+///
+/// Returned from [`render_to_wav`].
+#[derive(Debug)]
+pub struct RenderJob {
+    progress: Shared<f64>,
+    cancelled: Arc<AtomicBool>,
+    thread: thread::JoinHandle<Result<bool, WaveError>>,
+}
+
+impl RenderJob {
+    /// Get how much of the source has been written, from `0.0` to `1.0`
+    pub fn progress(&self) -> f64 {
+        self.progress.get()
+    }
+    /// Get a shared handle to the progress
+    ///
+    /// It can be read from anywhere, such as a UI thread drawing a progress bar.
+    pub fn shared_progress(&self) -> Shared<f64> {
+        self.progress.clone()
+    }
+    /// Stop the export and delete the partially written file
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+    /// Check if the export has finished, been cancelled, or failed
+    pub fn is_finished(&self) -> bool {
+        self.thread.is_finished()
+    }
+    /// Wait for the export to finish
+    ///
+    /// Returns `Ok(false)` if it was cancelled.
+    pub fn wait(self) -> Result<bool, WaveError> {
+        self.thread
+            .join()
+            .unwrap_or_else(|e| std::panic::resume_unwind(e))
+    }
+}
+
+/// The number of frames rendered at a time by [`render_to_wav`]
+const RENDER_CHUNK: usize = 4096;
+
+/// Render a source to a WAV file on background threads
+///
+/// One thread renders the source in chunks while another encodes and writes them,
+/// so a slow disk does not hold up rendering.
+/// The returned [`RenderJob`] reports progress and can cancel the export,
+/// which makes this a good fit for an app's export button.
+///
+/// The file is created before this function returns, so errors opening it are reported immediately.
+pub fn render_to_wav<S>(
+    path: impl AsRef<std::path::Path>,
+    mut source: S,
+    sample_rate: u32,
+) -> Result<RenderJob, WaveError>
+where
+    S: FiniteSource + Send + 'static,
+{
+    let path = path.as_ref().to_path_buf();
+    let channels = <S::Frame as Frame>::CHANNELS;
+    let spec = WavSpec {
+        channels: channels as u16,
+        sample_rate,
+        bits_per_sample: 32,
+        sample_format: SampleFormat::Float,
+    };
+    let mut writer = WavWriter::create(&path, spec)?;
+    let total = source.total(sample_rate as f64).as_secs_f64() * sample_rate as f64;
+    let progress = Shared::new(0.0);
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let (send, recv) = mpsc::sync_channel::<Vec<f32>>(4);
+    let render_cancelled = cancelled.clone();
+    thread::Builder::new()
+        .name("hodaun render".into())
+        .spawn(move || {
+            while !render_cancelled.load(Ordering::Relaxed) {
+                let mut chunk = Vec::with_capacity(RENDER_CHUNK * channels);
+                for frame in (0..RENDER_CHUNK).map_while(|_| source.next(sample_rate as f64)) {
+                    chunk.extend((0..channels).map(|i| frame.get_channel(i) as f32));
+                }
+                let ended = chunk.len() < RENDER_CHUNK * channels;
+                if send.send(chunk).is_err() || ended {
+                    break;
+                }
+            }
+        })?;
+    let write_progress = progress.clone();
+    let write_cancelled = cancelled.clone();
+    let thread = thread::Builder::new()
+        .name("hodaun export".into())
+        .spawn(move || {
+            let mut written = 0;
+            for chunk in recv {
+                if write_cancelled.load(Ordering::Relaxed) {
+                    break;
+                }
+                for sample in &chunk {
+                    writer.write_sample(*sample)?;
+                }
+                written += chunk.len() / channels;
+                if total > 0.0 {
+                    write_progress.set((written as f64 / total).min(1.0));
+                }
+            }
+            if write_cancelled.load(Ordering::Relaxed) {
+                drop(writer);
+                std::fs::remove_file(&path)?;
+                return Ok(false);
+            }
+            writer.finalize()?;
+            write_progress.set(1.0);
+            Ok(true)
+        })?;
+    Ok(RenderJob {
+        progress,
+        cancelled,
+        thread,
+    })
+}
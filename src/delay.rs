@@ -0,0 +1,91 @@
+use crate::{lerp, Frame, SourceState, StateReader};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// The sample rate a [`DelayLine`]'s history is allocated for unless another is given
+pub(crate) const DEFAULT_LINE_RATE: f64 = 48000.0;
+
+/// A delay line that can be read at fractional delays
+///
+/// Its history is a ring buffer that is allocated up front for a sample rate, never while playing.
+/// At higher sample rates, the longest delay is shortened to what the history can hold.
+#[derive(Debug, Clone)]
+pub(crate) struct DelayLine<F> {
+    frames: Vec<F>,
+    /// The index of the most recently pushed frame
+    pos: usize,
+    /// The longest delay that can be read, in seconds
+    max_delay: f64,
+    /// The sample rate the history was allocated for
+    rate: f64,
+}
+
+impl<F: Frame> DelayLine<F> {
+    /// Create a delay line that can be read up to `max_delay` seconds back at the given sample rate
+    pub fn new(max_delay: f64, sample_rate: f64) -> Self {
+        let max_delay = max_delay.max(0.0);
+        let len = (max_delay * sample_rate).ceil() as usize + 2;
+        DelayLine {
+            frames: vec![F::uniform(0.0); len],
+            pos: 0,
+            max_delay,
+            rate: sample_rate,
+        }
+    }
+    /// Get the longest delay that can be read, in frames
+    pub fn max_frames(&self, sample_rate: f64) -> f64 {
+        let held = (self.frames.len() - 2) as f64;
+        (self.max_delay * sample_rate).ceil().min(held)
+    }
+    /// Set the longest delay that can be read, in seconds
+    pub fn set_max_delay(&mut self, max_delay: f64) {
+        *self = DelayLine::new(max_delay, self.rate);
+    }
+    /// Set the sample rate the history is allocated for
+    pub fn set_sample_rate(&mut self, sample_rate: f64) {
+        *self = DelayLine::new(self.max_delay, sample_rate);
+    }
+    /// Push a frame
+    pub fn push(&mut self, frame: F) {
+        self.pos = (self.pos + 1) % self.frames.len();
+        self.frames[self.pos] = frame;
+    }
+    /// Read the frame from `delay` frames ago, interpolating between frames
+    ///
+    /// A delay of `0.0` is the most recently pushed frame.
+    /// Delays longer than the history are clamped to it.
+    /// Frames that have not been pushed yet are silent.
+    pub fn read(&self, delay: f64) -> F {
+        let len = self.frames.len();
+        let delay = delay.clamp(0.0, (len - 2) as f64);
+        let i = delay as usize;
+        let t = delay.fract();
+        let mut frame = self.frames[(self.pos + len - i) % len].clone();
+        if t > 0.0 {
+            let older = self.frames[(self.pos + len - i - 1) % len].clone();
+            frame.merge(older, |a, b| lerp(a, b, t));
+        }
+        frame
+    }
+    pub fn save(&self, state: &mut SourceState) {
+        state.push(self.pos as f64);
+        for frame in &self.frames {
+            for c in 0..F::CHANNELS {
+                state.push(frame.get_channel(c));
+            }
+        }
+    }
+    pub fn restore(&mut self, state: &mut StateReader) {
+        if let Some(pos) = state.read() {
+            self.pos = (pos as usize).min(self.frames.len() - 1);
+        }
+        for frame in &mut self.frames {
+            for c in 0..F::CHANNELS {
+                if let Some(amp) = state.read() {
+                    frame.set_channel(c, amp);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,504 @@
+use core::{f64::consts::FRAC_1_SQRT_2, time::Duration};
+
+use crate::{
+    filter::{Biquad, BiquadState},
+    lerp, Automation, FiniteSource, Frame, Source, SourceState, StateReader, StatefulSource,
+};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// A vowel sound for [`Source::formant`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Vowel {
+    /// The "a" in "father"
+    A,
+    /// The "e" in "bed"
+    E,
+    /// The "i" in "machine"
+    I,
+    /// The "o" in "more"
+    O,
+    /// The "u" in "rule"
+    U,
+}
+
+/// The number of formants used for each vowel
+const FORMANTS: usize = 3;
+
+impl Vowel {
+    /// Get the frequency, bandwidth, and gain of each of the vowel's formants
+    fn formants(self) -> [(f64, f64, f64); FORMANTS] {
+        match self {
+            Vowel::A => [
+                (800.0, 80.0, 1.0),
+                (1150.0, 90.0, 0.5),
+                (2900.0, 120.0, 0.025),
+            ],
+            Vowel::E => [
+                (350.0, 60.0, 1.0),
+                (2000.0, 100.0, 0.1),
+                (2800.0, 120.0, 0.18),
+            ],
+            Vowel::I => [
+                (270.0, 60.0, 1.0),
+                (2140.0, 90.0, 0.25),
+                (2950.0, 100.0, 0.05),
+            ],
+            Vowel::O => [
+                (450.0, 70.0, 1.0),
+                (800.0, 80.0, 0.28),
+                (2830.0, 100.0, 0.08),
+            ],
+            Vowel::U => [
+                (325.0, 50.0, 1.0),
+                (700.0, 60.0, 0.16),
+                (2700.0, 170.0, 0.018),
+            ],
+        }
+    }
+}
+
+/// Source returned from [`Source::formant`]
+#[derive(Debug, Clone)]
+pub struct Formant<S: Source, M> {
+    source: S,
+    vowels: Vec<Vowel>,
+    morph: M,
+    bands: [(Biquad, BiquadState<S::Frame>, f64); FORMANTS],
+    params: Option<(f64, f64)>,
+}
+
+impl<S, M> Formant<S, M>
+where
+    S: Source,
+{
+    pub(crate) fn new(source: S, vowels: impl IntoIterator<Item = Vowel>, morph: M) -> Self {
+        let mut vowels: Vec<Vowel> = vowels.into_iter().collect();
+        if vowels.is_empty() {
+            vowels.push(Vowel::A);
+        }
+        Formant {
+            source,
+            vowels,
+            morph,
+            // The filters are computed on the first frame
+            bands: core::array::from_fn(|_| {
+                (
+                    Biquad::band_pass(1000.0, 1.0, 44100.0),
+                    BiquadState::default(),
+                    0.0,
+                )
+            }),
+            params: None,
+        }
+    }
+}
+
+impl<S, M> Source for Formant<S, M>
+where
+    S: Source,
+    M: Automation,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let morph = self.morph.next_value(sample_rate)?;
+        let frame = self.source.next(sample_rate)?;
+        let morph = morph.clamp(0.0, (self.vowels.len() - 1) as f64);
+        if self.params != Some((morph, sample_rate)) {
+            self.params = Some((morph, sample_rate));
+            let i = morph as usize;
+            let t = morph.fract();
+            let a = self.vowels[i].formants();
+            let b = self.vowels[(i + 1).min(self.vowels.len() - 1)].formants();
+            for ((filter, _, gain), (a, b)) in self.bands.iter_mut().zip(a.into_iter().zip(b)) {
+                let freq = lerp(a.0, b.0, t);
+                let bandwidth = lerp(a.1, b.1, t);
+                *filter = Biquad::band_pass(freq, freq / bandwidth, sample_rate);
+                *gain = lerp(a.2, b.2, t);
+            }
+        }
+        let mut out = S::Frame::uniform(0.0);
+        for (filter, state, gain) in &mut self.bands {
+            let band = filter.process(state, frame.clone());
+            out.merge(band, |a, b| b.mul_add(*gain, a));
+        }
+        Some(out)
+    }
+}
+
+impl<S, M> FiniteSource for Formant<S, M>
+where
+    S: FiniteSource,
+    M: Automation,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+/// Source returned from [`Source::excite`]
+#[derive(Debug, Clone)]
+pub struct Exciter<S: Source, A> {
+    source: S,
+    amount: A,
+    crossover: f64,
+    drive: f64,
+    filters: Option<(Biquad, f64)>,
+    split: BiquadState<S::Frame>,
+    cleanup: BiquadState<S::Frame>,
+}
+
+impl<S, A> Exciter<S, A>
+where
+    S: Source,
+{
+    pub(crate) fn new(source: S, amount: A) -> Self {
+        Exciter {
+            source,
+            amount,
+            crossover: 3000.0,
+            drive: 4.0,
+            filters: None,
+            split: BiquadState::default(),
+            cleanup: BiquadState::default(),
+        }
+    }
+    /// Set the frequency above which harmonics are generated
+    ///
+    /// The default is 3000 Hz.
+    pub fn crossover(self, freq: f64) -> Self {
+        Exciter {
+            crossover: freq,
+            filters: None,
+            ..self
+        }
+    }
+    /// Set how hard the high frequencies are saturated
+    ///
+    /// Higher values generate more harmonics. The default is `4.0`.
+    pub fn drive(self, drive: f64) -> Self {
+        Exciter { drive, ..self }
+    }
+}
+
+impl<S, A> Source for Exciter<S, A>
+where
+    S: Source,
+    A: Automation,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let amount = self.amount.next_value(sample_rate)?;
+        let frame = self.source.next(sample_rate)?;
+        let filter = match self.filters {
+            Some((filter, rate)) if rate == sample_rate => filter,
+            _ => {
+                let filter = Biquad::high_pass(self.crossover, FRAC_1_SQRT_2, sample_rate);
+                self.filters = Some((filter, sample_rate));
+                filter
+            }
+        };
+        let drive = self.drive.max(1e-6);
+        let highs = filter.process(&mut self.split, frame.clone());
+        // Keep only what the saturation adds, so the phase-shifted highs do not interfere with the original
+        let added = highs.map(|a| (a * drive).tanh() / drive - a);
+        // Filter again to remove low intermodulation products
+        let harmonics = filter.process(&mut self.cleanup, added);
+        let mut out = frame;
+        out.merge(harmonics, |a, b| b.mul_add(amount, a));
+        Some(out)
+    }
+}
+
+impl<S, A> FiniteSource for Exciter<S, A>
+where
+    S: FiniteSource,
+    A: Automation,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+/// The sample rate [`Reverb`]'s delays are tuned for, and the one it is allocated for by default
+const REVERB_RATE: f64 = 44100.0;
+/// The comb filter delays of [`Reverb`] in frames at 44.1 kHz
+const REVERB_COMBS: [usize; 8] = [1116, 1188, 1277, 1356, 1422, 1491, 1557, 1617];
+/// The all-pass filter delays of [`Reverb`] in frames at 44.1 kHz
+const REVERB_ALLPASSES: [usize; 4] = [556, 441, 341, 225];
+/// How much longer each channel's delays are than the previous channel's, to decorrelate them
+const REVERB_SPREAD: usize = 23;
+/// The amplitude below which a [`Reverb`]'s tail is considered to have died out
+const REVERB_SILENCE: f64 = 1e-4;
+
+/// Source returned from [`Source::reverb`]
+///
+/// This is a Freeverb-style reverb: a bank of parallel damped comb filters
+/// followed by a series of all-pass filters. Each channel is reverberated separately,
+/// with slightly different delays so that stereo sources sound wide.
+///
+/// The filters are allocated when the reverb is created, never while it plays.
+/// They are sized for 44.1 kHz unless set with [`Reverb::sample_rate`].
+#[derive(Debug, Clone)]
+pub struct Reverb<S, R, D, M> {
+    /// The source, or [`None`] once it has ended
+    source: Option<S>,
+    room_size: R,
+    damping: D,
+    mix: M,
+    /// The filters for each channel
+    channels: Vec<ReverbChannel>,
+    /// The sample rate the filters were built for
+    rate: f64,
+    /// How many frames the tail has been silent since the source ended
+    silent: usize,
+}
+
+#[derive(Debug, Clone)]
+struct ReverbChannel {
+    combs: Vec<Comb>,
+    allpasses: Vec<Allpass>,
+}
+
+#[derive(Debug, Clone)]
+struct Comb {
+    buffer: Vec<f64>,
+    index: usize,
+    /// The state of the damping low-pass filter
+    store: f64,
+}
+
+#[derive(Debug, Clone)]
+struct Allpass {
+    buffer: Vec<f64>,
+    index: usize,
+}
+
+impl ReverbChannel {
+    fn new(channel: usize, sample_rate: f64) -> Self {
+        let len = |frames: usize| {
+            let frames = frames + channel * REVERB_SPREAD;
+            ((frames as f64 * sample_rate / REVERB_RATE).round() as usize).max(1)
+        };
+        ReverbChannel {
+            combs: (REVERB_COMBS.iter())
+                .map(|&frames| Comb {
+                    buffer: vec![0.0; len(frames)],
+                    index: 0,
+                    store: 0.0,
+                })
+                .collect(),
+            allpasses: (REVERB_ALLPASSES.iter())
+                .map(|&frames| Allpass {
+                    buffer: vec![0.0; len(frames)],
+                    index: 0,
+                })
+                .collect(),
+        }
+    }
+    fn process(&mut self, input: f64, feedback: f64, damp: f64) -> f64 {
+        // The input is scaled down so the parallel combs do not clip
+        let input = input * 0.015;
+        let mut out = 0.0;
+        for comb in &mut self.combs {
+            let delayed = comb.buffer[comb.index];
+            comb.store = delayed * (1.0 - damp) + comb.store * damp;
+            comb.buffer[comb.index] = input + comb.store * feedback;
+            comb.index = (comb.index + 1) % comb.buffer.len();
+            out += delayed;
+        }
+        for allpass in &mut self.allpasses {
+            let delayed = allpass.buffer[allpass.index];
+            allpass.buffer[allpass.index] = out + delayed * 0.5;
+            allpass.index = (allpass.index + 1) % allpass.buffer.len();
+            out = delayed - out;
+        }
+        // Restore the level lost to the input scaling
+        out * 3.0
+    }
+}
+
+impl<S: Source, R, D, M> Reverb<S, R, D, M> {
+    pub(crate) fn new(source: S, room_size: R, damping: D, mix: M) -> Self {
+        Reverb {
+            source: Some(source),
+            room_size,
+            damping,
+            mix,
+            channels: Self::build(REVERB_RATE),
+            rate: REVERB_RATE,
+            silent: 0,
+        }
+    }
+    fn build(sample_rate: f64) -> Vec<ReverbChannel> {
+        (0..S::Frame::CHANNELS)
+            .map(|i| ReverbChannel::new(i, sample_rate))
+            .collect()
+    }
+    /// Size the filters for the sample rate the reverb will be played at
+    ///
+    /// This allocates, so it is done here rather than while playing.
+    /// At other sample rates, the reverb still works, but sounds like a slightly different room.
+    pub fn sample_rate(self, sample_rate: f64) -> Self {
+        Reverb {
+            channels: Self::build(sample_rate),
+            rate: sample_rate,
+            ..self
+        }
+    }
+}
+
+impl<S, R, D, M> Source for Reverb<S, R, D, M>
+where
+    S: Source,
+    R: Automation,
+    D: Automation,
+    M: Automation,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let room_size = self.room_size.next_value(sample_rate)?.clamp(0.0, 1.0);
+        let damping = self.damping.next_value(sample_rate)?.clamp(0.0, 1.0);
+        let mix = self.mix.next_value(sample_rate)?;
+        let dry = match &mut self.source {
+            Some(source) => source.next(sample_rate),
+            None => None,
+        };
+        let ended = dry.is_none();
+        if ended {
+            self.source = None;
+        }
+        let dry = dry.unwrap_or_else(|| S::Frame::uniform(0.0));
+        let feedback = 0.7 + room_size * 0.28;
+        let damp = damping * 0.4;
+        let channels = &mut self.channels;
+        let wet = S::Frame::from_fn(|i| channels[i].process(dry.get_channel(i), feedback, damp));
+        if ended {
+            let loud = (0..S::Frame::CHANNELS).any(|i| wet.get_channel(i).abs() > REVERB_SILENCE);
+            self.silent = if loud { 0 } else { self.silent + 1 };
+            // Sound can hide in the longest comb filter for a whole pass
+            let longest = (REVERB_COMBS[REVERB_COMBS.len() - 1] as f64 * self.rate / REVERB_RATE)
+                as usize
+                + REVERB_SPREAD * S::Frame::CHANNELS;
+            if self.silent > longest {
+                return None;
+            }
+        }
+        let mut frame = dry;
+        frame.merge(wet, |d, w| lerp(d, w, mix));
+        Some(frame)
+    }
+}
+
+impl<S, R, D, M> StatefulSource for Reverb<S, R, D, M>
+where
+    S: StatefulSource,
+    R: Automation,
+    D: Automation,
+    M: Automation,
+{
+    fn save_state(&self, state: &mut SourceState) {
+        for channel in &self.channels {
+            for comb in &channel.combs {
+                state.push(comb.index as f64);
+                state.push(comb.store);
+                comb.buffer.iter().for_each(|&sample| state.push(sample));
+            }
+            for allpass in &channel.allpasses {
+                state.push(allpass.index as f64);
+                allpass.buffer.iter().for_each(|&sample| state.push(sample));
+            }
+        }
+        state.push(self.silent as f64);
+        state.push(self.source.is_none() as u8 as f64);
+        if let Some(source) = &self.source {
+            source.save_state(state);
+        }
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        let read_index = |state: &mut StateReader, len: usize| {
+            state
+                .read()
+                .map_or(0, |index| (index as usize).min(len - 1))
+        };
+        for channel in &mut self.channels {
+            for comb in &mut channel.combs {
+                comb.index = read_index(state, comb.buffer.len());
+                state.read_into(&mut comb.store);
+                comb.buffer
+                    .iter_mut()
+                    .for_each(|sample| state.read_into(sample));
+            }
+            for allpass in &mut channel.allpasses {
+                allpass.index = read_index(state, allpass.buffer.len());
+                allpass
+                    .buffer
+                    .iter_mut()
+                    .for_each(|sample| state.read_into(sample));
+            }
+        }
+        if let Some(silent) = state.read() {
+            self.silent = silent as usize;
+        }
+        if state.read().is_some_and(|ended| ended != 0.0) {
+            self.source = None;
+        } else if let Some(source) = &mut self.source {
+            source.restore_state(state);
+        }
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{gen::Impulse, test_util::*, SineWave};
+
+    const RATE: f64 = 44100.0;
+
+    /// Get the ratio of the energy above 4 kHz to the energy below 1 kHz in a reverb's tail
+    fn brightness(damping: f64) -> f64 {
+        let tail = Impulse::new(0.01).reverb(0.8, damping, 1.0);
+        let spectrum = spectrum(tail, RATE, 1 << 15);
+        let bin = |freq: f64| (freq * (spectrum.len() - 1) as f64 * 2.0 / RATE) as usize;
+        let energy = |bins: &[f64]| bins.iter().map(|m| m * m).sum::<f64>();
+        energy(&spectrum[bin(4000.0)..]) / energy(&spectrum[..bin(1000.0)])
+    }
+
+    #[test]
+    fn reverb_is_silent_until_the_shortest_comb() {
+        let frames = collect_frames(Impulse::new(0.01).reverb(0.5, 0.5, 1.0), RATE);
+        let first = frames.iter().position(|a| a.abs() > 1e-9);
+        assert_eq!(first, Some(REVERB_COMBS[0]));
+    }
+
+    #[test]
+    fn reverb_damping_darkens_the_tail() {
+        assert!(brightness(1.0) < brightness(0.0) * 0.5);
+    }
+
+    #[test]
+    fn reverb_tail_dies_out() {
+        let frames = collect_frames(SineWave::new(440.0).take(0.1).reverb(0.5, 0.5, 0.5), RATE);
+        assert!(frames.len() > (0.1 * RATE) as usize + REVERB_COMBS[0]);
+        assert!(frames.len() < 10 * RATE as usize);
+        let end = &frames[frames.len() - 100..];
+        assert!(end.iter().all(|a| a.abs() < 0.01));
+    }
+
+    #[test]
+    fn reverb_dry_mix_passes_the_source() {
+        let source = SineWave::new(440.0).take(0.1);
+        let expected = collect_frames(source, RATE);
+        let frames = collect_frames(source.reverb(0.5, 0.5, 0.0), RATE);
+        let (dry, tail) = frames.split_at(expected.len());
+        assert_eq!(dry, expected);
+        assert!(tail.iter().all(|&a| a == 0.0));
+    }
+}
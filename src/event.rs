@@ -0,0 +1,246 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Weak,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    sync::{ring, Mutex, RingReceiver, RingSender},
+    FiniteSource, Mixer, Source, ToDuration,
+};
+
+/// The number of events each [`EventSender`] can hold before they are received,
+/// when created with [`Mixer::events`]
+pub const DEFAULT_EVENT_CAPACITY: usize = 1024;
+
+/// An event emitted by a source while it was playing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Event<E> {
+    /// The index of the mixer frame during which the event was emitted
+    pub frame: u64,
+    /// The event value
+    pub value: E,
+}
+
+impl<E> Event<E> {
+    /// Get the time at which the event was emitted, relative to when the mixer started
+    pub fn time(&self, sample_rate: f64) -> Duration {
+        Duration::from_secs_f64(self.frame as f64 / sample_rate)
+    }
+}
+
+/// The receiving ends of every [`EventSender`]'s queue
+type Queues<E> = Mutex<Vec<RingReceiver<Event<E>>>>;
+
+/// Emits [`Event`]s timestamped with a [`Mixer`]'s current frame
+///
+/// Created with [`Mixer::events`].
+/// Sources playing in the mixer can hold an emitter and call [`EventSender::emit`]
+/// from [`Source::next`], which stamps the event with the exact frame being mixed.
+///
+/// Each sender has its own fixed-capacity queue, so emitting never blocks or allocates.
+/// Cloning a sender allocates a new queue, so it should be done before the sender is played.
+pub struct EventSender<E> {
+    clock: Arc<AtomicU64>,
+    send: RingSender<Event<E>>,
+    capacity: usize,
+    queues: Weak<Queues<E>>,
+}
+
+impl<E> fmt::Debug for EventSender<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EventSender")
+            .field("capacity", &self.capacity)
+            .field("pending", &self.send.len())
+            .finish()
+    }
+}
+
+impl<E> EventSender<E> {
+    fn new(clock: Arc<AtomicU64>, capacity: usize, queues: &Arc<Queues<E>>) -> Self {
+        let (send, recv) = ring(capacity);
+        queues.lock().push(recv);
+        EventSender {
+            clock,
+            send,
+            capacity,
+            queues: Arc::downgrade(queues),
+        }
+    }
+    /// Emit an event
+    ///
+    /// Returns `false` if the [`EventReceiver`] has been dropped,
+    /// or if this sender's queue is full because events are not being received quickly enough.
+    /// In either case, the event is dropped.
+    pub fn emit(&mut self, value: E) -> bool {
+        if self.send.is_closed() {
+            return false;
+        }
+        let frame = self.clock.load(Ordering::Relaxed);
+        self.send.push(Event { frame, value }).is_ok()
+    }
+}
+
+impl<E> Clone for EventSender<E> {
+    fn clone(&self) -> Self {
+        match self.queues.upgrade() {
+            Some(queues) => EventSender::new(self.clock.clone(), self.capacity, &queues),
+            // The receiver has been dropped, so the new sender is closed too
+            None => EventSender {
+                clock: self.clock.clone(),
+                send: ring(1).0,
+                capacity: self.capacity,
+                queues: Weak::new(),
+            },
+        }
+    }
+}
+
+/// Receives [`Event`]s emitted by sources
+///
+/// Created with [`Mixer::events`].
+/// Events that have been emitted by the time they are received are delivered in frame order,
+/// even if they come from different [`EventSender`]s.
+pub struct EventReceiver<E> {
+    queues: Arc<Queues<E>>,
+    pending: Mutex<VecDeque<Event<E>>>,
+}
+
+impl<E> fmt::Debug for EventReceiver<E> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("EventReceiver")
+            .field("senders", &self.queues.lock().len())
+            .finish()
+    }
+}
+
+impl<E> EventReceiver<E> {
+    /// Move emitted events into the pending queue, returning whether any sender is still alive
+    fn collect(&self) -> bool {
+        let mut queues = self.queues.lock();
+        let mut pending = self.pending.lock();
+        let start = pending.len();
+        for queue in queues.iter_mut() {
+            while let Some(event) = queue.pop() {
+                pending.push_back(event);
+            }
+        }
+        if pending.len() > start {
+            pending.make_contiguous()[start..].sort_by_key(|event| event.frame);
+        }
+        queues.retain(|queue| !queue.is_closed() || queue.len() > 0);
+        !queues.is_empty()
+    }
+    /// Take the next event if there is one
+    pub fn try_next(&self) -> Option<Event<E>> {
+        if let Some(event) = self.pending.lock().pop_front() {
+            return Some(event);
+        }
+        self.collect();
+        self.pending.lock().pop_front()
+    }
+    /// Iterate over all events that have been emitted so far
+    pub fn drain(&self) -> impl Iterator<Item = Event<E>> + '_ {
+        self.collect();
+        let events: Vec<_> = self.pending.lock().drain(..).collect();
+        events.into_iter()
+    }
+    /// Block the thread until the next event is emitted
+    ///
+    /// This polls the senders' queues every millisecond.
+    /// Returns [`None`] if all [`EventSender`]s have been dropped.
+    pub fn wait(&self) -> Option<Event<E>> {
+        loop {
+            if let Some(event) = self.pending.lock().pop_front() {
+                return Some(event);
+            }
+            let open = self.collect();
+            if let Some(event) = self.pending.lock().pop_front() {
+                return Some(event);
+            }
+            if !open {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+}
+
+impl<F> Mixer<F> {
+    /// Create a channel for events timestamped with this mixer's frames
+    ///
+    /// Each [`EventSender`] can hold [`DEFAULT_EVENT_CAPACITY`] events before they are received.
+    pub fn events<E>(&self) -> (EventSender<E>, EventReceiver<E>) {
+        self.events_with_capacity(DEFAULT_EVENT_CAPACITY)
+    }
+    /// Create a channel for events timestamped with this mixer's frames,
+    /// where each [`EventSender`] can hold `capacity` events before they are received
+    pub fn events_with_capacity<E>(&self, capacity: usize) -> (EventSender<E>, EventReceiver<E>) {
+        let queues = Arc::new(Mutex::new(Vec::new()));
+        let send = EventSender::new(self.clock.clone(), capacity, &queues);
+        let recv = EventReceiver {
+            queues,
+            pending: Mutex::new(VecDeque::new()),
+        };
+        (send, recv)
+    }
+    /// Get the number of frames the mixer has produced
+    pub fn frames(&self) -> u64 {
+        self.clock.load(Ordering::Relaxed)
+    }
+}
+
+/// Source returned from [`Source::cue`]
+pub struct Cue<S, E> {
+    source: S,
+    at: f64,
+    frames: u64,
+    events: EventSender<E>,
+    event: Option<E>,
+}
+
+impl<S, E> Source for Cue<S, E>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let frame = self.source.next(sample_rate)?;
+        if self.frames as f64 / sample_rate >= self.at {
+            if let Some(event) = self.event.take() {
+                self.events.emit(event);
+            }
+        }
+        self.frames += 1;
+        Some(frame)
+    }
+}
+
+impl<S, E> Cue<S, E> {
+    pub(crate) fn new(source: S, at: impl ToDuration, events: &EventSender<E>, event: E) -> Self {
+        Cue {
+            source,
+            at: at.to_duration().as_secs_f64(),
+            frames: 0,
+            events: events.clone(),
+            event: Some(event),
+        }
+    }
+}
+
+impl<S, E> FiniteSource for Cue<S, E>
+where
+    S: FiniteSource,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
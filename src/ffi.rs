@@ -0,0 +1,247 @@
+//! A C API for embedding hodaun in non-Rust hosts
+//!
+//! The API covers the basics of playing sounds from another language:
+//! opening the default output, loading buffers of audio, playing them as one-shots,
+//! and controlling their gain and pan with shared parameters.
+//!
+//! Every object is created and destroyed through this API and is passed around as an opaque pointer.
+//! Objects must only be freed once, with the matching `_free` function.
+//! Passing a null pointer to a `_free` function does nothing.
+//!
+//! Functions that can fail return null, and the reason can be read with [`hodaun_last_error`].
+//!
+//! To build a C library, make a crate with `crate-type = ["cdylib"]` or `["staticlib"]`
+//! that depends on hodaun with this feature enabled.
+//! The functions are exported unmangled with a `hodaun_` prefix,
+//! and are declared for C and C++ in the `include/hodaun.h` header in the repository.
+//!
+//! This module is only available when the `ffi` feature is enabled.
+
+use std::{cell::RefCell, ffi::CString, os::raw::c_char, ptr};
+
+use crate::{default_output, Frame, OutputDeviceMixer, Sample, Shared, Source, Stereo};
+
+/// An output device that mixes sounds in stereo
+pub struct HodaunOutput(OutputDeviceMixer<Stereo>);
+
+/// A buffer of stereo audio that can be played any number of times
+pub struct HodaunBuffer(Sample<Stereo>);
+
+/// A parameter that can be changed from any thread while sounds that use it play
+pub struct HodaunParam(Shared<f64>);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+/// Get a message describing the last error that occurred on this thread
+///
+/// Returns null if there has been no error.
+/// The string is owned by hodaun and is valid until the next error on this thread.
+#[no_mangle]
+pub extern "C" fn hodaun_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| (error.borrow().as_ref()).map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Open the default output device and start it playing
+///
+/// Returns null if the device could not be opened.
+#[no_mangle]
+pub extern "C" fn hodaun_output_new() -> *mut HodaunOutput {
+    match default_output() {
+        Ok(output) => Box::into_raw(Box::new(HodaunOutput(output))),
+        Err(e) => {
+            set_last_error(format!("failed to open output: {e}"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Stop and close an output
+///
+/// # Safety
+/// `output` must be null or a pointer returned from [`hodaun_output_new`] that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn hodaun_output_free(output: *mut HodaunOutput) {
+    if !output.is_null() {
+        drop(Box::from_raw(output));
+    }
+}
+
+/// Get the sample rate of an output
+///
+/// # Safety
+/// `output` must be a valid pointer returned from [`hodaun_output_new`].
+#[no_mangle]
+pub unsafe extern "C" fn hodaun_output_sample_rate(output: *const HodaunOutput) -> f64 {
+    (*output).0.sample_rate()
+}
+
+/// Create a buffer by copying interleaved samples
+///
+/// `frames` is the number of frames, so `samples` must point to `frames * channels` samples.
+/// Mono buffers are played on both channels, and channels past the second are ignored.
+/// Returns null if `channels` is zero, if `samples` is null, or if `frames * channels` is too large.
+///
+/// # Safety
+/// `samples` must point to at least `frames * channels` readable floats.
+#[no_mangle]
+pub unsafe extern "C" fn hodaun_buffer_new(
+    samples: *const f32,
+    frames: usize,
+    channels: u32,
+    sample_rate: f64,
+) -> *mut HodaunBuffer {
+    let channels = channels as usize;
+    if channels == 0 {
+        set_last_error("buffer has zero channels".into());
+        return ptr::null_mut();
+    }
+    // A slice cannot be larger than `isize::MAX` bytes
+    let Some(len) = (frames.checked_mul(channels))
+        .filter(|&len| len <= isize::MAX as usize / std::mem::size_of::<f32>())
+    else {
+        set_last_error(format!(
+            "buffer of {frames} frames of {channels} channels is too large"
+        ));
+        return ptr::null_mut();
+    };
+    let samples: &[f32] = if len == 0 {
+        &[]
+    } else if samples.is_null() {
+        set_last_error("buffer samples are null".into());
+        return ptr::null_mut();
+    } else {
+        std::slice::from_raw_parts(samples, len)
+    };
+    let frames: Vec<Stereo> = (samples.chunks_exact(channels))
+        .map(|frame| Stereo::from_fn(|i| frame[i.min(channels - 1)] as f64))
+        .collect();
+    let sample = Sample::from_frames(frames, sample_rate);
+    Box::into_raw(Box::new(HodaunBuffer(sample)))
+}
+
+/// Free a buffer
+///
+/// Sounds already playing from the buffer keep playing.
+///
+/// # Safety
+/// `buffer` must be null or a pointer returned from [`hodaun_buffer_new`] that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn hodaun_buffer_free(buffer: *mut HodaunBuffer) {
+    if !buffer.is_null() {
+        drop(Box::from_raw(buffer));
+    }
+}
+
+/// Get the length of a buffer in seconds
+///
+/// # Safety
+/// `buffer` must be a valid pointer returned from [`hodaun_buffer_new`].
+#[no_mangle]
+pub unsafe extern "C" fn hodaun_buffer_duration(buffer: *const HodaunBuffer) -> f64 {
+    (*buffer).0.duration().as_secs_f64()
+}
+
+/// Create a parameter with an initial value
+#[no_mangle]
+pub extern "C" fn hodaun_param_new(value: f64) -> *mut HodaunParam {
+    Box::into_raw(Box::new(HodaunParam(Shared::new(value))))
+}
+
+/// Set the value of a parameter
+///
+/// # Safety
+/// `param` must be a valid pointer returned from [`hodaun_param_new`].
+#[no_mangle]
+pub unsafe extern "C" fn hodaun_param_set(param: *const HodaunParam, value: f64) {
+    (*param).0.set(value);
+}
+
+/// Get the value of a parameter
+///
+/// # Safety
+/// `param` must be a valid pointer returned from [`hodaun_param_new`].
+#[no_mangle]
+pub unsafe extern "C" fn hodaun_param_get(param: *const HodaunParam) -> f64 {
+    (*param).0.get()
+}
+
+/// Free a parameter
+///
+/// Sounds already using the parameter keep its last value.
+///
+/// # Safety
+/// `param` must be null or a pointer returned from [`hodaun_param_new`] that has not been freed.
+#[no_mangle]
+pub unsafe extern "C" fn hodaun_param_free(param: *mut HodaunParam) {
+    if !param.is_null() {
+        drop(Box::from_raw(param));
+    }
+}
+
+/// Play a buffer once on an output
+///
+/// `gain` may be null, in which case the gain is `1.0`.
+/// `pan` may be null, in which case the buffer plays unpanned.
+/// Panned buffers are mixed to mono first. Pan goes from `-1.0` for left to `1.0` for right.
+/// Changes to the parameters are heard while the sound plays.
+///
+/// # Safety
+/// `output` and `buffer` must be valid pointers from this API.
+/// `gain` and `pan` must each be null or a valid pointer from [`hodaun_param_new`].
+#[no_mangle]
+pub unsafe extern "C" fn hodaun_play_oneshot(
+    output: *const HodaunOutput,
+    buffer: *const HodaunBuffer,
+    gain: *const HodaunParam,
+    pan: *const HodaunParam,
+) {
+    let gain = (gain.as_ref()).map_or_else(|| Shared::new(1.0), |gain| gain.0.clone());
+    let voice = (*buffer).0.play().amplify(gain);
+    if let Some(pan) = pan.as_ref() {
+        (*output).0.add(voice.pan(pan.0.clone()));
+    } else {
+        (*output).0.add(voice);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::ffi::CStr;
+
+    #[test]
+    fn header_declares_every_function() {
+        let header = include_str!("../include/hodaun.h");
+        let source = include_str!("ffi.rs");
+        let exported = (source.lines())
+            .filter_map(|line| line.split("extern \"C\" fn ").nth(1))
+            .map(|rest| &rest[..rest.find('(').unwrap()]);
+        for name in exported {
+            assert!(
+                header.contains(&format!(" *{name}(")) || header.contains(&format!(" {name}(")),
+                "{name} is not declared in hodaun.h"
+            );
+        }
+    }
+
+    #[test]
+    fn buffer_new_rejects_overflowing_lengths() {
+        let samples = [0.0f32; 4];
+        let buffer = unsafe { hodaun_buffer_new(samples.as_ptr(), usize::MAX / 2 + 1, 2, 48000.0) };
+        assert!(buffer.is_null());
+        let error = unsafe { CStr::from_ptr(hodaun_last_error()) };
+        assert!(error.to_str().unwrap().contains("too large"));
+        let buffer = unsafe { hodaun_buffer_new(ptr::null(), 4, 1, 48000.0) };
+        assert!(buffer.is_null());
+        let buffer = unsafe { hodaun_buffer_new(samples.as_ptr(), 2, 2, 48000.0) };
+        assert_eq!(unsafe { hodaun_buffer_duration(buffer) }, 2.0 / 48000.0);
+        unsafe { hodaun_buffer_free(buffer) };
+    }
+}
@@ -0,0 +1,264 @@
+use core::f64::consts::TAU;
+
+use crate::{Frame, SourceState, StateReader};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// The smallest q a biquad filter is designed with, since smaller values are unstable
+const MIN_Q: f64 = 1e-3;
+
+/// Coefficients for a biquad filter
+///
+/// Formulas are from the RBJ Audio EQ Cookbook.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+}
+
+impl Biquad {
+    fn normalized(b0: f64, b1: f64, b2: f64, a0: f64, a1: f64, a2: f64) -> Self {
+        Biquad {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: a1 / a0,
+            a2: a2 / a0,
+        }
+    }
+    /// A second-order low-pass filter
+    pub fn low_pass(freq: f64, q: f64, sample_rate: f64) -> Self {
+        let w0 = TAU * (freq / sample_rate).clamp(1e-6, 0.499);
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q.max(MIN_Q));
+        Biquad::normalized(
+            (1.0 - cos) / 2.0,
+            1.0 - cos,
+            (1.0 - cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+    /// A second-order high-pass filter
+    pub fn high_pass(freq: f64, q: f64, sample_rate: f64) -> Self {
+        let w0 = TAU * (freq / sample_rate).clamp(1e-6, 0.499);
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q.max(MIN_Q));
+        Biquad::normalized(
+            (1.0 + cos) / 2.0,
+            -(1.0 + cos),
+            (1.0 + cos) / 2.0,
+            1.0 + alpha,
+            -2.0 * cos,
+            1.0 - alpha,
+        )
+    }
+    /// A second-order band-pass filter with a peak gain of 0 dB
+    pub fn band_pass(freq: f64, q: f64, sample_rate: f64) -> Self {
+        let w0 = TAU * (freq / sample_rate).clamp(1e-6, 0.499);
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q.max(MIN_Q));
+        Biquad::normalized(alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+    /// A second-order notch filter
+    pub fn notch(freq: f64, q: f64, sample_rate: f64) -> Self {
+        let w0 = TAU * (freq / sample_rate).clamp(1e-6, 0.499);
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q.max(MIN_Q));
+        Biquad::normalized(1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha)
+    }
+    /// The one-pole low-pass filter used by [`Source::low_pass`](crate::Source::low_pass)
+    pub fn one_pole_low_pass(freq: f64, sample_rate: f64) -> Self {
+        let t = (freq / sample_rate).clamp(0.0, 1.0);
+        Biquad::normalized(t, 0.0, 0.0, 1.0, t - 1.0, 0.0)
+    }
+    /// Get the complex response at a frequency as `(re, im)`
+    pub fn response(&self, freq: f64, sample_rate: f64) -> (f64, f64) {
+        let w = TAU * freq / sample_rate;
+        let (sin1, cos1) = w.sin_cos();
+        let (sin2, cos2) = (2.0 * w).sin_cos();
+        // Evaluate both polynomials at z = e^(jw), where z^-k = cos(kw) - j sin(kw)
+        let num = (
+            self.b0 + self.b1 * cos1 + self.b2 * cos2,
+            -self.b1 * sin1 - self.b2 * sin2,
+        );
+        let den = (
+            1.0 + self.a1 * cos1 + self.a2 * cos2,
+            -self.a1 * sin1 - self.a2 * sin2,
+        );
+        let den_power = den.0 * den.0 + den.1 * den.1;
+        (
+            (num.0 * den.0 + num.1 * den.1) / den_power,
+            (num.1 * den.0 - num.0 * den.1) / den_power,
+        )
+    }
+    /// Filter a single frame
+    pub fn process<F: Frame>(&self, state: &mut BiquadState<F>, x: F) -> F {
+        let mut y = x.clone();
+        for c in 0..F::CHANNELS {
+            let xc = x.get_channel(c);
+            let yc = self.b0 * xc + state.z1.get_channel(c);
+            state
+                .z1
+                .set_channel(c, self.b1 * xc - self.a1 * yc + state.z2.get_channel(c));
+            state.z2.set_channel(c, self.b2 * xc - self.a2 * yc);
+            y.set_channel(c, yc);
+        }
+        y
+    }
+}
+
+/// The state of a single biquad filter
+#[derive(Debug, Clone)]
+pub(crate) struct BiquadState<F> {
+    z1: F,
+    z2: F,
+}
+
+impl<F: Frame> Default for BiquadState<F> {
+    fn default() -> Self {
+        BiquadState {
+            z1: F::uniform(0.0),
+            z2: F::uniform(0.0),
+        }
+    }
+}
+
+impl<F: Frame> BiquadState<F> {
+    pub fn save(&self, state: &mut SourceState) {
+        for c in 0..F::CHANNELS {
+            state.push(self.z1.get_channel(c));
+            state.push(self.z2.get_channel(c));
+        }
+    }
+    pub fn restore(&mut self, state: &mut StateReader) {
+        for c in 0..F::CHANNELS {
+            if let Some(z1) = state.read() {
+                self.z1.set_channel(c, z1);
+            }
+            if let Some(z2) = state.read() {
+                self.z2.set_channel(c, z2);
+            }
+        }
+    }
+}
+
+/// The frequency response of a chain of filters
+///
+/// This evaluates the same filters the audio path uses, so UIs can draw curves that match what is heard.
+///
+/// # Example
+/// ```
+/// use hodaun::*;
+///
+/// let response = FilterResponse::new(44100.0).high_pass(100.0, 0.707).low_pass(5000.0, 0.707);
+/// let curve = response.evaluate(FilterResponse::log_grid(20.0, 20000.0, 256));
+/// assert!(response.magnitude_db(1000.0).abs() < 0.5);
+/// assert!(response.magnitude_db(20.0) < -20.0);
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterResponse {
+    sample_rate: f64,
+    stages: Vec<Biquad>,
+}
+
+/// A point in a [`FilterResponse`]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ResponsePoint {
+    /// The frequency in Hz
+    pub freq: f64,
+    /// The gain in decibels
+    pub magnitude_db: f64,
+    /// The phase shift in radians, from -π to π
+    pub phase: f64,
+}
+
+impl FilterResponse {
+    /// Create a new response with no filters at a sample rate
+    ///
+    /// With no filters, the response is flat.
+    pub fn new(sample_rate: f64) -> Self {
+        FilterResponse {
+            sample_rate,
+            stages: Vec::new(),
+        }
+    }
+    /// Add the one-pole low-pass filter used by [`Source::low_pass`](crate::Source::low_pass)
+    pub fn one_pole_low_pass(mut self, freq: f64) -> Self {
+        (self.stages).push(Biquad::one_pole_low_pass(freq, self.sample_rate));
+        self
+    }
+    /// Add a second-order low-pass filter
+    pub fn low_pass(mut self, freq: f64, q: f64) -> Self {
+        (self.stages).push(Biquad::low_pass(freq, q, self.sample_rate));
+        self
+    }
+    /// Add a second-order high-pass filter
+    pub fn high_pass(mut self, freq: f64, q: f64) -> Self {
+        (self.stages).push(Biquad::high_pass(freq, q, self.sample_rate));
+        self
+    }
+    /// Add a second-order band-pass filter with a peak gain of 0 dB
+    pub fn band_pass(mut self, freq: f64, q: f64) -> Self {
+        (self.stages).push(Biquad::band_pass(freq, q, self.sample_rate));
+        self
+    }
+    /// Add a second-order notch filter
+    pub fn notch(mut self, freq: f64, q: f64) -> Self {
+        (self.stages).push(Biquad::notch(freq, q, self.sample_rate));
+        self
+    }
+    /// Get the sample rate
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+    /// Get the complex response at a frequency as `(re, im)`
+    fn response(&self, freq: f64) -> (f64, f64) {
+        self.stages.iter().fold((1.0, 0.0), |(re, im), stage| {
+            let (s_re, s_im) = stage.response(freq, self.sample_rate);
+            (re * s_re - im * s_im, re * s_im + im * s_re)
+        })
+    }
+    /// Get the linear gain at a frequency
+    pub fn magnitude(&self, freq: f64) -> f64 {
+        let (re, im) = self.response(freq);
+        re.hypot(im)
+    }
+    /// Get the gain at a frequency in decibels
+    pub fn magnitude_db(&self, freq: f64) -> f64 {
+        20.0 * self.magnitude(freq).log10()
+    }
+    /// Get the phase shift at a frequency in radians, from -π to π
+    pub fn phase(&self, freq: f64) -> f64 {
+        let (re, im) = self.response(freq);
+        im.atan2(re)
+    }
+    /// Evaluate the response at each frequency
+    pub fn evaluate(&self, freqs: impl IntoIterator<Item = f64>) -> Vec<ResponsePoint> {
+        (freqs.into_iter())
+            .map(|freq| {
+                let (re, im) = self.response(freq);
+                ResponsePoint {
+                    freq,
+                    magnitude_db: 20.0 * re.hypot(im).log10(),
+                    phase: im.atan2(re),
+                }
+            })
+            .collect()
+    }
+    /// Get `points` frequencies spaced logarithmically from `low` to `high` inclusive
+    ///
+    /// This spacing matches how frequency axes are usually drawn.
+    pub fn log_grid(low: f64, high: f64, points: usize) -> Vec<f64> {
+        let ratio = high / low;
+        let last = points.saturating_sub(1).max(1) as f64;
+        (0..points)
+            .map(|i| low * ratio.powf(i as f64 / last))
+            .collect()
+    }
+}
@@ -0,0 +1,298 @@
+//! Fixed-point processing for targets without fast floating point
+//!
+//! Samples are [`Q31`] numbers, which represent the range [-1, 1) in an [`i32`].
+//! A [`FixedSource`] produces them using only integer math,
+//! so it runs quickly on microcontrollers without a floating point unit.
+//!
+//! Unlike a [`Source`], a fixed-point source is created for a specific sample rate,
+//! so that per-sample work like phase increments can be computed ahead of time.
+//! Floating point is only used when a source is created.
+//!
+//! [`FixedSource::into_source`] converts a fixed-point source to a normal [`Source`],
+//! so it can be mixed with and processed by the rest of the crate.
+//!
+//! This module is only available when the `fixed` feature is enabled.
+//!
+//! # Example
+//! ```
+//! use hodaun::fixed::*;
+//!
+//! let mut source = FixedWave::new(FixedShape::Saw, 440.0, 48000)
+//!     .low_pass(2000.0, 48000)
+//!     .amplify(Q31::from_f64(0.5));
+//! let sample: Q31 = source.next().unwrap();
+//! assert!(sample.to_f64().abs() <= 0.5);
+//! ```
+
+use core::ops::{Add, Mul, Neg, Sub};
+
+use crate::{Mono, Source};
+
+/// A fixed-point number with 31 fractional bits
+///
+/// It represents values in the range [-1, 1).
+/// Arithmetic saturates rather than wrapping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Q31(pub i32);
+
+impl Q31 {
+    /// Zero
+    pub const ZERO: Self = Q31(0);
+    /// The largest value, just below `1.0`
+    pub const MAX: Self = Q31(i32::MAX);
+    /// The smallest value, `-1.0`
+    pub const MIN: Self = Q31(i32::MIN);
+    /// Convert from a float, saturating values outside the range
+    pub fn from_f64(f: f64) -> Self {
+        Q31((f * 2147483648.0) as i32)
+    }
+    /// Convert to a float
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 / 2147483648.0
+    }
+}
+
+impl Add for Q31 {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Q31(self.0.saturating_add(other.0))
+    }
+}
+
+impl Sub for Q31 {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Q31(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Mul for Q31 {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        // -1 * -1 is the only product that does not fit
+        Q31(((self.0 as i64 * other.0 as i64) >> 31).min(i32::MAX as i64) as i32)
+    }
+}
+
+impl Neg for Q31 {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Q31(self.0.saturating_neg())
+    }
+}
+
+/// A fixed-point audio source
+///
+/// See the [module documentation](self) for how it differs from a [`Source`].
+pub trait FixedSource {
+    /// Get the next sample
+    ///
+    /// Returning [`None`] indicates the source has no samples left
+    fn next(&mut self) -> Option<Q31>;
+    /// Amplify the source by some multiplier
+    fn amplify(self, gain: Q31) -> FixedAmplify<Self>
+    where
+        Self: Sized,
+    {
+        FixedAmplify { source: self, gain }
+    }
+    /// Mix the source with another
+    ///
+    /// The mixed source ends when both sources have ended.
+    fn mix<B>(self, other: B) -> FixedMix<Self, B>
+    where
+        Self: Sized,
+        B: FixedSource,
+    {
+        FixedMix { a: self, b: other }
+    }
+    /// Apply a one-pole low-pass filter
+    ///
+    /// This is the same filter as [`Source::low_pass`].
+    fn low_pass(self, freq: f64, sample_rate: u32) -> FixedLowPass<Self>
+    where
+        Self: Sized,
+    {
+        FixedLowPass {
+            source: self,
+            coef: Q31::from_f64((freq / sample_rate as f64).min(1.0)),
+            acc: None,
+        }
+    }
+    /// Convert to a [`Source`] of floating point samples
+    ///
+    /// The sample rate passed to the returned source is ignored,
+    /// so it should be played at the sample rate the fixed-point source was created for.
+    fn into_source(self) -> IntoSource<Self>
+    where
+        Self: Sized,
+    {
+        IntoSource(self)
+    }
+}
+
+/// A waveform for a [`FixedWave`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum FixedShape {
+    /// A sine wave
+    #[default]
+    Sine,
+    /// A square wave
+    Square,
+    /// A sawtooth wave, rising from `-1.0` to `1.0`
+    Saw,
+    /// A triangle wave
+    Triangle,
+}
+
+/// A fixed-point oscillator
+///
+/// The phase is a 32-bit accumulator that wraps once per cycle.
+/// Sine waves use a polynomial approximation that is accurate to about 0.1%.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct FixedWave {
+    shape: FixedShape,
+    phase: u32,
+    increment: u32,
+}
+
+impl FixedWave {
+    /// Create a new oscillator at a frequency for a sample rate
+    pub fn new(shape: FixedShape, freq: f64, sample_rate: u32) -> Self {
+        FixedWave {
+            shape,
+            phase: 0,
+            increment: Self::increment(freq, sample_rate),
+        }
+    }
+    /// Get the phase increment per sample for a frequency
+    ///
+    /// Precomputed increments can be passed to [`FixedWave::set_increment`]
+    /// to change the frequency without floating point.
+    pub fn increment(freq: f64, sample_rate: u32) -> u32 {
+        (freq / sample_rate as f64 * 4294967296.0) as u64 as u32
+    }
+    /// Set the phase increment per sample
+    pub fn set_increment(&mut self, increment: u32) {
+        self.increment = increment;
+    }
+    /// Set the frequency
+    pub fn set_freq(&mut self, freq: f64, sample_rate: u32) {
+        self.increment = Self::increment(freq, sample_rate);
+    }
+}
+
+/// Approximate the sine of a phase where the full range of a [`u32`] is one cycle
+fn sine(phase: u32) -> Q31 {
+    const ONE: i64 = 1 << 31;
+    // The phase as a Q31 in [-1, 1), where 1 is half a cycle
+    let x = phase.wrapping_add(1 << 31) as i32 as i64;
+    let x = -x;
+    // A parabola through the zero crossings and peaks
+    let y = ((4 * x * (ONE - x.abs())) >> 31).clamp(-ONE, ONE);
+    // Refine toward the sine curve, with a weight of 0.225
+    const P: i64 = (0.225 * (1u64 << 31) as f64) as i64;
+    let refined = y + ((P * (((y * y.abs()) >> 31) - y)) >> 31);
+    Q31(refined.clamp(i32::MIN as i64, i32::MAX as i64) as i32)
+}
+
+impl FixedSource for FixedWave {
+    fn next(&mut self) -> Option<Q31> {
+        let phase = self.phase;
+        self.phase = self.phase.wrapping_add(self.increment);
+        Some(match self.shape {
+            FixedShape::Sine => sine(phase),
+            FixedShape::Square => {
+                if phase < 1 << 31 {
+                    Q31::MAX
+                } else {
+                    Q31::MIN
+                }
+            }
+            FixedShape::Saw => Q31(phase.wrapping_add(1 << 31) as i32),
+            FixedShape::Triangle => {
+                // Fold the sawtooth into a triangle that starts at zero and rises
+                let saw = phase.wrapping_add(1 << 30) as i32;
+                let folded = if saw < 0 { !saw } else { saw };
+                Q31((folded as u32).wrapping_mul(2).wrapping_sub(1 << 31) as i32)
+            }
+        })
+    }
+}
+
+/// Source returned from [`FixedSource::amplify`]
+#[derive(Debug, Clone)]
+pub struct FixedAmplify<S> {
+    source: S,
+    gain: Q31,
+}
+
+impl<S> FixedAmplify<S> {
+    /// Set the multiplier
+    pub fn set_gain(&mut self, gain: Q31) {
+        self.gain = gain;
+    }
+}
+
+impl<S: FixedSource> FixedSource for FixedAmplify<S> {
+    fn next(&mut self) -> Option<Q31> {
+        self.source.next().map(|sample| sample * self.gain)
+    }
+}
+
+/// Source returned from [`FixedSource::mix`]
+#[derive(Debug, Clone)]
+pub struct FixedMix<A, B> {
+    a: A,
+    b: B,
+}
+
+impl<A, B> FixedSource for FixedMix<A, B>
+where
+    A: FixedSource,
+    B: FixedSource,
+{
+    fn next(&mut self) -> Option<Q31> {
+        match (self.a.next(), self.b.next()) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or_default() + b.unwrap_or_default()),
+        }
+    }
+}
+
+/// Source returned from [`FixedSource::low_pass`]
+#[derive(Debug, Clone)]
+pub struct FixedLowPass<S> {
+    source: S,
+    coef: Q31,
+    /// The filtered value, or [`None`] before the first sample
+    acc: Option<Q31>,
+}
+
+impl<S: FixedSource> FixedSource for FixedLowPass<S> {
+    fn next(&mut self) -> Option<Q31> {
+        let sample = self.source.next()?;
+        // Like the floating-point filter, start from the first sample instead of ramping up from silence
+        let Some(acc) = self.acc else {
+            self.acc = Some(sample);
+            return Some(sample);
+        };
+        // The difference can exceed the Q31 range, so it is computed at double width
+        let diff = sample.0 as i64 - acc.0 as i64;
+        let step = (diff * self.coef.0 as i64) >> 31;
+        let acc = Q31((acc.0 as i64 + step) as i32);
+        self.acc = Some(acc);
+        Some(acc)
+    }
+}
+
+/// Source returned from [`FixedSource::into_source`]
+#[derive(Debug, Clone)]
+pub struct IntoSource<S>(S);
+
+impl<S: FixedSource> Source for IntoSource<S> {
+    type Frame = Mono;
+    fn next(&mut self, _sample_rate: f64) -> Option<Self::Frame> {
+        self.0.next().map(Q31::to_f64)
+    }
+}
@@ -1,4 +1,4 @@
-use std::{fmt, mem::transmute, ops::*};
+use core::{fmt, ops::*};
 
 /// Mono [`Frame`] type
 pub type Mono = f64;
@@ -20,13 +20,34 @@ pub trait Frame: Clone + Send + 'static {
     /// Create a frame with a uniform amplitude across all channels
     fn uniform(amplitude: f64) -> Self;
     /// Get the amplitude of a channel
+    ///
+    /// Returns `0.0` if `index` is not less than [`Frame::CHANNELS`].
     fn get_channel(&self, index: usize) -> f64;
     /// Set the amplitude of a channel
+    ///
+    /// Does nothing if `index` is not less than [`Frame::CHANNELS`].
     fn set_channel(&mut self, index: usize, amplitude: f64);
     /// Apply a function to each channel
     fn map(self, f: impl Fn(f64) -> f64) -> Self;
     /// Combine two frames by applying a function
     fn merge(&mut self, other: Self, f: impl Fn(f64, f64) -> f64);
+    /// Create a frame by calling a function with the index of each channel
+    fn from_fn(mut f: impl FnMut(usize) -> f64) -> Self {
+        let mut frame = Self::uniform(0.0);
+        for i in 0..Self::CHANNELS {
+            frame.set_channel(i, f(i));
+        }
+        frame
+    }
+    /// Apply a function to each channel along with the channel's index
+    ///
+    /// This is useful for processing that differs between channels, like a different gain for each speaker.
+    fn map_indexed(mut self, mut f: impl FnMut(usize, f64) -> f64) -> Self {
+        for i in 0..Self::CHANNELS {
+            self.set_channel(i, f(i, self.get_channel(i)));
+        }
+        self
+    }
     /// Get the average amplitude
     fn avg(&self) -> f64 {
         (0..Self::CHANNELS)
@@ -60,11 +81,17 @@ impl Frame for f64 {
     fn uniform(amplitude: f64) -> Self {
         amplitude
     }
-    fn get_channel(&self, _index: usize) -> f64 {
-        *self
+    fn get_channel(&self, index: usize) -> f64 {
+        if index == 0 {
+            *self
+        } else {
+            0.0
+        }
     }
-    fn set_channel(&mut self, _index: usize, amplitude: f64) {
-        *self = amplitude;
+    fn set_channel(&mut self, index: usize, amplitude: f64) {
+        if index == 0 {
+            *self = amplitude;
+        }
     }
     fn map(self, f: impl Fn(f64) -> f64) -> Self {
         f(self)
@@ -85,11 +112,17 @@ impl Frame for f32 {
     fn uniform(amplitude: f64) -> Self {
         amplitude as f32
     }
-    fn get_channel(&self, _index: usize) -> f64 {
-        *self as f64
+    fn get_channel(&self, index: usize) -> f64 {
+        if index == 0 {
+            *self as f64
+        } else {
+            0.0
+        }
     }
-    fn set_channel(&mut self, _index: usize, amplitude: f64) {
-        *self = amplitude as f32;
+    fn set_channel(&mut self, index: usize, amplitude: f64) {
+        if index == 0 {
+            *self = amplitude as f32;
+        }
     }
     fn map(self, f: impl Fn(f64) -> f64) -> Self {
         f(self as f64) as f32
@@ -114,10 +147,12 @@ where
         [amplitude; N]
     }
     fn get_channel(&self, index: usize) -> f64 {
-        self[index]
+        self.get(index).copied().unwrap_or(0.0)
     }
     fn set_channel(&mut self, index: usize, amplitude: f64) {
-        self[index] = amplitude;
+        if let Some(channel) = self.get_mut(index) {
+            *channel = amplitude;
+        }
     }
     fn map(self, f: impl Fn(f64) -> f64) -> Self {
         self.map(f)
@@ -138,10 +173,12 @@ where
         [amplitude as f32; N]
     }
     fn get_channel(&self, index: usize) -> f64 {
-        self[index] as f64
+        self.get(index).map_or(0.0, |&amp| amp as f64)
     }
     fn set_channel(&mut self, index: usize, amplitude: f64) {
-        self[index] = amplitude as f32;
+        if let Some(channel) = self.get_mut(index) {
+            *channel = amplitude as f32;
+        }
     }
     fn map(self, f: impl Fn(f64) -> f64) -> Self {
         self.map(|v| f(v as f64) as f32)
@@ -153,6 +190,142 @@ where
     }
 }
 
+/// A [`Frame`] type with any number of channels
+///
+/// Unlike plain arrays, it supports the same arithmetic operators as [`Stereo`],
+/// which makes it convenient for surround processing.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
+pub struct MultiChannel<const N: usize>(pub [f64; N]);
+
+impl<const N: usize> MultiChannel<N> {
+    /// `[0.0; N]`
+    pub const ZERO: Self = Self([0.0; N]);
+    /// Create a new frame from the amplitudes of its channels
+    pub const fn new(channels: [f64; N]) -> Self {
+        Self(channels)
+    }
+    /// Get the amplitudes of the channels
+    pub const fn channels(&self) -> &[f64; N] {
+        &self.0
+    }
+}
+
+impl<const N: usize> Default for MultiChannel<N> {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl<const N: usize> From<[f64; N]> for MultiChannel<N> {
+    fn from(channels: [f64; N]) -> Self {
+        Self(channels)
+    }
+}
+
+impl<const N: usize> From<MultiChannel<N>> for [f64; N] {
+    fn from(frame: MultiChannel<N>) -> Self {
+        frame.0
+    }
+}
+
+impl<const N: usize> AsRef<[f64]> for MultiChannel<N> {
+    fn as_ref(&self) -> &[f64] {
+        &self.0
+    }
+}
+
+impl<const N: usize> Index<usize> for MultiChannel<N> {
+    type Output = f64;
+    fn index(&self, index: usize) -> &f64 {
+        &self.0[index]
+    }
+}
+
+impl<const N: usize> IndexMut<usize> for MultiChannel<N> {
+    fn index_mut(&mut self, index: usize) -> &mut f64 {
+        &mut self.0[index]
+    }
+}
+
+macro_rules! multi_channel_bin_op {
+    ($trait:ident, $method:ident, $assign_trait:ident, $assign_method:ident) => {
+        impl<const N: usize> $trait for MultiChannel<N> {
+            type Output = Self;
+            fn $method(mut self, other: Self) -> Self {
+                self.$assign_method(other);
+                self
+            }
+        }
+
+        impl<const N: usize> $assign_trait for MultiChannel<N> {
+            fn $assign_method(&mut self, other: Self) {
+                for (a, b) in self.0.iter_mut().zip(other.0) {
+                    a.$assign_method(b);
+                }
+            }
+        }
+
+        impl<const N: usize> $trait<f64> for MultiChannel<N> {
+            type Output = Self;
+            fn $method(mut self, other: f64) -> Self {
+                self.$assign_method(other);
+                self
+            }
+        }
+
+        impl<const N: usize> $trait<MultiChannel<N>> for f64 {
+            type Output = MultiChannel<N>;
+            fn $method(self, other: MultiChannel<N>) -> MultiChannel<N> {
+                MultiChannel(other.0.map(|v| $trait::$method(self, v)))
+            }
+        }
+
+        impl<const N: usize> $assign_trait<f64> for MultiChannel<N> {
+            fn $assign_method(&mut self, other: f64) {
+                for a in &mut self.0 {
+                    a.$assign_method(other);
+                }
+            }
+        }
+    };
+}
+
+multi_channel_bin_op!(Add, add, AddAssign, add_assign);
+multi_channel_bin_op!(Sub, sub, SubAssign, sub_assign);
+multi_channel_bin_op!(Mul, mul, MulAssign, mul_assign);
+multi_channel_bin_op!(Div, div, DivAssign, div_assign);
+multi_channel_bin_op!(Rem, rem, RemAssign, rem_assign);
+
+impl<const N: usize> Neg for MultiChannel<N> {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self(self.0.map(Neg::neg))
+    }
+}
+
+impl<const N: usize> Frame for MultiChannel<N> {
+    const CHANNELS: usize = N;
+    fn uniform(amplitude: f64) -> Self {
+        Self([amplitude; N])
+    }
+    fn get_channel(&self, index: usize) -> f64 {
+        self.0.get(index).copied().unwrap_or(0.0)
+    }
+    fn set_channel(&mut self, index: usize, amplitude: f64) {
+        if let Some(channel) = self.0.get_mut(index) {
+            *channel = amplitude;
+        }
+    }
+    fn map(self, f: impl Fn(f64) -> f64) -> Self {
+        Self(self.0.map(f))
+    }
+    fn merge(&mut self, other: Self, f: impl Fn(f64, f64) -> f64) {
+        for (a, b) in self.0.iter_mut().zip(other.0) {
+            *a = f(*a, b);
+        }
+    }
+}
+
 /// Stereo [`Frame`] type
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
 pub struct Stereo<T = f64> {
@@ -162,6 +335,15 @@ pub struct Stereo<T = f64> {
     pub right: T,
 }
 
+/// A side of a stereo image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Side {
+    /// The left channel
+    Left,
+    /// The right channel
+    Right,
+}
+
 impl<T> Stereo<T> {
     #[inline]
     /// Create a new stereo frame
@@ -291,10 +473,18 @@ impl Frame for Stereo {
         Self::both(amplitude)
     }
     fn get_channel(&self, index: usize) -> f64 {
-        unsafe { transmute::<&Self, &[f64; 2]>(self)[index] }
+        match index {
+            0 => self.left,
+            1 => self.right,
+            _ => 0.0,
+        }
     }
     fn set_channel(&mut self, index: usize, amplitude: f64) {
-        unsafe { transmute::<&mut Self, &mut [f64; 2]>(self)[index] = amplitude }
+        match index {
+            0 => self.left = amplitude,
+            1 => self.right = amplitude,
+            _ => {}
+        }
     }
     fn map(self, f: impl Fn(f64) -> f64) -> Self {
         Self::map(self, f)
@@ -310,10 +500,18 @@ impl Frame for Stereo<f32> {
         Self::both(amplitude as f32)
     }
     fn get_channel(&self, index: usize) -> f64 {
-        unsafe { transmute::<&Self, &[f32; 2]>(self)[index] as f64 }
+        match index {
+            0 => self.left as f64,
+            1 => self.right as f64,
+            _ => 0.0,
+        }
     }
     fn set_channel(&mut self, index: usize, amplitude: f64) {
-        unsafe { transmute::<&mut Self, &mut [f32; 2]>(self)[index] = amplitude as f32 }
+        match index {
+            0 => self.left = amplitude as f32,
+            1 => self.right = amplitude as f32,
+            _ => {}
+        }
     }
     fn map(self, f: impl Fn(f64) -> f64) -> Self {
         Self::map(self, |v| f(v as f64) as f32)
@@ -0,0 +1,195 @@
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    task::{Context, Poll, Waker},
+};
+
+use crate::{sync::Mutex, Frame, Maintainer, Mixer, Source, SourceHandle, ToDuration};
+
+/// Future returned from [`Mixer::idle`]
+///
+/// It resolves when the mixer has no sources left to play.
+pub struct Idle {
+    sources: Arc<dyn IsIdle>,
+    wakers: Arc<IdleWakers>,
+    /// Identifies this future's waker in the list
+    id: u64,
+}
+
+/// The wakers of the [`Idle`] futures waiting on a mixer, keyed by future
+pub(crate) type IdleWakers = Mutex<Vec<(u64, Waker)>>;
+
+static NEXT_IDLE_ID: AtomicU64 = AtomicU64::new(0);
+
+/// Type-erased access to whether a mixer has nothing to play
+trait IsIdle: Send + Sync {
+    /// Check if the mixer is idle, registering the waker if it is not
+    fn check(&self, wakers: &IdleWakers, id: u64, waker: &Waker) -> bool;
+}
+
+/// A mixer's source list and playing count
+///
+/// The count includes sources that have been moved out of the list,
+/// like those rendered by the workers of a [`ParallelMixer`](crate::ParallelMixer).
+struct MixerSources<F> {
+    sources: Arc<Mutex<Vec<crate::source::DynamicSource<F>>>>,
+    playing: Arc<AtomicUsize>,
+}
+
+impl<F: Send> IsIdle for MixerSources<F> {
+    fn check(&self, wakers: &IdleWakers, id: u64, waker: &Waker) -> bool {
+        // The source list stays locked while the waker is registered
+        // so that the mixer cannot become idle in between
+        let sources = self.sources.lock();
+        if sources.is_empty() && self.playing.load(Ordering::Relaxed) == 0 {
+            return true;
+        }
+        // Each future keeps only its latest waker, however many times it is polled
+        let mut wakers = wakers.lock();
+        match wakers.iter_mut().find(|(i, _)| *i == id) {
+            Some((_, w)) if w.will_wake(waker) => {}
+            Some((_, w)) => *w = waker.clone(),
+            None => wakers.push((id, waker.clone())),
+        }
+        false
+    }
+}
+
+impl Future for Idle {
+    type Output = ();
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.sources.check(&self.wakers, self.id, cx.waker()) {
+            Poll::Ready(())
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl Drop for Idle {
+    fn drop(&mut self) {
+        self.wakers.lock().retain(|(i, _)| *i != self.id);
+    }
+}
+
+impl<F> Mixer<F>
+where
+    F: Frame,
+{
+    /// Get a future that resolves when the mixer has no sources left to play
+    pub fn idle(&self) -> Idle {
+        Idle {
+            sources: Arc::new(MixerSources {
+                sources: self.sources.clone(),
+                playing: self.playing.clone(),
+            }),
+            wakers: self.idle_wakers.clone(),
+            id: NEXT_IDLE_ID.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+    /// Add a source to the mixer to start playing after some delay
+    ///
+    /// The returned [`Started`] future resolves when the source starts playing.
+    pub fn add_after<S>(&self, delay: impl ToDuration, source: S) -> (SourceHandle, Started)
+    where
+        S: Source<Frame = F> + Send + 'static,
+    {
+        let signal = Arc::new(Mutex::new(Signal::default()));
+        let handle = self.add(Delayed {
+            source,
+            delay: delay.to_duration().as_secs_f64(),
+            elapsed: 0.0,
+            signal: Some(signal.clone()),
+        });
+        (handle, Started { signal })
+    }
+}
+
+/// Future returned from [`Mixer::add_after`]
+///
+/// It resolves to `true` when the source starts playing,
+/// or `false` if the source was removed before it started.
+pub struct Started {
+    signal: Arc<Mutex<Signal>>,
+}
+
+#[derive(Default)]
+struct Signal {
+    result: Option<bool>,
+    waker: Option<Waker>,
+}
+
+impl Signal {
+    fn fire(&mut self, started: bool) {
+        if self.result.is_none() {
+            self.result = Some(started);
+            if let Some(waker) = self.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+}
+
+impl Future for Started {
+    type Output = bool;
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<bool> {
+        let mut signal = self.signal.lock();
+        if let Some(started) = signal.result {
+            Poll::Ready(started)
+        } else {
+            signal.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+/// A source that starts after a delay and signals when it does
+struct Delayed<S> {
+    source: S,
+    delay: f64,
+    elapsed: f64,
+    signal: Option<Arc<Mutex<Signal>>>,
+}
+
+impl<S> Source for Delayed<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        if self.elapsed < self.delay {
+            self.elapsed += 1.0 / sample_rate;
+            return Some(S::Frame::uniform(0.0));
+        }
+        if let Some(signal) = self.signal.take() {
+            signal.lock().fire(true);
+        }
+        self.source.next(sample_rate)
+    }
+}
+
+impl<S> Drop for Delayed<S> {
+    fn drop(&mut self) {
+        if let Some(signal) = self.signal.take() {
+            signal.lock().fire(false);
+        }
+    }
+}
+
+impl<R> Maintainer<R> {
+    /// Keep the maintainer alive until the given future completes
+    ///
+    /// If the future is cancelled, for example because its task was aborted,
+    /// the maintainer is dropped and its sources are released.
+    pub async fn scope<Fut>(self, fut: Fut) -> Fut::Output
+    where
+        Fut: Future,
+    {
+        let _maintainer = self;
+        fut.await
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{Frame, Mixer, Shared, Source, SourceHandle};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// How a [`GameAudio`] ducks its music bus while voice-over is playing
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ducking {
+    /// The music volume multiplier while voice-over is playing
+    pub gain: f64,
+    /// The time in seconds it takes for the music to duck when voice-over starts
+    pub attack: f64,
+    /// The time in seconds it takes for the music to recover when voice-over ends
+    pub release: f64,
+}
+
+impl Default for Ducking {
+    fn default() -> Self {
+        Ducking {
+            gain: 0.25,
+            attack: 0.05,
+            release: 0.5,
+        }
+    }
+}
+
+/// A mixer with separate music, sound effect, and voice-over buses
+///
+/// Each bus has its own volume, and the music bus is automatically ducked
+/// while any voice-over is playing.
+///
+/// [`GameAudio`] is a [`Source`], so it can be added to an [`OutputDeviceMixer`](crate::OutputDeviceMixer).
+/// Cloned instances share the same buses and volumes.
+/// Volume and ducking changes take effect within 64 frames, and volumes are smoothed over that time.
+#[derive(Clone)]
+pub struct GameAudio<F> {
+    music: Mixer<F>,
+    sfx: Mixer<F>,
+    voice: Mixer<F>,
+    master_volume: Shared<f64>,
+    music_volume: Shared<f64>,
+    sfx_volume: Shared<f64>,
+    voice_volume: Shared<f64>,
+    ducking: Shared<Ducking>,
+    duck_gain: f64,
+    /// The settings read at the start of the previous and current blocks
+    snapshots: [Snapshot; 2],
+    /// Frames left until the settings are read again
+    until_snapshot: usize,
+    started: bool,
+}
+
+/// The number of frames between reads of a [`GameAudio`]'s volumes and ducking settings
+const SNAPSHOT_BLOCK: usize = 64;
+
+/// The settings of a [`GameAudio`] at the start of a block
+#[derive(Debug, Clone, Copy)]
+struct Snapshot {
+    master: f64,
+    music: f64,
+    sfx: f64,
+    voice: f64,
+    ducking: Ducking,
+}
+
+impl<F> Default for GameAudio<F> {
+    fn default() -> Self {
+        GameAudio {
+            music: Mixer::new(),
+            sfx: Mixer::new(),
+            voice: Mixer::new(),
+            master_volume: Shared::new(1.0),
+            music_volume: Shared::new(1.0),
+            sfx_volume: Shared::new(1.0),
+            voice_volume: Shared::new(1.0),
+            ducking: Shared::new(Ducking::default()),
+            duck_gain: 1.0,
+            snapshots: [Snapshot {
+                master: 1.0,
+                music: 1.0,
+                sfx: 1.0,
+                voice: 1.0,
+                ducking: Ducking::default(),
+            }; 2],
+            until_snapshot: 0,
+            started: false,
+        }
+    }
+}
+
+impl<F> GameAudio<F> {
+    /// Create a new game audio mixer
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Get the music bus
+    pub fn music(&self) -> &Mixer<F> {
+        &self.music
+    }
+    /// Get the sound effect bus
+    pub fn sfx(&self) -> &Mixer<F> {
+        &self.sfx
+    }
+    /// Get the voice-over bus
+    pub fn voice(&self) -> &Mixer<F> {
+        &self.voice
+    }
+    /// Play a source on the music bus
+    pub fn play_music<S>(&self, source: S) -> SourceHandle
+    where
+        S: Source<Frame = F> + Send + 'static,
+    {
+        self.music.add(source)
+    }
+    /// Play a source on the sound effect bus
+    pub fn play_sfx<S>(&self, source: S) -> SourceHandle
+    where
+        S: Source<Frame = F> + Send + 'static,
+    {
+        self.sfx.add(source)
+    }
+    /// Play a source on the voice-over bus
+    pub fn play_voice<S>(&self, source: S) -> SourceHandle
+    where
+        S: Source<Frame = F> + Send + 'static,
+    {
+        self.voice.add(source)
+    }
+    /// Get the volume applied to all buses
+    pub fn master_volume(&self) -> Shared<f64> {
+        self.master_volume.clone()
+    }
+    /// Get the music bus volume
+    pub fn music_volume(&self) -> Shared<f64> {
+        self.music_volume.clone()
+    }
+    /// Get the sound effect bus volume
+    pub fn sfx_volume(&self) -> Shared<f64> {
+        self.sfx_volume.clone()
+    }
+    /// Get the voice-over bus volume
+    pub fn voice_volume(&self) -> Shared<f64> {
+        self.voice_volume.clone()
+    }
+    /// Get the ducking settings
+    pub fn ducking(&self) -> Shared<Ducking> {
+        self.ducking.clone()
+    }
+}
+
+impl<F> Source for GameAudio<F>
+where
+    F: Frame,
+{
+    type Frame = F;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        // Read the settings once per block, ramping the volumes toward them
+        if self.until_snapshot == 0 {
+            let snapshot = Snapshot {
+                master: self.master_volume.get(),
+                music: self.music_volume.get(),
+                sfx: self.sfx_volume.get(),
+                voice: self.voice_volume.get(),
+                ducking: self.ducking.get(),
+            };
+            // The first block starts at the settings instead of ramping to them
+            let prev = if self.started {
+                self.snapshots[1]
+            } else {
+                snapshot
+            };
+            self.snapshots = [prev, snapshot];
+            self.until_snapshot = SNAPSHOT_BLOCK;
+            self.started = true;
+        }
+        let t = 1.0 - (self.until_snapshot - 1) as f64 / SNAPSHOT_BLOCK as f64;
+        self.until_snapshot -= 1;
+        let ramp = |prev: f64, next: f64| prev + (next - prev) * t;
+        let [prev, snapshot] = self.snapshots;
+        let voice_active = self.voice.is_playing();
+        let ducking = snapshot.ducking;
+        let (target, time) = if voice_active {
+            (ducking.gain, ducking.attack)
+        } else {
+            (1.0, ducking.release)
+        };
+        if time <= 0.0 {
+            self.duck_gain = target;
+        } else {
+            let step = (1.0 - ducking.gain).abs() / (time * sample_rate);
+            self.duck_gain += (target - self.duck_gain).clamp(-step, step);
+        }
+        let music_volume = ramp(prev.music, snapshot.music) * self.duck_gain;
+        let sfx_volume = ramp(prev.sfx, snapshot.sfx);
+        let voice_volume = ramp(prev.voice, snapshot.voice);
+        let mut frame = self.music.next(sample_rate)?.map(|a| a * music_volume);
+        let sfx = self.sfx.next(sample_rate)?;
+        frame.merge(sfx, |a, b| b.mul_add(sfx_volume, a));
+        let voice = self.voice.next(sample_rate)?;
+        frame.merge(voice, |a, b| b.mul_add(voice_volume, a));
+        let master_volume = ramp(prev.master, snapshot.master);
+        Some(frame.map(|a| a * master_volume))
+    }
+}
@@ -1,11 +1,18 @@
 //! Wave generation
 
-use std::f64::consts::TAU;
+#[cfg(feature = "noise")]
+use alloc::sync::Arc;
+use core::{f64::consts::TAU, time::Duration};
 
 #[cfg(feature = "rand")]
 use rand::prelude::*;
 
-use crate::{lerp, source::*, Automation, Mono};
+#[cfg(feature = "noise")]
+use crate::Shared;
+use crate::{lerp, source::*, Automation, Frame, Mono, ToDuration};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
 
 /// Defines a waveform
 pub trait Waveform {
@@ -64,6 +71,19 @@ where
     }
 }
 
+impl<W, F> StatefulSource for Wave<W, F>
+where
+    W: Waveform,
+    F: Automation,
+{
+    fn save_state(&self, state: &mut SourceState) {
+        state.push(self.time);
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        state.read_into(&mut self.time);
+    }
+}
+
 /// A sine waveform
 #[derive(Debug, Clone, Copy, Default)]
 pub struct Sine;
@@ -133,17 +153,38 @@ impl Default for Noise {
 
 #[cfg(feature = "noise")]
 impl Noise {
-    /// Create new noise with the given sample rate
+    /// Create new noise seeded from the current time
     pub fn new() -> Self {
         Noise {
-            rng: SmallRng::seed_from_u64(
-                std::time::SystemTime::now()
-                    .duration_since(std::time::UNIX_EPOCH)
-                    .unwrap()
-                    .as_nanos() as u64,
-            ),
+            rng: time_seeded_rng(),
         }
     }
+    /// Create new noise with the given seed
+    ///
+    /// Noise created with the same seed will always produce the same samples.
+    pub fn with_seed(seed: u64) -> Self {
+        Noise {
+            rng: SmallRng::seed_from_u64(seed),
+        }
+    }
+}
+
+#[cfg(all(feature = "noise", feature = "std"))]
+pub(crate) fn time_seeded_rng() -> SmallRng {
+    SmallRng::seed_from_u64(
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_nanos() as u64,
+    )
+}
+
+/// Without a clock, generators are seeded from a counter so that each one differs
+#[cfg(all(feature = "noise", not(feature = "std")))]
+pub(crate) fn time_seeded_rng() -> SmallRng {
+    use core::sync::atomic::{AtomicU64, Ordering};
+    static SEED: AtomicU64 = AtomicU64::new(0x2545_f491_4f6c_dd1d);
+    SmallRng::seed_from_u64(SEED.fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed))
 }
 
 #[cfg(feature = "noise")]
@@ -154,6 +195,212 @@ impl Source for Noise {
     }
 }
 
+/// A source that holds a random value, choosing a new one `rate` times per second
+///
+/// This is a sample-and-hold random LFO. It outputs values in the range [-1, 1].
+#[cfg(feature = "noise")]
+#[derive(Debug, Clone)]
+pub struct RandomHold<R = f64> {
+    rate: R,
+    rng: SmallRng,
+    value: f64,
+    phase: f64,
+}
+
+#[cfg(feature = "noise")]
+impl<R> RandomHold<R> {
+    /// Create a new random hold seeded from the current time
+    pub fn new(rate: R) -> Self {
+        Self::from_rng(rate, time_seeded_rng())
+    }
+    /// Create a new random hold with the given seed
+    pub fn with_seed(rate: R, seed: u64) -> Self {
+        Self::from_rng(rate, SmallRng::seed_from_u64(seed))
+    }
+    fn from_rng(rate: R, mut rng: SmallRng) -> Self {
+        RandomHold {
+            rate,
+            value: rng.gen_range(-1.0..=1.0),
+            rng,
+            phase: 0.0,
+        }
+    }
+}
+
+#[cfg(feature = "noise")]
+impl<R> Source for RandomHold<R>
+where
+    R: Automation,
+{
+    type Frame = Mono;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let rate = self.rate.next_value(sample_rate)?;
+        let res = self.value;
+        self.phase += rate / sample_rate;
+        if self.phase >= 1.0 {
+            self.phase = self.phase.fract();
+            self.value = self.rng.gen_range(-1.0..=1.0);
+        }
+        Some(res)
+    }
+}
+
+/// A source that smoothly moves between random values, reaching a new one `rate` times per second
+///
+/// This is useful for organic-sounding modulation. It outputs values in the range [-1, 1].
+#[cfg(feature = "noise")]
+#[derive(Debug, Clone)]
+pub struct SmoothRandom<R = f64> {
+    rate: R,
+    rng: SmallRng,
+    from: f64,
+    to: f64,
+    phase: f64,
+}
+
+#[cfg(feature = "noise")]
+impl<R> SmoothRandom<R> {
+    /// Create a new smooth random source seeded from the current time
+    pub fn new(rate: R) -> Self {
+        Self::from_rng(rate, time_seeded_rng())
+    }
+    /// Create a new smooth random source with the given seed
+    pub fn with_seed(rate: R, seed: u64) -> Self {
+        Self::from_rng(rate, SmallRng::seed_from_u64(seed))
+    }
+    fn from_rng(rate: R, mut rng: SmallRng) -> Self {
+        SmoothRandom {
+            rate,
+            from: rng.gen_range(-1.0..=1.0),
+            to: rng.gen_range(-1.0..=1.0),
+            rng,
+            phase: 0.0,
+        }
+    }
+}
+
+#[cfg(feature = "noise")]
+impl<R> Source for SmoothRandom<R>
+where
+    R: Automation,
+{
+    type Frame = Mono;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let rate = self.rate.next_value(sample_rate)?;
+        let t = self.phase;
+        let res = lerp(self.from, self.to, t * t * (3.0 - 2.0 * t));
+        self.phase += rate / sample_rate;
+        if self.phase >= 1.0 {
+            self.phase = self.phase.fract();
+            self.from = self.to;
+            self.to = self.rng.gen_range(-1.0..=1.0);
+        }
+        Some(res)
+    }
+}
+
+/// A source that plays one of several variants, chosen at random each time it starts
+///
+/// Clones of a [`RandomChoice`] share their random state, so each repetition
+/// of a [`Repeat`] will choose its variant independently.
+///
+/// This is useful for sound effects that are played often, like footsteps.
+#[cfg(feature = "noise")]
+#[derive(Clone)]
+pub struct RandomChoice<S> {
+    variants: Arc<Vec<(S, f64)>>,
+    state: Shared<ChoiceState>,
+    curr: Option<S>,
+}
+
+#[cfg(feature = "noise")]
+struct ChoiceState {
+    rng: SmallRng,
+    last: Option<usize>,
+    no_immediate_repeat: bool,
+}
+
+#[cfg(feature = "noise")]
+impl<S> RandomChoice<S> {
+    /// Create a new random choice where each variant is equally likely
+    pub fn new(variants: impl IntoIterator<Item = S>) -> Self {
+        Self::weighted(variants.into_iter().map(|variant| (variant, 1.0)))
+    }
+    /// Create a new random choice where each variant is chosen proportionally to its weight
+    pub fn weighted(variants: impl IntoIterator<Item = (S, f64)>) -> Self {
+        RandomChoice {
+            variants: Arc::new(variants.into_iter().collect()),
+            state: Shared::new(ChoiceState {
+                rng: time_seeded_rng(),
+                last: None,
+                no_immediate_repeat: false,
+            }),
+            curr: None,
+        }
+    }
+    /// Never choose the same variant twice in a row
+    ///
+    /// This has no effect if there is only one variant.
+    pub fn no_immediate_repeat(self) -> Self {
+        self.state.with(|state| state.no_immediate_repeat = true);
+        self
+    }
+    /// Seed the random number generator
+    pub fn seed(self, seed: u64) -> Self {
+        self.state
+            .with(|state| state.rng = SmallRng::seed_from_u64(seed));
+        self
+    }
+    fn choose(&mut self) -> Option<usize> {
+        let variants = &self.variants;
+        self.state.with(|state| {
+            let skip = state
+                .last
+                .filter(|_| state.no_immediate_repeat && variants.len() > 1);
+            let weight = |i: usize| {
+                if Some(i) == skip {
+                    0.0
+                } else {
+                    variants[i].1.max(0.0)
+                }
+            };
+            let total: f64 = (0..variants.len()).map(weight).sum();
+            if total <= 0.0 {
+                return None;
+            }
+            let mut target = state.rng.gen::<f64>() * total;
+            let mut chosen = None;
+            for i in 0..variants.len() {
+                let weight = weight(i);
+                if weight > 0.0 {
+                    chosen = Some(i);
+                    if target < weight {
+                        break;
+                    }
+                    target -= weight;
+                }
+            }
+            state.last = chosen;
+            chosen
+        })
+    }
+}
+
+#[cfg(feature = "noise")]
+impl<S> Source for RandomChoice<S>
+where
+    S: Source + Clone,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        if self.curr.is_none() {
+            let i = self.choose()?;
+            self.curr = Some(self.variants[i].0.clone());
+        }
+        self.curr.as_mut()?.next(sample_rate)
+    }
+}
+
 #[derive(Clone, Copy)]
 /// A linear interpolation source
 pub struct Lerp<A, B, D> {
@@ -195,3 +442,409 @@ where
         Some(res)
     }
 }
+
+impl<A, B> FiniteSource for Lerp<A, B, f64>
+where
+    A: Automation,
+    B: Automation,
+{
+    fn total(&self, _sample_rate: f64) -> Duration {
+        Duration::from_secs_f64(self.duration.max(0.0))
+    }
+    fn remaining(&self, _sample_rate: f64) -> Duration {
+        Duration::from_secs_f64((self.duration - self.time).max(0.0))
+    }
+}
+
+/// An [`Automation`] that glides to its target whenever the target changes
+///
+/// Glides are exponential, meaning they are linear in semitones,
+/// and always take the glide time regardless of the interval.
+/// The target is usually a [`Shared`](crate::Shared) frequency or pitch
+/// that is set whenever a new note is played.
+#[derive(Debug, Clone)]
+pub struct Portamento<P, G = f64> {
+    target: P,
+    glide_time: G,
+    from: f64,
+    to: Option<f64>,
+    current: f64,
+    elapsed: f64,
+}
+
+impl<P, G> Portamento<P, G> {
+    /// Create a new portamento that glides to `target` over `glide_time` seconds
+    pub fn new(target: P, glide_time: G) -> Self {
+        Portamento {
+            target,
+            glide_time,
+            from: 0.0,
+            to: None,
+            current: 0.0,
+            elapsed: 0.0,
+        }
+    }
+}
+
+impl<P, G> Automation for Portamento<P, G>
+where
+    P: Automation,
+    G: Automation,
+{
+    fn next_value(&mut self, sample_rate: f64) -> Option<f64> {
+        let target = self.target.next_value(sample_rate)?;
+        let glide_time = self.glide_time.next_value(sample_rate)?;
+        match self.to {
+            // Start at the first target instead of gliding to it
+            None => {
+                self.current = target;
+                self.to = Some(target);
+                self.elapsed = f64::INFINITY;
+            }
+            Some(to) if to != target => {
+                self.from = self.current;
+                self.to = Some(target);
+                self.elapsed = 0.0;
+            }
+            _ => {}
+        }
+        if self.elapsed >= glide_time {
+            self.current = target;
+        } else {
+            let t = self.elapsed / glide_time;
+            self.current = if self.from > 0.0 && target > 0.0 {
+                self.from * (target / self.from).powf(t)
+            } else {
+                lerp(self.from, target, t)
+            };
+            self.elapsed += 1.0 / sample_rate;
+        }
+        Some(self.current)
+    }
+}
+
+/// An [`Automation`] that smooths changes in another automation
+///
+/// This removes the zipper noise caused by parameters that jump between values,
+/// like those set from a UI.
+/// The smoothing is a one-pole low pass with the given time constant,
+/// so the value covers about 63% of a jump in that time and settles after about five times that.
+#[derive(Debug, Clone)]
+pub struct Smoothed<A> {
+    inner: A,
+    time: f64,
+    current: Option<f64>,
+}
+
+impl<A> Smoothed<A> {
+    /// Create a new smoothed automation with a time constant
+    pub fn new(inner: A, time: impl ToDuration) -> Self {
+        Smoothed {
+            inner,
+            time: time.to_duration().as_secs_f64(),
+            current: None,
+        }
+    }
+}
+
+impl<A> Automation for Smoothed<A>
+where
+    A: Automation,
+{
+    fn next_value(&mut self, sample_rate: f64) -> Option<f64> {
+        let target = self.inner.next_value(sample_rate)?;
+        let current = match self.current {
+            // Start at the first value instead of smoothing toward it
+            Some(current) if self.time > 0.0 => {
+                let coef = (-1.0 / (self.time * sample_rate)).exp();
+                target + (current - target) * coef
+            }
+            _ => target,
+        };
+        self.current = Some(current);
+        Some(current)
+    }
+}
+
+/// How the frequency of a [`SineSweep`] changes over time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum SweepKind {
+    /// The frequency rises exponentially, spending the same time on every octave
+    ///
+    /// This is the usual choice for acoustic measurements.
+    #[default]
+    Log,
+    /// The frequency rises linearly
+    Linear,
+}
+
+/// A sine wave whose frequency sweeps from one frequency to another
+///
+/// Sweeps are used to measure the frequency and impulse response of rooms, speakers, and filters.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SineSweep {
+    start: f64,
+    end: f64,
+    duration: f64,
+    kind: SweepKind,
+    frames: u64,
+}
+
+impl SineSweep {
+    /// Create a new sweep from `start` to `end` Hz over `duration`
+    ///
+    /// For [`SweepKind::Log`], both frequencies must be positive.
+    pub fn new(start: f64, end: f64, duration: impl ToDuration, kind: SweepKind) -> Self {
+        SineSweep {
+            start,
+            end,
+            duration: duration.to_duration().as_secs_f64(),
+            kind,
+            frames: 0,
+        }
+    }
+    /// Get the starting frequency
+    pub fn start(&self) -> f64 {
+        self.start
+    }
+    /// Get the ending frequency
+    pub fn end(&self) -> f64 {
+        self.end
+    }
+    /// Get the duration in seconds
+    pub fn duration(&self) -> f64 {
+        self.duration
+    }
+    /// Get how the frequency changes
+    pub fn kind(&self) -> SweepKind {
+        self.kind
+    }
+    /// Get the phase in radians at a time in seconds
+    fn phase(&self, t: f64) -> f64 {
+        let (f1, f2, dur) = (self.start, self.end, self.duration);
+        match self.kind {
+            SweepKind::Log if f1 != f2 => {
+                let rate = (f2 / f1).ln();
+                TAU * f1 * dur / rate * ((t / dur * rate).exp() - 1.0)
+            }
+            _ => TAU * (f1 * t + (f2 - f1) * t * t / (2.0 * dur)),
+        }
+    }
+}
+
+impl Source for SineSweep {
+    type Frame = Mono;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let t = self.frames as f64 / sample_rate;
+        if t >= self.duration {
+            return None;
+        }
+        self.frames += 1;
+        Some(self.phase(t).sin())
+    }
+}
+
+impl FiniteSource for SineSweep {
+    fn total(&self, _sample_rate: f64) -> Duration {
+        Duration::from_secs_f64(self.duration.max(0.0))
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        let elapsed = self.frames as f64 / sample_rate;
+        Duration::from_secs_f64((self.duration - elapsed).max(0.0))
+    }
+}
+
+/// A source that plays a single full-scale sample followed by silence
+///
+/// An impulse contains every frequency at equal strength,
+/// so a system's response to it is its impulse response.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Impulse {
+    duration: f64,
+    frames: u64,
+}
+
+impl Impulse {
+    /// Create a new impulse followed by silence for a total of `duration`
+    pub fn new(duration: impl ToDuration) -> Self {
+        Impulse {
+            duration: duration.to_duration().as_secs_f64(),
+            frames: 0,
+        }
+    }
+}
+
+impl Source for Impulse {
+    type Frame = Mono;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        if self.frames > 0 && self.frames as f64 / sample_rate >= self.duration {
+            return None;
+        }
+        self.frames += 1;
+        Some(if self.frames == 1 { 1.0 } else { 0.0 })
+    }
+}
+
+impl FiniteSource for Impulse {
+    fn total(&self, _sample_rate: f64) -> Duration {
+        Duration::from_secs_f64(self.duration.max(0.0))
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        let elapsed = self.frames as f64 / sample_rate;
+        Duration::from_secs_f64((self.duration - elapsed).max(0.0))
+    }
+}
+
+/// Feedback taps of maximal linear-feedback shift registers, indexed by order
+const MLS_TAPS: [u32; 25] = [
+    0, 0x1, 0x3, 0x6, 0xC, 0x14, 0x30, 0x60, 0xB8, 0x110, 0x240, 0x500, 0xE08, 0x1C80, 0x3802,
+    0x6000, 0xD008, 0x12000, 0x20400, 0x72000, 0x90000, 0x140000, 0x300000, 0x420000, 0xE10000,
+];
+
+/// A maximum length sequence noise source
+///
+/// An MLS is a pseudorandom sequence of full-scale positive and negative samples
+/// that repeats every `2^order - 1` frames.
+/// Its spectrum is flat, and its periodic autocorrelation is an impulse,
+/// so the impulse response of a system can be recovered by cross-correlating its response with the sequence.
+/// The source never ends.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Mls {
+    order: u32,
+    state: u32,
+}
+
+impl Mls {
+    /// Create a new MLS with an order from `2` to `24`
+    ///
+    /// The order is clamped to that range.
+    pub fn new(order: u32) -> Self {
+        Mls {
+            order: order.clamp(2, 24),
+            state: 1,
+        }
+    }
+    /// Get the number of frames before the sequence repeats
+    pub fn period(&self) -> usize {
+        (1 << self.order) - 1
+    }
+}
+
+impl Source for Mls {
+    type Frame = Mono;
+    fn next(&mut self, _sample_rate: f64) -> Option<Self::Frame> {
+        let bit = self.state & 1;
+        self.state >>= 1;
+        if bit == 1 {
+            self.state ^= MLS_TAPS[self.order as usize];
+        }
+        Some(if bit == 1 { 1.0 } else { -1.0 })
+    }
+}
+
+/// The length of each beep in a [`ChannelCheck`], in seconds
+const CHECK_BEEP: f64 = 0.12;
+/// The silence after each beep in a [`ChannelCheck`], in seconds
+const CHECK_GAP: f64 = 0.08;
+/// The silence after each channel in a [`ChannelCheck`], in seconds
+const CHECK_CHANNEL_GAP: f64 = 0.6;
+/// The fade at the ends of each beep in a [`ChannelCheck`], in seconds
+const CHECK_FADE: f64 = 0.005;
+
+/// A source that beeps on each channel of a frame in turn, for checking speaker setups
+///
+/// Channel `i` plays `i + 1` beeps, and each channel is a half step higher than the one before.
+/// [`channel_name`] gives the conventional name of each channel.
+/// The source ends after the last channel.
+#[derive(Debug, Clone)]
+pub struct ChannelCheck<F> {
+    channel: usize,
+    time: f64,
+    wave: SineWave,
+    _frame: core::marker::PhantomData<F>,
+}
+
+impl<F> Default for ChannelCheck<F> {
+    fn default() -> Self {
+        ChannelCheck {
+            channel: 0,
+            time: 0.0,
+            wave: SineWave::new(440.0),
+            _frame: core::marker::PhantomData,
+        }
+    }
+}
+
+impl<F> ChannelCheck<F> {
+    /// Create a new channel check
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Get the index of the channel currently being checked
+    pub fn channel(&self) -> usize {
+        self.channel
+    }
+}
+
+impl<F> Source for ChannelCheck<F>
+where
+    F: Frame,
+{
+    type Frame = F;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        if self.channel >= F::CHANNELS {
+            return None;
+        }
+        let beeps = (self.channel + 1) as f64;
+        let period = CHECK_BEEP + CHECK_GAP;
+        let tone = self.wave.next(sample_rate)?;
+        let in_period = self.time % period;
+        let amp = if self.time < beeps * period && in_period < CHECK_BEEP {
+            (in_period.min(CHECK_BEEP - in_period) / CHECK_FADE).min(1.0) * 0.5
+        } else {
+            0.0
+        };
+        let mut frame = F::uniform(0.0);
+        frame.set_channel(self.channel, tone * amp);
+        self.time += 1.0 / sample_rate;
+        if self.time >= beeps * period + CHECK_CHANNEL_GAP {
+            self.channel += 1;
+            self.time = 0.0;
+            self.wave = SineWave::new(440.0 * 2f64.powf(self.channel as f64 / 12.0));
+        }
+        Some(frame)
+    }
+}
+
+/// Get the conventional name of a channel in a speaker layout with some number of channels
+///
+/// This follows the common WAV and USB channel order, where for example channel `3` of a 5.1 layout is the subwoofer.
+/// Returns `None` for layouts without a standard order.
+pub fn channel_name(index: usize, channels: usize) -> Option<&'static str> {
+    let names: &[&str] = match channels {
+        1 => &["mono"],
+        2 => &["front left", "front right"],
+        3 => &["front left", "front right", "front center"],
+        4 => &["front left", "front right", "rear left", "rear right"],
+        6 => &[
+            "front left",
+            "front right",
+            "front center",
+            "subwoofer",
+            "rear left",
+            "rear right",
+        ],
+        8 => &[
+            "front left",
+            "front right",
+            "front center",
+            "subwoofer",
+            "rear left",
+            "rear right",
+            "side left",
+            "side right",
+        ],
+        _ => return None,
+    };
+    names.get(index).copied()
+}
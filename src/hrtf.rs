@@ -0,0 +1,363 @@
+//! Binaural rendering with head-related transfer functions
+//!
+//! An [`Hrtf`] is a set of impulse response pairs measured from different directions.
+//! [`Hrtf::render`] convolves a source with the pair nearest to its direction,
+//! so positioned sounds are heard as coming from that direction on headphones.
+//!
+//! Directions use the same convention as the `ambisonics` module:
+//! azimuth is measured in radians counterclockwise from the front, so positive azimuths are to the left,
+//! and elevation is measured in radians upward from the horizon.
+//!
+//! Measured responses can be loaded from the MIT KEMAR dataset with [`Hrtf::load_kemar`],
+//! or added one at a time from any other dataset with [`Hrtf::with_measurement`].
+//!
+//! This module is only available when the `hrtf` feature is enabled.
+
+use alloc::sync::Arc;
+use core::f64::consts::TAU;
+
+use crate::{lerp, Automation, Frame, Source, Stereo};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// A single measurement in an [`Hrtf`]
+#[derive(Debug, Clone, PartialEq)]
+struct Measurement {
+    direction: [f64; 3],
+    left: Vec<f64>,
+    right: Vec<f64>,
+}
+
+/// A set of head-related impulse responses
+///
+/// Cloning an [`Hrtf`] is cheap, and clones share the same measurements.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hrtf {
+    sample_rate: f64,
+    measurements: Arc<Vec<Measurement>>,
+}
+
+/// Get the unit vector pointing in a direction
+fn unit_vector(azimuth: f64, elevation: f64) -> [f64; 3] {
+    let (sin_az, cos_az) = azimuth.sin_cos();
+    let (sin_el, cos_el) = elevation.sin_cos();
+    [cos_az * cos_el, sin_az * cos_el, sin_el]
+}
+
+/// The radius of the modeled head in meters
+const HEAD_RADIUS: f64 = 0.0875;
+/// The speed of sound in meters per second
+const SPEED_OF_SOUND: f64 = 343.0;
+/// The number of frames over which [`Binaural`] crossfades between impulse responses
+const CROSSFADE_FRAMES: usize = 128;
+
+impl Hrtf {
+    /// Create an empty set of impulse responses recorded at the given sample rate
+    ///
+    /// Measurements can be added with [`Hrtf::with_measurement`].
+    pub fn new(sample_rate: f64) -> Self {
+        Hrtf {
+            sample_rate,
+            measurements: Arc::new(Vec::new()),
+        }
+    }
+    /// Generate impulse responses from a simple spherical head model
+    ///
+    /// This models interaural time differences and head shadowing on the horizontal plane.
+    /// It is much less convincing than measured responses, but needs no data.
+    pub fn spherical_head(sample_rate: f64) -> Self {
+        const DIRECTIONS: usize = 24;
+        let mut hrtf = Hrtf::new(sample_rate);
+        let len = ((2.0 * HEAD_RADIUS / SPEED_OF_SOUND * sample_rate) as usize + 32).max(32);
+        for i in 0..DIRECTIONS {
+            let azimuth = TAU * i as f64 / DIRECTIONS as f64;
+            let side = azimuth.sin();
+            // Woodworth's formula for the interaural time difference
+            let lateral = side.asin().abs();
+            let itd = HEAD_RADIUS / SPEED_OF_SOUND * (lateral + lateral.sin());
+            let near = ear_response(len, 0.0, 0.0);
+            let far = ear_response(len, itd * sample_rate, side.abs());
+            let (left, right) = if side >= 0.0 {
+                (near, far)
+            } else {
+                (far, near)
+            };
+            hrtf = hrtf.with_measurement(azimuth, 0.0, left, right);
+        }
+        hrtf
+    }
+    /// Load the compact set of the MIT KEMAR measurements
+    ///
+    /// `dir` is the dataset's `compact` directory, which has a directory for each elevation, like `elev0`.
+    /// Each of those holds a stereo file for each azimuth, like `H0e090a.wav`.
+    /// KEMAR azimuths are measured clockwise, and the compact set only covers sources on the right,
+    /// so each measurement is mirrored for the left side.
+    #[cfg(feature = "wav")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "wav")))]
+    pub fn load_kemar(dir: impl AsRef<std::path::Path>) -> Result<Self, crate::wav::WaveError> {
+        use crate::{wav::WaveError, UnrolledSource};
+        let mut paths = Vec::new();
+        for elev_dir in std::fs::read_dir(dir)? {
+            let elev_dir = elev_dir?.path();
+            if elev_dir.is_dir() {
+                for file in std::fs::read_dir(elev_dir)? {
+                    paths.push(file?.path());
+                }
+            }
+        }
+        paths.sort();
+        let mut hrtf: Option<Hrtf> = None;
+        for path in paths {
+            let Some((elevation, azimuth)) = (path.file_name())
+                .and_then(|name| name.to_str())
+                .and_then(parse_kemar_name)
+            else {
+                continue;
+            };
+            let file = std::io::BufReader::new(std::fs::File::open(&path)?);
+            let source = crate::wav::WavSource::new(file)?;
+            let sample_rate = source.sample_rate();
+            if source.channels() != 2 {
+                return Err(WaveError::FormatError("KEMAR files must be stereo"));
+            }
+            let samples: Vec<f64> = source.collect();
+            let left: Vec<f64> = samples.iter().step_by(2).copied().collect();
+            let right: Vec<f64> = samples.iter().skip(1).step_by(2).copied().collect();
+            let mut set = hrtf.take().unwrap_or_else(|| Hrtf::new(sample_rate));
+            if set.sample_rate != sample_rate {
+                return Err(WaveError::FormatError(
+                    "KEMAR files must all have the same sample rate",
+                ));
+            }
+            // Sources straight ahead or behind are the same on both sides
+            let mirrored = azimuth != 0.0 && azimuth != 180.0;
+            let (azimuth, elevation) = (azimuth.to_radians(), elevation.to_radians());
+            if mirrored {
+                set = set.with_measurement(azimuth, elevation, right.clone(), left.clone());
+            }
+            hrtf = Some(set.with_measurement(-azimuth, elevation, left, right));
+        }
+        hrtf.ok_or(WaveError::FormatError("no KEMAR measurements were found"))
+    }
+    /// Add a measurement from a direction
+    pub fn with_measurement(
+        mut self,
+        azimuth: f64,
+        elevation: f64,
+        left: impl Into<Vec<f64>>,
+        right: impl Into<Vec<f64>>,
+    ) -> Self {
+        Arc::make_mut(&mut self.measurements).push(Measurement {
+            direction: unit_vector(azimuth, elevation),
+            left: left.into(),
+            right: right.into(),
+        });
+        self
+    }
+    /// Get the sample rate the impulse responses were recorded at
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+    /// Get the number of measurements
+    pub fn len(&self) -> usize {
+        self.measurements.len()
+    }
+    /// Check if there are no measurements
+    pub fn is_empty(&self) -> bool {
+        self.measurements.is_empty()
+    }
+    /// Resample the impulse responses to a different sample rate
+    pub fn resampled(&self, sample_rate: f64) -> Self {
+        if sample_rate == self.sample_rate {
+            return self.clone();
+        }
+        let ratio = self.sample_rate / sample_rate;
+        let resample = |ir: &[f64]| -> Vec<f64> {
+            let len = (ir.len() as f64 / ratio).ceil() as usize;
+            (0..len)
+                .map(|i| {
+                    let pos = i as f64 * ratio;
+                    let j = pos as usize;
+                    let a = ir.get(j).copied().unwrap_or(0.0);
+                    let b = ir.get(j + 1).copied().unwrap_or(0.0);
+                    // Scale so that the overall gain of the response is unchanged
+                    lerp(a, b, pos.fract()) * ratio
+                })
+                .collect()
+        };
+        Hrtf {
+            sample_rate,
+            measurements: Arc::new(
+                self.measurements
+                    .iter()
+                    .map(|m| Measurement {
+                        direction: m.direction,
+                        left: resample(&m.left),
+                        right: resample(&m.right),
+                    })
+                    .collect(),
+            ),
+        }
+    }
+    /// Get the length of the longest impulse response
+    fn max_len(&self) -> usize {
+        let lens = self.measurements.iter();
+        let len = lens.map(|m| m.left.len().max(m.right.len())).max();
+        len.unwrap_or(0).max(1)
+    }
+    /// Find the index of the measurement nearest to a direction
+    fn nearest(&self, azimuth: f64, elevation: f64) -> Option<usize> {
+        let dir = unit_vector(azimuth, elevation);
+        let dot =
+            |m: &Measurement| -> f64 { m.direction.iter().zip(dir).map(|(a, b)| a * b).sum() };
+        (0..self.measurements.len())
+            .max_by(|&a, &b| dot(&self.measurements[a]).total_cmp(&dot(&self.measurements[b])))
+    }
+    /// Render a source binaurally from a direction
+    ///
+    /// The source is mixed to mono first.
+    /// The direction can be automated, and the impulse responses are crossfaded as it changes.
+    ///
+    /// The responses are used at the sample rate they were recorded at.
+    /// If the output sample rate is different, set it with [`Binaural::sample_rate`].
+    /// Playing at any other rate panics in debug builds.
+    pub fn render<S, A, E>(&self, source: S, azimuth: A, elevation: E) -> Binaural<S, A, E>
+    where
+        S: Source,
+        A: Automation,
+        E: Automation,
+    {
+        Binaural {
+            source,
+            azimuth,
+            elevation,
+            hrtf: self.clone(),
+            history: vec![0.0; self.max_len()],
+            pos: 0,
+            direction: None,
+            current: None,
+            previous: None,
+        }
+    }
+}
+
+/// Parse the elevation and azimuth in degrees from a compact KEMAR file name, like `H-10e045a.wav`
+#[cfg(feature = "wav")]
+fn parse_kemar_name(name: &str) -> Option<(f64, f64)> {
+    let (elevation, azimuth) = name
+        .strip_prefix('H')?
+        .strip_suffix("a.wav")?
+        .split_once('e')?;
+    Some((elevation.parse().ok()?, azimuth.parse().ok()?))
+}
+
+/// Generate the impulse response of one ear
+///
+/// The response is delayed by `delay` frames and low-passed more the more `shadow` approaches `1.0`.
+fn ear_response(len: usize, delay: f64, shadow: f64) -> Vec<f64> {
+    let mut ir = vec![0.0; len];
+    let start = delay as usize;
+    let t = delay.fract();
+    // A one-pole low pass, so that the far ear loses high frequencies
+    let pole = 0.6 * shadow;
+    let gain = (1.0 - pole) * (1.0 - 0.3 * shadow);
+    let mut value = gain;
+    for i in start..len {
+        let sample = value;
+        ir[i] += sample * (1.0 - t);
+        if let Some(next) = ir.get_mut(i + 1) {
+            *next += sample * t;
+        }
+        value *= pole;
+        if value.abs() < 1e-6 {
+            break;
+        }
+    }
+    ir
+}
+
+/// Source returned from [`Hrtf::render`]
+#[derive(Debug, Clone)]
+pub struct Binaural<S, A, E> {
+    source: S,
+    azimuth: A,
+    elevation: E,
+    hrtf: Hrtf,
+    history: Vec<f64>,
+    pos: usize,
+    /// The direction the current measurement was chosen for
+    direction: Option<(f64, f64)>,
+    current: Option<usize>,
+    previous: Option<(usize, usize)>,
+}
+
+impl<S, A, E> Binaural<S, A, E> {
+    /// Resample the impulse responses to the sample rate the source will be played at
+    ///
+    /// Resampling allocates, so it is done here rather than while playing.
+    pub fn sample_rate(self, sample_rate: f64) -> Self {
+        let hrtf = self.hrtf.resampled(sample_rate);
+        Binaural {
+            history: vec![0.0; hrtf.max_len()],
+            hrtf,
+            pos: 0,
+            ..self
+        }
+    }
+    fn convolve(&self, index: usize) -> Stereo {
+        let m = &self.hrtf.measurements[index];
+        let len = self.history.len();
+        let ear = |ir: &[f64]| -> f64 {
+            ir.iter()
+                .enumerate()
+                .map(|(k, h)| h * self.history[(self.pos + len - k) % len])
+                .sum()
+        };
+        Stereo::new(ear(&m.left), ear(&m.right))
+    }
+}
+
+impl<S, A, E> Source for Binaural<S, A, E>
+where
+    S: Source,
+    A: Automation,
+    E: Automation,
+{
+    type Frame = Stereo;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let azimuth = self.azimuth.next_value(sample_rate)?;
+        let elevation = self.elevation.next_value(sample_rate)?;
+        let sample = self.source.next(sample_rate)?.avg();
+        // The nearest measurement is only searched for when the direction changes
+        if self.direction != Some((azimuth, elevation)) {
+            self.direction = Some((azimuth, elevation));
+            let nearest = self.hrtf.nearest(azimuth, elevation);
+            if let (Some(current), Some(nearest)) = (self.current, nearest) {
+                if current != nearest {
+                    self.previous = Some((current, 0));
+                }
+            }
+            self.current = nearest;
+        }
+        let Some(current) = self.current else {
+            return Some(Stereo::both(sample));
+        };
+        debug_assert!(
+            sample_rate == self.hrtf.sample_rate,
+            "binaural source is playing at {sample_rate} Hz, but its responses are at {} Hz; \
+            set the rate with `Binaural::sample_rate`",
+            self.hrtf.sample_rate
+        );
+        self.pos = (self.pos + 1) % self.history.len();
+        self.history[self.pos] = sample;
+        let mut out = self.convolve(current);
+        if let Some((previous, elapsed)) = self.previous {
+            let t = elapsed as f64 / CROSSFADE_FRAMES as f64;
+            let old = self.convolve(previous);
+            out = Stereo::new(lerp(old.left, out.left, t), lerp(old.right, out.right, t));
+            self.previous = (elapsed + 1 < CROSSFADE_FRAMES).then_some((previous, elapsed + 1));
+        }
+        Some(out)
+    }
+}
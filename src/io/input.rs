@@ -1,4 +1,4 @@
-use std::sync::mpsc;
+use std::{collections::VecDeque, sync::mpsc};
 
 use crate::cpal::{
     traits::{DeviceTrait, HostTrait, StreamTrait},
@@ -6,7 +6,8 @@ use crate::cpal::{
 };
 
 use crate::{
-    source::UnrolledSource, BuildSystemAudioError, BuildSystemAudioResult, DeviceIoBuilder,
+    input_config, lerp, source::UnrolledSource, BuildSystemAudioError, BuildSystemAudioResult,
+    DeviceIoBuilder, InputChannels, ThreadSetup, ToDuration,
 };
 
 /// Create an audio input source using the default input device
@@ -32,10 +33,11 @@ pub fn default_input_device() -> Option<Device> {
 ///
 /// It can be turned into a source with [`UnrolledSource::resample`]
 pub struct InputDeviceSource {
-    _stream: Stream,
+    stream: Stream,
     recv: mpsc::Receiver<f64>,
     sample_rate: u32,
     channels: u16,
+    paused: bool,
 }
 
 unsafe impl Send for InputDeviceSource {}
@@ -57,34 +59,99 @@ impl UnrolledSource for InputDeviceSource {
 }
 
 impl InputDeviceSource {
-    pub(crate) fn from_builder(builder: DeviceIoBuilder) -> BuildSystemAudioResult<Self> {
-        let device = if let Some(device) = builder.device {
+    /// Pause capturing audio
+    ///
+    /// The stream and its configuration are kept, so capture can continue with [`InputDeviceSource::resume`].
+    /// Samples that were captured but not yet read remain queued.
+    pub fn pause(&mut self) -> Result<(), PauseStreamError> {
+        self.stream.pause()?;
+        self.paused = true;
+        Ok(())
+    }
+    /// Resume capturing audio after [`InputDeviceSource::pause`]
+    pub fn resume(&mut self) -> Result<(), PlayStreamError> {
+        self.stream.play()?;
+        self.paused = false;
+        Ok(())
+    }
+    /// Check if capture is paused
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+    /// Take the next sample if one has already been captured
+    fn try_next(&self) -> Option<f64> {
+        self.recv.try_recv().ok()
+    }
+    /// Drop all samples that were captured but not yet read
+    ///
+    /// This is useful after resuming, so that playback does not lag behind the input.
+    /// Only whole frames are dropped, so channels stay aligned.
+    pub fn clear_backlog(&mut self) {
+        let channels = self.channels.max(1) as usize;
+        let mut dropped = self.recv.try_iter().count();
+        // The stream callback may have been partway through sending a frame
+        while dropped % channels != 0 && self.recv.recv().is_ok() {
+            dropped += 1;
+        }
+    }
+    pub(crate) fn from_builder(mut builder: DeviceIoBuilder) -> BuildSystemAudioResult<Self> {
+        let device = if let Some(device) = builder.device.take() {
             device
         } else {
             default_input_device().ok_or(BuildSystemAudioError::NoDevice)?
         };
-        let config = if let Some(config) = builder.config {
-            config
-        } else {
-            device.default_input_config()?
-        };
+        let config = input_config(&device, &builder)?;
         let err_fn = |err| eprintln!("An error occurred on the input audio stream: {err}");
         let sample_format = config.sample_format();
         let config: StreamConfig = config.into();
         let (send, recv) = mpsc::channel();
+        let mut setup = ThreadSetup::new(builder.thread_priority, builder.on_thread_start);
+        let gain = builder.input_gain;
+        let device_channels = (config.channels as usize).max(1);
+        let selection = match builder.input_channels {
+            InputChannels::Select(channels) | InputChannels::Merge(channels)
+                if channels.is_empty() =>
+            {
+                InputChannels::All
+            }
+            selection => selection,
+        };
+        let channels = match &selection {
+            InputChannels::All => config.channels,
+            InputChannels::Select(channels) => channels.len() as u16,
+            InputChannels::Merge(_) => 1,
+        };
         macro_rules! input_stream {
-            ($sample:ty, |$x:ident| $convert:expr) => {
+            ($sample:ty, |$x:ident| $convert:expr) => {{
+                let selection = selection.clone();
                 device.build_input_stream(
                     &config,
                     move |data: &[$sample], _: &InputCallbackInfo| {
-                        for &$x in data {
-                            let _ = send.send($convert);
+                        setup.run();
+                        for frame in data.chunks_exact(device_channels) {
+                            let amp = |i: usize| frame.get(i).map_or(0.0, |&$x| $convert) * gain;
+                            match &selection {
+                                InputChannels::All => {
+                                    for i in 0..device_channels {
+                                        let _ = send.send(amp(i));
+                                    }
+                                }
+                                InputChannels::Select(channels) => {
+                                    for &i in channels {
+                                        let _ = send.send(amp(i));
+                                    }
+                                }
+                                InputChannels::Merge(channels) => {
+                                    let sum: f64 = channels.iter().map(|&i| amp(i)).sum();
+                                    let _ = send.send(sum / channels.len() as f64);
+                                }
+                            }
                         }
                     },
                     err_fn,
                     None,
                 )
-            };
+            }};
         }
         let stream = match sample_format {
             SampleFormat::F32 => input_stream!(f32, |x| x as f64),
@@ -103,10 +170,142 @@ impl InputDeviceSource {
         stream.play()?;
 
         Ok(InputDeviceSource {
-            _stream: stream,
+            stream,
             recv,
-            channels: config.channels,
+            channels,
             sample_rate: config.sample_rate.0,
+            paused: false,
         })
     }
 }
+
+/// How long it takes [`AggregateInput`] to respond to changes in an input's backlog, in seconds
+const DRIFT_SMOOTHING: f64 = 0.5;
+/// The largest relative change [`AggregateInput`] makes to an input's rate to compensate for drift
+const MAX_DRIFT_CORRECTION: f64 = 0.005;
+
+/// An [`UnrolledSource`] that combines several [`InputDeviceSource`]s into one multi-channel source
+///
+/// The channels of each input are placed one after another, in the order the inputs were given.
+///
+/// Separate devices run on separate clocks that drift apart over time.
+/// To keep them in sync, every input is resampled to the sample rate of the first one,
+/// and its rate is adjusted slightly to keep the amount of audio buffered from it near
+/// the latency set with [`AggregateInput::latency`].
+///
+/// It can be turned into a source with [`UnrolledSource::resample`]
+pub struct AggregateInput {
+    inputs: Vec<AggregateMember>,
+    sample_rate: f64,
+    channels: usize,
+    latency: f64,
+    frame: Vec<f64>,
+    i: usize,
+}
+
+struct AggregateMember {
+    source: InputDeviceSource,
+    samples: VecDeque<f64>,
+    pos: f64,
+    backlog: Option<f64>,
+}
+
+impl AggregateInput {
+    /// Combine several inputs
+    pub fn new(inputs: impl IntoIterator<Item = InputDeviceSource>) -> Self {
+        let inputs: Vec<AggregateMember> = inputs
+            .into_iter()
+            .map(|source| AggregateMember {
+                source,
+                samples: VecDeque::new(),
+                pos: 0.0,
+                backlog: None,
+            })
+            .collect();
+        let sample_rate = inputs.first().map_or(0.0, |m| m.source.sample_rate());
+        let channels = inputs.iter().map(|m| m.source.channels()).sum();
+        AggregateInput {
+            inputs,
+            sample_rate,
+            channels,
+            latency: 0.05,
+            frame: Vec::new(),
+            i: 0,
+        }
+    }
+    /// Set how much audio to keep buffered from each input
+    ///
+    /// Higher latencies are more tolerant of devices that deliver audio in large blocks.
+    /// The default is 50 milliseconds.
+    pub fn latency(self, latency: impl ToDuration) -> Self {
+        AggregateInput {
+            latency: latency.to_duration().as_secs_f64(),
+            ..self
+        }
+    }
+    /// Get the inputs
+    pub fn inputs(&self) -> impl Iterator<Item = &InputDeviceSource> {
+        self.inputs.iter().map(|m| &m.source)
+    }
+    fn next_frame(&mut self) -> Option<()> {
+        self.frame.clear();
+        for member in &mut self.inputs {
+            let source_rate = member.source.sample_rate();
+            let channels = member.source.channels().max(1);
+            while let Some(sample) = member.source.try_next() {
+                member.samples.push_back(sample);
+            }
+            while member.samples.len() < (member.pos as usize + 2) * channels {
+                member.samples.push_back(member.source.next()?);
+            }
+            let j = member.pos as usize * channels;
+            let t = member.pos.fract();
+            for c in 0..channels {
+                let a = member.samples[j + c];
+                let b = member.samples[j + channels + c];
+                self.frame.push(lerp(a, b, t));
+            }
+            // Nudge the rate to keep the backlog near the target latency
+            let target = (self.latency * source_rate).max(1.0);
+            let backlog = (member.samples.len() / channels) as f64 - member.pos;
+            let smoothed = match member.backlog {
+                Some(smoothed) => lerp(
+                    smoothed,
+                    backlog,
+                    1.0 / (DRIFT_SMOOTHING * self.sample_rate),
+                ),
+                None => backlog,
+            };
+            member.backlog = Some(smoothed);
+            let correction = (0.01 * (smoothed - target) / target)
+                .clamp(-MAX_DRIFT_CORRECTION, MAX_DRIFT_CORRECTION);
+            member.pos += source_rate / self.sample_rate * (1.0 + correction);
+            let whole = member.pos as usize;
+            member.samples.drain(..whole * channels);
+            member.pos -= whole as f64;
+        }
+        Some(())
+    }
+}
+
+impl Iterator for AggregateInput {
+    type Item = f64;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.i >= self.frame.len() {
+            self.next_frame()?;
+            self.i = 0;
+        }
+        let sample = self.frame.get(self.i).copied();
+        self.i += 1;
+        sample
+    }
+}
+
+impl UnrolledSource for AggregateInput {
+    fn channels(&self) -> usize {
+        self.channels
+    }
+    fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+}
@@ -6,6 +6,7 @@ mod output;
 use self::cpal::{traits::DeviceTrait, *};
 #[cfg(feature = "output")]
 use crate::Frame;
+use crate::{set_current_thread_priority, ClipDetector, ThreadPriority};
 #[cfg(feature = "input")]
 pub use input::*;
 #[cfg(feature = "output")]
@@ -45,14 +46,95 @@ pub type BuildSystemAudioResult<T> = Result<T, BuildSystemAudioError>;
 /**
 A builder for creating [`InputDeviceSource`]s and [`OutputDeviceMixer`]s
 */
-#[derive(Default)]
 pub struct DeviceIoBuilder {
     /// The device to use. If not set, the default device will be used.
     pub device: Option<Device>,
     /// The stream configuration to be used. If not set, the default will be used.
     pub config: Option<SupportedStreamConfig>,
+    /// The priority to request for the stream's audio thread
+    pub thread_priority: ThreadPriority,
+    /// A function to run on the stream's audio thread before it processes any audio
+    ///
+    /// It is passed whether the requested thread priority was applied.
+    pub on_thread_start: Option<ThreadStartHook>,
+    /// The preferred sample rate
+    ///
+    /// If set, the supported stream configuration with the nearest sample rate is used.
+    pub sample_rate: Option<u32>,
+    /// The preferred number of channels
+    ///
+    /// If set, the supported stream configuration with the nearest channel count is used.
+    pub channels: Option<u16>,
+    /// The gain applied to captured input samples
+    ///
+    /// This is not used for output.
+    pub input_gain: f64,
+    /// Which of the input device's channels to capture
+    ///
+    /// This is not used for output.
+    pub input_channels: InputChannels,
+    /// How to fill output channels that the frame type does not have
+    ///
+    /// This is not used for input.
+    pub upmix: Upmix,
+    /// Counts output samples that clip
+    ///
+    /// This is not used for input.
+    pub clip_detector: Option<ClipDetector>,
 }
 
+impl Default for DeviceIoBuilder {
+    fn default() -> Self {
+        DeviceIoBuilder {
+            device: None,
+            config: None,
+            thread_priority: ThreadPriority::default(),
+            on_thread_start: None,
+            sample_rate: None,
+            channels: None,
+            input_gain: 1.0,
+            input_channels: InputChannels::default(),
+            upmix: Upmix::default(),
+            clip_detector: None,
+        }
+    }
+}
+
+/// How to fill output device channels beyond those of the frame type
+///
+/// For example, this decides what the rear speakers of a 6-channel device play
+/// when the mixer produces [`Stereo`](crate::Stereo) frames.
+/// Mono frames are always played on every channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Upmix {
+    /// Leave the extra channels silent
+    #[default]
+    Silence,
+    /// Repeat the frame's channels in order, so that rear speakers copy the front ones
+    CopyFronts,
+    /// Play the average of the frame's channels on every extra channel
+    Spread,
+}
+
+/// Which of an input device's channels to capture
+///
+/// Channel indices start at `0`. Indices the device does not have are captured as silence.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum InputChannels {
+    /// Capture every channel
+    #[default]
+    All,
+    /// Capture only the given channels, in the given order
+    Select(Vec<usize>),
+    /// Capture the average of the given channels as a single channel
+    Merge(Vec<usize>),
+}
+
+/// A function run on an audio thread when it starts
+///
+/// It is passed whether the requested thread priority was applied.
+pub type ThreadStartHook = Box<dyn FnOnce(bool) + Send + 'static>;
+
 impl DeviceIoBuilder {
     /// Initialize a builder with the default input device and stream configuration
     #[cfg(feature = "input")]
@@ -61,7 +143,11 @@ impl DeviceIoBuilder {
         let config = device
             .as_ref()
             .and_then(|device| device.default_input_config().ok());
-        DeviceIoBuilder { device, config }
+        DeviceIoBuilder {
+            device,
+            config,
+            ..Default::default()
+        }
     }
     /// Initialize a builder with the default output device and stream configuration
     #[cfg(feature = "output")]
@@ -70,7 +156,11 @@ impl DeviceIoBuilder {
         let config = device
             .as_ref()
             .and_then(|device| device.default_output_config().ok());
-        DeviceIoBuilder { device, config }
+        DeviceIoBuilder {
+            device,
+            config,
+            ..Default::default()
+        }
     }
     /// Set the input device
     pub fn device(self, device: Device) -> Self {
@@ -86,6 +176,82 @@ impl DeviceIoBuilder {
             ..self
         }
     }
+    /// Set the preferred sample rate
+    ///
+    /// When the stream is built, the device's supported configuration with the nearest sample rate is chosen,
+    /// so the actual rate may differ. It can be checked after building.
+    pub fn sample_rate(self, sample_rate: u32) -> Self {
+        DeviceIoBuilder {
+            sample_rate: Some(sample_rate),
+            ..self
+        }
+    }
+    /// Set the preferred number of channels
+    ///
+    /// When the stream is built, the device's supported configuration with the nearest channel count is chosen,
+    /// so the actual count may differ.
+    pub fn channels(self, channels: u16) -> Self {
+        DeviceIoBuilder {
+            channels: Some(channels),
+            ..self
+        }
+    }
+    /// Set the priority to request for the stream's audio thread
+    ///
+    /// Whether it was applied is passed to the [`DeviceIoBuilder::on_thread_start`] function.
+    ///
+    /// The default is [`ThreadPriority::Default`]
+    pub fn thread_priority(self, thread_priority: ThreadPriority) -> Self {
+        DeviceIoBuilder {
+            thread_priority,
+            ..self
+        }
+    }
+    /// Set a function to run on the stream's audio thread before it processes any audio
+    ///
+    /// This can be used to configure the thread in ways this crate does not support,
+    /// like pinning it to a core with [`pin_current_thread`](crate::pin_current_thread).
+    ///
+    /// The function is passed whether the priority set with [`DeviceIoBuilder::thread_priority`] was applied,
+    /// so that a failure to get realtime priority can be reported.
+    pub fn on_thread_start(self, f: impl FnOnce(bool) + Send + 'static) -> Self {
+        DeviceIoBuilder {
+            on_thread_start: Some(Box::new(f)),
+            ..self
+        }
+    }
+    /// Set the gain applied to captured input samples
+    ///
+    /// The default is `1.0`
+    pub fn input_gain(self, input_gain: f64) -> Self {
+        DeviceIoBuilder { input_gain, ..self }
+    }
+    /// Set which of the input device's channels to capture
+    ///
+    /// Channels are selected as audio is captured, so unwanted channels are never queued or resampled.
+    /// The default is [`InputChannels::All`]
+    pub fn input_channels(self, input_channels: InputChannels) -> Self {
+        DeviceIoBuilder {
+            input_channels,
+            ..self
+        }
+    }
+    /// Set how to fill output channels that the frame type does not have
+    ///
+    /// The default is [`Upmix::Silence`]
+    pub fn upmix(self, upmix: Upmix) -> Self {
+        DeviceIoBuilder { upmix, ..self }
+    }
+    /// Count output samples beyond `±1.0` with a [`ClipDetector`]
+    ///
+    /// They are counted before being converted to the device's sample format.
+    /// By default, clipping is not counted.
+    pub fn clip_detector(self, clip_detector: ClipDetector) -> Self {
+        DeviceIoBuilder {
+            clip_detector: Some(clip_detector),
+            ..self
+        }
+    }
     /// Build an [`InputDeviceSource`]
     #[cfg(feature = "input")]
     pub fn build_input(self) -> BuildSystemAudioResult<InputDeviceSource> {
@@ -100,3 +266,164 @@ impl DeviceIoBuilder {
         OutputDeviceMixer::from_builder(self)
     }
 }
+
+/// Applies a [`DeviceIoBuilder`]'s thread settings the first time a stream callback runs
+pub(crate) struct ThreadSetup {
+    priority: ThreadPriority,
+    on_start: Option<ThreadStartHook>,
+    done: bool,
+}
+
+impl ThreadSetup {
+    pub(crate) fn new(priority: ThreadPriority, on_start: Option<ThreadStartHook>) -> Self {
+        ThreadSetup {
+            priority,
+            on_start,
+            done: false,
+        }
+    }
+    pub(crate) fn run(&mut self) {
+        if self.done {
+            return;
+        }
+        self.done = true;
+        let applied = set_current_thread_priority(self.priority);
+        if let Some(f) = self.on_start.take() {
+            f(applied);
+        }
+    }
+}
+
+/// Choose the supported stream configuration that best matches a builder's preferences
+///
+/// Channel count is matched before sample rate, and the sample format of `config` is preferred on ties.
+fn choose_config(
+    config: SupportedStreamConfig,
+    supported: impl Iterator<Item = SupportedStreamConfigRange>,
+    sample_rate: Option<u32>,
+    channels: Option<u16>,
+) -> SupportedStreamConfig {
+    if sample_rate.is_none() && channels.is_none() {
+        return config;
+    }
+    let sample_rate = sample_rate.unwrap_or(config.sample_rate().0);
+    let channels = channels.unwrap_or(config.channels());
+    supported
+        .map(|range| {
+            let rate = sample_rate.clamp(range.min_sample_rate().0, range.max_sample_rate().0);
+            let score = (
+                range.channels().abs_diff(channels),
+                rate.abs_diff(sample_rate),
+                range.sample_format() != config.sample_format(),
+            );
+            (score, range.with_sample_rate(SampleRate(rate)))
+        })
+        .min_by_key(|(score, _)| *score)
+        .map_or(config, |(_, config)| config)
+}
+
+/// Get the stream configuration to use for an input device
+#[cfg(feature = "input")]
+pub(crate) fn input_config(
+    device: &Device,
+    builder: &DeviceIoBuilder,
+) -> BuildSystemAudioResult<SupportedStreamConfig> {
+    let config = match &builder.config {
+        Some(config) => config.clone(),
+        None => device.default_input_config()?,
+    };
+    if builder.sample_rate.is_none() && builder.channels.is_none() {
+        return Ok(config);
+    }
+    let supported = device.supported_input_configs()?;
+    Ok(choose_config(
+        config,
+        supported,
+        builder.sample_rate,
+        builder.channels,
+    ))
+}
+
+/// Get the stream configuration to use for an output device
+#[cfg(feature = "output")]
+pub(crate) fn output_config(
+    device: &Device,
+    builder: &DeviceIoBuilder,
+) -> BuildSystemAudioResult<SupportedStreamConfig> {
+    let config = match &builder.config {
+        Some(config) => config.clone(),
+        None => device.default_output_config()?,
+    };
+    if builder.sample_rate.is_none() && builder.channels.is_none() {
+        return Ok(config);
+    }
+    let supported = device.supported_output_configs()?;
+    Ok(choose_config(
+        config,
+        supported,
+        builder.sample_rate,
+        builder.channels,
+    ))
+}
+
+/// Measure the impulse response of the path from an output to an input, like a room or a speaker and microphone
+///
+/// A sweep is played on the output while the input is recorded.
+/// Recording continues for `tail` after the sweep ends, which should be at least as long as the expected response,
+/// like the reverb time of a room. The recording is then [`deconvolve`](crate::deconvolve)d with the sweep.
+///
+/// This blocks until the measurement is done.
+/// The output and input must run at the same sample rate, or a [`SampleRateMismatch`] is returned
+/// without playing anything.
+/// The returned impulse response's channels are mixed to mono. It includes the latency of the devices.
+#[cfg(all(feature = "input", feature = "output"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "input", feature = "output"))))]
+pub fn measure_ir<F: Frame>(
+    output: &OutputDeviceMixer<F>,
+    input: &mut InputDeviceSource,
+    sweep: crate::SineSweep,
+    tail: impl crate::ToDuration,
+) -> Result<crate::Sample<crate::Mono>, SampleRateMismatch> {
+    use crate::{source::UnrolledSource, Source};
+    let sample_rate = input.sample_rate();
+    if output.sample_rate() != sample_rate {
+        return Err(SampleRateMismatch {
+            output: output.sample_rate(),
+            input: sample_rate,
+        });
+    }
+    let channels = input.channels().max(1);
+    let mut excitation = Vec::new();
+    let mut rendered = sweep;
+    while let Some(sample) = rendered.next(sample_rate) {
+        excitation.push(sample);
+    }
+    let len = excitation.len() + (tail.to_duration().as_secs_f64() * sample_rate) as usize;
+    input.clear_backlog();
+    output.add(sweep.map(F::uniform));
+    let mut response = Vec::with_capacity(len);
+    'record: while response.len() < len {
+        let mut sum = 0.0;
+        for _ in 0..channels {
+            let Some(sample) = input.next() else {
+                break 'record;
+            };
+            sum += sample;
+        }
+        response.push(sum / channels as f64);
+    }
+    let ir = crate::deconvolve(&excitation, &response);
+    Ok(crate::Sample::from_frames(ir, sample_rate))
+}
+
+/// The error returned by [`measure_ir`] when the output and input run at different sample rates
+#[cfg(all(feature = "input", feature = "output"))]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "input", feature = "output"))))]
+#[derive(Debug, Clone, Copy, PartialEq, thiserror::Error)]
+#[error("the output runs at {output} Hz but the input runs at {input} Hz")]
+pub struct SampleRateMismatch {
+    /// The output's sample rate
+    pub output: f64,
+    /// The input's sample rate
+    pub input: f64,
+}
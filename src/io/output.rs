@@ -1,15 +1,23 @@
-use std::{thread, time::Duration};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 
 use crate::{
     cpal::{
         traits::{DeviceTrait, HostTrait, StreamTrait},
         *,
     },
-    Mixer,
+    ChannelCheck, ClipDetector, Mixer, SourceHandle,
 };
 
 use crate::{
-    Amplitude, BuildSystemAudioError, BuildSystemAudioResult, DeviceIoBuilder, Frame, Source,
+    output_config, Amplitude, BuildSystemAudioError, BuildSystemAudioResult, DeviceIoBuilder,
+    Frame, Source, ThreadSetup, Upmix,
 };
 
 /// Create an audio output mixer using the default output device and start it playing immediately
@@ -19,6 +27,15 @@ pub fn default_output<F: Frame>() -> BuildSystemAudioResult<OutputDeviceMixer<F>
     DeviceIoBuilder::default_output().build_output()
 }
 
+/// Beep on each channel of an output in turn, for checking speaker setups
+///
+/// See [`ChannelCheck`] for how channels are identified.
+/// Only the channels of the frame type are checked, so to check every channel of a surround device,
+/// use a frame type with as many channels, like [`MultiChannel`](crate::MultiChannel).
+pub fn channel_check<F: Frame>(output: &OutputDeviceMixer<F>) -> SourceHandle {
+    output.add(ChannelCheck::new())
+}
+
 /// Get the default output device
 pub fn default_output_device() -> Option<Device> {
     default_host().default_output_device()
@@ -31,6 +48,90 @@ pub struct OutputDeviceMixer<F> {
     mixer: Mixer<F>,
     _stream: Stream,
     sample_rate: u32,
+    channels: u16,
+    stats: Arc<StatsCounters>,
+}
+
+/// Statistics about an [`OutputDeviceMixer`]'s audio callback
+///
+/// These can be used to warn users about glitches or to reduce the amount of processing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OutputStats {
+    /// The number of times the audio callback has been called
+    pub callbacks: u64,
+    /// The number of frames written to the device
+    pub frames: u64,
+    /// The number of times the device likely ran out of audio to play
+    ///
+    /// This is detected when the time between callbacks is much longer
+    /// than the audio written in the previous callback.
+    pub underruns: u64,
+    /// The number of callbacks that took longer to run than the audio they produced
+    pub overloads: u64,
+    /// The number of errors reported by the audio stream
+    pub errors: u64,
+    /// The total time spent in the audio callback
+    pub total_callback_time: Duration,
+    /// The longest time spent in a single audio callback
+    pub max_callback_time: Duration,
+}
+
+impl OutputStats {
+    /// Get the average time spent in the audio callback
+    pub fn average_callback_time(&self) -> Duration {
+        if self.callbacks == 0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(self.total_callback_time.as_secs_f64() / self.callbacks as f64)
+        }
+    }
+}
+
+/// The counters behind [`OutputStats`], updated by the audio callback without locking
+#[derive(Default)]
+struct StatsCounters {
+    callbacks: AtomicU64,
+    frames: AtomicU64,
+    underruns: AtomicU64,
+    overloads: AtomicU64,
+    errors: AtomicU64,
+    /// In nanoseconds
+    total_callback_time: AtomicU64,
+    /// In nanoseconds
+    max_callback_time: AtomicU64,
+}
+
+impl StatsCounters {
+    fn counters(&self) -> [&AtomicU64; 7] {
+        [
+            &self.callbacks,
+            &self.frames,
+            &self.underruns,
+            &self.overloads,
+            &self.errors,
+            &self.total_callback_time,
+            &self.max_callback_time,
+        ]
+    }
+    fn get(&self) -> OutputStats {
+        let [callbacks, frames, underruns, overloads, errors, total, max] = self
+            .counters()
+            .map(|counter| counter.load(Ordering::Relaxed));
+        OutputStats {
+            callbacks,
+            frames,
+            underruns,
+            overloads,
+            errors,
+            total_callback_time: Duration::from_nanos(total),
+            max_callback_time: Duration::from_nanos(max),
+        }
+    }
+    fn reset(&self) {
+        for counter in self.counters() {
+            counter.store(0, Ordering::Relaxed);
+        }
+    }
 }
 
 impl<F> OutputDeviceMixer<F>
@@ -41,6 +142,12 @@ where
     pub fn sample_rate(&self) -> f64 {
         self.sample_rate as f64
     }
+    /// Get the number of channels the device was opened with
+    ///
+    /// This may be more than the frame type has. Extra channels are filled according to the builder's [`Upmix`].
+    pub fn channels(&self) -> usize {
+        self.channels as usize
+    }
     /// Block the thread until all sources have finished
     pub fn block(&mut self) {
         while self
@@ -52,38 +159,66 @@ where
             thread::sleep(Duration::from_millis(1));
         }
     }
+    /// Get a future that resolves when all sources have finished
+    ///
+    /// This is the async equivalent of [`block`](Self::block).
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub fn wait_idle(&self) -> crate::Idle {
+        self.mixer.idle()
+    }
     /// Add a source to the mixer to be played immediately
-    pub fn add<S>(&self, source: S)
+    pub fn add<S>(&self, source: S) -> SourceHandle
     where
         S: Source<Frame = F> + Send + 'static,
     {
-        self.mixer.add(source);
+        self.mixer.add(source)
     }
     /// Get a reference to the mixer
     pub fn mixer(&self) -> &Mixer<F> {
         &self.mixer
     }
-    pub(crate) fn from_builder(builder: DeviceIoBuilder) -> BuildSystemAudioResult<Self> {
-        let device = if let Some(device) = builder.device {
+    /// Get statistics about the audio callback
+    pub fn stats(&self) -> OutputStats {
+        self.stats.get()
+    }
+    /// Reset the audio callback statistics
+    pub fn reset_stats(&self) {
+        self.stats.reset();
+    }
+    pub(crate) fn from_builder(mut builder: DeviceIoBuilder) -> BuildSystemAudioResult<Self> {
+        let device = if let Some(device) = builder.device.take() {
             device
         } else {
             default_output_device().ok_or(BuildSystemAudioError::NoDevice)?
         };
-        let config = if let Some(config) = builder.config {
-            config
-        } else {
-            device.default_output_config()?
-        };
+        let config = output_config(&device, &builder)?;
         let sample_format = config.sample_format();
         let config = StreamConfig::from(config);
-        let err_fn = |err| eprintln!("an error occurred on the output audio stream: {err}");
+        let stats = Arc::new(StatsCounters::default());
+        let err_stats = stats.clone();
+        let err_fn = move |err| {
+            err_stats.errors.fetch_add(1, Ordering::Relaxed);
+            eprintln!("an error occurred on the output audio stream: {err}")
+        };
         let mixer = Mixer::new();
         let mixer_clone = mixer.clone();
+        let stats_clone = stats.clone();
+        let setup = ThreadSetup::new(builder.thread_priority, builder.on_thread_start);
+        let upmix = builder.upmix;
+        let clips = builder.clip_detector;
         macro_rules! output_stream {
             ($sample:ty) => {
                 device.build_output_stream(
                     &config,
-                    write_sources::<F, $sample>(mixer_clone, &config),
+                    write_sources::<F, $sample>(
+                        mixer_clone,
+                        &config,
+                        stats_clone,
+                        setup,
+                        upmix,
+                        clips,
+                    ),
                     err_fn,
                     None,
                 )
@@ -110,6 +245,8 @@ where
             mixer,
             _stream: stream,
             sample_rate: config.sample_rate.0,
+            channels: config.channels,
+            stats,
         })
     }
 }
@@ -117,6 +254,10 @@ where
 fn write_sources<F, A>(
     mut mixer: Mixer<F>,
     config: &StreamConfig,
+    stats: Arc<StatsCounters>,
+    mut setup: ThreadSetup,
+    upmix: Upmix,
+    clips: Option<ClipDetector>,
 ) -> impl FnMut(&mut [A], &OutputCallbackInfo)
 where
     F: Frame,
@@ -126,13 +267,22 @@ where
     let sample_rate = config.sample_rate.0 as f64;
     let mut frame_buffer = vec![0.0; channels];
     let mut i = channels;
-    move |buffer, _| {
+    let mut last_callback: Option<(StreamInstant, Duration)> = None;
+    move |buffer, info| {
+        setup.run();
+        let start = Instant::now();
+        let frames = (buffer.len() / channels.max(1)) as u64;
+        let audio_time = Duration::from_secs_f64(frames as f64 / sample_rate);
         buffer.fill(A::MIDPOINT);
         for out_sample in buffer {
             if i >= channels {
                 i = 0;
                 if let Some(frame) = mixer.next(sample_rate) {
                     frame.write_slice(&mut frame_buffer);
+                    if let Some(clips) = &clips {
+                        clips.count_master(&frame_buffer[..F::CHANNELS.min(channels)]);
+                    }
+                    upmix_frame(upmix, F::CHANNELS, &mut frame_buffer);
                 } else {
                     break;
                 }
@@ -140,5 +290,37 @@ where
             *out_sample = A::from_f64(frame_buffer[i]);
             i += 1;
         }
+        let elapsed = start.elapsed();
+        let callback = info.timestamp().callback;
+        let underrun = last_callback.is_some_and(|(last, last_audio_time)| {
+            callback
+                .duration_since(&last)
+                .is_some_and(|gap| gap > last_audio_time.mul_f64(1.5))
+        });
+        last_callback = Some((callback, audio_time));
+        let elapsed_nanos = elapsed.as_nanos() as u64;
+        stats.callbacks.fetch_add(1, Ordering::Relaxed);
+        stats.frames.fetch_add(frames, Ordering::Relaxed);
+        (stats.underruns).fetch_add(underrun as u64, Ordering::Relaxed);
+        (stats.overloads).fetch_add((elapsed > audio_time) as u64, Ordering::Relaxed);
+        (stats.total_callback_time).fetch_add(elapsed_nanos, Ordering::Relaxed);
+        (stats.max_callback_time).fetch_max(elapsed_nanos, Ordering::Relaxed);
+    }
+}
+
+/// Fill the channels of a device frame beyond the first `frame_channels`
+fn upmix_frame(upmix: Upmix, frame_channels: usize, buffer: &mut [f64]) {
+    if frame_channels <= 1 || frame_channels >= buffer.len() {
+        return;
+    }
+    let (frame, extra) = buffer.split_at_mut(frame_channels);
+    match upmix {
+        Upmix::Silence => {}
+        Upmix::CopyFronts => {
+            for (i, sample) in extra.iter_mut().enumerate() {
+                *sample = frame[i % frame_channels];
+            }
+        }
+        Upmix::Spread => extra.fill(frame.iter().sum::<f64>() / frame_channels as f64),
     }
 }
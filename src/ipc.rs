@@ -0,0 +1,256 @@
+//! Streaming audio between processes
+//!
+//! Audio is sent over any byte stream, such as a Unix domain socket, a local TCP connection, or a pipe.
+//! [`send`] streams the frames of a source while it plays, and [`receive`] plays a stream as a source.
+//!
+//! The stream starts with a header containing the channel count and sample rate,
+//! followed by interleaved little-endian [`f32`] samples.
+//!
+//! This module is only available when the `ipc` feature is enabled.
+
+use std::{
+    io::{self, Read, Write},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    thread,
+};
+
+use crate::{Frame, QueueProducer, QueueSource, Source, ToDuration, Underrun};
+
+/// The first bytes of a stream
+const MAGIC: &[u8; 8] = b"HODAUNIP";
+/// The number of frames sent to the writer thread at a time
+const CHUNK_FRAMES: usize = 256;
+/// The number of chunks that can be waiting to be written before new ones are dropped
+const CHUNK_CAPACITY: usize = 64;
+
+/// Stream the frames of a source to a writer while the source plays
+///
+/// Writing happens on a separate thread, so a slow reader never blocks the audio thread.
+/// If the writer falls too far behind, frames are dropped.
+pub fn send<S, W>(source: S, writer: W) -> IpcSink<S>
+where
+    S: Source,
+    W: Write + Send + 'static,
+{
+    let (send, recv) = mpsc::sync_channel::<(f64, Vec<f32>)>(CHUNK_CAPACITY);
+    // Chunks are allocated here and passed back and forth so that the audio thread never allocates
+    let chunk_len = CHUNK_FRAMES * S::Frame::CHANNELS;
+    let (recycle, spare) = mpsc::sync_channel(CHUNK_CAPACITY);
+    for _ in 0..CHUNK_CAPACITY {
+        let _ = recycle.try_send(Vec::with_capacity(chunk_len));
+    }
+    let connected = Arc::new(AtomicBool::new(true));
+    let thread_connected = connected.clone();
+    thread::Builder::new()
+        .name("hodaun ipc writer".into())
+        .spawn(move || {
+            let mut writer = writer;
+            let mut header_written = false;
+            let res = recv.iter().try_for_each(|(sample_rate, mut samples)| {
+                if !header_written {
+                    header_written = true;
+                    writer.write_all(MAGIC)?;
+                    writer.write_all(&(S::Frame::CHANNELS as u32).to_le_bytes())?;
+                    writer.write_all(&sample_rate.to_le_bytes())?;
+                }
+                let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+                writer.write_all(&bytes)?;
+                samples.clear();
+                let _ = recycle.try_send(samples);
+                writer.flush()
+            });
+            if res.is_err() {
+                thread_connected.store(false, Ordering::Relaxed);
+            }
+        })
+        .expect("failed to spawn ipc writer thread");
+    IpcSink {
+        source,
+        send: Some(send),
+        buffer: Vec::with_capacity(chunk_len),
+        spare,
+        connected,
+    }
+}
+
+/// Source returned from [`send`]
+///
+/// It plays the inner source unchanged.
+pub struct IpcSink<S> {
+    source: S,
+    send: Option<mpsc::SyncSender<(f64, Vec<f32>)>>,
+    buffer: Vec<f32>,
+    /// Empty chunks returned by the writer thread
+    spare: mpsc::Receiver<Vec<f32>>,
+    connected: Arc<AtomicBool>,
+}
+
+impl<S> IpcSink<S> {
+    /// Check if the stream can still be written to
+    pub fn is_connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+}
+
+impl<S> Source for IpcSink<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let frame = self.source.next(sample_rate);
+        let Some(send) = &self.send else {
+            return frame;
+        };
+        if let Some(frame) = &frame {
+            for i in 0..S::Frame::CHANNELS {
+                self.buffer.push(frame.get_channel(i) as f32);
+            }
+        }
+        if self.buffer.len() >= CHUNK_FRAMES * S::Frame::CHANNELS
+            || frame.is_none() && !self.buffer.is_empty()
+        {
+            match self.spare.try_recv() {
+                Ok(spare) => {
+                    let chunk = std::mem::replace(&mut self.buffer, spare);
+                    // There are only as many chunks as the channel holds, so it is never full here
+                    if let Err(mpsc::TrySendError::Disconnected(_)) =
+                        send.try_send((sample_rate, chunk))
+                    {
+                        self.send = None;
+                    }
+                }
+                // Every chunk is waiting to be written, so the writer is too far behind
+                Err(mpsc::TryRecvError::Empty) => self.buffer.clear(),
+                Err(mpsc::TryRecvError::Disconnected) => self.send = None,
+            }
+        }
+        if frame.is_none() {
+            // Closing the channel lets the writer thread finish
+            self.send = None;
+        }
+        frame
+    }
+}
+
+/// Play audio streamed from a reader
+///
+/// The stream header is read before this function returns.
+/// The rest of the stream is read on a separate thread.
+/// The source ends when the stream does.
+pub fn receive<F, R>(mut reader: R) -> io::Result<IpcSource<F>>
+where
+    F: Frame + Send + 'static,
+    R: Read + Send + 'static,
+{
+    let mut header = [0; 20];
+    reader.read_exact(&mut header)?;
+    if &header[..8] != MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid ipc stream header",
+        ));
+    }
+    let channels = u32::from_le_bytes(header[8..12].try_into().unwrap()) as usize;
+    let sample_rate = f64::from_le_bytes(header[12..20].try_into().unwrap());
+    if channels == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "ipc stream has no channels",
+        ));
+    }
+    let (producer, queue) = QueueSource::new();
+    thread::Builder::new()
+        .name("hodaun ipc reader".into())
+        .spawn(move || read_frames(reader, channels, producer))?;
+    Ok(IpcSource {
+        queue,
+        channels,
+        sample_rate,
+    })
+}
+
+fn read_frames<F, R>(mut reader: R, channels: usize, producer: QueueProducer<F>)
+where
+    F: Frame,
+    R: Read,
+{
+    let mut bytes = vec![0; CHUNK_FRAMES * channels * 4];
+    let mut filled = 0;
+    loop {
+        match reader.read(&mut bytes[filled..]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => filled += n,
+        }
+        let whole = filled - filled % (channels * 4);
+        let samples: Vec<f64> = bytes[..whole]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()) as f64)
+            .collect();
+        producer.extend(samples.chunks_exact(channels).map(|samples| {
+            if F::CHANNELS == 1 {
+                F::uniform(samples.iter().sum::<f64>() / channels as f64)
+            } else if channels == 1 {
+                F::uniform(samples[0])
+            } else {
+                let mut frame = F::uniform(0.0);
+                for (i, &sample) in samples.iter().enumerate().take(F::CHANNELS) {
+                    frame.set_channel(i, sample);
+                }
+                frame
+            }
+        }));
+        bytes.copy_within(whole..filled, 0);
+        filled -= whole;
+    }
+}
+
+/// Source returned from [`receive`]
+///
+/// Frames are played at the output sample rate, so the stream's
+/// [`IpcSource::sample_rate`] should match it.
+pub struct IpcSource<F> {
+    queue: QueueSource<F>,
+    channels: usize,
+    sample_rate: f64,
+}
+
+impl<F> IpcSource<F> {
+    /// Get the number of channels in the stream
+    pub fn channels(&self) -> usize {
+        self.channels
+    }
+    /// Get the sample rate of the stream
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+    /// Set how much audio must be received before playback starts or resumes after an underrun
+    ///
+    /// See [`QueueSource::jitter_buffer`].
+    pub fn jitter_buffer(self, duration: impl ToDuration) -> Self {
+        IpcSource {
+            queue: self.queue.jitter_buffer(duration),
+            ..self
+        }
+    }
+    /// Set what the source does when it runs out of frames
+    pub fn on_underrun(self, underrun: Underrun) -> Self {
+        IpcSource {
+            queue: self.queue.on_underrun(underrun),
+            ..self
+        }
+    }
+}
+
+impl<F> Source for IpcSource<F>
+where
+    F: Frame,
+{
+    type Frame = F;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        self.queue.next(sample_rate)
+    }
+}
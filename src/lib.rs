@@ -1,3 +1,4 @@
+#![cfg_attr(not(feature = "std"), no_std)]
 #![warn(missing_docs)]
 #![allow(clippy::needless_range_loop)]
 
@@ -14,9 +15,13 @@ This crate provides interfaces for audio synthesis, mixing, input, and output.
 
 The [`Source`] trait generalizes streamed audio data. The associated type [`Source::Frame`]
 implements the [`Frame`] trait, and represents a single sample of audio data for multiple channels.
+[`Mono`], [`Stereo`], and [`MultiChannel`] are the common frame types. [`MultiChannel`] is useful for surround audio.
 
 [`Source`] has many utility functions, much like [`Iterator`], for processing and combining audio data.
 
+[`StatefulSource::snapshot`] captures the playback position of a source chain as a [`SourceState`],
+and [`StatefulSource::restore`] resumes a newly built chain from exactly that position.
+
 ## Automation
 
 Many [`Source`] functions take parameters that can be automated, meaning they may change either automatically
@@ -26,13 +31,53 @@ as an automation parameter.
 The primary [`Automation`] implementors are:
 - [`f64`], `(`[`Letter`]`,`[`Octave`]`)`, and [`Pitch`] for constant values
 - [`Shared`]`<A: `[`Automation`]`>` for values that can be changed by other code
+- [`SharedView`]`<T, A: `[`Automation`]`>` for values within a larger [`Shared`] value
+- [`DbParam`] and [`LogHzParam`] for gain and frequency knobs set from normalized UI values
 - [`Source`]`<Frame = f64>` for values that change over time
 
+[`FrameAutomation`] is for parameters that can be automated per channel, such as [`Source::amplify`]'s.
+Every [`Automation`] implements it, as do [`Stereo`] and [`PerChannel`]`<S: `[`Source`]`>`.
+
+[`Portamento`] glides smoothly to a target whenever it changes, for monosynth-style pitch slides.
+
+The [`midi`] module maps MIDI controllers to parameters with configurable ranges and curves, including MIDI learn.
+[`midi::MidiVoices`] plays a voice for each MIDI note, with MPE support for per-note pitch bend, pressure, and timbre.
+[`midi::MidiVoices::mono`] plays one note at a time with last, high, or low note priority, and optional legato.
+It is only available when the `midi` feature is enabled.
+
+[`Smoothed`] smooths changes in another automation to avoid zipper noise.
+[`Source::amplify_smoothed`], [`Source::pan_smoothed`], and [`Source::low_pass_smoothed`] apply it for you.
+
 ## Mixing
 
 [`Mixer`] is a [`Source`] that allows simple audio mixing.
 
 Sources can be added to a [`Mixer`] with [`Mixer::add`].
+It returns a [`SourceHandle`], which can stop the source with [`SourceHandle::stop_with_fade`].
+
+[`Mixer::timeline`] creates an automation whose changes are scheduled at exact mixer frames.
+
+[`Mixer::events`] creates a channel for [`Event`]s that sources emit while playing, without blocking or allocating.
+Events are timestamped with the exact mixer frame they were emitted during.
+
+[`Sample`] is a buffer of audio that can be played at different rates.
+With the `noise` feature, [`Mixer::play_oneshot`] plays a sample with a randomized gain and pitch.
+[`Sample::with_sustain_loop`] repeats part of a sample while a note is held, crossfading at the seam.
+[`MultiSample`] switches between velocity layers of samples and alternates between round-robin samples within a layer.
+With the `notes` feature, a [`KeyMap`] assigns samples to ranges of keys and repitches them, forming a playable instrument.
+
+[`ParallelMixer`] renders its sources on a pool of worker threads, for very dense mixes and faster offline rendering.
+
+[`Player`] is a [`Source`] that plays a queue of tracks one after another, with gapless or crossfaded transitions. `Player::enqueue_file` streams WAV, Ogg Vorbis, and Opus files.
+
+[`Slot`] is a [`Source`] whose inner source can be swapped from another thread, with an optional crossfade.
+[`Source::ab_switch`] toggles between two processing chains fed by the same source, optionally matching their loudness.
+
+[`QueueSource`] plays frames pushed from another thread through a [`QueueProducer`].
+
+[`ClipDetector`] counts samples beyond ±1.0 per source and on the output, to find which source is clipping.
+
+[`GameAudio`] mixes separate music, sound effect, and voice-over buses, and ducks the music while voice-over plays.
 
 ## Synthesis
 
@@ -44,30 +89,134 @@ There are helpful type aliases for common waveforms such as [`SineWave`] and [`S
 
 [`Noise`] is a source that generates white noise. It requires the `noise` feature.
 
+[`RandomHold`] and [`SmoothRandom`] generate random control signals for modulation. They also require the `noise` feature.
+
+[`RandomChoice`] is a source that plays one of several variants at random. It also requires the `noise` feature.
+
+[`SineSweep`], [`Impulse`], and [`Mls`] generate signals for measuring rooms, speakers, and filters.
+
+[`Tempo`] is a shared tempo and time signature. Its [`Beats`] durations follow tempo changes,
+so `repeat.every(tempo.bars(1.0))` stays locked to the tempo.
+[`Tempo::note`] gives the length of a [`NoteValue`], like a dotted eighth, for tempo-synced delay times,
+and [`Beats::rate`] turns a duration into a tempo-synced LFO rate.
+
+[`Pattern`] describes a rhythm of hits and rests, such as a euclidean rhythm.
+It can be played with [`Repeat::pattern`](source::Repeat::pattern).
+
+[`Source::notch`] removes a single frequency, such as mains hum, with an automatable frequency and q.
+
+[`Source::formant`] filters a source through vowel formants, morphing between [`Vowel`]s for robotic vocal effects.
+
+[`Source::delay`] adds echoes with feedback, and its time, feedback, and mix can all be automated.
+
+[`Source::reverb`] adds Freeverb-style reverberation with automatable room size, damping, and mix.
+
+[`Source::excite`] brightens dull sources by adding harmonics of their high frequencies.
+
+[`FilterResponse`] evaluates the magnitude and phase response of filters for drawing EQ curves.
+
+[`Source::freeze`] renders an expensive source into a buffer once and plays the buffer after that.
+
+[`Source::looper`] records a source, such as an input device, into loops that play back in sync, with overdub and undo.
+
+## Analysis
+
+[`Source::onsets`] detects onsets in a source as it plays and estimates its tempo.
+
+[`Source::bands`] measures the energy in log-spaced frequency bands for visualizers.
+
+[`Source::goniometer`] measures the correlation between stereo channels and keeps recent samples for vectorscopes.
+
+[`Peaks`] records the minimum and maximum amplitudes of a finite source for drawing waveform overviews.
+Peaks can be saved to disk with [`Peaks::save`] so long files only need to be scanned once.
+
+[`detect_silence`] finds the silent parts of a source, and [`Source::trim_silence`] skips leading and trailing silence.
+
+[`Source::vad`] detects speech in a source, which is useful for gating or segmenting voice recordings.
+
+[`Source::meter`] measures the CPU time spent producing a source's frames, and [`AdaptiveQuality`] uses it
+to lower the quality of expensive settings when processing is overloaded.
+They are only available when the `meter` feature is enabled.
+
+[`deconvolve`] recovers an impulse response from a recorded sweep, and with the `input` and `output` features,
+[`measure_ir`] plays and records the sweep as well.
+
+## Spatial Audio
+
+The [`ambisonics`] module encodes sources into a first-order ambisonic sound field,
+which can be rotated and decoded to stereo speakers or headphones.
+It is only available when the `ambisonics` feature is enabled.
+
+The [`hrtf`] module renders positioned sources binaurally for headphones using head-related impulse responses.
+It is only available when the `hrtf` feature is enabled.
+With the `wav` feature, [`Hrtf::load_kemar`](hrtf::Hrtf::load_kemar) loads the MIT KEMAR measurements.
+
 ## Output
 
 [`OutputDeviceMixer`] allows the mixing of audio [`Source`]s and output to an audio device.
 An [`OutputDeviceMixer`] for the default output device can be created with [`default_output`].
 For more nuanced control, use [`DeviceIoBuilder::build_output`].
 
+Realtime scheduling for the audio thread can be requested with [`DeviceIoBuilder::thread_priority`].
+
+If the device has more channels than the frame type, [`OutputDeviceMixer::channels`] reports how many,
+and [`DeviceIoBuilder::upmix`] chooses what the extra channels play.
+[`channel_check`] beeps on each channel in turn to help check speaker setups.
+
 Output functionality is only available when the `output` feature is enabled.
 
+[`Source::process`] fills planar channel buffers from a source, so a [`Mixer`] can be driven by a plugin host's
+process callback instead of an output device.
+
+[`NullOutput`] mixes sources without an audio device and discards the result. It is useful for profiling.
+
+With the `async` feature, [`OutputDeviceMixer::wait_idle`] and [`Mixer::idle`] return futures that resolve
+when all sources have finished, and [`Mixer::add_after`] returns a future that resolves when a source starts.
+
 ## Input
 
 [`InputDeviceSource`] is a [`Source`] interface for an audio input device.
 An [`InputDeviceSource`] for the default input device can be created with
 [`default_input`].
 For more nuanced control, use [`DeviceIoBuilder::build_input`].
+Capture can be paused and resumed with [`InputDeviceSource::pause`] and [`InputDeviceSource::resume`].
+[`AggregateInput`] combines several input devices into one multi-channel source, compensating for clock drift between them.
 
 Input functionality is only available when the `input` feature is enabled.
 
+## Inter-Process Audio
+
+The [`ipc`] module streams audio to and from other processes over sockets or pipes.
+It is only available when the `ipc` feature is enabled.
+
+## Embedding
+
+The [`ffi`] module exposes a small C API for playing sounds from non-Rust hosts.
+It is only available when the `ffi` feature is enabled.
+
 ## Audio Files
 
 The [`wav`] module provides [`wav::WavSource`] for reading WAV files and
 [`wav::write_source`] for writing WAV files.
 
+[`wav::render_to_wav`] exports a source on background threads with progress reporting and cancellation.
+
 WAV functionality is only available when the `wav` feature is enabled.
 
+The [`ogg`] module provides [`ogg::OggSource`] for reading ogg vorbis files.
+It is only available when the `ogg` feature is enabled.
+
+The [`opus`] module provides [`opus::OpusSource`] for reading ogg opus files.
+It is only available when the `opus` feature is enabled.
+
+Decoding errors are reported according to a [`DecodeErrorPolicy`] and can be
+checked through a [`DecodeErrors`] handle.
+
+[`render_pcm`] and [`write_pcm`] render a source to interleaved raw PCM bytes for external encoders or protocols.
+[`PcmSource`] plays raw PCM bytes without needing a file header.
+
+[`SoundBank`] preloads sounds into shared buffers so they can be played many times without being decoded again.
+
 ## Musical Notes
 
 A [`Letter`] is a note in the western chromatic scale, such as `A` or `C#`.
@@ -80,8 +229,28 @@ It also implements [`Automation`].
 [`Mode`] is a musical mode, such as major or minor.
 It can be used to choose notes from a scale.
 
+[`Chord::detect`] names the chord formed by a set of pitches, and [`PitchClassSet::scales`] finds the scales that contain them.
+
+[`Clip`] is a piano-roll style collection of timed, overlapping notes that can be played through a voice function.
+
 Musical note functionality is only available when the `notes` feature is enabled.
 
+## Testing
+
+The [`test_util`] module provides utilities for testing sources without an audio device.
+It is only available when the `test-util` feature is enabled.
+
+## Embedded targets
+
+The synthesis and processing core works without the standard library.
+Disable default features and enable `libm`, which provides the floating point math `std` would.
+Sources, frames, generators, effects, mixers, samples, and musical notes are available this way,
+but device I/O, audio files, analysis, and anything that spawns threads or measures time require `std`.
+
+For targets without a floating point unit, the [`fixed`] module provides oscillators and simple effects
+that process [`fixed::Q31`] samples using only integer math.
+It is only available when the `fixed` feature is enabled.
+
 ## A note on sample types
 
 While this library can handle audio input and output streams that work with various sample types,
@@ -92,38 +261,133 @@ There are two reasons for this:
 - [`f64`] has higher precision than [`f32`], which is important for this library's audio synthesis algorithms.
 */
 
-#[cfg(any(feature = "wav", feature = "ogg"))]
-mod codec;
+#[cfg(not(any(feature = "std", feature = "libm")))]
+compile_error!("either the `std` or `libm` feature must be enabled");
+
+extern crate alloc;
+
+#[cfg(feature = "ambisonics")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ambisonics")))]
+pub mod ambisonics;
+#[cfg(feature = "std")]
+mod analysis;
+#[cfg(feature = "std")]
+mod bank;
+#[cfg(feature = "notes")]
+mod clip;
+mod clipping;
+#[cfg(any(feature = "wav", feature = "ogg", feature = "opus"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "wav", feature = "ogg", feature = "opus")))
+)]
+pub mod codec;
+mod delay;
+mod effect;
+#[cfg(feature = "std")]
+mod event;
+#[cfg(feature = "ffi")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ffi")))]
+pub mod ffi;
+mod filter;
+#[cfg(feature = "fixed")]
+#[cfg_attr(docsrs, doc(cfg(feature = "fixed")))]
+pub mod fixed;
 mod frame;
+#[cfg(feature = "async")]
+mod future;
+mod game;
 pub mod gen;
+#[cfg(feature = "hrtf")]
+#[cfg_attr(docsrs, doc(cfg(feature = "hrtf")))]
+pub mod hrtf;
 #[cfg(any(feature = "input", feature = "output"))]
 mod io;
+#[cfg(feature = "ipc")]
+#[cfg_attr(docsrs, doc(cfg(feature = "ipc")))]
+pub mod ipc;
+#[cfg(feature = "std")]
+mod looper;
+#[cfg(not(feature = "std"))]
+mod math;
+#[cfg(feature = "midi")]
+#[cfg_attr(docsrs, doc(cfg(feature = "midi")))]
+pub mod midi;
 mod mixer;
 #[cfg(feature = "notes")]
 mod note;
+#[cfg(feature = "std")]
+mod null;
+#[cfg(feature = "std")]
+mod parallel;
+mod param;
+mod pattern;
+mod pcm;
+#[cfg(feature = "std")]
+mod player;
+#[cfg(feature = "meter")]
+mod quality;
+mod queue;
+mod sampler;
+mod slot;
 pub mod source;
-
-#[cfg(any(feature = "wav", feature = "ogg"))]
+mod sync;
+mod tempo;
+#[cfg(any(feature = "test-util", all(test, feature = "std")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "test-util")))]
+pub mod test_util;
+#[cfg(feature = "std")]
+mod thread;
+mod timeline;
+
+use alloc::sync::Arc;
+#[cfg(feature = "notes")]
+pub use clip::*;
+#[cfg(any(feature = "wav", feature = "ogg", feature = "opus"))]
+#[doc(no_inline)]
 pub use codec::*;
+use core::{
+    cmp::Ordering,
+    fmt,
+    hash::{Hash, Hasher},
+    sync::atomic::{self, AtomicU64},
+    time::Duration,
+};
+#[cfg(feature = "async")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+pub use future::*;
 #[cfg(any(feature = "input", feature = "output"))]
 #[cfg_attr(docsrs, doc(cfg(any(feature = "input", feature = "output"))))]
 pub use io::*;
 #[cfg(feature = "notes")]
 pub use note::*;
+#[cfg(feature = "meter")]
+#[cfg_attr(docsrs, doc(cfg(feature = "meter")))]
+pub use quality::*;
 #[doc(inline)]
-pub use source::{AdsEnvelope, Buffered, Constant, Maintainer, Source, UnrolledSource};
-use std::{
-    cmp::Ordering,
-    fmt,
-    hash::{Hash, Hasher},
-    sync::Arc,
-    time::Duration,
+pub use source::{
+    AdsEnvelope, Buffered, Constant, FiniteSource, Frozen, Loop, Maintainer, Overlap, Source,
+    SourceState, StateReader, StatefulSource, UnrolledSource,
+};
+#[cfg(feature = "std")]
+pub use {analysis::*, bank::*, event::*, looper::*, null::*, parallel::*, player::*, thread::*};
+pub use {
+    clipping::*, effect::*, filter::*, frame::*, game::*, gen::*, mixer::*, param::*, pattern::*,
+    pcm::*, queue::*, sampler::*, slot::*, tempo::*, timeline::*,
 };
-pub use {frame::*, gen::*, mixer::*};
 
-use parking_lot::Mutex;
+#[cfg(not(feature = "std"))]
+use prelude::*;
+use sync::{Mutex, MutexGuard};
 
-trait Amplitude: Clone + std::ops::AddAssign<Self> {
+/// Items that are in the `std` prelude but not the `core` one
+#[cfg(not(feature = "std"))]
+mod prelude {
+    pub(crate) use crate::math::Float;
+    pub(crate) use alloc::{boxed::Box, string::String, vec, vec::Vec};
+}
+
+trait Amplitude: Clone + core::ops::AddAssign<Self> {
     const MIDPOINT: Self;
     fn from_f64(f: f64) -> Self;
 }
@@ -262,13 +526,25 @@ impl Automation for u64 {
     }
 }
 
+// Advancing an automation does not count as modifying it
+
 impl<A> Automation for Shared<A>
 where
     A: Automation,
 {
     #[inline(always)]
     fn next_value(&mut self, sample_rate: f64) -> Option<f64> {
-        self.with(|auto| auto.next_value(sample_rate))
+        self.0.value.lock().next_value(sample_rate)
+    }
+}
+
+impl<T, U> Automation for SharedView<T, U>
+where
+    U: Automation,
+{
+    #[inline(always)]
+    fn next_value(&mut self, sample_rate: f64) -> Option<f64> {
+        (self.lens)(&mut *self.shared.0.value.lock()).next_value(sample_rate)
     }
 }
 
@@ -281,25 +557,130 @@ where
     }
 }
 
+/// What an adapter does when its automation ends
+///
+/// The default is [`AutomationEnd::Hold`], so that finite control envelopes
+/// do not cut off the audio they are controlling.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum AutomationEnd {
+    /// Keep using the last value
+    ///
+    /// If the automation ends before producing any value, the source ends.
+    #[default]
+    Hold,
+    /// Use a fixed value for every channel
+    Value(f64),
+    /// End the source
+    End,
+}
+
+/// Trait for automating source control values per channel
+///
+/// Every [`Automation`] is also a `FrameAutomation` that applies the same value to every channel.
+/// Multi-channel sources can be used as automation by wrapping them in [`PerChannel`].
+pub trait FrameAutomation<F: Frame> {
+    /// Get the next frame of values
+    fn next_frame(&mut self, sample_rate: f64) -> Option<F>;
+}
+
+impl<F, A> FrameAutomation<F> for A
+where
+    F: Frame,
+    A: Automation,
+{
+    #[inline(always)]
+    fn next_frame(&mut self, sample_rate: f64) -> Option<F> {
+        self.next_value(sample_rate).map(F::uniform)
+    }
+}
+
+impl FrameAutomation<Stereo> for Stereo {
+    #[inline(always)]
+    fn next_frame(&mut self, _sample_rate: f64) -> Option<Stereo> {
+        Some(*self)
+    }
+}
+
+impl<const N: usize> FrameAutomation<[f64; N]> for [f64; N]
+where
+    Self: Frame,
+{
+    #[inline(always)]
+    fn next_frame(&mut self, _sample_rate: f64) -> Option<[f64; N]> {
+        Some(*self)
+    }
+}
+
+/// Per-channel automation driven by a [`Source`]
+///
+/// Each channel of the source's frames controls the corresponding channel.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PerChannel<S>(pub S);
+
+impl<S> FrameAutomation<S::Frame> for PerChannel<S>
+where
+    S: Source,
+{
+    #[inline(always)]
+    fn next_frame(&mut self, sample_rate: f64) -> Option<S::Frame> {
+        self.0.next(sample_rate)
+    }
+}
+
 /// A thread-safe, reference-counted, locked wrapper
 ///
 /// This is mostly used to allow audio source parameters
 /// to be changed while the source is playing.
 #[derive(Default)]
-pub struct Shared<T>(Arc<Mutex<T>>);
+pub struct Shared<T>(Arc<SharedInner<T>>);
+
+#[derive(Default)]
+struct SharedInner<T> {
+    value: Mutex<T>,
+    /// Incremented each time the value is set or modified
+    version: AtomicU64,
+}
 
 impl<T> Shared<T> {
     /// Create a new shared
     pub fn new(val: T) -> Self {
-        Shared(Arc::new(Mutex::new(val)))
+        Shared(Arc::new(SharedInner {
+            value: Mutex::new(val),
+            version: AtomicU64::new(0),
+        }))
     }
     /// Set the value
-    pub fn set(&mut self, val: T) {
-        *self.0.lock() = val;
+    pub fn set(&self, val: T) {
+        *self.lock_mut() = val;
     }
     /// Modify the value
-    pub fn with<R>(&mut self, f: impl FnOnce(&mut T) -> R) -> R {
-        f(&mut *self.0.lock())
+    pub fn with<R>(&self, f: impl FnOnce(&mut T) -> R) -> R {
+        f(&mut *self.lock_mut())
+    }
+    /// Lock the value to change it
+    fn lock_mut(&self) -> MutexGuard<'_, T> {
+        let guard = self.0.value.lock();
+        self.0.version.fetch_add(1, atomic::Ordering::Release);
+        guard
+    }
+    /// Get a number that changes each time the value is set or modified
+    ///
+    /// Reading it does not lock.
+    pub(crate) fn version(&self) -> u64 {
+        self.0.version.load(atomic::Ordering::Acquire)
+    }
+    /// Create a view of part of the value
+    ///
+    /// This is useful for exposing a single parameter of a larger settings struct,
+    /// for example to a UI binding.
+    pub fn map_view<U>(
+        &self,
+        lens: impl Fn(&mut T) -> &mut U + Send + Sync + 'static,
+    ) -> SharedView<T, U> {
+        SharedView {
+            shared: self.clone(),
+            lens: Arc::new(lens),
+        }
     }
 }
 
@@ -309,7 +690,7 @@ where
 {
     /// Copy the value out
     pub fn get(&self) -> T {
-        *self.0.lock()
+        *self.0.value.lock()
     }
 }
 
@@ -319,7 +700,7 @@ where
 {
     /// Clone the value out
     pub fn cloned(&self) -> T {
-        self.0.lock().clone()
+        self.0.value.lock().clone()
     }
 }
 
@@ -328,7 +709,7 @@ where
     T: PartialEq,
 {
     fn eq(&self, other: &Self) -> bool {
-        *self.0.lock() == *other.0.lock()
+        *self.0.value.lock() == *other.0.value.lock()
     }
 }
 
@@ -345,7 +726,7 @@ where
     T: PartialOrd,
 {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.0.lock().partial_cmp(&*other.0.lock())
+        self.0.value.lock().partial_cmp(&*other.0.value.lock())
     }
 }
 
@@ -354,7 +735,7 @@ where
     T: Ord,
 {
     fn cmp(&self, other: &Self) -> Ordering {
-        self.0.lock().cmp(&*other.0.lock())
+        self.0.value.lock().cmp(&*other.0.value.lock())
     }
 }
 
@@ -363,7 +744,7 @@ where
     T: Hash,
 {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        self.0.lock().hash(state);
+        self.0.value.lock().hash(state);
     }
 }
 
@@ -372,7 +753,68 @@ where
     T: fmt::Debug,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.0.lock().fmt(f)
+        self.0.value.lock().fmt(f)
+    }
+}
+
+/// A view of part of a [`Shared`] value
+///
+/// It is created with [`Shared::map_view`].
+pub struct SharedView<T, U> {
+    shared: Shared<T>,
+    lens: Arc<dyn Fn(&mut T) -> &mut U + Send + Sync>,
+}
+
+impl<T, U> SharedView<T, U> {
+    /// Set the viewed value
+    pub fn set(&self, val: U) {
+        *(self.lens)(&mut *self.shared.lock_mut()) = val;
+    }
+    /// Modify the viewed value
+    pub fn with<R>(&self, f: impl FnOnce(&mut U) -> R) -> R {
+        f((self.lens)(&mut *self.shared.lock_mut()))
+    }
+    /// Get the [`Shared`] value being viewed
+    pub fn shared(&self) -> &Shared<T> {
+        &self.shared
+    }
+}
+
+impl<T, U> SharedView<T, U>
+where
+    U: Copy,
+{
+    /// Copy the viewed value out
+    pub fn get(&self) -> U {
+        self.with(|val| *val)
+    }
+}
+
+impl<T, U> SharedView<T, U>
+where
+    U: Clone,
+{
+    /// Clone the viewed value out
+    pub fn cloned(&self) -> U {
+        self.with(|val| val.clone())
+    }
+}
+
+impl<T, U> Clone for SharedView<T, U> {
+    fn clone(&self) -> Self {
+        SharedView {
+            shared: self.shared.clone(),
+            lens: Arc::clone(&self.lens),
+        }
+    }
+}
+
+impl<T, U> fmt::Debug for SharedView<T, U>
+where
+    U: fmt::Debug,
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.with(|val| val.fmt(f))
     }
 }
 
@@ -381,7 +823,7 @@ where
     T: fmt::Display,
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        self.0.lock().fmt(f)
+        self.0.value.lock().fmt(f)
     }
 }
 
@@ -0,0 +1,419 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc, Arc,
+    },
+    time::Duration,
+};
+
+use crate::{sync::Mutex, Frame, Source, ToDuration};
+
+/// The most loops a [`Looper`] can hold
+const MAX_LOOPS: usize = 32;
+/// The most steps a [`Looper`] can undo
+const MAX_UNDO: usize = 32;
+
+enum LoopCommand<F> {
+    Record(Vec<F>),
+    Overdub(usize, Vec<F>),
+    Stop,
+    Undo,
+    Gain(usize, f64),
+    Clear,
+}
+
+/// Controls a [`Looper`]
+///
+/// Created with [`Source::looper`]
+///
+/// Buffers for recordings are allocated here rather than on the audio thread,
+/// and buffers the looper no longer needs are dropped here.
+#[derive(Clone)]
+pub struct LooperControl<F> {
+    send: mpsc::Sender<LoopCommand<F>>,
+    retired: Arc<Mutex<mpsc::Receiver<Vec<F>>>>,
+    /// The most frames a recording can hold, or `0` before the looper starts playing
+    capacity: Arc<AtomicUsize>,
+    recording: Arc<AtomicBool>,
+    overdubbing: Arc<AtomicBool>,
+    loops: Arc<AtomicUsize>,
+}
+
+impl<F> LooperControl<F> {
+    fn command(&self, command: LoopCommand<F>) {
+        // Drop buffers the looper is done with
+        while self.retired.lock().try_recv().is_ok() {}
+        let _ = self.send.send(command);
+    }
+    fn buffer(&self) -> Vec<F> {
+        Vec::with_capacity(self.capacity.load(Ordering::Relaxed))
+    }
+    /// Start recording a new loop
+    ///
+    /// Recording stops on its own once it reaches [`Looper::max_length`].
+    /// Does nothing if the looper is already recording or overdubbing,
+    /// if it has not started playing yet, or if it already holds the maximum number of loops.
+    pub fn record(&self) {
+        self.command(LoopCommand::Record(self.buffer()));
+    }
+    /// Start recording on top of an existing loop
+    ///
+    /// Does nothing if the looper is already recording or overdubbing.
+    pub fn overdub(&self, index: usize) {
+        self.command(LoopCommand::Overdub(index, self.buffer()));
+    }
+    /// Stop recording or overdubbing
+    ///
+    /// When a new loop stops recording, its length is rounded to the nearest multiple of the quantum.
+    /// If that is longer than what has been recorded, recording continues until the loop is full.
+    pub fn stop(&self) {
+        self.command(LoopCommand::Stop);
+    }
+    /// Undo the last recording or overdub
+    pub fn undo(&self) {
+        self.command(LoopCommand::Undo);
+    }
+    /// Set the gain of a loop
+    pub fn set_gain(&self, index: usize, gain: f64) {
+        self.command(LoopCommand::Gain(index, gain));
+    }
+    /// Remove all loops
+    pub fn clear(&self) {
+        self.command(LoopCommand::Clear);
+    }
+    /// Check if a new loop is being recorded
+    pub fn is_recording(&self) -> bool {
+        self.recording.load(Ordering::Relaxed)
+    }
+    /// Check if a loop is being overdubbed
+    pub fn is_overdubbing(&self) -> bool {
+        self.overdubbing.load(Ordering::Relaxed)
+    }
+    /// Get the number of recorded loops
+    pub fn loops(&self) -> usize {
+        self.loops.load(Ordering::Relaxed)
+    }
+}
+
+struct Loop<F> {
+    frames: Vec<F>,
+    /// The playhead position when the loop started recording, so that it stays in sync
+    offset: u64,
+    gain: f64,
+}
+
+impl<F: Frame> Loop<F> {
+    fn index(&self, pos: u64) -> usize {
+        ((pos - self.offset) % self.frames.len() as u64) as usize
+    }
+}
+
+struct Recording<F> {
+    frames: Vec<F>,
+    offset: u64,
+    target: Option<usize>,
+}
+
+struct Overdub<F> {
+    index: usize,
+    /// The loop index where the overdub started
+    start: usize,
+    /// The frames of the loop before they were overdubbed, starting at `start`
+    saved: Vec<F>,
+}
+
+enum UndoStep<F> {
+    Record,
+    Overdub(Overdub<F>),
+}
+
+/// Source returned from [`Source::looper`]
+///
+/// It plays the input mixed with all recorded loops.
+pub struct Looper<S: Source> {
+    source: S,
+    recv: mpsc::Receiver<LoopCommand<S::Frame>>,
+    retired: mpsc::SyncSender<Vec<S::Frame>>,
+    capacity: Arc<AtomicUsize>,
+    max_length: f64,
+    recording_flag: Arc<AtomicBool>,
+    overdubbing_flag: Arc<AtomicBool>,
+    loops_count: Arc<AtomicUsize>,
+    quantum: Option<Duration>,
+    monitor: bool,
+    loops: Vec<Loop<S::Frame>>,
+    recording: Option<Recording<S::Frame>>,
+    overdub: Option<Overdub<S::Frame>>,
+    undo: Vec<UndoStep<S::Frame>>,
+    pos: u64,
+}
+
+impl<S: Source> Looper<S> {
+    pub(crate) fn new(source: S, quantum: Duration) -> (LooperControl<S::Frame>, Self) {
+        let (send, recv) = mpsc::channel();
+        let (retired, retired_recv) = mpsc::sync_channel(MAX_LOOPS + MAX_UNDO + 2);
+        let control = LooperControl {
+            send,
+            retired: Arc::new(Mutex::new(retired_recv)),
+            capacity: Arc::new(AtomicUsize::new(0)),
+            recording: Arc::new(AtomicBool::new(false)),
+            overdubbing: Arc::new(AtomicBool::new(false)),
+            loops: Arc::new(AtomicUsize::new(0)),
+        };
+        let looper = Looper {
+            source,
+            recv,
+            retired,
+            capacity: control.capacity.clone(),
+            max_length: 60.0,
+            recording_flag: control.recording.clone(),
+            overdubbing_flag: control.overdubbing.clone(),
+            loops_count: control.loops.clone(),
+            quantum: (!quantum.is_zero()).then_some(quantum),
+            monitor: true,
+            loops: Vec::with_capacity(MAX_LOOPS),
+            recording: None,
+            overdub: None,
+            undo: Vec::with_capacity(MAX_UNDO),
+            pos: 0,
+        };
+        (control, looper)
+    }
+    /// Set whether the input is heard along with the loops
+    ///
+    /// Default is `true`
+    pub fn monitor(self, monitor: bool) -> Self {
+        Looper { monitor, ..self }
+    }
+    /// Set the maximum length of a loop
+    ///
+    /// Buffers of this length are allocated by the [`LooperControl`] for each recording and overdub.
+    ///
+    /// Default is 60 seconds
+    pub fn max_length(self, max: impl ToDuration) -> Self {
+        Looper {
+            max_length: max.to_duration().as_secs_f64(),
+            ..self
+        }
+    }
+    /// Send a buffer back to the [`LooperControl`] to be dropped
+    fn retire(&self, buffer: Vec<S::Frame>) {
+        let _ = self.retired.try_send(buffer);
+    }
+    fn push_undo(&mut self, step: UndoStep<S::Frame>) {
+        if self.undo.len() == MAX_UNDO {
+            if let UndoStep::Overdub(overdub) = self.undo.remove(0) {
+                self.retire(overdub.saved);
+            }
+        }
+        self.undo.push(step);
+    }
+    fn quantum_frames(&self, sample_rate: f64) -> Option<usize> {
+        match self.quantum {
+            Some(quantum) => Some((quantum.as_secs_f64() * sample_rate).round() as usize),
+            None => self.loops.first().map(|lp| lp.frames.len()),
+        }
+        .filter(|&frames| frames > 0)
+    }
+    fn handle(&mut self, command: LoopCommand<S::Frame>, sample_rate: f64) {
+        let busy = self.recording.is_some() || self.overdub.is_some();
+        match command {
+            LoopCommand::Record(frames) if !busy && self.loops.len() < MAX_LOOPS => {
+                self.recording = Some(Recording {
+                    frames,
+                    offset: self.pos,
+                    target: None,
+                });
+            }
+            LoopCommand::Overdub(index, saved) if !busy && index < self.loops.len() => {
+                let start = self.loops[index].index(self.pos);
+                self.overdub = Some(Overdub {
+                    index,
+                    start,
+                    saved,
+                });
+            }
+            LoopCommand::Record(frames) | LoopCommand::Overdub(_, frames) => self.retire(frames),
+            LoopCommand::Stop => {
+                if let Some(overdub) = self.overdub.take() {
+                    self.push_undo(UndoStep::Overdub(overdub));
+                }
+                let quantum = self.quantum_frames(sample_rate);
+                if let Some(recording) = &mut self.recording {
+                    let len = recording.frames.len();
+                    let target = match quantum {
+                        Some(quantum) => {
+                            ((len as f64 / quantum as f64).round() as usize).max(1) * quantum
+                        }
+                        None => len,
+                    };
+                    recording.target = Some(target.min(recording.frames.capacity()));
+                }
+            }
+            LoopCommand::Undo if !busy => match self.undo.pop() {
+                Some(UndoStep::Record) => {
+                    if let Some(lp) = self.loops.pop() {
+                        self.retire(lp.frames);
+                    }
+                }
+                Some(UndoStep::Overdub(mut overdub)) => {
+                    let frames = &mut self.loops[overdub.index].frames;
+                    let len = frames.len();
+                    for (i, frame) in overdub.saved.drain(..).enumerate() {
+                        frames[(overdub.start + i) % len] = frame;
+                    }
+                    self.retire(overdub.saved);
+                }
+                None => {}
+            },
+            LoopCommand::Gain(index, gain) => {
+                if let Some(lp) = self.loops.get_mut(index) {
+                    lp.gain = gain;
+                }
+            }
+            LoopCommand::Clear => {
+                for lp in self.loops.drain(..) {
+                    let _ = self.retired.try_send(lp.frames);
+                }
+                for step in self.undo.drain(..) {
+                    if let UndoStep::Overdub(overdub) = step {
+                        let _ = self.retired.try_send(overdub.saved);
+                    }
+                }
+                if let Some(recording) = self.recording.take() {
+                    self.retire(recording.frames);
+                }
+                if let Some(overdub) = self.overdub.take() {
+                    self.retire(overdub.saved);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+impl<S> Source for Looper<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        while let Ok(command) = self.recv.try_recv() {
+            self.handle(command, sample_rate);
+        }
+        self.capacity
+            .store((self.max_length * sample_rate) as usize, Ordering::Relaxed);
+        let input = self.source.next(sample_rate)?;
+        if let Some(recording) = &self.recording {
+            if let Some(target) = recording.target.filter(|&t| recording.frames.len() >= t) {
+                let mut recording = self.recording.take().unwrap();
+                recording.frames.truncate(target);
+                if recording.frames.is_empty() {
+                    self.retire(recording.frames);
+                } else {
+                    self.loops.push(Loop {
+                        frames: recording.frames,
+                        offset: recording.offset,
+                        gain: 1.0,
+                    });
+                    self.push_undo(UndoStep::Record);
+                }
+            }
+        }
+        let mut frame = if self.monitor {
+            input.clone()
+        } else {
+            S::Frame::uniform(0.0)
+        };
+        for lp in &self.loops {
+            frame.merge(lp.frames[lp.index(self.pos)].clone(), |a, b| {
+                a + b * lp.gain
+            });
+        }
+        if let Some(overdub) = &mut self.overdub {
+            let lp = &mut self.loops[overdub.index];
+            let i = lp.index(self.pos);
+            // Save each frame the first time it is overdubbed so that it can be undone
+            if overdub.saved.len() < lp.frames.len()
+                && overdub.saved.len() < overdub.saved.capacity()
+            {
+                overdub.saved.push(lp.frames[i].clone());
+            }
+            lp.frames[i].merge(input.clone(), |a, b| a + b);
+        }
+        if let Some(recording) = &mut self.recording {
+            // The buffer was allocated by the control, so recording stops when it is full
+            if recording.frames.len() < recording.frames.capacity() {
+                recording.frames.push(input);
+            } else {
+                recording.target = Some(recording.frames.len());
+            }
+        }
+        self.recording_flag
+            .store(self.recording.is_some(), Ordering::Relaxed);
+        self.overdubbing_flag
+            .store(self.overdub.is_some(), Ordering::Relaxed);
+        self.loops_count.store(self.loops.len(), Ordering::Relaxed);
+        self.pos += 1;
+        Some(frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::StepSource;
+
+    const RATE: f64 = 100.0;
+
+    fn run<S: Source>(looper: &mut Looper<S>, frames: usize) -> Vec<S::Frame> {
+        (0..frames).map_while(|_| looper.next(RATE)).collect()
+    }
+
+    #[test]
+    fn undo_reverts_overdubs_then_recordings() {
+        let (control, looper) = StepSource::new(1.0, 0.0).looper(Duration::ZERO);
+        let mut looper = looper.monitor(false);
+        run(&mut looper, 1);
+        control.record();
+        run(&mut looper, 10);
+        control.stop();
+        run(&mut looper, 1);
+        assert_eq!(control.loops(), 1);
+        assert_eq!(run(&mut looper, 10), [1.0; 10]);
+
+        control.overdub(0);
+        run(&mut looper, 10);
+        control.stop();
+        run(&mut looper, 1);
+        assert!(!control.is_overdubbing());
+        assert_eq!(run(&mut looper, 10), [2.0; 10]);
+
+        control.undo();
+        run(&mut looper, 1);
+        assert_eq!(run(&mut looper, 10), [1.0; 10]);
+
+        control.undo();
+        run(&mut looper, 1);
+        assert_eq!(control.loops(), 0);
+        assert_eq!(run(&mut looper, 10), [0.0; 10]);
+
+        // There is nothing left to undo
+        control.undo();
+        assert_eq!(run(&mut looper, 10), [0.0; 10]);
+    }
+
+    #[test]
+    fn undo_waits_for_recording_to_stop() {
+        let (control, mut looper) = StepSource::new(1.0, 0.0).looper(Duration::ZERO);
+        run(&mut looper, 1);
+        control.record();
+        run(&mut looper, 10);
+        control.undo();
+        run(&mut looper, 1);
+        assert!(control.is_recording());
+        control.stop();
+        run(&mut looper, 1);
+        assert_eq!(control.loops(), 1);
+    }
+}
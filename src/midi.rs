@@ -0,0 +1,938 @@
+//! Controlling parameters with MIDI controllers
+//!
+//! A [`MidiMap`] maps control changes, pitch bend, and channel pressure to registered parameters,
+//! so hardware knobs and faders can control sources without custom code for each parameter.
+//! Each parameter has a range and a [`Curve`], and controls can be assigned to it
+//! explicitly or by MIDI learn.
+//!
+//! [`MidiVoices`] plays a voice for each held note.
+//! It supports MPE, so expressive controllers can bend and shape each note separately.
+//!
+//! This module does not talk to MIDI devices itself.
+//! Raw messages from any MIDI input library are passed to [`MidiMap::handle`] or [`VoiceInput::handle`].
+//!
+//! This module is only available when the `midi` feature is enabled.
+//!
+//! # Example
+//! ```
+//! use hodaun::{midi::*, *};
+//!
+//! let cutoff = LogHzParam::new(20.0, 20000.0, 1000.0);
+//! let map = MidiMap::new();
+//! map.register("cutoff", MidiParam::new(cutoff.clone()));
+//!
+//! // Turn a knob while learning to assign it
+//! map.learn("cutoff");
+//! map.handle(&[0xB0, 74, 127]);
+//! assert_eq!(map.control("cutoff"), Some(MidiControl::Cc { channel: 0, controller: 74 }));
+//! assert_eq!(cutoff.hz(), 20000.0);
+//!
+//! map.handle(&[0xB0, 74, 0]);
+//! assert_eq!(cutoff.hz(), 20.0);
+//! ```
+
+use alloc::{boxed::Box, collections::VecDeque, string::String, sync::Arc, vec::Vec};
+use core::fmt;
+
+use crate::{
+    source::*,
+    sync::{ring, Mutex, RingReceiver, RingSender},
+    DbParam, Frame, LogHzParam, Shared,
+};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// A MIDI channel voice message
+///
+/// Channels are numbered from `0` to `15`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MidiMessage {
+    /// A key was released
+    NoteOff {
+        /// The channel
+        channel: u8,
+        /// The key number
+        key: u8,
+        /// The release velocity
+        velocity: u8,
+    },
+    /// A key was pressed
+    NoteOn {
+        /// The channel
+        channel: u8,
+        /// The key number
+        key: u8,
+        /// The velocity
+        velocity: u8,
+    },
+    /// The pressure on a single key changed
+    PolyPressure {
+        /// The channel
+        channel: u8,
+        /// The key number
+        key: u8,
+        /// The pressure
+        pressure: u8,
+    },
+    /// A controller changed
+    ControlChange {
+        /// The channel
+        channel: u8,
+        /// The controller number
+        controller: u8,
+        /// The controller value
+        value: u8,
+    },
+    /// The pressure on the whole channel changed
+    ChannelPressure {
+        /// The channel
+        channel: u8,
+        /// The pressure
+        pressure: u8,
+    },
+    /// The pitch bend wheel moved
+    PitchBend {
+        /// The channel
+        channel: u8,
+        /// The 14-bit bend value, where `8192` is centered
+        value: u16,
+    },
+}
+
+impl MidiMessage {
+    /// Parse a message from raw bytes
+    ///
+    /// Returns `None` for messages that are not channel voice messages this type represents,
+    /// such as program changes and system messages.
+    /// A note on with a velocity of `0` is parsed as a note off.
+    pub fn parse(bytes: &[u8]) -> Option<Self> {
+        let (&status, data) = bytes.split_first()?;
+        let channel = status & 0x0F;
+        let byte = |i: usize| data.get(i).copied().filter(|b| b & 0x80 == 0);
+        Some(match status & 0xF0 {
+            0x80 => MidiMessage::NoteOff {
+                channel,
+                key: byte(0)?,
+                velocity: byte(1)?,
+            },
+            0x90 => match (byte(0)?, byte(1)?) {
+                (key, 0) => MidiMessage::NoteOff {
+                    channel,
+                    key,
+                    velocity: 0,
+                },
+                (key, velocity) => MidiMessage::NoteOn {
+                    channel,
+                    key,
+                    velocity,
+                },
+            },
+            0xA0 => MidiMessage::PolyPressure {
+                channel,
+                key: byte(0)?,
+                pressure: byte(1)?,
+            },
+            0xB0 => MidiMessage::ControlChange {
+                channel,
+                controller: byte(0)?,
+                value: byte(1)?,
+            },
+            0xD0 => MidiMessage::ChannelPressure {
+                channel,
+                pressure: byte(0)?,
+            },
+            0xE0 => MidiMessage::PitchBend {
+                channel,
+                value: byte(0)? as u16 | (byte(1)? as u16) << 7,
+            },
+            _ => return None,
+        })
+    }
+    /// Get the channel the message was sent on
+    pub fn channel(&self) -> u8 {
+        match *self {
+            MidiMessage::NoteOff { channel, .. }
+            | MidiMessage::NoteOn { channel, .. }
+            | MidiMessage::PolyPressure { channel, .. }
+            | MidiMessage::ControlChange { channel, .. }
+            | MidiMessage::ChannelPressure { channel, .. }
+            | MidiMessage::PitchBend { channel, .. } => channel,
+        }
+    }
+    /// Get the control this message changes and its new value, from `0.0` to `1.0`
+    ///
+    /// Pitch bend is centered at `0.5`.
+    pub fn control(&self) -> Option<(MidiControl, f64)> {
+        Some(match *self {
+            MidiMessage::ControlChange {
+                channel,
+                controller,
+                value,
+            } => (
+                MidiControl::Cc {
+                    channel,
+                    controller,
+                },
+                value as f64 / 127.0,
+            ),
+            MidiMessage::ChannelPressure { channel, pressure } => (
+                MidiControl::ChannelPressure { channel },
+                pressure as f64 / 127.0,
+            ),
+            MidiMessage::PitchBend { channel, value } => {
+                (MidiControl::PitchBend { channel }, value as f64 / 16383.0)
+            }
+            _ => return None,
+        })
+    }
+}
+
+/// A MIDI control that can be mapped to a parameter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MidiControl {
+    /// A control change controller
+    Cc {
+        /// The channel
+        channel: u8,
+        /// The controller number
+        controller: u8,
+    },
+    /// The pitch bend wheel
+    PitchBend {
+        /// The channel
+        channel: u8,
+    },
+    /// Channel pressure, also called aftertouch
+    ChannelPressure {
+        /// The channel
+        channel: u8,
+    },
+}
+
+/// How a control's travel is spread across a parameter's range
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Curve {
+    /// Evenly across the range
+    #[default]
+    Linear,
+    /// The normalized value raised to a power
+    ///
+    /// Powers above `1.0` give finer control at the low end of the range.
+    Power(f64),
+    /// Each equal step multiplies the value by the same amount
+    ///
+    /// This suits frequencies and times. Both ends of the range must be positive.
+    Exponential,
+}
+
+impl Curve {
+    /// Map a normalized value from `0.0` to `1.0` into a range
+    pub fn apply(self, normalized: f64, min: f64, max: f64) -> f64 {
+        let normalized = normalized.clamp(0.0, 1.0);
+        match self {
+            Curve::Linear => min + normalized * (max - min),
+            Curve::Power(power) => min + normalized.powf(power) * (max - min),
+            Curve::Exponential => min * (max / min).powf(normalized),
+        }
+    }
+}
+
+/// A value that can be set from a [`MidiMap`]
+pub trait MidiTarget: Send + 'static {
+    /// Set the value
+    fn set_value(&self, value: f64);
+}
+
+impl MidiTarget for Shared<f64> {
+    fn set_value(&self, value: f64) {
+        self.set(value);
+    }
+}
+
+/// The value is the normalized value
+impl MidiTarget for DbParam {
+    fn set_value(&self, value: f64) {
+        self.set_normalized(value);
+    }
+}
+
+/// The value is the normalized value
+impl MidiTarget for LogHzParam {
+    fn set_value(&self, value: f64) {
+        self.set_normalized(value);
+    }
+}
+
+/// A parameter registered with a [`MidiMap`]
+pub struct MidiParam {
+    target: Box<dyn MidiTarget>,
+    min: f64,
+    max: f64,
+    curve: Curve,
+}
+
+impl MidiParam {
+    /// Create a new parameter that sets a target
+    ///
+    /// The range defaults to `0.0` to `1.0` with a linear curve,
+    /// which suits [`DbParam`] and [`LogHzParam`] since they do their own scaling.
+    pub fn new(target: impl MidiTarget) -> Self {
+        MidiParam {
+            target: Box::new(target),
+            min: 0.0,
+            max: 1.0,
+            curve: Curve::Linear,
+        }
+    }
+    /// Set the values the bottom and top of the control's travel map to
+    ///
+    /// `min` may be greater than `max` to invert the control.
+    pub fn range(self, min: f64, max: f64) -> Self {
+        MidiParam { min, max, ..self }
+    }
+    /// Set how the control's travel is spread across the range
+    pub fn curve(self, curve: Curve) -> Self {
+        MidiParam { curve, ..self }
+    }
+}
+
+impl fmt::Debug for MidiParam {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MidiParam")
+            .field("min", &self.min)
+            .field("max", &self.max)
+            .field("curve", &self.curve)
+            .finish()
+    }
+}
+
+/// Maps MIDI controls to registered parameters
+///
+/// Cloning a map is cheap, and clones share the same parameters and assignments.
+/// This way, one clone can handle messages on a MIDI input thread
+/// while another is used by a UI to start MIDI learn.
+///
+/// A control can be assigned to any number of parameters, but each parameter has at most one control.
+#[derive(Debug, Clone, Default)]
+pub struct MidiMap {
+    state: Arc<Mutex<MidiMapState>>,
+}
+
+#[derive(Debug, Default)]
+struct MidiMapState {
+    params: Vec<Mapping>,
+    /// The index of the parameter waiting for a control
+    learning: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Mapping {
+    name: String,
+    param: MidiParam,
+    control: Option<MidiControl>,
+}
+
+impl MidiMapState {
+    fn index(&self, name: &str) -> Option<usize> {
+        self.params.iter().position(|mapping| mapping.name == name)
+    }
+}
+
+impl MidiMap {
+    /// Create a new map with no parameters
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Register a parameter under a name
+    ///
+    /// A parameter already registered under the name is replaced, but keeps its assigned control.
+    pub fn register(&self, name: impl Into<String>, param: MidiParam) {
+        let name = name.into();
+        let mut state = self.state.lock();
+        match state.index(&name) {
+            Some(i) => state.params[i].param = param,
+            None => state.params.push(Mapping {
+                name,
+                param,
+                control: None,
+            }),
+        }
+    }
+    /// Remove a registered parameter
+    pub fn unregister(&self, name: &str) {
+        let mut state = self.state.lock();
+        if let Some(i) = state.index(name) {
+            state.params.remove(i);
+            state.learning = match state.learning {
+                Some(l) if l == i => None,
+                Some(l) if l > i => Some(l - 1),
+                learning => learning,
+            };
+        }
+    }
+    /// Assign a control to a registered parameter
+    ///
+    /// Returns `false` if no parameter is registered under the name.
+    pub fn assign(&self, name: &str, control: MidiControl) -> bool {
+        let mut state = self.state.lock();
+        let Some(i) = state.index(name) else {
+            return false;
+        };
+        state.params[i].control = Some(control);
+        true
+    }
+    /// Remove a parameter's assigned control
+    pub fn unassign(&self, name: &str) {
+        let mut state = self.state.lock();
+        if let Some(i) = state.index(name) {
+            state.params[i].control = None;
+        }
+    }
+    /// Get the control assigned to a parameter
+    pub fn control(&self, name: &str) -> Option<MidiControl> {
+        let state = self.state.lock();
+        state.params[state.index(name)?].control
+    }
+    /// Assign the next control that is moved to a registered parameter
+    ///
+    /// Returns `false` if no parameter is registered under the name.
+    /// Only one parameter learns at a time, so this cancels learning for any other parameter.
+    pub fn learn(&self, name: &str) -> bool {
+        let mut state = self.state.lock();
+        state.learning = state.index(name);
+        state.learning.is_some()
+    }
+    /// Stop waiting for a control to learn
+    pub fn cancel_learn(&self) {
+        self.state.lock().learning = None;
+    }
+    /// Get the name of the parameter waiting for a control, if any
+    pub fn learning(&self) -> Option<String> {
+        let state = self.state.lock();
+        Some(state.params[state.learning?].name.clone())
+    }
+    /// Handle a raw MIDI message
+    ///
+    /// Returns whether any parameter was set.
+    pub fn handle(&self, bytes: &[u8]) -> bool {
+        MidiMessage::parse(bytes).is_some_and(|message| self.handle_message(message))
+    }
+    /// Handle a parsed MIDI message
+    ///
+    /// Returns whether any parameter was set.
+    pub fn handle_message(&self, message: MidiMessage) -> bool {
+        let Some((control, normalized)) = message.control() else {
+            return false;
+        };
+        let mut state = self.state.lock();
+        if let Some(i) = state.learning.take() {
+            state.params[i].control = Some(control);
+        }
+        let mut set = false;
+        for mapping in &state.params {
+            if mapping.control == Some(control) {
+                let param = &mapping.param;
+                (param.target).set_value(param.curve.apply(normalized, param.min, param.max));
+                set = true;
+            }
+        }
+        set
+    }
+}
+
+/// A note being played by [`MidiVoices`]
+///
+/// The voice function receives one of these for each note.
+/// Its parameters are [`Shared`] so they can be used as automations and follow the note's expression.
+#[derive(Debug)]
+pub struct MidiNote {
+    /// The key number
+    pub key: u8,
+    /// The channel the note was played on
+    pub channel: u8,
+    /// The velocity, from `0.0` to `1.0`
+    pub velocity: f64,
+    /// The frequency in hertz, including pitch bend
+    pub pitch: Shared<f64>,
+    /// The pressure, from `0.0` to `1.0`
+    ///
+    /// This follows both channel pressure and polyphonic key pressure.
+    pub pressure: Shared<f64>,
+    /// The timbre, from `0.0` to `1.0`
+    ///
+    /// This follows controller 74, the MPE timbre dimension, and starts at `0.5`.
+    pub timbre: Shared<f64>,
+    /// Held until the note is released
+    ///
+    /// Voices should use [`Source::maintained`] or [`SampleVoice::held_by`](crate::SampleVoice::held_by)
+    /// with it so that they end after the note is released.
+    pub gate: Maintainer,
+}
+
+/// Sends MIDI messages to a [`MidiVoices`]
+///
+/// Cloning it is cheap, so it can be moved to a MIDI input thread.
+/// Messages are handled on the thread that sends them, which is also where voices are created,
+/// so the audio thread only mixes voices that are ready to play.
+#[derive(Clone)]
+pub struct VoiceInput {
+    control: Arc<Mutex<dyn HandleMidi>>,
+}
+
+impl fmt::Debug for VoiceInput {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VoiceInput").finish_non_exhaustive()
+    }
+}
+
+impl VoiceInput {
+    /// Handle a raw MIDI message
+    ///
+    /// Returns `false` if the message could not be parsed.
+    pub fn handle(&self, bytes: &[u8]) -> bool {
+        MidiMessage::parse(bytes)
+            .map(|message| self.handle_message(message))
+            .is_some()
+    }
+    /// Handle a parsed MIDI message
+    pub fn handle_message(&self, message: MidiMessage) {
+        self.control.lock().handle(message);
+    }
+}
+
+/// The number of voices that can be started between two frames of a [`MidiVoices`]
+const VOICE_QUEUE_CAPACITY: usize = 256;
+
+/// A [`Source`] that plays a voice for each note played over MIDI
+///
+/// Voices are created by a function from a [`MidiNote`], and mixed together.
+/// Pitch bend, pressure, and timbre are routed to the notes on the channel they are sent on.
+/// With [`MidiVoices::mpe`], each note is played on its own channel,
+/// so every note can be bent and shaped separately.
+/// With [`MidiVoices::mono`], it plays one note at a time like a monosynth.
+///
+/// The voice function is called by the thread that sends the note to the [`VoiceInput`],
+/// and finished voices are dropped there too, so playing never allocates or frees voices.
+///
+/// It never finishes on its own, and plays silence when no voices are playing.
+///
+/// # Example
+/// ```
+/// use hodaun::{midi::*, *};
+///
+/// let (input, voices) = MidiVoices::new(|note: &MidiNote| {
+///     SineWave::new(note.pitch.clone())
+///         .amplify(note.pressure.clone())
+///         .maintained(&note.gate)
+/// });
+/// let voices = voices.mpe();
+/// input.handle(&[0x91, 69, 100]);
+/// input.handle(&[0xE1, 0x00, 0x44]); // Bend only the note on channel 1
+/// ```
+pub struct MidiVoices<V, F> {
+    control: Arc<Mutex<VoiceControl<V, F>>>,
+    started: RingReceiver<ActiveVoice<V>>,
+    retired: RingSender<ActiveVoice<V>>,
+    /// Preallocated for the maximum number of voices
+    voices: Vec<ActiveVoice<V>>,
+    max_voices: usize,
+}
+
+/// Handles MIDI messages for a [`VoiceInput`] without naming the voice types
+trait HandleMidi: Send {
+    fn handle(&mut self, message: MidiMessage);
+}
+
+/// The state of a [`MidiVoices`] that is only touched by the threads sending it messages
+struct VoiceControl<V, F> {
+    voice: F,
+    started: RingSender<ActiveVoice<V>>,
+    retired: RingReceiver<ActiveVoice<V>>,
+    /// The expression of every voice that may still be playing, oldest first
+    notes: VecDeque<NoteHandle>,
+    next_id: u64,
+    channels: [ChannelState; 16],
+    max_voices: usize,
+    bend_range: f64,
+    release: f64,
+    mpe: bool,
+    mono: Option<NotePriority>,
+    legato: bool,
+    /// The notes held in mono mode, in the order they were pressed
+    held: Vec<HeldNote>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct HeldNote {
+    key: u8,
+    channel: u8,
+    velocity: u8,
+}
+
+struct ActiveVoice<V> {
+    id: u64,
+    source: V,
+}
+
+/// The controls of a voice that may be playing
+struct NoteHandle {
+    id: u64,
+    key: u8,
+    channel: u8,
+    pitch: Shared<f64>,
+    pressure: Shared<f64>,
+    timbre: Shared<f64>,
+    gate: Option<Maintainer>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ChannelState {
+    /// The pitch bend, from `-1.0` to `1.0`
+    bend: f64,
+    pressure: f64,
+    timbre: f64,
+}
+
+impl Default for ChannelState {
+    fn default() -> Self {
+        ChannelState {
+            bend: 0.0,
+            pressure: 0.0,
+            timbre: 0.5,
+        }
+    }
+}
+
+/// Which held note plays in [`MidiVoices::mono`] mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum NotePriority {
+    /// The most recently pressed note
+    #[default]
+    Last,
+    /// The highest note
+    High,
+    /// The lowest note
+    Low,
+}
+
+/// The MPE master channel, whose messages apply to every note
+const MPE_MASTER: u8 = 0;
+/// The MPE timbre controller
+const TIMBRE_CC: u8 = 74;
+/// The default maximum number of voices
+const DEFAULT_MAX_VOICES: usize = 32;
+
+impl<V, F> MidiVoices<V, F>
+where
+    V: Send + 'static,
+    F: FnMut(&MidiNote) -> V + Send + 'static,
+{
+    /// Create a new voice manager that creates voices with a function
+    ///
+    /// The returned [`VoiceInput`] sends MIDI messages to it.
+    pub fn new(voice: F) -> (VoiceInput, Self) {
+        let (started_send, started) = ring(VOICE_QUEUE_CAPACITY);
+        let (retired, retired_recv) = ring(DEFAULT_MAX_VOICES + VOICE_QUEUE_CAPACITY);
+        let control = Arc::new(Mutex::new(VoiceControl {
+            voice,
+            started: started_send,
+            retired: retired_recv,
+            notes: VecDeque::new(),
+            next_id: 0,
+            channels: [ChannelState::default(); 16],
+            max_voices: DEFAULT_MAX_VOICES,
+            bend_range: 2.0,
+            release: 0.0,
+            mpe: false,
+            mono: None,
+            legato: false,
+            held: Vec::new(),
+        }));
+        let input = VoiceInput {
+            control: control.clone(),
+        };
+        let voices = MidiVoices {
+            control,
+            started,
+            retired,
+            voices: Vec::with_capacity(DEFAULT_MAX_VOICES),
+            max_voices: DEFAULT_MAX_VOICES,
+        };
+        (input, voices)
+    }
+}
+
+impl<V, F> MidiVoices<V, F> {
+    /// Set the maximum number of voices that can play at once
+    ///
+    /// When a note is played with every voice in use, the oldest voice is cut off.
+    /// The default is 32.
+    pub fn max_voices(self, max_voices: usize) -> Self {
+        let max_voices = max_voices.max(1);
+        let (retired, retired_recv) = ring(max_voices + VOICE_QUEUE_CAPACITY);
+        let mut control = self.control.lock();
+        control.max_voices = max_voices;
+        control.retired = retired_recv;
+        drop(control);
+        MidiVoices {
+            retired,
+            voices: Vec::with_capacity(max_voices),
+            max_voices,
+            ..self
+        }
+    }
+    /// Set how many semitones a full pitch bend moves a note
+    ///
+    /// The default is 2, or 48 in MPE mode.
+    pub fn bend_range(self, semitones: f64) -> Self {
+        self.control.lock().bend_range = semitones;
+        self
+    }
+    /// Set the release duration in seconds of each note's [`MidiNote::gate`]
+    ///
+    /// The default is 0.
+    pub fn release(self, release: f64) -> Self {
+        self.control.lock().release = release;
+        self
+    }
+    /// Use MPE, with channel 0 as the master channel
+    ///
+    /// Each note is expected on its own member channel,
+    /// so pitch bend, pressure, and timbre on a member channel only affect that note.
+    /// Pitch bend and pressure on the master channel affect every note,
+    /// with the master pitch bend range fixed at 2 semitones.
+    /// This also sets the bend range to 48 semitones, the MPE default.
+    pub fn mpe(self) -> Self {
+        let mut control = self.control.lock();
+        control.mpe = true;
+        control.bend_range = 48.0;
+        drop(control);
+        self
+    }
+    /// Play one note at a time, choosing among held notes by priority
+    ///
+    /// When the playing note is released while other notes are still held,
+    /// the held note with the highest priority plays.
+    /// Released notes still ring out for their release, so more than one voice may sound at once.
+    pub fn mono(self, priority: NotePriority) -> Self {
+        self.control.lock().mono = Some(priority);
+        self
+    }
+    /// Set whether changing notes in mono mode keeps the playing voice
+    ///
+    /// With legato, the playing voice changes pitch instead of a new voice being started,
+    /// so envelopes are not retriggered. Wrapping [`MidiNote::pitch`] in a
+    /// [`Portamento`](crate::Portamento) makes it glide between notes.
+    /// Without legato, which is the default, every note change starts a new voice.
+    pub fn legato(self, legato: bool) -> Self {
+        self.control.lock().legato = legato;
+        self
+    }
+    /// Get the number of voices currently playing, including released ones
+    pub fn voices(&self) -> usize {
+        self.voices.len()
+    }
+    fn retire(&mut self, voice: ActiveVoice<V>) {
+        // If too many voices are waiting to be dropped, this one is dropped here
+        let _ = self.retired.push(voice);
+    }
+}
+
+impl<V, F> VoiceControl<V, F> {
+    fn freq(&self, key: u8, channel: u8) -> f64 {
+        let mut semitones = key as f64 - 69.0;
+        semitones += self.channels[channel as usize].bend * self.bend_range;
+        if self.mpe && channel != MPE_MASTER {
+            semitones += self.channels[MPE_MASTER as usize].bend * 2.0;
+        }
+        440.0 * 2f64.powf(semitones / 12.0)
+    }
+    fn pressure(&self, channel: u8) -> f64 {
+        let pressure = self.channels[channel as usize].pressure;
+        if self.mpe {
+            pressure.max(self.channels[MPE_MASTER as usize].pressure)
+        } else {
+            pressure
+        }
+    }
+    /// Update the expression of the voices affected by a change on a channel
+    fn update_channel(&self, channel: u8) {
+        let all = self.mpe && channel == MPE_MASTER;
+        for note in &self.notes {
+            if all || note.channel == channel {
+                note.pitch.set(self.freq(note.key, note.channel));
+                note.pressure.set(self.pressure(note.channel));
+                (note.timbre).set(self.channels[note.channel as usize].timbre);
+            }
+        }
+    }
+    /// Drop the voices that have finished playing
+    fn collect(&mut self) {
+        while let Some(voice) = self.retired.pop() {
+            if let Some(i) = self.notes.iter().position(|note| note.id == voice.id) {
+                self.notes.remove(i);
+            }
+        }
+    }
+    fn start_voice(&mut self, key: u8, channel: u8, velocity: u8)
+    where
+        F: FnMut(&MidiNote) -> V,
+    {
+        let note = MidiNote {
+            key,
+            channel,
+            velocity: velocity as f64 / 127.0,
+            pitch: Shared::new(self.freq(key, channel)),
+            pressure: Shared::new(self.pressure(channel)),
+            timbre: Shared::new(self.channels[channel as usize].timbre),
+            gate: Maintainer::with_release(self.release),
+        };
+        let source = (self.voice)(&note);
+        self.next_id += 1;
+        let id = self.next_id;
+        if self.started.push(ActiveVoice { id, source }).is_err() {
+            return;
+        }
+        // The player cuts off its oldest voice to make room
+        if self.notes.len() >= self.max_voices {
+            self.notes.pop_front();
+        }
+        self.notes.push_back(NoteHandle {
+            id,
+            key,
+            channel,
+            pitch: note.pitch,
+            pressure: note.pressure,
+            timbre: note.timbre,
+            gate: Some(note.gate),
+        });
+    }
+    /// Make the playing voice match the held note with the highest priority
+    fn update_mono(&mut self, priority: NotePriority)
+    where
+        F: FnMut(&MidiNote) -> V,
+    {
+        let target = match priority {
+            NotePriority::Last => self.held.last(),
+            NotePriority::High => self.held.iter().max_by_key(|held| held.key),
+            NotePriority::Low => self.held.iter().rev().min_by_key(|held| held.key),
+        }
+        .copied();
+        // The playing voice is always the newest one
+        let playing = (self.notes.back()).filter(|note| note.gate.is_some());
+        let playing = playing.map(|note| (note.key, note.channel));
+        match (target, playing) {
+            (None, None) => {}
+            (None, Some(_)) => self.notes.back_mut().unwrap().gate = None,
+            (Some(target), Some(playing)) if (target.key, target.channel) == playing => {}
+            (Some(target), Some(_)) if self.legato => {
+                let freq = self.freq(target.key, target.channel);
+                let pressure = self.pressure(target.channel);
+                let timbre = self.channels[target.channel as usize].timbre;
+                let note = self.notes.back_mut().unwrap();
+                note.key = target.key;
+                note.channel = target.channel;
+                note.pitch.set(freq);
+                note.pressure.set(pressure);
+                note.timbre.set(timbre);
+            }
+            (Some(target), playing) => {
+                if playing.is_some() {
+                    self.notes.back_mut().unwrap().gate = None;
+                }
+                self.start_voice(target.key, target.channel, target.velocity);
+            }
+        }
+    }
+}
+
+impl<V, F> HandleMidi for VoiceControl<V, F>
+where
+    V: Send,
+    F: FnMut(&MidiNote) -> V + Send,
+{
+    fn handle(&mut self, message: MidiMessage) {
+        self.collect();
+        let channel = message.channel();
+        match message {
+            MidiMessage::NoteOn { key, velocity, .. } => {
+                if let Some(priority) = self.mono {
+                    let note = HeldNote {
+                        key,
+                        channel,
+                        velocity,
+                    };
+                    self.held
+                        .retain(|held| (held.key, held.channel) != (key, channel));
+                    self.held.push(note);
+                    self.update_mono(priority);
+                } else {
+                    self.start_voice(key, channel, velocity);
+                }
+            }
+            MidiMessage::NoteOff { key, .. } => {
+                if let Some(priority) = self.mono {
+                    self.held
+                        .retain(|held| (held.key, held.channel) != (key, channel));
+                    self.update_mono(priority);
+                } else if let Some(note) = (self.notes.iter_mut())
+                    .find(|note| note.key == key && note.channel == channel && note.gate.is_some())
+                {
+                    note.gate = None;
+                }
+            }
+            MidiMessage::PolyPressure { key, pressure, .. } => {
+                for note in &self.notes {
+                    if note.key == key && note.channel == channel {
+                        note.pressure.set(pressure as f64 / 127.0);
+                    }
+                }
+            }
+            MidiMessage::ControlChange {
+                controller, value, ..
+            } => {
+                if controller == TIMBRE_CC {
+                    self.channels[channel as usize].timbre = value as f64 / 127.0;
+                    self.update_channel(channel);
+                }
+            }
+            MidiMessage::ChannelPressure { pressure, .. } => {
+                self.channels[channel as usize].pressure = pressure as f64 / 127.0;
+                self.update_channel(channel);
+            }
+            MidiMessage::PitchBend { value, .. } => {
+                self.channels[channel as usize].bend = (value as f64 - 8192.0) / 8192.0;
+                self.update_channel(channel);
+            }
+        }
+    }
+}
+
+impl<V, F> Source for MidiVoices<V, F>
+where
+    V: Source,
+{
+    type Frame = V::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        while let Some(voice) = self.started.pop() {
+            if self.voices.len() >= self.max_voices {
+                let stolen = self.voices.remove(0);
+                self.retire(stolen);
+            }
+            self.voices.push(voice);
+        }
+        let mut frame = V::Frame::uniform(0.0);
+        let mut i = 0;
+        while i < self.voices.len() {
+            if let Some(next) = self.voices[i].source.next(sample_rate) {
+                frame.merge(next, |a, b| a + b);
+                i += 1;
+            } else {
+                let finished = self.voices.remove(i);
+                self.retire(finished);
+            }
+        }
+        Some(frame)
+    }
+}
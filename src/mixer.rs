@@ -1,19 +1,34 @@
-use std::sync::Arc;
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
 
-use parking_lot::Mutex;
+use crate::{source::*, sync::Mutex, Frame, ToDuration};
 
-use crate::{source::*, Frame};
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
 
 /// An [`Source`] that mixes multiple [`Source`]s together
 #[derive(Clone)]
 pub struct Mixer<F> {
     pub(crate) sources: Arc<Mutex<Vec<DynamicSource<F>>>>,
+    on_idle: Arc<Mutex<Option<IdleCallback>>>,
+    pub(crate) clock: Arc<AtomicU64>,
+    /// The number of sources, so that it can be checked without locking
+    pub(crate) playing: Arc<AtomicUsize>,
+    #[cfg(feature = "async")]
+    pub(crate) idle_wakers: Arc<crate::future::IdleWakers>,
 }
 
+type IdleCallback = Box<dyn FnMut() + Send + 'static>;
+
 impl<F> Default for Mixer<F> {
     fn default() -> Self {
         Mixer {
             sources: Arc::new(Mutex::new(Vec::new())),
+            on_idle: Arc::new(Mutex::new(None)),
+            clock: Arc::new(AtomicU64::new(0)),
+            playing: Arc::new(AtomicUsize::new(0)),
+            #[cfg(feature = "async")]
+            idle_wakers: Arc::new(Mutex::new(Vec::new())),
         }
     }
 }
@@ -24,11 +39,133 @@ impl<F> Mixer<F> {
         Self::default()
     }
     /// Add a source to the mixer to be played immediately
-    pub fn add<S>(&self, source: S)
+    ///
+    /// The returned [`SourceHandle`] can be used to stop the source.
+    pub fn add<S>(&self, source: S) -> SourceHandle
     where
         S: Source<Frame = F> + Send + 'static,
     {
-        self.sources.lock().push(Box::new(source));
+        let handle = SourceHandle {
+            state: Arc::new(HandleState {
+                stop: AtomicU64::new(NOT_STOPPED),
+                finished: AtomicBool::new(false),
+            }),
+        };
+        let mut sources = self.sources.lock();
+        sources.push(Box::new(Handled {
+            source,
+            state: handle.state.clone(),
+            fade: None,
+        }));
+        self.playing.store(sources.len(), Ordering::Relaxed);
+        handle
+    }
+    /// Set a function to be called whenever the last playing source finishes
+    ///
+    /// The function is called on the audio thread, so it should return quickly.
+    /// It may add more sources to the mixer, or replace itself by calling this method again.
+    pub fn on_idle(&self, f: impl FnMut() + Send + 'static) {
+        *self.on_idle.lock() = Some(Box::new(f));
+    }
+    /// Check if any sources are playing without locking them
+    ///
+    /// Sources added from outside the mixer's own methods are counted after its next frame.
+    pub(crate) fn is_playing(&self) -> bool {
+        self.playing.load(Ordering::Relaxed) > 0
+    }
+    /// Call the idle callback and wake the [`Idle`](crate::Idle) futures
+    pub(crate) fn fire_idle(&self) {
+        // The callback is taken out of the lock so that it can call `on_idle` itself
+        let callback = self.on_idle.lock().take();
+        if let Some(mut f) = callback {
+            f();
+            // Keep a callback that was set while this one ran
+            self.on_idle.lock().get_or_insert(f);
+        }
+        #[cfg(feature = "async")]
+        for (_, waker) in self.idle_wakers.lock().drain(..) {
+            waker.wake();
+        }
+    }
+}
+
+/// A handle to a source that has been added to a [`Mixer`]
+///
+/// Dropping the handle does not stop the source.
+#[derive(Debug, Clone)]
+pub struct SourceHandle {
+    state: Arc<HandleState>,
+}
+
+#[derive(Debug)]
+struct HandleState {
+    /// The bits of the fade-out duration, or [`NOT_STOPPED`]
+    stop: AtomicU64,
+    finished: AtomicBool,
+}
+
+const NOT_STOPPED: u64 = u64::MAX;
+
+impl SourceHandle {
+    /// Stop the source immediately and remove it from the mixer
+    pub fn stop(&self) {
+        self.stop_with_fade(0.0);
+    }
+    /// Fade the source out, then remove it from the mixer
+    ///
+    /// This avoids the click of stopping a source abruptly.
+    /// Calling this again after a fade has started has no effect.
+    pub fn stop_with_fade(&self, fade: impl ToDuration) {
+        let fade = fade.to_duration().as_secs_f64();
+        let _ = self.state.stop.compare_exchange(
+            NOT_STOPPED,
+            fade.to_bits(),
+            Ordering::Relaxed,
+            Ordering::Relaxed,
+        );
+    }
+    /// Check if the source has finished and been removed from the mixer
+    pub fn is_finished(&self) -> bool {
+        self.state.finished.load(Ordering::Relaxed)
+    }
+}
+
+/// A source in a [`Mixer`] that can be controlled by a [`SourceHandle`]
+struct Handled<S> {
+    source: S,
+    state: Arc<HandleState>,
+    /// The fade-out duration and the time elapsed in it
+    fade: Option<(f64, f64)>,
+}
+
+impl<S> Source for Handled<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        if self.fade.is_none() {
+            let stop = self.state.stop.load(Ordering::Relaxed);
+            if stop != NOT_STOPPED {
+                self.fade = Some((f64::from_bits(stop), 0.0));
+            }
+        }
+        let frame = self.source.next(sample_rate)?;
+        let Some((fade, elapsed)) = &mut self.fade else {
+            return Some(frame);
+        };
+        if *elapsed >= *fade {
+            return None;
+        }
+        let amp = 1.0 - *elapsed / *fade;
+        *elapsed += 1.0 / sample_rate;
+        Some(frame.map(|a| a * amp))
+    }
+}
+
+impl<S> Drop for Handled<S> {
+    fn drop(&mut self) {
+        self.state.finished.store(true, Ordering::Relaxed);
     }
 }
 
@@ -39,6 +176,7 @@ where
     type Frame = F;
     fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
         let mut sources = self.sources.lock();
+        let was_playing = !sources.is_empty();
         let mut frame = F::uniform(0.0);
         sources.retain_mut(|source| {
             if let Some(this_frame) = source.next(sample_rate) {
@@ -48,6 +186,13 @@ where
                 false
             }
         });
+        let became_idle = was_playing && sources.is_empty();
+        self.playing.store(sources.len(), Ordering::Relaxed);
+        drop(sources);
+        self.clock.fetch_add(1, Ordering::Relaxed);
+        if became_idle {
+            self.fire_idle();
+        }
         Some(frame)
     }
 }
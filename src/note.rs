@@ -3,6 +3,9 @@ use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 use crate::Automation;
 
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
 /// Type alias for an octave
 pub type Octave = i8;
 
@@ -150,7 +153,7 @@ impl<'de> Deserialize<'de> for Letter {
             "a" => Ok(Letter::A),
             "bb" | "a#" | "ash" => Ok(Letter::Bb),
             "b" => Ok(Letter::B),
-            _ => Err(serde::de::Error::custom(format!(
+            _ => Err(serde::de::Error::custom(alloc::format!(
                 "Invalid note letter: {s:?}"
             ))),
         }
@@ -280,3 +283,331 @@ impl Mode {
         self.note(base, steps)
     }
 }
+
+/// Every [`Mode`]
+const MODES: [Mode; 12] = [
+    Mode::Major,
+    Mode::Minor,
+    Mode::Dorian,
+    Mode::Phrygian,
+    Mode::Lydian,
+    Mode::Mixolydian,
+    Mode::Aeolian,
+    Mode::Locrian,
+    Mode::HarmonicMinor,
+    Mode::MelodicMinor,
+    Mode::WholeTone,
+    Mode::Diminished,
+];
+
+/// A set of [`Letter`]s, ignoring octaves
+///
+/// # Example
+/// ```
+/// use hodaun::*;
+/// use Letter::*;
+///
+/// let set = PitchClassSet::from_pitches([(C, 3), (E, 4), (G, 3), (C, 5)]);
+/// assert_eq!(set.len(), 3);
+/// assert!(set.contains(E));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct PitchClassSet(u16);
+
+impl PitchClassSet {
+    /// Create an empty set
+    pub const fn new() -> Self {
+        PitchClassSet(0)
+    }
+    /// Create a set from the letters of some pitches
+    pub fn from_pitches<P: Into<Pitch>>(pitches: impl IntoIterator<Item = P>) -> Self {
+        let mut set = PitchClassSet::new();
+        for pitch in pitches {
+            set.insert(pitch.into().letter);
+        }
+        set
+    }
+    /// Add a letter to the set
+    pub fn insert(&mut self, letter: Letter) {
+        self.0 |= 1 << letter as u16;
+    }
+    /// Remove a letter from the set
+    pub fn remove(&mut self, letter: Letter) {
+        self.0 &= !(1 << letter as u16);
+    }
+    /// Check if the set contains a letter
+    pub const fn contains(&self, letter: Letter) -> bool {
+        self.0 & (1 << letter as u16) != 0
+    }
+    /// Get the number of letters in the set
+    pub const fn len(&self) -> usize {
+        self.0.count_ones() as usize
+    }
+    /// Check if the set is empty
+    pub const fn is_empty(&self) -> bool {
+        self.0 == 0
+    }
+    /// Iterate over the letters in the set, starting from C
+    pub fn letters(&self) -> impl Iterator<Item = Letter> + '_ {
+        (0..12)
+            .map(|i| Pitch::from_half_steps(i).letter)
+            .filter(|&letter| self.contains(letter))
+    }
+    /// Shift every letter in the set by some number of half-steps
+    pub const fn transpose(self, half_steps: i16) -> Self {
+        let shift = half_steps.rem_euclid(12) as u32;
+        let bits = self.0 as u32;
+        PitchClassSet((((bits << shift) | (bits >> (12 - shift))) & 0xfff) as u16)
+    }
+    /// Find the chords that contain exactly the letters in the set
+    ///
+    /// See [`Chord::detect`] for choosing a single chord.
+    pub fn chords(&self) -> Vec<Chord> {
+        let mut chords = Vec::new();
+        for root in self.letters() {
+            let relative = self.transpose(-(root as i16));
+            for quality in CHORD_QUALITIES {
+                if relative == quality.intervals() {
+                    chords.push(Chord {
+                        root,
+                        quality,
+                        bass: None,
+                    });
+                }
+            }
+        }
+        chords
+    }
+    /// Find the scales that contain every letter in the set
+    ///
+    /// Scales are returned as a tonic and a [`Mode`].
+    /// Modes with the same notes, like [`Mode::Minor`] and [`Mode::Aeolian`], are both returned.
+    pub fn scales(&self) -> Vec<(Letter, Mode)> {
+        let mut scales = Vec::new();
+        for i in 0..12 {
+            let tonic = Pitch::from_half_steps(i).letter;
+            for mode in MODES {
+                let scale = PitchClassSet(
+                    mode.scale()
+                        .iter()
+                        .fold(0, |bits, &step| bits | 1 << step.rem_euclid(12)),
+                )
+                .transpose(i);
+                if scale.0 & self.0 == self.0 {
+                    scales.push((tonic, mode));
+                }
+            }
+        }
+        scales
+    }
+}
+
+impl FromIterator<Letter> for PitchClassSet {
+    fn from_iter<T: IntoIterator<Item = Letter>>(iter: T) -> Self {
+        let mut set = PitchClassSet::new();
+        for letter in iter {
+            set.insert(letter);
+        }
+        set
+    }
+}
+
+/// The quality of a [`Chord`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[allow(missing_docs)]
+pub enum ChordQuality {
+    Major,
+    Minor,
+    Diminished,
+    Augmented,
+    Sus2,
+    Sus4,
+    Power,
+    Major6,
+    Minor6,
+    Dominant7,
+    Major7,
+    Minor7,
+    MinorMajor7,
+    HalfDiminished7,
+    Diminished7,
+    Augmented7,
+    Dominant9,
+    Major9,
+    Minor9,
+    Add9,
+}
+
+/// Every [`ChordQuality`]
+const CHORD_QUALITIES: [ChordQuality; 20] = [
+    ChordQuality::Major,
+    ChordQuality::Minor,
+    ChordQuality::Diminished,
+    ChordQuality::Augmented,
+    ChordQuality::Sus2,
+    ChordQuality::Sus4,
+    ChordQuality::Power,
+    ChordQuality::Major6,
+    ChordQuality::Minor6,
+    ChordQuality::Dominant7,
+    ChordQuality::Major7,
+    ChordQuality::Minor7,
+    ChordQuality::MinorMajor7,
+    ChordQuality::HalfDiminished7,
+    ChordQuality::Diminished7,
+    ChordQuality::Augmented7,
+    ChordQuality::Dominant9,
+    ChordQuality::Major9,
+    ChordQuality::Minor9,
+    ChordQuality::Add9,
+];
+
+impl ChordQuality {
+    /// Get the half-steps above the root of each note in the chord
+    pub const fn steps(&self) -> &'static [i16] {
+        match self {
+            ChordQuality::Major => &[0, 4, 7],
+            ChordQuality::Minor => &[0, 3, 7],
+            ChordQuality::Diminished => &[0, 3, 6],
+            ChordQuality::Augmented => &[0, 4, 8],
+            ChordQuality::Sus2 => &[0, 2, 7],
+            ChordQuality::Sus4 => &[0, 5, 7],
+            ChordQuality::Power => &[0, 7],
+            ChordQuality::Major6 => &[0, 4, 7, 9],
+            ChordQuality::Minor6 => &[0, 3, 7, 9],
+            ChordQuality::Dominant7 => &[0, 4, 7, 10],
+            ChordQuality::Major7 => &[0, 4, 7, 11],
+            ChordQuality::Minor7 => &[0, 3, 7, 10],
+            ChordQuality::MinorMajor7 => &[0, 3, 7, 11],
+            ChordQuality::HalfDiminished7 => &[0, 3, 6, 10],
+            ChordQuality::Diminished7 => &[0, 3, 6, 9],
+            ChordQuality::Augmented7 => &[0, 4, 8, 10],
+            ChordQuality::Dominant9 => &[0, 4, 7, 10, 14],
+            ChordQuality::Major9 => &[0, 4, 7, 11, 14],
+            ChordQuality::Minor9 => &[0, 3, 7, 10, 14],
+            ChordQuality::Add9 => &[0, 4, 7, 14],
+        }
+    }
+    /// Get the suffix used when naming a chord of this quality
+    pub const fn suffix(&self) -> &'static str {
+        match self {
+            ChordQuality::Major => "",
+            ChordQuality::Minor => "m",
+            ChordQuality::Diminished => "dim",
+            ChordQuality::Augmented => "aug",
+            ChordQuality::Sus2 => "sus2",
+            ChordQuality::Sus4 => "sus4",
+            ChordQuality::Power => "5",
+            ChordQuality::Major6 => "6",
+            ChordQuality::Minor6 => "m6",
+            ChordQuality::Dominant7 => "7",
+            ChordQuality::Major7 => "maj7",
+            ChordQuality::Minor7 => "m7",
+            ChordQuality::MinorMajor7 => "m(maj7)",
+            ChordQuality::HalfDiminished7 => "m7b5",
+            ChordQuality::Diminished7 => "dim7",
+            ChordQuality::Augmented7 => "aug7",
+            ChordQuality::Dominant9 => "9",
+            ChordQuality::Major9 => "maj9",
+            ChordQuality::Minor9 => "m9",
+            ChordQuality::Add9 => "add9",
+        }
+    }
+    /// Get the letters of this chord quality built on C
+    fn intervals(&self) -> PitchClassSet {
+        self.steps()
+            .iter()
+            .map(|&step| Pitch::from_half_steps(step % 12).letter)
+            .collect()
+    }
+}
+
+/// A chord, identified by its root and quality
+///
+/// # Example
+/// ```
+/// use hodaun::*;
+/// use Letter::*;
+///
+/// let chord = Chord::detect([(B, 2), (D, 3), (G, 3), (F, 4)]).unwrap();
+/// assert_eq!(chord.to_string(), "G7/B");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Chord {
+    /// The root of the chord
+    pub root: Letter,
+    /// The quality of the chord
+    pub quality: ChordQuality,
+    /// The bass note, if it is not the root
+    pub bass: Option<Letter>,
+}
+
+impl Chord {
+    /// Identify the chord formed by some pitches, such as currently held notes
+    ///
+    /// When the pitches could form more than one chord, like C6 and Am7,
+    /// the one whose root is the lowest pitch is preferred.
+    /// Returns [`None`] if the pitches do not form a known chord.
+    pub fn detect<P: Into<Pitch>>(pitches: impl IntoIterator<Item = P>) -> Option<Chord> {
+        let pitches: Vec<Pitch> = pitches.into_iter().map(Into::into).collect();
+        let bass = pitches.iter().min()?.letter;
+        let chords = PitchClassSet::from_pitches(pitches.iter().copied()).chords();
+        let chord = chords
+            .iter()
+            .find(|chord| chord.root == bass)
+            .or_else(|| chords.first())?;
+        Some(Chord {
+            bass: (chord.root != bass).then_some(bass),
+            ..*chord
+        })
+    }
+    /// Get the pitches of the chord with the root in the given octave
+    ///
+    /// The bass note, if any, is placed an octave below the root.
+    pub fn pitches(&self, octave: Octave) -> Vec<Pitch> {
+        let root = self.root.half_steps(octave);
+        let bass = self.bass.map(|bass| {
+            let bass = bass.half_steps(octave);
+            Pitch::from_half_steps(if bass >= root { bass - 12 } else { bass })
+        });
+        bass.into_iter()
+            .chain(
+                self.quality
+                    .steps()
+                    .iter()
+                    .map(|&step| Pitch::from_half_steps(root + step)),
+            )
+            .collect()
+    }
+}
+
+impl core::fmt::Display for Letter {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let name = match self {
+            Letter::C => "C",
+            Letter::Db => "Db",
+            Letter::D => "D",
+            Letter::Eb => "Eb",
+            Letter::E => "E",
+            Letter::F => "F",
+            Letter::Gb => "Gb",
+            Letter::G => "G",
+            Letter::Ab => "Ab",
+            Letter::A => "A",
+            Letter::Bb => "Bb",
+            Letter::B => "B",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl core::fmt::Display for Chord {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        write!(f, "{}{}", self.root, self.quality.suffix())?;
+        if let Some(bass) = self.bass {
+            write!(f, "/{bass}")?;
+        }
+        Ok(())
+    }
+}
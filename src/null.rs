@@ -0,0 +1,131 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+use crate::{Frame, Mixer, Source, SourceHandle};
+
+/// Create a [`NullOutput`] that pulls frames as fast as possible
+pub fn null_output<F: Frame>(sample_rate: f64) -> NullOutput<F> {
+    NullOutput::new(sample_rate, None)
+}
+
+/// Mixes audio sources and discards the output
+///
+/// Frames are pulled from the mixer on a dedicated thread, either as fast as
+/// possible or at a fixed rate, without any audio device. This is useful for
+/// profiling sources and for performance tests in environments without audio.
+///
+/// It can be created with either [`null_output`] or [`NullOutput::new`]
+pub struct NullOutput<F> {
+    mixer: Mixer<F>,
+    sample_rate: f64,
+    frames: Arc<AtomicU64>,
+    running: Arc<AtomicBool>,
+    start: Instant,
+    thread: Option<JoinHandle<()>>,
+}
+
+/// The number of frames pulled between checks of the output's state
+const BLOCK_SIZE: u64 = 512;
+
+impl<F> NullOutput<F>
+where
+    F: Frame,
+{
+    /// Create a new null output
+    ///
+    /// Sources will receive the given sample rate.
+    /// If `frames_per_second` is [`None`], frames are pulled as fast as possible.
+    /// Otherwise, they are pulled at that rate.
+    pub fn new(sample_rate: f64, frames_per_second: Option<f64>) -> Self {
+        let mixer = Mixer::new();
+        let frames = Arc::new(AtomicU64::new(0));
+        let running = Arc::new(AtomicBool::new(true));
+        let start = Instant::now();
+        let thread = {
+            let mut mixer = mixer.clone();
+            let frames = frames.clone();
+            let running = running.clone();
+            thread::spawn(move || {
+                let mut pulled = 0;
+                while running.load(Ordering::Relaxed) {
+                    for _ in 0..BLOCK_SIZE {
+                        mixer.next(sample_rate);
+                    }
+                    pulled += BLOCK_SIZE;
+                    frames.store(pulled, Ordering::Relaxed);
+                    if let Some(rate) = frames_per_second {
+                        let target = Duration::from_secs_f64(pulled as f64 / rate);
+                        if let Some(ahead) = target.checked_sub(start.elapsed()) {
+                            thread::sleep(ahead);
+                        }
+                    }
+                }
+            })
+        };
+        NullOutput {
+            mixer,
+            sample_rate,
+            frames,
+            running,
+            start,
+            thread: Some(thread),
+        }
+    }
+    /// Get the sample rate
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+    /// Get the number of frames that have been pulled from the mixer
+    pub fn frames(&self) -> u64 {
+        self.frames.load(Ordering::Relaxed)
+    }
+    /// Get the average number of frames pulled per second since the output was created
+    pub fn frames_per_second(&self) -> f64 {
+        self.frames() as f64 / self.start.elapsed().as_secs_f64()
+    }
+    /// Block the thread until all sources have finished
+    pub fn block(&mut self) {
+        while !self
+            .mixer
+            .sources
+            .try_lock()
+            .is_some_and(|sources| sources.is_empty())
+        {
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+    /// Get a future that resolves when all sources have finished
+    ///
+    /// This is the async equivalent of [`block`](Self::block).
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub fn wait_idle(&self) -> crate::Idle {
+        self.mixer.idle()
+    }
+    /// Add a source to the mixer to be played immediately
+    pub fn add<S>(&self, source: S) -> SourceHandle
+    where
+        S: Source<Frame = F> + Send + 'static,
+    {
+        self.mixer.add(source)
+    }
+    /// Get a reference to the mixer
+    pub fn mixer(&self) -> &Mixer<F> {
+        &self.mixer
+    }
+}
+
+impl<F> Drop for NullOutput<F> {
+    fn drop(&mut self) {
+        self.running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
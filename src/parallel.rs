@@ -0,0 +1,278 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        mpsc::{self, Receiver, Sender, SyncSender, TryRecvError},
+        Arc,
+    },
+    thread,
+};
+
+use crate::{Frame, Mixer, Source, SourceHandle};
+
+/// A [`Mixer`] that renders its sources on a pool of worker threads
+///
+/// Sources are distributed among the workers, which each render a block of frames
+/// from their share of the sources. The blocks are summed on a coordinating thread.
+/// This helps with very dense mixes, where one thread cannot render every source in time,
+/// and speeds up offline rendering on machines with many cores.
+///
+/// The next block is rendered while the current one plays.
+/// By default, reading a frame never waits for the workers.
+/// If a block is not ready in time, a block of silence is played and [`ParallelMixer::underruns`] is incremented.
+/// For offline rendering, use [`ParallelMixer::wait_free`] to wait for every block instead.
+///
+/// The worker threads are started with [`ParallelMixer::start`], and stop when the parallel mixer is dropped.
+/// Until they are started, sources are mixed on the thread that reads the frames.
+///
+/// Once the workers have started, [`ParallelMixer::on_idle`] callbacks are called on the coordinating thread
+/// at the end of the block in which the last source finished.
+pub struct ParallelMixer<F: Frame> {
+    mixer: Mixer<F>,
+    threads: usize,
+    block_size: usize,
+    wait_free: bool,
+    underruns: Arc<AtomicU64>,
+    state: ParallelState<F>,
+}
+
+enum ParallelState<F> {
+    Idle,
+    Running {
+        ready: Receiver<Vec<F>>,
+        recycle: SyncSender<Vec<F>>,
+        block: Vec<F>,
+        pos: usize,
+    },
+    Failed,
+}
+
+impl<F: Frame> ParallelMixer<F> {
+    /// Create a new parallel mixer with some number of worker threads
+    pub fn new(threads: usize) -> Self {
+        ParallelMixer {
+            mixer: Mixer::new(),
+            threads: threads.max(1),
+            block_size: 256,
+            wait_free: true,
+            underruns: Arc::new(AtomicU64::new(0)),
+            state: ParallelState::Idle,
+        }
+    }
+    /// Set the number of frames rendered at a time
+    ///
+    /// Larger blocks reduce the overhead of coordinating the workers, but add latency.
+    /// The default is 256. It cannot be changed once the workers have started.
+    pub fn block_size(self, block_size: usize) -> Self {
+        ParallelMixer {
+            block_size: block_size.max(1),
+            ..self
+        }
+    }
+    /// Set whether reading a frame never waits for the workers
+    ///
+    /// The default is `true`, which is needed for realtime output.
+    /// Set it to `false` when rendering offline so that no blocks are skipped.
+    pub fn wait_free(self, wait_free: bool) -> Self {
+        ParallelMixer { wait_free, ..self }
+    }
+    /// Add a source to be played immediately
+    ///
+    /// It is assigned to a worker at the start of the next block.
+    pub fn add<S>(&self, source: S) -> SourceHandle
+    where
+        S: Source<Frame = F> + Send + 'static,
+    {
+        self.mixer.add(source)
+    }
+    /// Set a function to be called whenever the last playing source finishes
+    ///
+    /// See [`Mixer::on_idle`].
+    pub fn on_idle(&self, f: impl FnMut() + Send + 'static) {
+        self.mixer.on_idle(f);
+    }
+    /// Get a future that resolves when the parallel mixer has no sources left to play
+    #[cfg(feature = "async")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "async")))]
+    pub fn idle(&self) -> crate::Idle {
+        self.mixer.idle()
+    }
+    /// Get the number of blocks that were not ready in time and were replaced with silence
+    pub fn underruns(&self) -> u64 {
+        self.underruns.load(Ordering::Relaxed)
+    }
+    /// Start the worker threads at the sample rate the mixer will be played at
+    ///
+    /// This should be called before the mixer is added to an output,
+    /// so that threads are not spawned on the audio thread.
+    /// It has no effect if the workers have already been started.
+    pub fn start(mut self, sample_rate: f64) -> Self {
+        if let ParallelState::Idle = self.state {
+            self.spawn(sample_rate);
+        }
+        self
+    }
+    fn spawn(&mut self, sample_rate: f64) {
+        // The ready channel holds one block while another plays
+        let (ready_send, ready) = mpsc::sync_channel(1);
+        let (recycle, recycled) = mpsc::sync_channel(2);
+        let mut workers = Vec::with_capacity(self.threads);
+        for i in 0..self.threads {
+            let (job_send, jobs) = mpsc::channel::<Vec<F>>();
+            let (done_send, done) = mpsc::channel();
+            let mixer = Mixer::<F>::new();
+            let worker_mixer = mixer.clone();
+            let spawned = thread::Builder::new()
+                .name(format!("hodaun worker {i}"))
+                .spawn(move || render_blocks(worker_mixer, sample_rate, jobs, done_send));
+            if spawned.is_err() {
+                self.state = ParallelState::Failed;
+                return;
+            }
+            workers.push(Worker {
+                mixer,
+                jobs: job_send,
+                done,
+            });
+        }
+        let front = self.mixer.clone();
+        let block_size = self.block_size;
+        let spawned = thread::Builder::new()
+            .name("hodaun mixer".into())
+            .spawn(move || coordinate(front, workers, block_size, ready_send, recycled));
+        self.state = if spawned.is_ok() {
+            ParallelState::Running {
+                ready,
+                recycle,
+                // Without waiting, one block of silence plays while the first block renders
+                block: if self.wait_free {
+                    vec![F::uniform(0.0); self.block_size]
+                } else {
+                    Vec::new()
+                },
+                pos: 0,
+            }
+        } else {
+            ParallelState::Failed
+        };
+    }
+}
+
+struct Worker<F> {
+    mixer: Mixer<F>,
+    jobs: Sender<Vec<F>>,
+    done: Receiver<Vec<F>>,
+}
+
+/// Render a block of frames from a worker's mixer for each buffer sent to it
+fn render_blocks<F: Frame>(
+    mut mixer: Mixer<F>,
+    sample_rate: f64,
+    jobs: Receiver<Vec<F>>,
+    done: Sender<Vec<F>>,
+) {
+    for mut block in jobs {
+        for frame in &mut block {
+            *frame = mixer.next(sample_rate).unwrap_or_else(|| F::uniform(0.0));
+        }
+        if done.send(block).is_err() {
+            break;
+        }
+    }
+}
+
+/// Distribute new sources among the workers and sum their blocks until the parallel mixer is dropped
+fn coordinate<F: Frame>(
+    front: Mixer<F>,
+    workers: Vec<Worker<F>>,
+    block_size: usize,
+    ready: SyncSender<Vec<F>>,
+    recycled: Receiver<Vec<F>>,
+) {
+    let new_block = || vec![F::uniform(0.0); block_size];
+    let mut worker_blocks: Vec<Vec<F>> = workers.iter().map(|_| new_block()).collect();
+    let mut was_playing = false;
+    loop {
+        // Give new sources to the workers with the fewest sources
+        let added = std::mem::take(&mut *front.sources.lock());
+        for source in added {
+            let worker = (workers.iter())
+                .min_by_key(|worker| worker.mixer.sources.lock().len())
+                .expect("there is at least one worker");
+            worker.mixer.sources.lock().push(source);
+        }
+        for (worker, block) in workers.iter().zip(worker_blocks.drain(..)) {
+            if worker.jobs.send(block).is_err() {
+                return;
+            }
+        }
+        let mut sum = recycled.try_recv().unwrap_or_else(|_| new_block());
+        sum.clear();
+        sum.resize(block_size, F::uniform(0.0));
+        for worker in &workers {
+            let Ok(block) = worker.done.recv() else {
+                return;
+            };
+            for (sum, frame) in sum.iter_mut().zip(&block) {
+                sum.merge(frame.clone(), |a, b| a + b);
+            }
+            worker_blocks.push(block);
+        }
+        front.clock.fetch_add(block_size as u64, Ordering::Relaxed);
+        // The front mixer counts every worker's sources so that its idle futures wait for them.
+        // It is stored while its source list is locked, like in `Mixer::next`.
+        let sources = front.sources.lock();
+        let playing = sources.len()
+            + (workers.iter())
+                .map(|worker| worker.mixer.playing.load(Ordering::Relaxed))
+                .sum::<usize>();
+        front.playing.store(playing, Ordering::Relaxed);
+        drop(sources);
+        if was_playing && playing == 0 {
+            front.fire_idle();
+        }
+        was_playing = playing > 0;
+        if ready.send(sum).is_err() {
+            return;
+        }
+    }
+}
+
+impl<F: Frame> Source for ParallelMixer<F> {
+    type Frame = F;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let ParallelState::Running {
+            ready,
+            recycle,
+            block,
+            pos,
+        } = &mut self.state
+        else {
+            return self.mixer.next(sample_rate);
+        };
+        if *pos >= block.len() {
+            let next = if self.wait_free {
+                match ready.try_recv() {
+                    Ok(next) => Some(next),
+                    Err(TryRecvError::Empty) => None,
+                    Err(TryRecvError::Disconnected) => return None,
+                }
+            } else {
+                Some(ready.recv().ok()?)
+            };
+            match next {
+                Some(next) => {
+                    // Send the old block back to be reused so it is not freed on this thread
+                    let _ = recycle.try_send(std::mem::replace(block, next));
+                }
+                None => {
+                    self.underruns.fetch_add(1, Ordering::Relaxed);
+                    block.fill(F::uniform(0.0));
+                }
+            }
+            *pos = 0;
+        }
+        let frame = block[*pos].clone();
+        *pos += 1;
+        Some(frame)
+    }
+}
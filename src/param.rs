@@ -0,0 +1,117 @@
+use crate::{Automation, Shared};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// A gain parameter set from a normalized value and read in decibels
+///
+/// This is useful for volume knobs and faders, where a UI sets a value in the range [0, 1]
+/// that should map to a perceptually even range of loudness.
+/// As an [`Automation`], it produces the linear gain.
+///
+/// Cloning a [`DbParam`] is cheap, and clones share the same value.
+#[derive(Debug, Clone)]
+pub struct DbParam {
+    normalized: Shared<f64>,
+    min_db: f64,
+    max_db: f64,
+}
+
+impl DbParam {
+    /// Create a new parameter that spans from `min_db` to `max_db`, starting at `db`
+    ///
+    /// A normalized value of `0.0` is silent rather than `min_db`, so faders can be turned all the way down.
+    pub fn new(min_db: f64, max_db: f64, db: f64) -> Self {
+        let param = DbParam {
+            normalized: Shared::new(0.0),
+            min_db,
+            max_db,
+        };
+        param.set_db(db);
+        param
+    }
+    /// Set the normalized value, from `0.0` to `1.0`
+    pub fn set_normalized(&self, normalized: f64) {
+        self.normalized.set(normalized.clamp(0.0, 1.0));
+    }
+    /// Get the normalized value, from `0.0` to `1.0`
+    pub fn normalized(&self) -> f64 {
+        self.normalized.get()
+    }
+    /// Set the value in decibels
+    pub fn set_db(&self, db: f64) {
+        self.set_normalized((db - self.min_db) / (self.max_db - self.min_db));
+    }
+    /// Get the value in decibels
+    ///
+    /// This is negative infinity if the normalized value is `0.0`.
+    pub fn db(&self) -> f64 {
+        let normalized = self.normalized();
+        if normalized <= 0.0 {
+            f64::NEG_INFINITY
+        } else {
+            self.min_db + normalized * (self.max_db - self.min_db)
+        }
+    }
+    /// Get the linear gain
+    pub fn gain(&self) -> f64 {
+        10f64.powf(self.db() / 20.0)
+    }
+}
+
+impl Automation for DbParam {
+    fn next_value(&mut self, _sample_rate: f64) -> Option<f64> {
+        Some(self.gain())
+    }
+}
+
+/// A frequency parameter set from a normalized value and scaled logarithmically
+///
+/// This is useful for filter cutoff knobs, where a UI sets a value in the range [0, 1]
+/// and each octave should take up the same amount of the knob's travel.
+/// As an [`Automation`], it produces the frequency in hertz.
+///
+/// Cloning a [`LogHzParam`] is cheap, and clones share the same value.
+#[derive(Debug, Clone)]
+pub struct LogHzParam {
+    normalized: Shared<f64>,
+    min_hz: f64,
+    max_hz: f64,
+}
+
+impl LogHzParam {
+    /// Create a new parameter that spans from `min_hz` to `max_hz`, starting at `hz`
+    ///
+    /// Both bounds must be positive.
+    pub fn new(min_hz: f64, max_hz: f64, hz: f64) -> Self {
+        let param = LogHzParam {
+            normalized: Shared::new(0.0),
+            min_hz,
+            max_hz,
+        };
+        param.set_hz(hz);
+        param
+    }
+    /// Set the normalized value, from `0.0` to `1.0`
+    pub fn set_normalized(&self, normalized: f64) {
+        self.normalized.set(normalized.clamp(0.0, 1.0));
+    }
+    /// Get the normalized value, from `0.0` to `1.0`
+    pub fn normalized(&self) -> f64 {
+        self.normalized.get()
+    }
+    /// Set the frequency in hertz
+    pub fn set_hz(&self, hz: f64) {
+        self.set_normalized((hz / self.min_hz).ln() / (self.max_hz / self.min_hz).ln());
+    }
+    /// Get the frequency in hertz
+    pub fn hz(&self) -> f64 {
+        self.min_hz * (self.max_hz / self.min_hz).powf(self.normalized())
+    }
+}
+
+impl Automation for LogHzParam {
+    fn next_value(&mut self, _sample_rate: f64) -> Option<f64> {
+        Some(self.hz())
+    }
+}
@@ -0,0 +1,117 @@
+use core::fmt;
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// A rhythm of hits and rests over a number of steps
+///
+/// Patterns repeat, so they can be indexed past their length.
+/// A pattern can be played with [`Repeat::pattern`](crate::source::Repeat::pattern).
+///
+/// # Example
+/// ```
+/// use hodaun::*;
+///
+/// let tresillo = Pattern::euclidean(3, 8, 0);
+/// assert_eq!(tresillo.to_string(), "x..x..x.");
+/// let backbeat = Pattern::parse("..x...x.");
+/// assert_eq!(tresillo.or(&backbeat).to_string(), "x.xx..x.");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Pattern {
+    steps: Vec<bool>,
+}
+
+impl Pattern {
+    /// Create a pattern from a sequence of steps, where `true` is a hit
+    pub fn new(steps: impl IntoIterator<Item = bool>) -> Self {
+        Pattern {
+            steps: steps.into_iter().collect(),
+        }
+    }
+    /// Create a pattern from a string, where `x` or `X` is a hit and any other character is a rest
+    ///
+    /// Whitespace is ignored.
+    pub fn parse(s: &str) -> Self {
+        Pattern::new(
+            s.chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| c.eq_ignore_ascii_case(&'x')),
+        )
+    }
+    /// Create a pattern that spreads `hits` as evenly as possible over `steps`
+    ///
+    /// The pattern is rotated right by `rotation` steps.
+    /// Many traditional rhythms are euclidean, like the tresillo, `euclidean(3, 8, 0)`.
+    pub fn euclidean(hits: usize, steps: usize, rotation: usize) -> Self {
+        let hits = hits.min(steps);
+        Pattern::new((0..steps).map(|i| i * hits % steps < hits)).offset(rotation as isize)
+    }
+    /// Get the number of steps
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+    /// Check if the pattern has no steps
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+    /// Get the number of hits
+    pub fn hits(&self) -> usize {
+        self.steps.iter().filter(|&&hit| hit).count()
+    }
+    /// Check if a step is a hit
+    ///
+    /// The index wraps around the end of the pattern.
+    pub fn is_hit(&self, step: usize) -> bool {
+        !self.steps.is_empty() && self.steps[step % self.steps.len()]
+    }
+    /// Iterate over the steps, where `true` is a hit
+    pub fn steps(&self) -> impl Iterator<Item = bool> + '_ {
+        self.steps.iter().copied()
+    }
+    /// Rotate the pattern right by some number of steps
+    ///
+    /// Negative offsets rotate left.
+    pub fn offset(mut self, steps: isize) -> Self {
+        if !self.steps.is_empty() {
+            let len = self.steps.len() as isize;
+            self.steps.rotate_right(steps.rem_euclid(len) as usize);
+        }
+        self
+    }
+    /// Combine with another pattern, keeping only steps that are hits in both
+    ///
+    /// The result is as long as the longer pattern, and the shorter one repeats.
+    pub fn and(&self, other: &Pattern) -> Self {
+        self.combine(other, |a, b| a && b)
+    }
+    /// Combine with another pattern, keeping steps that are hits in either
+    ///
+    /// The result is as long as the longer pattern, and the shorter one repeats.
+    pub fn or(&self, other: &Pattern) -> Self {
+        self.combine(other, |a, b| a || b)
+    }
+    /// Swap hits and rests
+    pub fn invert(&self) -> Self {
+        Pattern::new(self.steps().map(|hit| !hit))
+    }
+    fn combine(&self, other: &Pattern, f: impl Fn(bool, bool) -> bool) -> Self {
+        let len = self.len().max(other.len());
+        Pattern::new((0..len).map(|i| f(self.is_hit(i), other.is_hit(i))))
+    }
+}
+
+impl FromIterator<bool> for Pattern {
+    fn from_iter<T: IntoIterator<Item = bool>>(iter: T) -> Self {
+        Pattern::new(iter)
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for hit in self.steps() {
+            write!(f, "{}", if hit { 'x' } else { '.' })?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,329 @@
+use crate::{Amplitude, Frame, Source, UnrolledSource};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// A raw PCM sample format
+///
+/// Multi-byte samples are little-endian.
+/// Unsigned formats are offset so that silence is at the middle of their range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PcmFormat {
+    /// Unsigned 8-bit integers
+    U8,
+    /// Signed 8-bit integers
+    I8,
+    /// Unsigned 16-bit integers
+    U16,
+    /// Signed 16-bit integers
+    I16,
+    /// Unsigned 32-bit integers
+    U32,
+    /// Signed 32-bit integers
+    I32,
+    /// 32-bit floats
+    F32,
+    /// 64-bit floats
+    F64,
+}
+
+impl PcmFormat {
+    /// Get the number of bytes in a single sample
+    pub fn bytes_per_sample(&self) -> usize {
+        match self {
+            PcmFormat::U8 | PcmFormat::I8 => 1,
+            PcmFormat::U16 | PcmFormat::I16 => 2,
+            PcmFormat::U32 | PcmFormat::I32 | PcmFormat::F32 => 4,
+            PcmFormat::F64 => 8,
+        }
+    }
+    /// Convert an amplitude to bytes and append them to a buffer
+    fn push(&self, sample: f64, bytes: &mut Vec<u8>) {
+        // Integer conversions saturate on their own, but floats would keep the overshoot
+        let sample = sample.clamp(-1.0, 1.0);
+        match self {
+            PcmFormat::U8 => bytes.push(u8::from_f64(sample)),
+            PcmFormat::I8 => bytes.extend(i8::from_f64(sample).to_le_bytes()),
+            PcmFormat::U16 => bytes.extend(u16::from_f64(sample).to_le_bytes()),
+            PcmFormat::I16 => bytes.extend(i16::from_f64(sample).to_le_bytes()),
+            PcmFormat::U32 => bytes.extend(u32::from_f64(sample).to_le_bytes()),
+            PcmFormat::I32 => bytes.extend(i32::from_f64(sample).to_le_bytes()),
+            PcmFormat::F32 => bytes.extend(f32::from_f64(sample).to_le_bytes()),
+            PcmFormat::F64 => bytes.extend(sample.to_le_bytes()),
+        }
+    }
+    /// Convert the bytes of a single sample to an amplitude
+    ///
+    /// This is the inverse of [`PcmFormat::push`].
+    fn read(&self, bytes: &[u8]) -> f64 {
+        macro_rules! int {
+            ($ty:ty, $offset:expr) => {{
+                let sample = <$ty>::from_le_bytes(bytes.try_into().unwrap());
+                let half = <$ty>::MAX as f64 * 0.5;
+                if $offset {
+                    (sample as f64 - half) / half
+                } else {
+                    sample as f64 / <$ty>::MAX as f64
+                }
+            }};
+        }
+        match self {
+            PcmFormat::U8 => int!(u8, true),
+            PcmFormat::I8 => int!(i8, false),
+            PcmFormat::U16 => int!(u16, true),
+            PcmFormat::I16 => int!(i16, false),
+            PcmFormat::U32 => int!(u32, true),
+            PcmFormat::I32 => int!(i32, false),
+            PcmFormat::F32 => f32::from_le_bytes(bytes.try_into().unwrap()) as f64,
+            PcmFormat::F64 => f64::from_le_bytes(bytes.try_into().unwrap()),
+        }
+    }
+}
+
+/// Render a source to interleaved raw PCM bytes
+///
+/// The source is rendered at the given sample rate and mapped to the given number of channels
+/// the same way frames are written to an output device.
+/// Samples beyond `±1.0` are clipped.
+///
+/// The source must be finite. To render part of an infinite source, use [`Source::take`].
+///
+/// # Example
+/// ```
+/// use hodaun::*;
+///
+/// let bytes = render_pcm(SineWave::new(440.0).take(0.5), 48000.0, 2, PcmFormat::I16);
+/// assert_eq!(bytes.len(), 24000 * 2 * 2);
+/// ```
+pub fn render_pcm<S>(source: S, sample_rate: f64, channels: usize, format: PcmFormat) -> Vec<u8>
+where
+    S: Source,
+{
+    let mut source = source;
+    let mut bytes = Vec::new();
+    let mut frame_buffer = vec![0.0; channels];
+    while let Some(frame) = source.next(sample_rate) {
+        write_frame(frame, &mut frame_buffer, format, &mut bytes);
+    }
+    bytes
+}
+
+/// Render frames of a source to interleaved raw PCM bytes until a buffer is full
+///
+/// This is like [`render_pcm`], but it fills an existing buffer with as many whole frames as fit,
+/// so audio can be streamed in chunks to an encoder or network protocol.
+/// Returns the number of bytes written, which is less than the buffer length if the source finished.
+pub fn write_pcm<S>(
+    source: &mut S,
+    sample_rate: f64,
+    channels: usize,
+    format: PcmFormat,
+    buffer: &mut [u8],
+) -> usize
+where
+    S: Source,
+{
+    let frame_size = channels * format.bytes_per_sample();
+    if frame_size == 0 {
+        return 0;
+    }
+    let mut frame_buffer = vec![0.0; channels];
+    let mut bytes = Vec::with_capacity(frame_size);
+    let mut written = 0;
+    for chunk in buffer.chunks_exact_mut(frame_size) {
+        let Some(frame) = source.next(sample_rate) else {
+            break;
+        };
+        bytes.clear();
+        write_frame(frame, &mut frame_buffer, format, &mut bytes);
+        chunk.copy_from_slice(&bytes);
+        written += frame_size;
+    }
+    written
+}
+
+fn write_frame<F: Frame>(
+    frame: F,
+    frame_buffer: &mut [f64],
+    format: PcmFormat,
+    bytes: &mut Vec<u8>,
+) {
+    frame_buffer.fill(0.0);
+    frame.write_slice(frame_buffer);
+    for &sample in &*frame_buffer {
+        format.push(sample, bytes);
+    }
+}
+
+/// A source that reads interleaved raw PCM bytes
+///
+/// This plays audio that arrives without a file header, like from a network protocol or FFI.
+/// The bytes can be anything that can be borrowed as a byte slice, like a `Vec<u8>` or `&[u8]`.
+/// Bytes left over after the last whole sample are ignored.
+///
+/// # Example
+/// ```
+/// use hodaun::*;
+///
+/// let bytes = render_pcm(SineWave::new(440.0).take(0.5), 48000.0, 2, PcmFormat::F32);
+/// let source = PcmSource::new(bytes, PcmFormat::F32, 2, 48000);
+/// assert_eq!(source.len(), 48000);
+/// let stereo = source.resample::<Stereo>();
+/// ```
+#[derive(Debug, Clone)]
+pub struct PcmSource<B> {
+    bytes: B,
+    format: PcmFormat,
+    channels: usize,
+    sample_rate: u32,
+    pos: usize,
+}
+
+impl<B> PcmSource<B>
+where
+    B: AsRef<[u8]>,
+{
+    /// Create a new PCM source
+    pub fn new(bytes: B, format: PcmFormat, channels: usize, sample_rate: u32) -> Self {
+        PcmSource {
+            bytes,
+            format,
+            channels,
+            sample_rate,
+            pos: 0,
+        }
+    }
+    /// Get the sample format
+    pub fn format(&self) -> PcmFormat {
+        self.format
+    }
+    /// Get the underlying bytes
+    pub fn into_inner(self) -> B {
+        self.bytes
+    }
+}
+
+impl<B> Iterator for PcmSource<B>
+where
+    B: AsRef<[u8]>,
+{
+    type Item = f64;
+    fn next(&mut self) -> Option<Self::Item> {
+        let size = self.format.bytes_per_sample();
+        let bytes = self.bytes.as_ref().get(self.pos..self.pos + size)?;
+        self.pos += size;
+        Some(self.format.read(bytes))
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<B> ExactSizeIterator for PcmSource<B>
+where
+    B: AsRef<[u8]>,
+{
+    fn len(&self) -> usize {
+        (self.bytes.as_ref().len() - self.pos) / self.format.bytes_per_sample()
+    }
+}
+
+impl<B> UnrolledSource for PcmSource<B>
+where
+    B: AsRef<[u8]>,
+{
+    fn channels(&self) -> usize {
+        self.channels
+    }
+    fn sample_rate(&self) -> f64 {
+        self.sample_rate as f64
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{test_util::*, SineWave, Stereo};
+
+    const RATE: f64 = 8000.0;
+    const FORMATS: [PcmFormat; 8] = [
+        PcmFormat::U8,
+        PcmFormat::I8,
+        PcmFormat::U16,
+        PcmFormat::I16,
+        PcmFormat::U32,
+        PcmFormat::I32,
+        PcmFormat::F32,
+        PcmFormat::F64,
+    ];
+
+    fn source() -> impl Source<Frame = Stereo> + Clone {
+        (SineWave::new(440.0).take(0.1)).map(|a| Stereo::new(a * 0.9, a * -0.5))
+    }
+
+    /// The most a sample can change by being quantized to a format
+    fn tolerance(format: PcmFormat) -> f64 {
+        match format {
+            PcmFormat::F32 => 1e-7,
+            PcmFormat::F64 => 0.0,
+            _ => 2.0 / (1u64 << (8 * format.bytes_per_sample() - 1)) as f64,
+        }
+    }
+
+    #[test]
+    fn pcm_source_round_trips_render_pcm() {
+        for format in FORMATS {
+            let bytes = render_pcm(source(), RATE, 2, format);
+            let decoded = PcmSource::new(bytes, format, 2, RATE as u32);
+            assert_eq!(decoded.len(), collect_frames(source(), RATE).len() * 2);
+            assert_source_eq(
+                source(),
+                decoded.resample::<Stereo>(),
+                RATE,
+                tolerance(format),
+            );
+        }
+    }
+
+    #[test]
+    fn write_pcm_matches_render_pcm() {
+        for format in FORMATS {
+            let rendered = render_pcm(source(), RATE, 2, format);
+            let mut source = source();
+            let mut streamed = Vec::new();
+            // An odd chunk size, so that frames do not line up with chunks
+            let mut chunk = vec![0; 2 * format.bytes_per_sample() * 7 + 1];
+            loop {
+                let written = write_pcm(&mut source, RATE, 2, format, &mut chunk);
+                streamed.extend_from_slice(&chunk[..written]);
+                if written < chunk.len() - 1 {
+                    break;
+                }
+            }
+            assert_eq!(streamed, rendered, "{format:?}");
+        }
+    }
+
+    #[test]
+    fn render_pcm_clips_every_format() {
+        let loud = (SineWave::new(440.0).take(0.1)).map(|a| Stereo::new(a * 2.0, a * -3.0));
+        for format in FORMATS {
+            let bytes = render_pcm(loud, RATE, 2, format);
+            let decoded = PcmSource::new(bytes, format, 2, RATE as u32);
+            let clipped = loud.map(|f| f.map(|a| a.clamp(-1.0, 1.0)));
+            assert_source_eq(
+                clipped,
+                decoded.resample::<Stereo>(),
+                RATE,
+                tolerance(format),
+            );
+        }
+    }
+
+    #[test]
+    fn pcm_source_ignores_partial_samples() {
+        let source = PcmSource::new([0u8, 0, 0, 64, 1], PcmFormat::I16, 1, 8000);
+        assert_eq!(source.collect::<Vec<_>>(), [0.0, 16384.0 / i16::MAX as f64]);
+    }
+}
@@ -0,0 +1,807 @@
+#[cfg(any(feature = "wav", feature = "ogg", feature = "opus"))]
+use std::fmt;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    sync::{ring, Mutex, RingReceiver, RingSender},
+    FiniteSource, Frame, Source, ToDuration,
+};
+
+type TrackSource<F> = Box<dyn FiniteSource<Frame = F> + Send + 'static>;
+type Reopen<F> = Arc<dyn Fn() -> Option<TrackSource<F>> + Send + Sync + 'static>;
+type TrackChangeCallback = Box<dyn FnMut(Option<&str>) + Send + 'static>;
+
+/// The most tracks that can wait in a [`Player`]'s queue
+const QUEUE_CAPACITY: usize = 1024;
+/// The number of commands other than enqueued tracks that can wait to be handled
+const COMMAND_CAPACITY: usize = 64;
+/// The number of finished tracks and sources that can wait to be dropped
+const RETIRED_CAPACITY: usize = QUEUE_CAPACITY + COMMAND_CAPACITY;
+/// Encodes [`Transition::Gapless`]
+const GAPLESS: u64 = u64::MAX;
+
+/// How a [`Player`] transitions from one track to the next
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Transition {
+    /// Start the next track immediately after the current one ends
+    #[default]
+    Gapless,
+    /// Fade out the current track while fading in the next one over some duration
+    Crossfade(Duration),
+}
+
+impl Transition {
+    /// Create a crossfade transition
+    pub fn crossfade(fade: impl ToDuration) -> Self {
+        Transition::Crossfade(fade.to_duration())
+    }
+    fn to_bits(self) -> u64 {
+        match self {
+            Transition::Gapless => GAPLESS,
+            Transition::Crossfade(fade) => (fade.as_nanos() as u64).min(GAPLESS - 1),
+        }
+    }
+    fn from_bits(bits: u64) -> Self {
+        if bits == GAPLESS {
+            Transition::Gapless
+        } else {
+            Transition::Crossfade(Duration::from_nanos(bits))
+        }
+    }
+}
+
+/// A queue of tracks that are played one after another
+///
+/// [`Player`] is a [`Source`], so it can be added to a [`Mixer`](crate::Mixer)
+/// or an [`OutputDeviceMixer`](crate::OutputDeviceMixer).
+/// Cloned players share the same queue, so one clone can be added to a mixer
+/// while another is used to control playback.
+/// Only one clone should be played at a time.
+///
+/// Playing never waits on the threads that control the player,
+/// and finished tracks are dropped on those threads rather than the audio thread.
+///
+/// A player never ends on its own. It produces silence while paused or when its queue is empty.
+pub struct Player<F> {
+    shared: Arc<PlayerShared<F>>,
+}
+
+struct PlayerShared<F> {
+    /// Commands for the playing clone, only locked by controlling clones
+    commands: Mutex<RingSender<Command<F>>>,
+    /// The names of queued tracks and how to reopen them, only locked by controlling clones
+    tracks: Mutex<TrackList<F>>,
+    /// Tracks and sources the playing clone is done with, dropped by controlling clones
+    retired: Mutex<RingReceiver<Retired<F>>>,
+    /// Only locked by the clone that is playing
+    playback: Mutex<Playback<F>>,
+    paused: AtomicBool,
+    /// The bits of the [`Transition`]
+    transition: AtomicU64,
+    /// The number of tracks that have been enqueued and not yet started or cleared
+    queued: AtomicUsize,
+    /// The id of the current track, or 0 if there is none
+    current: AtomicU64,
+    /// The bits of the playback position in the current track in seconds
+    position: AtomicU64,
+    /// The bits of the sample rate the player is being played at, or 0 before it starts
+    sample_rate: AtomicU64,
+    /// Incremented for each seek so that stale seeks are discarded
+    seek_generation: AtomicU64,
+    /// The track and target of the latest seek, only locked by controlling clones and seek threads
+    seek_target: Mutex<(u64, f64)>,
+    on_track_change: Mutex<Option<TrackChangeCallback>>,
+}
+
+struct TrackList<F> {
+    next_id: u64,
+    tracks: VecDeque<TrackInfo<F>>,
+}
+
+struct TrackInfo<F> {
+    id: u64,
+    name: Arc<str>,
+    reopen: Option<Reopen<F>>,
+}
+
+enum Command<F> {
+    Enqueue(Track<F>),
+    Skip,
+    Clear,
+    /// A seek has finished decoding
+    Seeked(Seeked<F>),
+    /// Hand the current track's source to a seek thread to be decoded forward
+    SeekForward {
+        id: u64,
+        handoff: RingSender<Option<(TrackSource<F>, f64)>>,
+    },
+}
+
+struct Track<F> {
+    id: u64,
+    name: Arc<str>,
+    /// [`None`] while a seek is decoding the source
+    source: Option<TrackSource<F>>,
+    elapsed: f64,
+}
+
+struct Seeked<F> {
+    id: u64,
+    /// The seek generation of a reopened track, or [`None`] if the track's own source was decoded
+    generation: Option<u64>,
+    source: TrackSource<F>,
+    elapsed: f64,
+}
+
+/// Something the player is done with, only held so that it is dropped off the audio thread
+#[allow(dead_code)]
+enum Retired<F> {
+    Track(Track<F>),
+    Source(TrackSource<F>),
+    Handoff(RingSender<Option<(TrackSource<F>, f64)>>),
+}
+
+struct Playback<F> {
+    commands: RingReceiver<Command<F>>,
+    retired: RingSender<Retired<F>>,
+    queue: VecDeque<Track<F>>,
+    current: Option<Track<F>>,
+    outgoing: Option<Track<F>>,
+    fade_elapsed: f64,
+    track_changed: bool,
+}
+
+impl<F> Clone for Player<F> {
+    fn clone(&self) -> Self {
+        Player {
+            shared: Arc::clone(&self.shared),
+        }
+    }
+}
+
+impl<F> Default for Player<F> {
+    fn default() -> Self {
+        let (commands, commands_recv) = ring(QUEUE_CAPACITY + COMMAND_CAPACITY);
+        let (retired, retired_recv) = ring(RETIRED_CAPACITY);
+        Player {
+            shared: Arc::new(PlayerShared {
+                commands: Mutex::new(commands),
+                tracks: Mutex::new(TrackList {
+                    next_id: 0,
+                    tracks: VecDeque::new(),
+                }),
+                retired: Mutex::new(retired_recv),
+                playback: Mutex::new(Playback {
+                    commands: commands_recv,
+                    retired,
+                    queue: VecDeque::with_capacity(QUEUE_CAPACITY),
+                    current: None,
+                    outgoing: None,
+                    fade_elapsed: 0.0,
+                    track_changed: false,
+                }),
+                paused: AtomicBool::new(false),
+                transition: AtomicU64::new(Transition::default().to_bits()),
+                queued: AtomicUsize::new(0),
+                current: AtomicU64::new(0),
+                position: AtomicU64::new(0),
+                sample_rate: AtomicU64::new(0),
+                seek_generation: AtomicU64::new(0),
+                seek_target: Mutex::new((0, 0.0)),
+                on_track_change: Mutex::new(None),
+            }),
+        }
+    }
+}
+
+impl<F> Player<F>
+where
+    F: Frame + 'static,
+{
+    /// Create a new player with an empty queue
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Add a track to the end of the queue
+    ///
+    /// Returns `false` if the queue already holds 1024 tracks, in which case the track is dropped.
+    pub fn enqueue<S>(&self, name: impl Into<String>, source: S) -> bool
+    where
+        S: FiniteSource<Frame = F> + Send + 'static,
+    {
+        self.push_track(name.into(), Box::new(source), None)
+    }
+    /// Add a WAV file to the end of the queue
+    ///
+    /// The file is streamed from disk as it plays.
+    /// Unlike other tracks, file tracks can be seeked backwards.
+    /// Returns `Ok(false)` if the queue is full.
+    #[cfg(feature = "wav")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "wav")))]
+    pub fn enqueue_wav(
+        &self,
+        path: impl AsRef<std::path::Path>,
+    ) -> Result<bool, crate::wav::WaveError> {
+        use crate::UnrolledSource;
+        fn open<F: Frame>(path: &std::path::Path) -> Result<TrackSource<F>, crate::wav::WaveError> {
+            let file = std::io::BufReader::new(std::fs::File::open(path)?);
+            Ok(Box::new(crate::wav::WavSource::new(file)?.resample()))
+        }
+        let path = path.as_ref().to_path_buf();
+        let source = open(&path)?;
+        let name = path.display().to_string();
+        let reopen: Reopen<F> = Arc::new(move || open(&path).ok());
+        Ok(self.push_track(name, source, Some(reopen)))
+    }
+    /// Add an audio file to the end of the queue
+    ///
+    /// The format is chosen by the file's extension.
+    /// WAV (`.wav`), Ogg Vorbis (`.ogg`), and Opus (`.opus`) files are supported
+    /// when the `wav`, `ogg`, and `opus` features are enabled.
+    ///
+    /// The file is streamed from disk as it plays, and can be seeked backwards.
+    /// Ogg Vorbis and Opus files do not report their length,
+    /// so a crossfade out of one starts when it ends rather than before.
+    /// Returns `Ok(false)` if the queue is full.
+    #[cfg(any(feature = "wav", feature = "ogg", feature = "opus"))]
+    #[cfg_attr(
+        docsrs,
+        doc(cfg(any(feature = "wav", feature = "ogg", feature = "opus")))
+    )]
+    pub fn enqueue_file(&self, path: impl AsRef<std::path::Path>) -> Result<bool, OpenFileError> {
+        let path = path.as_ref().to_path_buf();
+        let source = open_file(&path)?;
+        let name = path.display().to_string();
+        let reopen: Reopen<F> = Arc::new(move || open_file(&path).ok());
+        Ok(self.push_track(name, source, Some(reopen)))
+    }
+    fn push_track(&self, name: String, source: TrackSource<F>, reopen: Option<Reopen<F>>) -> bool {
+        let shared = &*self.shared;
+        let mut tracks = shared.tracks.lock();
+        let mut commands = shared.commands.lock();
+        if shared.queued.load(Ordering::Relaxed) >= QUEUE_CAPACITY {
+            return false;
+        }
+        let name: Arc<str> = name.into();
+        let id = tracks.next_id + 1;
+        let track = Track {
+            id,
+            name: name.clone(),
+            source: Some(source),
+            elapsed: 0.0,
+        };
+        if commands.push(Command::Enqueue(track)).is_err() {
+            return false;
+        }
+        shared.queued.fetch_add(1, Ordering::Relaxed);
+        tracks.next_id = id;
+        tracks.tracks.push_back(TrackInfo { id, name, reopen });
+        drop(commands);
+        shared.prune(&mut tracks);
+        true
+    }
+    /// Set how the player transitions between tracks
+    ///
+    /// The default is [`Transition::Gapless`]
+    pub fn set_transition(&self, transition: Transition) {
+        (self.shared.transition).store(transition.to_bits(), Ordering::Relaxed);
+    }
+    /// Pause playback
+    pub fn pause(&self) {
+        self.shared.paused.store(true, Ordering::Relaxed);
+    }
+    /// Resume playback
+    pub fn resume(&self) {
+        self.shared.paused.store(false, Ordering::Relaxed);
+    }
+    /// Check if playback is paused
+    pub fn is_paused(&self) -> bool {
+        self.shared.paused.load(Ordering::Relaxed)
+    }
+    /// Skip to the next track in the queue
+    ///
+    /// If the transition is [`Transition::Crossfade`], the current track is faded out.
+    pub fn skip(&self) {
+        self.shared.command(Command::Skip);
+    }
+    /// Seek to a time in the current track
+    ///
+    /// The seek happens on a background thread, so it never blocks the audio thread.
+    /// File tracks are reopened and keep playing until the new position is ready.
+    /// Seeking forward in other tracks decodes and discards the skipped audio,
+    /// and the track is silent until it is done.
+    /// Seeking backward is only possible for tracks added from files, and is ignored otherwise.
+    pub fn seek(&self, time: impl ToDuration) {
+        let target = time.to_duration().as_secs_f64();
+        let shared = &self.shared;
+        let id = shared.current.load(Ordering::Relaxed);
+        if id == 0 {
+            return;
+        }
+        let reopen = {
+            let tracks = shared.tracks.lock();
+            let info = tracks.tracks.iter().find(|info| info.id == id);
+            info.and_then(|info| info.reopen.clone())
+        };
+        if reopen.is_none() && target < self.position().as_secs_f64() {
+            return;
+        }
+        let generation = shared.seek_generation.fetch_add(1, Ordering::Relaxed) + 1;
+        *shared.seek_target.lock() = (id, target);
+        let thread_shared = shared.clone();
+        let builder = thread::Builder::new().name("hodaun player seek".into());
+        if let Some(reopen) = reopen {
+            let _ = builder.spawn(move || {
+                PlayerShared::seek_reopen(thread_shared, id, generation, reopen, target)
+            });
+        } else {
+            let (handoff, receiver) = ring(1);
+            let spawned = builder
+                .spawn(move || PlayerShared::seek_forward(thread_shared, id, receiver, target));
+            // Without a thread to decode it, the source must not be handed off
+            if spawned.is_ok() {
+                shared.command(Command::SeekForward { id, handoff });
+            }
+        }
+    }
+    /// Remove all tracks from the queue, stopping the current one
+    pub fn clear(&self) {
+        let mut tracks = self.shared.tracks.lock();
+        self.shared.command(Command::Clear);
+        tracks.tracks.clear();
+    }
+    /// Get the number of tracks waiting in the queue
+    ///
+    /// This does not include the current track.
+    pub fn queue_len(&self) -> usize {
+        self.shared.queued.load(Ordering::Relaxed)
+    }
+    /// Get the name of the current track
+    pub fn now_playing(&self) -> Option<String> {
+        let id = self.shared.current.load(Ordering::Relaxed);
+        let mut tracks = self.shared.tracks.lock();
+        self.shared.prune(&mut tracks);
+        let info = tracks.tracks.iter().find(|info| info.id == id)?;
+        Some(info.name.to_string())
+    }
+    /// Get the playback position in the current track
+    pub fn position(&self) -> Duration {
+        let position = f64::from_bits(self.shared.position.load(Ordering::Relaxed));
+        Duration::from_secs_f64(position)
+    }
+    /// Set a function to be called when the current track changes
+    ///
+    /// It is passed the name of the new track, or [`None`] if the queue has run out.
+    /// The function is called on the audio thread, so it should return quickly.
+    pub fn on_track_change(&self, f: impl FnMut(Option<&str>) + Send + 'static) {
+        *self.shared.on_track_change.lock() = Some(Box::new(f));
+    }
+}
+
+impl<F> PlayerShared<F>
+where
+    F: Frame,
+{
+    /// Send a command to the playing clone, returning whether there was room for it
+    fn command(&self, command: Command<F>) -> bool {
+        // Drop what the player is done with
+        let mut retired = self.retired.lock();
+        while retired.pop().is_some() {}
+        drop(retired);
+        self.commands.lock().push(command).is_ok()
+    }
+    /// Forget the tracks before the current one
+    fn prune(&self, tracks: &mut TrackList<F>) {
+        let current = self.current.load(Ordering::Relaxed);
+        if current == 0 {
+            return;
+        }
+        while tracks.tracks.front().is_some_and(|info| info.id < current) {
+            tracks.tracks.pop_front();
+        }
+    }
+    fn track_changed(&self, name: Option<&str>) {
+        if let Some(f) = &mut *self.on_track_change.lock() {
+            f(name);
+        }
+    }
+    /// Wait until the player has started playing, returning its sample rate
+    ///
+    /// Returns [`None`] if every player was dropped before it started.
+    fn wait_for_sample_rate(shared: &Arc<Self>) -> Option<f64> {
+        loop {
+            let sample_rate = f64::from_bits(shared.sample_rate.load(Ordering::Relaxed));
+            if sample_rate > 0.0 {
+                return Some(sample_rate);
+            }
+            if Arc::strong_count(shared) == 1 {
+                return None;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+    /// Hand a finished seek back to the player
+    fn send_seeked(shared: &Arc<Self>, mut seeked: Seeked<F>) {
+        // The command queue only fills up while the player is not playing
+        while let Err(command) = shared.commands.lock().push(Command::Seeked(seeked)) {
+            let Command::Seeked(returned) = command else {
+                unreachable!()
+            };
+            if Arc::strong_count(shared) == 1 {
+                return;
+            }
+            seeked = returned;
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+    /// Reopen a file track and decode it up to a seek target
+    fn seek_reopen(shared: Arc<Self>, id: u64, generation: u64, reopen: Reopen<F>, target: f64) {
+        let Some(sample_rate) = Self::wait_for_sample_rate(&shared) else {
+            return;
+        };
+        let Some(mut source) = reopen() else {
+            return;
+        };
+        let elapsed = decode(&mut source, 0.0, target, sample_rate);
+        if shared.seek_generation.load(Ordering::Relaxed) != generation {
+            return;
+        }
+        let seeked = Seeked {
+            id,
+            generation: Some(generation),
+            source,
+            elapsed,
+        };
+        Self::send_seeked(&shared, seeked);
+    }
+    /// Take a track's source from the player and decode it forward to a seek target
+    fn seek_forward(
+        shared: Arc<Self>,
+        id: u64,
+        mut receiver: RingReceiver<Option<(TrackSource<F>, f64)>>,
+        mut target: f64,
+    ) {
+        let (mut source, mut elapsed) = loop {
+            match receiver.pop() {
+                Some(Some(handed)) => break handed,
+                // The track was not playing, or another seek is already decoding it
+                Some(None) => return,
+                None if receiver.is_closed() => match receiver.pop() {
+                    Some(Some(handed)) => break handed,
+                    _ => return,
+                },
+                None => thread::sleep(Duration::from_millis(1)),
+            }
+        };
+        let sample_rate = f64::from_bits(shared.sample_rate.load(Ordering::Relaxed));
+        loop {
+            elapsed = decode(&mut source, elapsed, target, sample_rate);
+            // Later seeks of the same track continue from here
+            match *shared.seek_target.lock() {
+                (seek_id, retarget) if seek_id == id && retarget > elapsed => target = retarget,
+                _ => break,
+            }
+        }
+        let seeked = Seeked {
+            id,
+            generation: None,
+            source,
+            elapsed,
+        };
+        Self::send_seeked(&shared, seeked);
+    }
+}
+
+/// Decode and discard a source's frames from `elapsed` up to `target`, returning the new elapsed time
+fn decode<F: Frame>(source: &mut TrackSource<F>, mut elapsed: f64, target: f64, rate: f64) -> f64 {
+    while elapsed + 0.5 / rate < target {
+        if source.next(rate).is_none() {
+            break;
+        }
+        elapsed += 1.0 / rate;
+    }
+    elapsed
+}
+
+impl<F> Playback<F>
+where
+    F: Frame,
+{
+    fn retire(&mut self, retired: Retired<F>) {
+        // If too many things are waiting to be dropped, this one is dropped here
+        let _ = self.retired.push(retired);
+    }
+    fn retire_track(&mut self, track: Option<Track<F>>) {
+        if let Some(track) = track {
+            self.retire(Retired::Track(track));
+        }
+    }
+    fn handle(&mut self, command: Command<F>, shared: &PlayerShared<F>, transition: Transition) {
+        match command {
+            Command::Enqueue(track) => self.queue.push_back(track),
+            Command::Skip => {
+                if let Transition::Crossfade(_) = transition {
+                    let outgoing = self.outgoing.take();
+                    self.retire_track(outgoing);
+                    self.outgoing = self.current.take();
+                    self.fade_elapsed = 0.0;
+                } else {
+                    let current = self.current.take();
+                    self.retire_track(current);
+                }
+                self.advance(shared);
+            }
+            Command::Clear => {
+                shared.queued.fetch_sub(self.queue.len(), Ordering::Relaxed);
+                while let Some(track) = self.queue.pop_front() {
+                    self.retire(Retired::Track(track));
+                }
+                let current = self.current.take();
+                self.track_changed |= current.is_some();
+                self.retire_track(current);
+                let outgoing = self.outgoing.take();
+                self.retire_track(outgoing);
+            }
+            Command::Seeked(seeked) => {
+                let latest = shared.seek_generation.load(Ordering::Relaxed);
+                let current = (self.current.as_mut())
+                    .filter(|track| track.id == seeked.id)
+                    .filter(|_| seeked.generation.is_none_or(|gen| gen == latest));
+                match current {
+                    Some(track) => {
+                        let old = track.source.replace(seeked.source);
+                        track.elapsed = seeked.elapsed;
+                        if let Some(old) = old {
+                            self.retire(Retired::Source(old));
+                        }
+                    }
+                    None => self.retire(Retired::Source(seeked.source)),
+                }
+            }
+            Command::SeekForward { id, mut handoff } => {
+                let track = (self.current.as_mut()).filter(|track| track.id == id);
+                let handed = track.and_then(|track| Some((track.source.take()?, track.elapsed)));
+                // The receiver is waiting, so there is always room
+                let _ = handoff.push(handed);
+                self.retire(Retired::Handoff(handoff));
+            }
+        }
+    }
+    /// Start the next track in the queue
+    fn advance(&mut self, shared: &PlayerShared<F>) {
+        self.current = self.queue.pop_front();
+        if self.current.is_some() {
+            shared.queued.fetch_sub(1, Ordering::Relaxed);
+        }
+        self.track_changed = true;
+    }
+    /// Get the name of the current track if it has changed since the last call
+    fn take_track_change(&mut self) -> Option<Option<Arc<str>>> {
+        let changed = std::mem::take(&mut self.track_changed);
+        changed.then(|| self.current.as_ref().map(|track| track.name.clone()))
+    }
+    fn next_frame(
+        &mut self,
+        sample_rate: f64,
+        transition: Transition,
+        shared: &PlayerShared<F>,
+    ) -> F {
+        let mut frame = F::uniform(0.0);
+        // Start a crossfade if the current track is about to end
+        if let (Transition::Crossfade(fade), None, Some(current)) =
+            (transition, &self.outgoing, &self.current)
+        {
+            let remaining =
+                (current.source.as_ref()).map(|source| source.remaining(sample_rate).as_secs_f64());
+            if remaining.is_some_and(|remaining| remaining <= fade.as_secs_f64())
+                && !self.queue.is_empty()
+            {
+                self.outgoing = self.current.take();
+                self.fade_elapsed = 0.0;
+                self.advance(shared);
+            }
+        }
+        let fade_t = match transition {
+            Transition::Crossfade(fade) if self.outgoing.is_some() && !fade.is_zero() => {
+                (self.fade_elapsed / fade.as_secs_f64()).min(1.0)
+            }
+            _ => 1.0,
+        };
+        // Play the current track, moving on to the next one if it ends
+        if self.current.is_none() && !self.queue.is_empty() {
+            self.advance(shared);
+        }
+        while let Some(track) = &mut self.current {
+            // The track is silent while it is being seeked
+            let Some(source) = &mut track.source else {
+                break;
+            };
+            if let Some(this_frame) = source.next(sample_rate) {
+                track.elapsed += 1.0 / sample_rate;
+                frame = this_frame.map(|a| a * fade_t);
+                break;
+            }
+            let finished = self.current.take();
+            self.retire_track(finished);
+            self.advance(shared);
+            if self.current.is_none() {
+                break;
+            }
+        }
+        // Fade out the previous track
+        if let Some(track) = &mut self.outgoing {
+            let this_frame = track
+                .source
+                .as_mut()
+                .and_then(|source| source.next(sample_rate));
+            match this_frame {
+                Some(this_frame) if fade_t < 1.0 => {
+                    frame.merge(this_frame, |a, b| b.mul_add(1.0 - fade_t, a));
+                    self.fade_elapsed += 1.0 / sample_rate;
+                }
+                _ => {
+                    let outgoing = self.outgoing.take();
+                    self.retire_track(outgoing);
+                }
+            }
+        }
+        frame
+    }
+}
+
+impl<F> Source for Player<F>
+where
+    F: Frame,
+{
+    type Frame = F;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let shared = &*self.shared;
+        // Another clone is playing
+        let Some(mut playback) = shared.playback.try_lock() else {
+            return Some(F::uniform(0.0));
+        };
+        let playback = &mut *playback;
+        (shared.sample_rate).store(sample_rate.to_bits(), Ordering::Relaxed);
+        let transition = Transition::from_bits(shared.transition.load(Ordering::Relaxed));
+        while let Some(command) = playback.commands.pop() {
+            playback.handle(command, shared, transition);
+        }
+        let frame = if shared.paused.load(Ordering::Relaxed) {
+            F::uniform(0.0)
+        } else {
+            playback.next_frame(sample_rate, transition, shared)
+        };
+        let (id, elapsed) =
+            (playback.current.as_ref()).map_or((0, 0.0), |track| (track.id, track.elapsed));
+        shared.current.store(id, Ordering::Relaxed);
+        shared.position.store(elapsed.to_bits(), Ordering::Relaxed);
+        if let Some(name) = playback.take_track_change() {
+            shared.track_changed(name.as_deref());
+        }
+        Some(frame)
+    }
+}
+
+/// A track whose length is not known until it ends
+#[cfg(any(feature = "ogg", feature = "opus"))]
+struct UnknownLength<S>(S);
+
+#[cfg(any(feature = "ogg", feature = "opus"))]
+impl<S: Source> Source for UnknownLength<S> {
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        self.0.next(sample_rate)
+    }
+}
+
+#[cfg(any(feature = "ogg", feature = "opus"))]
+impl<S: Source> FiniteSource for UnknownLength<S> {
+    fn total(&self, _sample_rate: f64) -> Duration {
+        Duration::MAX
+    }
+    fn remaining(&self, _sample_rate: f64) -> Duration {
+        Duration::MAX
+    }
+}
+
+/// Open an audio file as a track, choosing the format by its extension
+#[cfg(any(feature = "wav", feature = "ogg", feature = "opus"))]
+fn open_file<F: Frame>(path: &std::path::Path) -> Result<TrackSource<F>, OpenFileError> {
+    #[allow(unused_imports)]
+    use crate::UnrolledSource;
+    let extension = path.extension().and_then(|ext| ext.to_str());
+    let extension = extension.map(str::to_ascii_lowercase);
+    let open = || -> std::io::Result<_> { Ok(std::io::BufReader::new(std::fs::File::open(path)?)) };
+    Ok(match extension.as_deref() {
+        #[cfg(feature = "wav")]
+        Some("wav" | "wave") => Box::new(crate::wav::WavSource::new(open()?)?.resample()),
+        #[cfg(feature = "ogg")]
+        Some("ogg" | "oga") => Box::new(UnknownLength(
+            crate::ogg::OggSource::new(open()?)?.resample(),
+        )),
+        #[cfg(feature = "opus")]
+        Some("opus") => Box::new(UnknownLength(
+            crate::opus::OpusSource::new(open()?)?.resample(),
+        )),
+        _ => return Err(OpenFileError::UnsupportedFormat),
+    })
+}
+
+/// An error opening a file with [`Player::enqueue_file`]
+#[cfg(any(feature = "wav", feature = "ogg", feature = "opus"))]
+#[cfg_attr(
+    docsrs,
+    doc(cfg(any(feature = "wav", feature = "ogg", feature = "opus")))
+)]
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum OpenFileError {
+    /// The file could not be read
+    Io(std::io::Error),
+    /// The file's extension is not a format that can be decoded with the enabled features
+    UnsupportedFormat,
+    /// The WAV file could not be decoded
+    #[cfg(feature = "wav")]
+    Wav(crate::wav::WaveError),
+    /// The Ogg Vorbis file could not be decoded
+    #[cfg(feature = "ogg")]
+    Ogg(crate::ogg::OggError),
+    /// The Opus file could not be decoded
+    #[cfg(feature = "opus")]
+    Opus(crate::opus::OpusError),
+}
+
+#[cfg(any(feature = "wav", feature = "ogg", feature = "opus"))]
+impl fmt::Display for OpenFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OpenFileError::Io(e) => write!(f, "{e}"),
+            OpenFileError::UnsupportedFormat => write!(f, "unsupported audio file format"),
+            #[cfg(feature = "wav")]
+            OpenFileError::Wav(e) => write!(f, "{e}"),
+            #[cfg(feature = "ogg")]
+            OpenFileError::Ogg(e) => write!(f, "{e}"),
+            #[cfg(feature = "opus")]
+            OpenFileError::Opus(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+#[cfg(any(feature = "wav", feature = "ogg", feature = "opus"))]
+impl std::error::Error for OpenFileError {}
+
+#[cfg(any(feature = "wav", feature = "ogg", feature = "opus"))]
+impl From<std::io::Error> for OpenFileError {
+    fn from(e: std::io::Error) -> Self {
+        OpenFileError::Io(e)
+    }
+}
+
+#[cfg(feature = "wav")]
+impl From<crate::wav::WaveError> for OpenFileError {
+    fn from(e: crate::wav::WaveError) -> Self {
+        OpenFileError::Wav(e)
+    }
+}
+
+#[cfg(feature = "ogg")]
+impl From<crate::ogg::OggError> for OpenFileError {
+    fn from(e: crate::ogg::OggError) -> Self {
+        OpenFileError::Ogg(e)
+    }
+}
+
+#[cfg(feature = "opus")]
+impl From<crate::opus::OpusError> for OpenFileError {
+    fn from(e: crate::opus::OpusError) -> Self {
+        OpenFileError::Opus(e)
+    }
+}
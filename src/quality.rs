@@ -0,0 +1,121 @@
+use crate::{source::CpuMeter, Shared};
+
+type QualityParam = Box<dyn FnMut(f64) + Send>;
+type QualityPolicy = Box<dyn FnMut(f64, f64) -> f64 + Send>;
+
+/// Automatically reduces the quality of expensive settings when processing is overloaded
+///
+/// The quality level is a value in the range [0, 1]. It is lowered when the
+/// measured [`CpuMeter::load`] stays high and raised again when it stays low.
+/// Parameters registered with [`AdaptiveQuality::register`] are updated
+/// whenever the quality level changes.
+///
+/// [`AdaptiveQuality::update`] should be called periodically, for example every 100 milliseconds.
+pub struct AdaptiveQuality {
+    meter: CpuMeter,
+    quality: Shared<f64>,
+    params: Vec<QualityParam>,
+    policy: QualityPolicy,
+}
+
+/// The default [`AdaptiveQuality`] policy
+///
+/// It lowers the quality when the load is above `0.8` and raises it when the load is below `0.5`.
+pub fn default_quality_policy() -> impl FnMut(f64, f64) -> f64 + Send {
+    const HIGH_LOAD: f64 = 0.8;
+    const LOW_LOAD: f64 = 0.5;
+    const STEP: f64 = 0.1;
+    /// The number of consecutive updates the load must be high or low before the quality changes
+    const SUSTAIN: u32 = 3;
+    let mut high = 0;
+    let mut low = 0;
+    move |load, quality| {
+        if load > HIGH_LOAD {
+            high += 1;
+            low = 0;
+        } else if load < LOW_LOAD {
+            low += 1;
+            high = 0;
+        } else {
+            high = 0;
+            low = 0;
+        }
+        if high >= SUSTAIN {
+            high = 0;
+            quality - STEP
+        } else if low >= SUSTAIN {
+            low = 0;
+            quality + STEP
+        } else {
+            quality
+        }
+    }
+}
+
+impl AdaptiveQuality {
+    /// Create a new adaptive quality controller that watches the given meter
+    ///
+    /// Usually the meter will be for the entire mix, created by calling
+    /// [`Source::meter`](crate::Source::meter) on a [`Mixer`](crate::Mixer).
+    pub fn new(meter: CpuMeter) -> Self {
+        AdaptiveQuality {
+            meter,
+            quality: Shared::new(1.0),
+            params: Vec::new(),
+            policy: Box::new(default_quality_policy()),
+        }
+    }
+    /// Set the policy that determines the quality level
+    ///
+    /// The policy is called on every update with the load since the previous
+    /// update and the current quality level, and returns the new quality level.
+    pub fn policy(self, policy: impl FnMut(f64, f64) -> f64 + Send + 'static) -> Self {
+        AdaptiveQuality {
+            policy: Box::new(policy),
+            ..self
+        }
+    }
+    /// Register a parameter to be set from the quality level
+    ///
+    /// `map` converts a quality level in the range [0, 1] to the parameter's value.
+    pub fn register<T>(
+        &mut self,
+        param: &Shared<T>,
+        map: impl Fn(f64) -> T + Send + 'static,
+    ) -> &mut Self
+    where
+        T: Send + 'static,
+    {
+        let param = param.clone();
+        param.set(map(self.quality.get()));
+        self.params
+            .push(Box::new(move |quality| param.set(map(quality))));
+        self
+    }
+    /// Get the current quality level
+    pub fn quality(&self) -> f64 {
+        self.quality.get()
+    }
+    /// Get a shared handle to the quality level
+    ///
+    /// The handle can also be used directly as an [`Automation`](crate::Automation).
+    pub fn quality_handle(&self) -> Shared<f64> {
+        self.quality.clone()
+    }
+    /// Measure the load since the last update and adjust the quality level
+    ///
+    /// Returns the new quality level.
+    pub fn update(&mut self) -> f64 {
+        let load = self.meter.load();
+        self.meter.reset();
+        let old = self.quality.get();
+        let new = (self.policy)(load, old).clamp(0.0, 1.0);
+        if new != old {
+            self.quality.set(new);
+            for param in &mut self.params {
+                param(new);
+            }
+        }
+        new
+    }
+}
@@ -0,0 +1,212 @@
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::{
+    sync::{ring, Mutex, RingReceiver, RingSender},
+    Frame, Source, ToDuration,
+};
+
+/// The number of frames a [`QueueSource`] can hold by default
+const DEFAULT_CAPACITY: usize = 1 << 16;
+
+/// What a [`QueueSource`] does when it runs out of frames
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Underrun {
+    /// Play silence until more frames arrive
+    #[default]
+    Silence,
+    /// Repeat the last frame until more frames arrive
+    Hold,
+    /// End the source
+    End,
+}
+
+/// Pushes frames to a [`QueueSource`]
+///
+/// Created with [`QueueSource::new`].
+/// Cloned producers push to the same queue.
+/// Once all producers are dropped, the source ends after playing the remaining frames.
+///
+/// The queue has a fixed capacity. Frames pushed while it is full are dropped.
+#[derive(Clone)]
+pub struct QueueProducer<F> {
+    /// Producers only lock each other out. The source never locks.
+    frames: Arc<Mutex<RingSender<F>>>,
+    /// Frames pushed before this count have been cleared
+    cleared: Arc<AtomicUsize>,
+}
+
+impl<F> QueueProducer<F>
+where
+    F: Frame,
+{
+    /// Push a single frame
+    ///
+    /// Returns `false` if the queue is full and the frame was dropped.
+    pub fn push(&self, frame: F) -> bool {
+        self.frames.lock().push(frame).is_ok()
+    }
+    /// Push many frames
+    ///
+    /// Frames that do not fit in the queue are dropped.
+    pub fn extend(&self, frames: impl IntoIterator<Item = F>) {
+        let mut sender = self.frames.lock();
+        for frame in frames {
+            if sender.push(frame).is_err() {
+                break;
+            }
+        }
+    }
+    /// Push interleaved samples, such as decoded PCM
+    ///
+    /// Samples that do not fill a whole frame are ignored.
+    pub fn push_interleaved(&self, samples: &[f64]) {
+        let frames = samples.chunks_exact(F::CHANNELS).map(|chunk| {
+            let mut frame = F::uniform(0.0);
+            for (i, &sample) in chunk.iter().enumerate() {
+                frame.set_channel(i, sample);
+            }
+            frame
+        });
+        self.extend(frames);
+    }
+    /// Get the number of frames that have not been played yet
+    pub fn len(&self) -> usize {
+        let sender = self.frames.lock();
+        let uncleared = (sender.pushed()).wrapping_sub(self.cleared.load(Ordering::Relaxed));
+        sender.len().min(uncleared)
+    }
+    /// Check if all pushed frames have been played
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    /// Remove all frames that have not been played yet
+    ///
+    /// The frames are skipped by the source the next time it plays.
+    pub fn clear(&self) {
+        let sender = self.frames.lock();
+        self.cleared.store(sender.pushed(), Ordering::Release);
+    }
+}
+
+/// A [`Source`] that plays frames pushed from another thread
+///
+/// This is useful for playing audio produced outside of hodaun,
+/// such as voice chat decoded from the network.
+/// Frames are played one per output frame, so they should be produced at the output sample rate.
+///
+/// Playing never locks or allocates, so the producer can never stall the audio thread.
+pub struct QueueSource<F> {
+    frames: RingReceiver<F>,
+    cleared: Arc<AtomicUsize>,
+    jitter_buffer: f64,
+    max_latency: Option<f64>,
+    underrun: Underrun,
+    buffering: bool,
+    last: Option<F>,
+}
+
+impl<F> QueueSource<F> {
+    /// Create a new queue source and its producer
+    ///
+    /// The queue can hold 65536 frames.
+    pub fn new() -> (QueueProducer<F>, Self) {
+        QueueSource::with_capacity(DEFAULT_CAPACITY)
+    }
+    /// Create a new queue source and its producer with room for the given number of frames
+    ///
+    /// The queue is allocated up front.
+    pub fn with_capacity(capacity: usize) -> (QueueProducer<F>, Self) {
+        let (sender, frames) = ring(capacity);
+        let cleared = Arc::new(AtomicUsize::new(0));
+        (
+            QueueProducer {
+                frames: Arc::new(Mutex::new(sender)),
+                cleared: cleared.clone(),
+            },
+            QueueSource {
+                frames,
+                cleared,
+                jitter_buffer: 0.0,
+                max_latency: None,
+                underrun: Underrun::Silence,
+                buffering: true,
+                last: None,
+            },
+        )
+    }
+    /// Set how much audio must be queued before playback starts or resumes after an underrun
+    ///
+    /// A larger buffer smooths out irregular delivery at the cost of latency.
+    /// The default is no buffering.
+    pub fn jitter_buffer(self, duration: impl ToDuration) -> Self {
+        QueueSource {
+            jitter_buffer: duration.to_duration().as_secs_f64(),
+            ..self
+        }
+    }
+    /// Set the maximum amount of audio that can be queued
+    ///
+    /// If more is queued, the oldest frames are dropped to catch up.
+    /// This keeps latency from growing when the producer is slightly faster than playback.
+    pub fn max_latency(self, duration: impl ToDuration) -> Self {
+        QueueSource {
+            max_latency: Some(duration.to_duration().as_secs_f64()),
+            ..self
+        }
+    }
+    /// Set what the source does when it runs out of frames
+    pub fn on_underrun(self, underrun: Underrun) -> Self {
+        QueueSource { underrun, ..self }
+    }
+}
+
+impl<F> Source for QueueSource<F>
+where
+    F: Frame,
+{
+    type Frame = F;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let closed = self.frames.is_closed();
+        let frames = &mut self.frames;
+        // Skip cleared frames
+        let cleared = self.cleared.load(Ordering::Acquire);
+        while (cleared.wrapping_sub(frames.popped()) as isize) > 0 && frames.pop().is_some() {}
+        if let Some(max_latency) = self.max_latency {
+            let max = ((max_latency * sample_rate) as usize).max(1);
+            for _ in max..frames.len() {
+                frames.pop();
+            }
+        }
+        if self.buffering {
+            if !closed && (frames.len() as f64) < self.jitter_buffer * sample_rate {
+                return Some(self.filler());
+            }
+            self.buffering = false;
+        }
+        if let Some(frame) = frames.pop() {
+            self.last = Some(frame.clone());
+            return Some(frame);
+        }
+        if closed {
+            return None;
+        }
+        self.buffering = true;
+        match self.underrun {
+            Underrun::End => None,
+            _ => Some(self.filler()),
+        }
+    }
+}
+
+impl<F> QueueSource<F>
+where
+    F: Frame,
+{
+    fn filler(&self) -> F {
+        match (&self.underrun, &self.last) {
+            (Underrun::Hold, Some(last)) => last.clone(),
+            _ => F::uniform(0.0),
+        }
+    }
+}
@@ -0,0 +1,479 @@
+use alloc::sync::{Arc, Weak};
+use core::{
+    ops::Range,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Duration,
+};
+
+#[cfg(feature = "notes")]
+use crate::Pitch;
+use crate::{lerp, Frame, Maintainer, Source, SourceState, StateReader, StatefulSource};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// A buffer of audio frames that can be played back at different rates
+///
+/// Cloning a sample is cheap, and clones share the same buffer.
+#[derive(Debug, Clone)]
+pub struct Sample<F> {
+    frames: Arc<[F]>,
+    sample_rate: f64,
+    sustain: Option<SustainLoop>,
+}
+
+/// A range of frames that is repeated while a note is held
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct SustainLoop {
+    range: Range<usize>,
+    crossfade: usize,
+}
+
+impl<F: Frame> Sample<F> {
+    /// Render a source into a sample
+    ///
+    /// The source must be finite.
+    pub fn new(mut source: impl Source<Frame = F>, sample_rate: f64) -> Self {
+        let mut frames = Vec::new();
+        while let Some(frame) = source.next(sample_rate) {
+            frames.push(frame);
+        }
+        Sample::from_frames(frames, sample_rate)
+    }
+    /// Create a sample from frames recorded at the given sample rate
+    pub fn from_frames(frames: impl Into<Arc<[F]>>, sample_rate: f64) -> Self {
+        Sample {
+            frames: frames.into(),
+            sample_rate,
+            sustain: None,
+        }
+    }
+    /// Load a WAV file into a sample
+    ///
+    /// The sample keeps the file's sample rate and is resampled during playback.
+    #[cfg(feature = "wav")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "wav")))]
+    pub fn load_wav(path: impl AsRef<std::path::Path>) -> Result<Self, crate::wav::WaveError> {
+        use crate::UnrolledSource;
+        let file = std::io::BufReader::new(std::fs::File::open(path)?);
+        let source = crate::wav::WavSource::new(file)?;
+        let sample_rate = source.sample_rate();
+        Ok(Sample::new(source.resample(), sample_rate))
+    }
+    /// Get the frames of the sample
+    pub fn frames(&self) -> &[F] {
+        &self.frames
+    }
+    /// Get the sample rate the sample was recorded at
+    pub fn sample_rate(&self) -> f64 {
+        self.sample_rate
+    }
+    /// Get the duration of the sample when played at its original rate
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs_f64(self.frames.len() as f64 / self.sample_rate)
+    }
+    /// Set a range of frames to repeat while the sample is held
+    ///
+    /// The end of each repetition is crossfaded over `crossfade` frames
+    /// with the audio leading up to the start of the loop, so there is no click at the seam.
+    /// The crossfade is shortened if there are not enough frames before the loop.
+    ///
+    /// See [`SampleVoice::held_by`] for how the loop is released.
+    pub fn with_sustain_loop(self, range: Range<usize>, crossfade: usize) -> Self {
+        let end = range.end.min(self.frames.len());
+        let start = range.start.min(end);
+        let crossfade = crossfade.min(start).min(end - start);
+        let sustain = (start < end).then_some(SustainLoop {
+            range: start..end,
+            crossfade,
+        });
+        Sample { sustain, ..self }
+    }
+    /// Get the range of frames repeated while the sample is held, if there is one
+    pub fn sustain_loop(&self) -> Option<Range<usize>> {
+        self.sustain.as_ref().map(|sustain| sustain.range.clone())
+    }
+    /// Create a source that plays the sample
+    ///
+    /// If the sample has a sustain loop, the loop repeats forever unless the voice is [`SampleVoice::held_by`] a [`Maintainer`].
+    pub fn play(&self) -> SampleVoice<F> {
+        SampleVoice {
+            sample: self.clone(),
+            pos: 0.0,
+            rate: 1.0,
+            gain: 1.0,
+            pan: 0.0,
+            held: None,
+        }
+    }
+    /// Get the frame at a fractional position
+    fn frame_at(&self, pos: f64) -> Option<F> {
+        let i = pos as usize;
+        let mut frame = self.frames.get(i)?.clone();
+        let t = pos.fract();
+        if let Some(next) = self.frames.get(i + 1).filter(|_| t > 0.0) {
+            frame.merge(next.clone(), |a, b| lerp(a, b, t));
+        }
+        Some(frame)
+    }
+}
+
+/// Source returned from [`Sample::play`]
+#[derive(Debug, Clone)]
+pub struct SampleVoice<F> {
+    sample: Sample<F>,
+    pos: f64,
+    rate: f64,
+    gain: f64,
+    pan: f64,
+    held: Option<Weak<()>>,
+}
+
+impl<F> SampleVoice<F> {
+    /// The slowest playback rate
+    pub const MIN_RATE: f64 = 1.0 / 1024.0;
+    /// Set the playback rate
+    ///
+    /// A rate of `2.0` plays the sample an octave higher and twice as fast.
+    /// Rates below [`SampleVoice::MIN_RATE`], including negative and NaN rates, are clamped to it
+    /// so that the voice always ends.
+    pub fn rate(self, rate: f64) -> Self {
+        SampleVoice {
+            rate: rate.max(Self::MIN_RATE),
+            ..self
+        }
+    }
+    /// Set the gain
+    pub fn gain(self, gain: f64) -> Self {
+        SampleVoice { gain, ..self }
+    }
+    /// Set the pan, from -1 (left) to 1 (right)
+    ///
+    /// This only has an effect on 2-channel frames.
+    pub fn pan(self, pan: f64) -> Self {
+        SampleVoice { pan, ..self }
+    }
+    /// Only repeat the sample's sustain loop while the given [`Maintainer`] is not dropped
+    ///
+    /// Once the maintainer is dropped, the rest of the sample after the loop plays as its release.
+    pub fn held_by<R>(self, maintainer: &Maintainer<R>) -> Self {
+        SampleVoice {
+            held: Some(Arc::downgrade(&maintainer.arc)),
+            ..self
+        }
+    }
+}
+
+impl<F> Source for SampleVoice<F>
+where
+    F: Frame,
+{
+    type Frame = F;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let held = (self.held.as_ref()).is_none_or(|held| held.strong_count() > 0);
+        let sustain = self.sample.sustain.as_ref().filter(|_| held);
+        let mut frame = self.sample.frame_at(self.pos)?;
+        if let Some(SustainLoop { range, crossfade }) = sustain {
+            let fade_start = (range.end - crossfade) as f64;
+            if *crossfade > 0 && self.pos >= fade_start && self.pos < range.end as f64 {
+                let t = (self.pos - fade_start) / *crossfade as f64;
+                let len = (range.end - range.start) as f64;
+                if let Some(before) = self.sample.frame_at(self.pos - len) {
+                    frame.merge(before, |a, b| lerp(a, b, t));
+                }
+            }
+        }
+        self.pos += self.rate * self.sample.sample_rate / sample_rate;
+        if let Some(SustainLoop { range, .. }) = sustain {
+            while self.pos >= range.end as f64 {
+                self.pos -= (range.end - range.start) as f64;
+            }
+        }
+        if F::CHANNELS == 2 {
+            let left = frame.get_channel(0) * (1.0 - self.pan).min(1.0);
+            let right = frame.get_channel(1) * (1.0 + self.pan).min(1.0);
+            frame.set_channel(0, left);
+            frame.set_channel(1, right);
+        }
+        Some(frame.map(|a| a * self.gain))
+    }
+}
+
+impl<F> StatefulSource for SampleVoice<F>
+where
+    F: Frame,
+{
+    fn save_state(&self, state: &mut SourceState) {
+        state.push(self.pos);
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        state.read_into(&mut self.pos);
+    }
+}
+
+/// A multisampled note with velocity layers and round-robin alternation
+///
+/// Each layer is used for velocities at or above its minimum velocity, up to the next layer's.
+/// Within a layer, successive plays cycle through the layer's samples,
+/// so repeated notes do not sound mechanical.
+///
+/// Cloning a [`MultiSample`] is cheap, and clones share the same buffers and round-robin positions.
+#[derive(Debug, Clone)]
+pub struct MultiSample<F> {
+    layers: Vec<VelocityLayer<F>>,
+}
+
+#[derive(Debug, Clone)]
+struct VelocityLayer<F> {
+    min_velocity: f64,
+    samples: Vec<Sample<F>>,
+    next: Arc<AtomicUsize>,
+}
+
+impl<F> Default for MultiSample<F> {
+    fn default() -> Self {
+        MultiSample { layers: Vec::new() }
+    }
+}
+
+impl<F: Frame> MultiSample<F> {
+    /// Create a multisample with no layers
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Add a layer of round-robin samples used for velocities at or above `min_velocity`
+    ///
+    /// Velocities are in the range [0, 1].
+    pub fn with_layer(
+        mut self,
+        min_velocity: f64,
+        samples: impl IntoIterator<Item = Sample<F>>,
+    ) -> Self {
+        let layer = VelocityLayer {
+            min_velocity,
+            samples: samples.into_iter().collect(),
+            next: Arc::new(AtomicUsize::new(0)),
+        };
+        let i = (self.layers).partition_point(|l| l.min_velocity <= min_velocity);
+        self.layers.insert(i, layer);
+        self
+    }
+    /// Get the number of layers
+    pub fn layers(&self) -> usize {
+        self.layers.len()
+    }
+    /// Get the samples of the layer used for a velocity
+    ///
+    /// Velocities below every layer's minimum use the lowest layer.
+    pub fn layer(&self, velocity: f64) -> &[Sample<F>] {
+        self.layer_at(velocity)
+            .map(|layer| layer.samples.as_slice())
+            .unwrap_or_default()
+    }
+    fn layer_at(&self, velocity: f64) -> Option<&VelocityLayer<F>> {
+        let i = (self.layers).partition_point(|l| l.min_velocity <= velocity);
+        self.layers.get(i.saturating_sub(1))
+    }
+    /// Pick the next sample for a velocity, advancing the layer's round-robin position
+    pub fn pick(&self, velocity: f64) -> Option<&Sample<F>> {
+        let layer = self.layer_at(velocity)?;
+        if layer.samples.is_empty() {
+            return None;
+        }
+        let i = layer.next.fetch_add(1, Ordering::Relaxed);
+        Some(&layer.samples[i % layer.samples.len()])
+    }
+    /// Play the next sample for a velocity
+    ///
+    /// The voice's gain is not changed, since velocity layers are usually recorded at their own loudness.
+    pub fn play(&self, velocity: f64) -> Option<SampleVoice<F>> {
+        self.pick(velocity).map(Sample::play)
+    }
+}
+
+impl<F: Frame> From<Sample<F>> for MultiSample<F> {
+    fn from(sample: Sample<F>) -> Self {
+        MultiSample::new().with_layer(0.0, [sample])
+    }
+}
+
+/// A range of keys in a [`KeyMap`] that plays a [`MultiSample`]
+#[cfg(feature = "notes")]
+#[cfg_attr(docsrs, doc(cfg(feature = "notes")))]
+#[derive(Debug, Clone)]
+pub struct KeyZone<F> {
+    low: Pitch,
+    high: Pitch,
+    root: Pitch,
+    tune: f64,
+    samples: MultiSample<F>,
+}
+
+#[cfg(feature = "notes")]
+impl<F: Frame> KeyZone<F> {
+    /// Create a zone that plays samples for keys from `low` to `high` inclusive
+    ///
+    /// `root` is the pitch the samples were recorded at. Other keys repitch the samples to match.
+    pub fn new(
+        low: impl Into<Pitch>,
+        high: impl Into<Pitch>,
+        root: impl Into<Pitch>,
+        samples: impl Into<MultiSample<F>>,
+    ) -> Self {
+        KeyZone {
+            low: low.into(),
+            high: high.into(),
+            root: root.into(),
+            tune: 0.0,
+            samples: samples.into(),
+        }
+    }
+    /// Set the tuning of the zone in cents
+    ///
+    /// This is useful for correcting samples that were recorded slightly out of tune.
+    pub fn tune(self, cents: f64) -> Self {
+        KeyZone {
+            tune: cents,
+            ..self
+        }
+    }
+    /// Check if a key is in the zone
+    pub fn contains(&self, key: impl Into<Pitch>) -> bool {
+        (self.low..=self.high).contains(&key.into())
+    }
+    /// Get the root pitch
+    pub fn root(&self) -> Pitch {
+        self.root
+    }
+    /// Get the samples
+    pub fn samples(&self) -> &MultiSample<F> {
+        &self.samples
+    }
+    /// Get the playback rate for a key
+    pub fn rate(&self, key: impl Into<Pitch>) -> f64 {
+        let half_steps = key.into().to_half_steps() - self.root.to_half_steps();
+        2f64.powf((half_steps as f64 + self.tune / 100.0) / 12.0)
+    }
+}
+
+/// Assigns [`KeyZone`]s to ranges of keys, forming a playable sampled instrument
+///
+/// If zones overlap, the one added first is used.
+///
+/// # Example
+/// ```
+/// use hodaun::*;
+/// use Letter::*;
+///
+/// let piano_c4 = Sample::new(SineWave::new((C, 4)).take(1.0), 44100.0);
+/// let piano_c5 = Sample::new(SineWave::new((C, 5)).take(1.0), 44100.0);
+/// let keymap = KeyMap::new()
+///     .with_zone(KeyZone::new((C, 0), (Gb, 4), (C, 4), piano_c4))
+///     .with_zone(KeyZone::new((G, 4), (C, 8), (C, 5), piano_c5));
+/// let voice = keymap.play((E, 4), 0.8).unwrap();
+/// ```
+#[cfg(feature = "notes")]
+#[cfg_attr(docsrs, doc(cfg(feature = "notes")))]
+#[derive(Debug, Clone)]
+pub struct KeyMap<F> {
+    zones: Vec<KeyZone<F>>,
+}
+
+#[cfg(feature = "notes")]
+impl<F> Default for KeyMap<F> {
+    fn default() -> Self {
+        KeyMap { zones: Vec::new() }
+    }
+}
+
+#[cfg(feature = "notes")]
+impl<F: Frame> KeyMap<F> {
+    /// Create a key map with no zones
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Add a zone
+    pub fn with_zone(mut self, zone: KeyZone<F>) -> Self {
+        self.zones.push(zone);
+        self
+    }
+    /// Get the zones
+    pub fn zones(&self) -> &[KeyZone<F>] {
+        &self.zones
+    }
+    /// Get the zone that contains a key
+    pub fn zone(&self, key: impl Into<Pitch>) -> Option<&KeyZone<F>> {
+        let key = key.into();
+        self.zones.iter().find(|zone| zone.contains(key))
+    }
+    /// Play a key with a velocity
+    ///
+    /// The sample is chosen from the key's zone by velocity and repitched to the key.
+    /// Returns `None` if no zone contains the key.
+    pub fn play(&self, key: impl Into<Pitch>, velocity: f64) -> Option<SampleVoice<F>> {
+        let key = key.into();
+        let zone = self.zone(key)?;
+        let voice = zone.samples.play(velocity)?;
+        Some(voice.rate(zone.rate(key)))
+    }
+}
+
+/// Randomization parameters for [`Mixer::play_oneshot`]
+#[cfg(feature = "noise")]
+#[cfg_attr(docsrs, doc(cfg(feature = "noise")))]
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlayParams {
+    /// The range of gains to choose from
+    pub gain_range: core::ops::RangeInclusive<f64>,
+    /// The range of playback rates to choose from
+    ///
+    /// A rate of `2.0` plays the sample an octave higher.
+    pub pitch_range: core::ops::RangeInclusive<f64>,
+    /// The pan, from -1 (left) to 1 (right)
+    pub pan: f64,
+}
+
+#[cfg(feature = "noise")]
+impl Default for PlayParams {
+    fn default() -> Self {
+        PlayParams {
+            gain_range: 1.0..=1.0,
+            pitch_range: 1.0..=1.0,
+            pan: 0.0,
+        }
+    }
+}
+
+#[cfg(feature = "noise")]
+impl<F> crate::Mixer<F>
+where
+    F: Frame + Sync,
+{
+    /// Play a sample once with a randomized gain and pitch
+    ///
+    /// Randomizing each play keeps frequently repeated sound effects from sounding mechanical.
+    pub fn play_oneshot(&self, sample: &Sample<F>, params: &PlayParams) -> crate::SourceHandle {
+        use rand::{rngs::SmallRng, Rng};
+        let pick = |rng: &mut SmallRng| {
+            let mut pick = |range: &core::ops::RangeInclusive<f64>| {
+                if range.start() < range.end() {
+                    rng.gen_range(range.clone())
+                } else {
+                    *range.start()
+                }
+            };
+            (pick(&params.gain_range), pick(&params.pitch_range))
+        };
+        #[cfg(feature = "std")]
+        let (gain, rate) = {
+            thread_local! {
+                static RNG: std::cell::RefCell<SmallRng> =
+                    std::cell::RefCell::new(crate::gen::time_seeded_rng());
+            }
+            RNG.with(|rng| pick(&mut rng.borrow_mut()))
+        };
+        #[cfg(not(feature = "std"))]
+        let (gain, rate) = pick(&mut crate::gen::time_seeded_rng());
+        self.add(sample.play().gain(gain).rate(rate).pan(params.pan))
+    }
+}
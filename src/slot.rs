@@ -0,0 +1,421 @@
+use alloc::sync::Arc;
+use core::{
+    ptr,
+    sync::atomic::{AtomicBool, AtomicPtr, Ordering},
+};
+
+use crate::{
+    source::*,
+    sync::{ring, Mutex, RingReceiver, RingSender},
+    Frame, ToDuration,
+};
+
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
+
+/// A [`Source`] whose inner source can be replaced while it plays
+///
+/// Cloning a slot is cheap, and clones share the same inner source.
+/// One clone can be added to a [`Mixer`](crate::Mixer) or output
+/// while another is used to switch patches or tracks from a different thread,
+/// without removing the slot and losing its place.
+/// Only one clone should be played at a time.
+///
+/// Playing never waits on the thread that replaces the source,
+/// and replaced sources are dropped on that thread rather than the audio thread.
+///
+/// A slot plays silence while it is empty and never finishes on its own.
+#[derive(Clone)]
+pub struct Slot<F> {
+    shared: Arc<SlotShared<F>>,
+}
+
+/// The most sources a slot fades out at once
+const MAX_FADING: usize = 4;
+/// The number of replaced sources that can wait to be dropped
+const RETIRED_CAPACITY: usize = 32;
+
+struct SlotShared<F> {
+    /// The latest replacement that has not been picked up by the player, or null
+    swap: AtomicPtr<Swap<F>>,
+    /// Replaced sources waiting to be dropped off the audio thread
+    retired: Mutex<RingReceiver<Retired<F>>>,
+    empty: AtomicBool,
+    /// Only locked by the clone that is playing
+    playback: Mutex<Playback<F>>,
+}
+
+/// A new inner source and how long to fade to it
+struct Swap<F> {
+    source: Option<DynamicSource<F>>,
+    fade: f64,
+}
+
+/// Something the player is done with, only held so that it is dropped off the audio thread
+#[allow(dead_code)]
+enum Retired<F> {
+    Source(DynamicSource<F>),
+    Swap(Box<Swap<F>>),
+}
+
+struct Voice<F> {
+    source: DynamicSource<F>,
+    gain: f64,
+    /// The change in gain per second
+    rate: f64,
+}
+
+impl<F: Frame> Voice<F> {
+    /// Mix the next frame into a frame, returning whether the voice is still playing
+    fn mix(&mut self, frame: &mut F, sample_rate: f64) -> bool {
+        let Some(next) = self.source.next(sample_rate) else {
+            return false;
+        };
+        let gain = self.gain;
+        frame.merge(next, |a, b| a + b * gain);
+        self.gain = (self.gain + self.rate / sample_rate).min(1.0);
+        self.gain > 0.0
+    }
+}
+
+struct Playback<F> {
+    current: Option<Voice<F>>,
+    /// Sources being faded out
+    fading: Vec<Voice<F>>,
+    retired: RingSender<Retired<F>>,
+}
+
+impl<F> Playback<F> {
+    fn retire(&mut self, retired: Retired<F>) {
+        // If too many sources are waiting to be dropped, this one is dropped here
+        let _ = self.retired.push(retired);
+    }
+    fn swap(&mut self, swap: &mut Swap<F>) {
+        let fade = swap.fade;
+        let new = swap.source.take().map(|source| Voice {
+            source,
+            gain: if fade > 0.0 { 0.0 } else { 1.0 },
+            rate: if fade > 0.0 { 1.0 / fade } else { 0.0 },
+        });
+        let Some(mut old) = core::mem::replace(&mut self.current, new) else {
+            return;
+        };
+        if fade <= 0.0 {
+            self.retire(Retired::Source(old.source));
+            return;
+        }
+        // Fade out from wherever the old source's gain is, even if it was still fading in
+        old.rate = -1.0 / fade;
+        if self.fading.len() == MAX_FADING {
+            let quietest = (0..self.fading.len())
+                .min_by(|&a, &b| self.fading[a].gain.total_cmp(&self.fading[b].gain))
+                .unwrap();
+            let voice = self.fading.swap_remove(quietest);
+            self.retire(Retired::Source(voice.source));
+        }
+        self.fading.push(old);
+    }
+}
+
+impl<F> Drop for SlotShared<F> {
+    fn drop(&mut self) {
+        let swap = *self.swap.get_mut();
+        if !swap.is_null() {
+            // SAFETY: the pointer came from `Box::into_raw` and was never taken
+            drop(unsafe { Box::from_raw(swap) });
+        }
+    }
+}
+
+impl<F> Default for Slot<F> {
+    fn default() -> Self {
+        let (retired, retired_recv) = ring(RETIRED_CAPACITY);
+        Slot {
+            shared: Arc::new(SlotShared {
+                swap: AtomicPtr::new(ptr::null_mut()),
+                retired: Mutex::new(retired_recv),
+                empty: AtomicBool::new(true),
+                playback: Mutex::new(Playback {
+                    current: None,
+                    fading: Vec::with_capacity(MAX_FADING),
+                    retired,
+                }),
+            }),
+        }
+    }
+}
+
+impl<F> Slot<F> {
+    /// Create a new empty slot
+    pub fn new() -> Self {
+        Self::default()
+    }
+    /// Create a new slot playing a source
+    pub fn with<S>(source: S) -> Self
+    where
+        S: Source<Frame = F> + Send + 'static,
+    {
+        let slot = Self::new();
+        slot.set(source);
+        slot
+    }
+    /// Replace the inner source immediately
+    ///
+    /// Sources still fading out from earlier crossfades keep fading.
+    pub fn set<S>(&self, source: S)
+    where
+        S: Source<Frame = F> + Send + 'static,
+    {
+        self.set_with_fade(source, 0.0);
+    }
+    /// Replace the inner source, crossfading from the old one over some duration
+    ///
+    /// The new source fades in even if the slot was empty.
+    /// If a previous crossfade is still in progress, the source it was fading out keeps fading,
+    /// finishing no later than the new crossfade.
+    pub fn set_with_fade<S>(&self, source: S, fade: impl ToDuration)
+    where
+        S: Source<Frame = F> + Send + 'static,
+    {
+        self.replace(Some(Box::new(source)), fade);
+    }
+    /// Remove the inner source immediately
+    pub fn clear(&self) {
+        self.clear_with_fade(0.0);
+    }
+    /// Fade out and remove the inner source
+    pub fn clear_with_fade(&self, fade: impl ToDuration) {
+        self.replace(None, fade);
+    }
+    /// Check if the slot has an inner source
+    ///
+    /// Inner sources that finish are removed automatically.
+    pub fn is_empty(&self) -> bool {
+        self.shared.empty.load(Ordering::Relaxed)
+    }
+    fn replace(&self, source: Option<DynamicSource<F>>, fade: impl ToDuration) {
+        let fade = fade.to_duration().as_secs_f64();
+        let shared = &*self.shared;
+        shared.empty.store(source.is_none(), Ordering::Relaxed);
+        let swap = Box::into_raw(Box::new(Swap { source, fade }));
+        let superseded = shared.swap.swap(swap, Ordering::AcqRel);
+        if !superseded.is_null() {
+            // SAFETY: the pointer came from `Box::into_raw` and was never taken by the player
+            drop(unsafe { Box::from_raw(superseded) });
+        }
+        // Drop the sources the player has replaced
+        let mut retired = shared.retired.lock();
+        while retired.pop().is_some() {}
+    }
+}
+
+impl<F> Source for Slot<F>
+where
+    F: Frame,
+{
+    type Frame = F;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let shared = &*self.shared;
+        let mut frame = F::uniform(0.0);
+        // Another clone is playing
+        let Some(mut playback) = shared.playback.try_lock() else {
+            return Some(frame);
+        };
+        let playback = &mut *playback;
+        let swap = shared.swap.swap(ptr::null_mut(), Ordering::AcqRel);
+        if !swap.is_null() {
+            // SAFETY: the pointer came from `Box::into_raw`, and swapping in null means only this thread has it
+            let mut swap = unsafe { Box::from_raw(swap) };
+            playback.swap(&mut swap);
+            playback.retire(Retired::Swap(swap));
+        }
+        let mut i = 0;
+        while i < playback.fading.len() {
+            if playback.fading[i].mix(&mut frame, sample_rate) {
+                i += 1;
+            } else {
+                let voice = playback.fading.swap_remove(i);
+                playback.retire(Retired::Source(voice.source));
+            }
+        }
+        if let Some(voice) = &mut playback.current {
+            if !voice.mix(&mut frame, sample_rate) {
+                let voice = playback.current.take().unwrap();
+                playback.retire(Retired::Source(voice.source));
+            }
+        }
+        // Don't overwrite the state of a replacement that has not been picked up yet
+        if shared.swap.load(Ordering::Acquire).is_null() {
+            (shared.empty).store(playback.current.is_none(), Ordering::Relaxed);
+        }
+        Some(frame)
+    }
+}
+
+/// Which chain an [`AbSwitch`] plays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum AbChoice {
+    /// The first chain
+    #[default]
+    A,
+    /// The second chain
+    B,
+}
+
+/// Controls an [`AbSwitch`]
+///
+/// Returned from [`Source::ab_switch`].
+#[derive(Debug, Clone)]
+pub struct AbControl {
+    state: Arc<AbState>,
+}
+
+#[derive(Debug)]
+struct AbState {
+    b: AtomicBool,
+    level_match: AtomicBool,
+}
+
+impl AbControl {
+    /// Choose which chain to hear
+    pub fn select(&self, choice: AbChoice) {
+        (self.state.b).store(choice == AbChoice::B, Ordering::Relaxed);
+    }
+    /// Switch to the chain that is not currently selected
+    pub fn toggle(&self) {
+        self.state.b.fetch_xor(true, Ordering::Relaxed);
+    }
+    /// Get the chain that is currently selected
+    pub fn selected(&self) -> AbChoice {
+        if self.state.b.load(Ordering::Relaxed) {
+            AbChoice::B
+        } else {
+            AbChoice::A
+        }
+    }
+    /// Set whether the B chain is scaled to match the loudness of the A chain
+    ///
+    /// This keeps an effect that makes a source louder from sounding better just because it is louder.
+    /// It is off by default.
+    pub fn level_match(&self, level_match: bool) {
+        (self.state.level_match).store(level_match, Ordering::Relaxed);
+    }
+    /// Check whether level matching is on
+    pub fn is_level_matched(&self) -> bool {
+        self.state.level_match.load(Ordering::Relaxed)
+    }
+}
+
+/// Source returned from [`Source::ab_switch`]
+///
+/// Both chains always run, so switching between them is instant and stays in sync.
+#[derive(Debug, Clone)]
+pub struct AbSwitch<A, B> {
+    a: A,
+    b: B,
+    state: Arc<AbState>,
+    /// 0 plays A and 1 plays B
+    mix: f64,
+    fade: f64,
+    window: f64,
+    /// The smoothed power of each chain
+    power: [f64; 2],
+}
+
+/// The largest gain applied to match levels, about 24 dB
+const MAX_MATCH_GAIN: f64 = 16.0;
+
+impl<A, B> AbSwitch<A, B> {
+    pub(crate) fn new(a: A, b: B) -> (AbControl, Self) {
+        let state = Arc::new(AbState {
+            b: AtomicBool::new(false),
+            level_match: AtomicBool::new(false),
+        });
+        let control = AbControl {
+            state: state.clone(),
+        };
+        let switch = AbSwitch {
+            a,
+            b,
+            state,
+            mix: 0.0,
+            fade: 0.02,
+            window: 0.4,
+            power: [0.0; 2],
+        };
+        (control, switch)
+    }
+    /// Set how long switching between chains takes
+    ///
+    /// The default is 20 ms, which is short enough to compare by ear without clicking.
+    pub fn fade(self, fade: impl ToDuration) -> Self {
+        AbSwitch {
+            fade: fade.to_duration().as_secs_f64(),
+            ..self
+        }
+    }
+    /// Set the time over which loudness is measured for level matching
+    ///
+    /// The default is 0.4 seconds.
+    pub fn level_match_window(self, window: impl ToDuration) -> Self {
+        AbSwitch {
+            window: window.to_duration().as_secs_f64(),
+            ..self
+        }
+    }
+}
+
+fn power<F: Frame>(frame: &F) -> f64 {
+    (0..F::CHANNELS)
+        .map(|i| frame.get_channel(i).powi(2))
+        .sum::<f64>()
+        / F::CHANNELS as f64
+}
+
+impl<A, B> Source for AbSwitch<A, B>
+where
+    A: Source,
+    B: Source<Frame = A::Frame>,
+{
+    type Frame = A::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let (a, b) = match (self.a.next(sample_rate), self.b.next(sample_rate)) {
+            (None, None) => return None,
+            (a, b) => (
+                a.unwrap_or_else(|| A::Frame::uniform(0.0)),
+                b.unwrap_or_else(|| A::Frame::uniform(0.0)),
+            ),
+        };
+        // Move toward the selected chain
+        let target = if self.state.b.load(Ordering::Relaxed) {
+            1.0
+        } else {
+            0.0
+        };
+        let step = if self.fade > 0.0 {
+            1.0 / (self.fade * sample_rate)
+        } else {
+            1.0
+        };
+        self.mix = if self.mix < target {
+            (self.mix + step).min(target)
+        } else {
+            (self.mix - step).max(target)
+        };
+        // Measure loudness and match B to A
+        let coef = 1.0 - (-1.0 / (self.window * sample_rate)).exp();
+        self.power[0] += (power(&a) - self.power[0]) * coef;
+        self.power[1] += (power(&b) - self.power[1]) * coef;
+        let match_gain = if self.state.level_match.load(Ordering::Relaxed) {
+            ((self.power[0] + 1e-12) / (self.power[1] + 1e-12))
+                .sqrt()
+                .clamp(1.0 / MAX_MATCH_GAIN, MAX_MATCH_GAIN)
+        } else {
+            1.0
+        };
+        let (a_gain, b_gain) = (1.0 - self.mix, self.mix * match_gain);
+        let mut frame = a;
+        frame.merge(b, |a, b| a * a_gain + b * b_gain);
+        Some(frame)
+    }
+}
@@ -1,13 +1,41 @@
 //! Audio sources
 
-use std::{
-    marker::PhantomData,
+use alloc::{
+    collections::VecDeque,
     sync::{Arc, Weak},
 };
+use core::{
+    marker::PhantomData,
+    sync::atomic::{AtomicBool, AtomicU64, Ordering},
+    time::Duration,
+};
+#[cfg(feature = "meter")]
+use std::time::Instant;
+#[cfg(feature = "std")]
+use std::{
+    sync::mpsc::{self, Receiver, SyncSender, TryRecvError},
+    thread,
+};
 
-use parking_lot::Mutex;
+#[cfg(feature = "noise")]
+use rand::prelude::*;
+
+use crate::{
+    delay::{DelayLine, DEFAULT_LINE_RATE},
+    filter::{Biquad, BiquadState},
+    lerp,
+    sync::Mutex,
+    AbControl, AbSwitch, Automation, AutomationEnd, Exciter, Formant, Frame, FrameAutomation,
+    Pattern, Reverb, Shared, Side, Smoothed, Stereo, ToDuration, Vowel,
+};
+#[cfg(feature = "std")]
+use crate::{
+    BandAnalyzer, BandLevels, Cue, EventSender, Goniometer, Looper, LooperControl, OnsetDetector,
+    OnsetTracker, StereoScope, TrimSilence, Vad, VoiceActivity,
+};
 
-use crate::{lerp, Automation, Frame, Shared, Stereo, ToDuration};
+#[cfg(not(feature = "std"))]
+use crate::prelude::*;
 
 /// An audio source with a dynamic frame size
 ///
@@ -50,15 +78,73 @@ pub trait Source {
     ///
     /// Returning [`None`] indicates the source has no samples left
     fn next(&mut self, sample_rate: f64) -> Option<Self::Frame>;
+    /// Fill a block of planar channel buffers with frames
+    ///
+    /// This lets a source, like a [`Mixer`](crate::Mixer), be driven by a host's process callback
+    /// instead of an output device, such as inside a CLAP or VST3 plugin.
+    /// Each slice is one channel, and all slices should be the same length.
+    /// Frames are mapped to the channels the same way they are written to an output device.
+    ///
+    /// This method itself does not allocate or lock, but it is only as realtime safe as the source.
+    /// For example, a [`Mixer`](crate::Mixer) locks its source list on every frame
+    /// and drops sources when they finish, so adding sources from another thread can delay it.
+    /// Returns the number of frames written.
+    /// If the source finishes, the rest of the block is filled with silence.
+    fn process(&mut self, channels: &mut [&mut [f32]], sample_rate: f64) -> usize {
+        let len = channels
+            .iter()
+            .map(|channel| channel.len())
+            .min()
+            .unwrap_or(0);
+        let count = channels.len();
+        let mut written = 0;
+        while written < len {
+            let Some(frame) = self.next(sample_rate) else {
+                break;
+            };
+            // The same mapping as `Frame::write_slice`, without a buffer
+            for (i, channel) in channels.iter_mut().enumerate() {
+                channel[written] = match (Self::Frame::CHANNELS, count) {
+                    (1, _) => frame.get_channel(0),
+                    (_, 1) => frame.avg(),
+                    _ => frame.get_channel(i),
+                } as f32;
+            }
+            written += 1;
+        }
+        for channel in channels.iter_mut() {
+            channel[written..].fill(0.0);
+        }
+        written
+    }
     /// Amplify the source by some multiplier
+    ///
+    /// The multiplier can be any [`Automation`], or a [`FrameAutomation`]
+    /// to amplify each channel separately.
     fn amplify<A>(self, amp: A) -> Amplify<Self, A>
+    where
+        Self: Sized,
+        A: FrameAutomation<Self::Frame>,
+    {
+        Amplify {
+            source: self,
+            amp: EndingAutomation::new(amp),
+        }
+    }
+    /// Amplify the source, smoothing changes in the amplitude
+    ///
+    /// This is like [`Source::amplify`], but the amplitude is wrapped in [`Smoothed`] with the given time constant.
+    fn amplify_smoothed<A>(self, amp: A, time: impl ToDuration) -> Amplify<Self, Smoothed<A>>
     where
         Self: Sized,
         A: Automation,
     {
-        Amplify { source: self, amp }
+        self.amplify(Smoothed::new(amp, time))
     }
     /// End the source after some duration
+    ///
+    /// As a [`FiniteSource`], the result reports the taken duration,
+    /// even if the source would end before it.
     fn take(self, dur: impl ToDuration) -> Take<Self, f64>
     where
         Self: Sized,
@@ -67,7 +153,7 @@ pub trait Source {
             source: self,
             duration: dur.to_duration().as_secs_f64(),
             elapsed: 0.0,
-            release: 0.0,
+            release: EndingAutomation::new(0.0),
         }
     }
     /// End the source after some duration and apply a release envelope
@@ -80,11 +166,39 @@ pub trait Source {
             source: self,
             duration: dur.to_duration().as_secs_f64(),
             elapsed: 0.0,
-            release,
+            release: EndingAutomation::new(release),
+        }
+    }
+    /// Fade the source in when it starts and out when it ends
+    ///
+    /// When the source ends, its last frame is faded to silence over the
+    /// same duration rather than being cut off. This prevents the audible
+    /// clicks caused by starting or stopping a waveform mid-cycle,
+    /// for example with [`Source::take`].
+    ///
+    /// A fade of 2-5 milliseconds is usually enough.
+    fn declick(self, fade: impl ToDuration) -> Declick<Self>
+    where
+        Self: Sized,
+    {
+        Declick {
+            source: self,
+            fade: fade.to_duration().as_secs_f64(),
+            time: 0.0,
+            last: None,
+            release: None,
         }
     }
     /// Chain the source with another
     fn chain<B>(self, next: B) -> Chain<Self, B>
+    where
+        Self: Sized,
+        B: Source<Frame = Self::Frame>,
+    {
+        self.chain_gap(next, 0.0)
+    }
+    /// Chain the source with another, with a period of silence in between
+    fn chain_gap<B>(self, next: B, gap: impl ToDuration) -> Chain<Self, B>
     where
         Self: Sized,
         B: Source<Frame = Self::Frame>,
@@ -92,8 +206,27 @@ pub trait Source {
         Chain {
             a: self,
             b: next,
-            b_start: None,
-            time: 0.0,
+            gap: gap.to_duration().as_secs_f64(),
+            gap_elapsed: None,
+        }
+    }
+    /// Chain the source with another, starting the next source
+    /// before this one ends
+    ///
+    /// The sources are summed while they overlap.
+    /// To crossfade between them instead, use [`ChainOverlap::crossfade`].
+    fn chain_overlap<B>(self, next: B, overlap: impl ToDuration) -> ChainOverlap<Self, B>
+    where
+        Self: FiniteSource + Sized,
+        B: Source<Frame = Self::Frame>,
+    {
+        ChainOverlap {
+            a: self,
+            b: next,
+            overlap: overlap.to_duration().as_secs_f64(),
+            crossfade: false,
+            a_done: false,
+            b_started: false,
         }
     }
     /// Apply a low-pass filter with the given cut-off frequency
@@ -104,10 +237,79 @@ pub trait Source {
     {
         LowPass {
             source: self,
-            freq,
+            freq: EndingAutomation::new(freq),
             acc: None,
         }
     }
+    /// Apply a low-pass filter, smoothing changes in the cutoff frequency
+    ///
+    /// This is like [`Source::low_pass`], but the frequency is wrapped in [`Smoothed`] with the given time constant.
+    fn low_pass_smoothed<F>(self, freq: F, time: impl ToDuration) -> LowPass<Self, Smoothed<F>>
+    where
+        Self: Sized,
+        F: Automation,
+    {
+        self.low_pass(Smoothed::new(freq, time))
+    }
+    /// Apply a notch filter that removes a narrow band of frequencies around `freq`
+    ///
+    /// Higher `q` values make the notch narrower. Values of `q` below 0.001 are treated as 0.001.
+    /// This is useful for removing a single tone, like 50 or 60 Hz mains hum from live input.
+    fn notch<F, Q>(self, freq: F, q: Q) -> Notch<Self, F, Q>
+    where
+        Self: Sized,
+        F: Automation,
+        Q: Automation,
+    {
+        Notch {
+            source: self,
+            freq: EndingAutomation::new(freq),
+            q: EndingAutomation::new(q),
+            params: None,
+            filter: Biquad::notch(1000.0, 1.0, 44100.0),
+            state: BiquadState::default(),
+        }
+    }
+    /// Filter the source through the formants of a sequence of vowels
+    ///
+    /// `morph` selects a position in the sequence, from `0` for the first vowel
+    /// to one less than the number of vowels for the last.
+    /// Fractional values blend between neighboring vowels.
+    /// This works best on harmonically rich sources like saw waves.
+    fn formant<M>(self, vowels: impl IntoIterator<Item = Vowel>, morph: M) -> Formant<Self, M>
+    where
+        Self: Sized,
+        M: Automation,
+    {
+        Formant::new(self, vowels, morph)
+    }
+    /// Brighten the source by adding harmonics of its high frequencies
+    ///
+    /// Frequencies above the [`Exciter::crossover`] are split off, saturated,
+    /// and mixed back in, scaled by `amount`.
+    fn excite<A>(self, amount: A) -> Exciter<Self, A>
+    where
+        Self: Sized,
+        A: Automation,
+    {
+        Exciter::new(self, amount)
+    }
+    /// Add reverberation to the source
+    ///
+    /// `room_size` and `damping` range from `0.0` to `1.0`.
+    /// Larger rooms ring for longer, and more damping absorbs high frequencies faster.
+    /// `mix` is the balance between the dry source at `0.0` and only the reverberation at `1.0`.
+    ///
+    /// All three can be automated. After the source ends, the reverberation continues until it dies out.
+    fn reverb<R, D, M>(self, room_size: R, damping: D, mix: M) -> Reverb<Self, R, D, M>
+    where
+        Self: Sized,
+        R: Automation,
+        D: Automation,
+        M: Automation,
+    {
+        Reverb::new(self, room_size, damping, mix)
+    }
     /// Transform each frame with the given function
     fn map<F, B>(self, f: F) -> Map<Self, F>
     where
@@ -116,7 +318,28 @@ pub trait Source {
     {
         Map { source: self, f }
     }
+    /// Transform each frame with a function that has access to some state
+    ///
+    /// The function receives the state, the frame, and the sample rate.
+    /// Returning [`None`] ends the source.
+    ///
+    /// This is useful for simple stateful processing like envelope followers
+    /// or filters without defining a new [`Source`].
+    fn scan<St, F, B>(self, initial_state: St, f: F) -> Scan<Self, St, F>
+    where
+        Self: Sized,
+        F: FnMut(&mut St, Self::Frame, f64) -> Option<B>,
+    {
+        Scan {
+            source: self,
+            state: initial_state,
+            f,
+        }
+    }
     /// Combine this source with another using the given frame-combining function
+    ///
+    /// By default, the combined source ends when either source ends.
+    /// Use [`Zip::length`] to change this.
     fn zip<F, B>(self, other: B, f: F) -> Zip<Self, B, F>
     where
         Self: Sized,
@@ -127,6 +350,9 @@ pub trait Source {
             a: self,
             b: other,
             f,
+            length: ZipLength::Shortest,
+            a_done: false,
+            b_done: false,
         }
     }
     /// Combine this source with another by adding their frames
@@ -138,6 +364,16 @@ pub trait Source {
     {
         self.zip(other, Frame::add)
     }
+    /// Combine this source with another by adding their frames,
+    /// continuing until both sources have ended
+    #[allow(clippy::type_complexity)]
+    fn mix_longest<B>(self, other: B) -> Zip<Self, B, fn(Self::Frame, Self::Frame) -> Self::Frame>
+    where
+        Self: Sized,
+        B: Source<Frame = Self::Frame>,
+    {
+        self.mix(other).length(ZipLength::Longest)
+    }
     /// Apply a pan to the source
     ///
     /// Non-mono sources will be averaged before panning
@@ -148,6 +384,82 @@ pub trait Source {
     {
         Pan { source: self, pan }
     }
+    /// Pan the source, smoothing changes in the pan
+    ///
+    /// This is like [`Source::pan`], but the pan is wrapped in [`Smoothed`] with the given time constant.
+    fn pan_smoothed<P>(self, pan: P, time: impl ToDuration) -> Pan<Self, Smoothed<P>>
+    where
+        Self: Sized,
+        P: Automation,
+    {
+        self.pan(Smoothed::new(pan, time))
+    }
+    /// Delay one stereo channel by a few milliseconds
+    ///
+    /// Because of the precedence effect, short delays of up to about 35 milliseconds
+    /// are not heard as echoes. Instead, the sound seems to come from the undelayed side,
+    /// or to be wider when the delayed channel is processed differently.
+    ///
+    /// Mono sources are duplicated to both channels first.
+    /// Delays longer than [`Haas::max_delay`] are clamped to it.
+    fn haas<D>(self, delay_ms: D, side: Side) -> Haas<Self, D>
+    where
+        Self: Sized,
+        D: Automation,
+    {
+        Haas {
+            source: self,
+            delay_ms,
+            side,
+            line: DelayLine::new(DEFAULT_MAX_HAAS, DEFAULT_LINE_RATE),
+        }
+    }
+    /// Add echoes of the source
+    ///
+    /// `time` is the time between echoes in seconds.
+    /// `feedback` is how much of each echo is fed back into the next one.
+    /// Values close to `1.0` make echoes ring for a long time.
+    /// `mix` is the balance between the dry source at `0.0` and only the echoes at `1.0`.
+    ///
+    /// All three can be automated, for example with [`Shared`] values to change them while the source plays.
+    /// For a delay time synced to a [`Tempo`](crate::Tempo), use [`Tempo::note`](crate::Tempo::note).
+    /// After the source ends, the echoes continue until they die out.
+    /// Times longer than [`Delay::max_time`] are clamped to it.
+    fn delay<T, B, M>(self, time: T, feedback: B, mix: M) -> Delay<Self, T, B, M>
+    where
+        Self: Sized,
+        T: Automation,
+        B: Automation,
+        M: Automation,
+    {
+        Delay {
+            source: Some(self),
+            time,
+            feedback,
+            mix,
+            line: DelayLine::new(DEFAULT_MAX_DELAY, DEFAULT_LINE_RATE),
+            silent: 0,
+            played: 0,
+            ended_at: None,
+        }
+    }
+    /// Convert the source to mono by averaging its channels
+    fn to_mono(self) -> ToMono<Self>
+    where
+        Self: Sized,
+    {
+        ToMono { source: self }
+    }
+    /// Convert the source to stereo
+    ///
+    /// Mono sources are duplicated to both channels.
+    /// Sources with more than two channels keep only their first two.
+    fn to_stereo(self) -> ToStereo<Self>
+    where
+        Self: Sized,
+    {
+        ToStereo { source: self }
+    }
     /// Map the source's amplitude's range from [-1, 1] to [0, 1]
     ///
     /// This is useful for sources that are used as automation, since
@@ -168,8 +480,16 @@ pub trait Source {
             count_left: Some(n),
             curr: Vec::new(),
             period: None,
+            gap: 0.0,
+            overlap: Overlap::Sum,
             time: 0.0,
+            idle: 0.0,
             started: false,
+            pattern: None,
+            step: 0,
+            swing: 0.5,
+            #[cfg(feature = "noise")]
+            humanize: Humanize::default(),
         }
     }
     /// Repeat a source indefinitely
@@ -182,8 +502,16 @@ pub trait Source {
             count_left: None,
             curr: Vec::new(),
             period: None,
+            gap: 0.0,
+            overlap: Overlap::Sum,
             time: 0.0,
+            idle: 0.0,
             started: false,
+            pattern: None,
+            step: 0,
+            swing: 0.5,
+            #[cfg(feature = "noise")]
+            humanize: Humanize::default(),
         }
     }
     /// When repeated, make the source continue where it left off instead of starting over
@@ -238,23 +566,256 @@ pub trait Source {
             InspectedSource { source: self, curr },
         )
     }
+    /// Call a function on each frame as it is produced
+    ///
+    /// Unlike [`Source::inspect`], this sees every frame, so it is useful
+    /// for debugging, logging, or feeding a visualizer.
+    fn inspect_each<F>(self, f: F) -> InspectEach<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(&Self::Frame),
+    {
+        InspectEach { source: self, f }
+    }
+    #[cfg(feature = "std")]
+    /// Detect onsets and estimate the tempo of the source as it plays
+    ///
+    /// The source passes through unchanged.
+    /// Detected onsets and the estimated tempo can be read from the returned [`OnsetTracker`],
+    /// which is useful for driving beat-synced visuals.
+    fn onsets(self) -> (OnsetTracker, OnsetDetector<Self>)
+    where
+        Self: Sized,
+    {
+        OnsetDetector::new(self)
+    }
+    #[cfg(feature = "std")]
+    /// Measure the energy in log-spaced frequency bands as the source plays
+    ///
+    /// The source passes through unchanged.
+    /// The levels can be read from the returned [`BandLevels`] without blocking,
+    /// which makes this a good back end for spectrum visualizers.
+    /// To analyze everything that is playing, call this on a [`Mixer`](crate::Mixer).
+    fn bands(self, bands: usize) -> (BandLevels, BandAnalyzer<Self>)
+    where
+        Self: Sized,
+    {
+        BandAnalyzer::new(self, bands)
+    }
+    #[cfg(feature = "std")]
+    /// Measure the stereo image of the source as it plays
+    ///
+    /// The source passes through unchanged.
+    /// The correlation between the channels and the last `points` sample pairs
+    /// can be read from the returned [`StereoScope`] without blocking,
+    /// which makes this a good back end for goniometers and vectorscopes.
+    fn goniometer(self, points: usize) -> (StereoScope, Goniometer<Self>)
+    where
+        Self: Sized,
+    {
+        Goniometer::new(self, points)
+    }
+    #[cfg(feature = "std")]
+    /// Skip leading and trailing silence
+    ///
+    /// A frame is silent if the amplitudes of all of its channels are at most `threshold`.
+    /// Silence in the middle of the source is kept.
+    /// Because it cannot know whether silence is trailing until the source ends,
+    /// the source buffers silent frames until it hears sound again.
+    /// At most 10 seconds of silence are buffered. Silence that lasts longer is played,
+    /// and only the last 10 seconds of trailing silence are trimmed.
+    fn trim_silence(self, threshold: f64) -> TrimSilence<Self>
+    where
+        Self: Sized,
+    {
+        TrimSilence::new(self, threshold)
+    }
+    #[cfg(feature = "std")]
+    /// Detect speech in the source as it plays
+    ///
+    /// The returned [`VoiceActivity`] reports whether speech is detected and the completed speech segments.
+    /// This is useful for segmenting recordings from an input device.
+    /// Use [`Vad::gate`] to silence the source when no one is speaking.
+    fn vad(self) -> (VoiceActivity, Vad<Self>)
+    where
+        Self: Sized,
+    {
+        Vad::new(self)
+    }
+    #[cfg(feature = "std")]
+    /// Emit an event once the source has played for some amount of time
+    ///
+    /// This can be used for cues that need to line up exactly with the audio,
+    /// like subtitles or rhythm game notes.
+    fn cue<E>(self, at: impl ToDuration, events: &EventSender<E>, event: E) -> Cue<Self, E>
+    where
+        Self: Sized,
+    {
+        Cue::new(self, at, events, event)
+    }
+    /// Run the source at a multiple of the sample rate and filter the result back down
+    ///
+    /// This is useful around nonlinear processing, like distortion or waveshaping,
+    /// to reduce the aliasing it would otherwise cause.
+    /// Higher factors reduce aliasing more but are more expensive.
+    fn oversample(self, factor: usize) -> Oversample<Self>
+    where
+        Self: Sized,
+    {
+        Oversample {
+            source: self,
+            factor: factor.max(1),
+            filters: Vec::new(),
+            sample_rate: 0.0,
+        }
+    }
+    #[cfg(feature = "std")]
+    /// Record the source into loops that play back in sync
+    ///
+    /// The source passes through, mixed with all recorded loops.
+    /// Recording, overdubbing, and undo are controlled with the returned [`LooperControl`].
+    ///
+    /// The length of each new loop is rounded to a multiple of `quantum`, for example one bar at the current tempo.
+    /// If `quantum` is zero, the length of the first loop is used as the quantum for the rest.
+    fn looper(self, quantum: impl ToDuration) -> (LooperControl<Self::Frame>, Looper<Self>)
+    where
+        Self: Sized,
+    {
+        Looper::new(self, quantum.to_duration())
+    }
+    #[cfg(feature = "meter")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "meter")))]
+    /// Measure the CPU time spent producing the source's frames
+    ///
+    /// This is useful for finding which sources are the most expensive.
+    fn meter(self) -> (CpuMeter, Metered<Self>)
+    where
+        Self: Sized,
+    {
+        let meter = CpuMeter::default();
+        (
+            meter.clone(),
+            Metered {
+                source: self,
+                meter,
+            },
+        )
+    }
+    #[cfg(feature = "std")]
+    /// Run the source on its own thread, ending it if it stalls
+    ///
+    /// Frames are produced ahead of time on a feeder thread and stored in a small buffer.
+    /// The thread is started right away, and starts producing frames when the source is first played
+    /// or when [`Guarded::sample_rate`] is called.
+    /// If no frames are available for longer than `timeout`, the source is considered stalled
+    /// and ends. This keeps a source that blocks, for example on network I/O,
+    /// from freezing everything else playing on the same output.
+    ///
+    /// The returned [`Watchdog`] reports whether the source stalled.
+    fn guard(self, timeout: impl ToDuration) -> (Watchdog, Guarded<Self>)
+    where
+        Self: Send + Sized + 'static,
+    {
+        let watchdog = Watchdog::default();
+        (
+            watchdog.clone(),
+            Guarded {
+                feeder: Feeder::new(self, DEFAULT_FEED_BUFFER),
+                timeout: timeout.to_duration().as_secs_f64(),
+                waited: 0.0,
+                watchdog,
+            },
+        )
+    }
+    #[cfg(feature = "std")]
+    /// Produce the source's frames ahead of time on a worker thread
+    ///
+    /// This moves expensive or blocking work, like reading and decoding files,
+    /// off of the audio thread.
+    /// `buffer` is how much audio is produced ahead of time.
+    ///
+    /// The worker starts filling the buffer immediately,
+    /// so `sample_rate` should be the sample rate the source will be played at.
+    /// If the worker falls behind, silence is played until it catches up.
+    fn prefetch(self, buffer: impl ToDuration, sample_rate: f64) -> Prefetch<Self>
+    where
+        Self: Send + Sized + 'static,
+    {
+        let mut feeder = Feeder::new(self, buffer.to_duration().as_secs_f64());
+        feeder.start(sample_rate);
+        Prefetch { feeder }
+    }
     /// Buffer the source
     ///
     /// The source returned by this function can be cloned, and while each clone will
     /// track its time seperately, the underlying source will only be read once.
     ///
     /// Useful when reading from sound files or with other sources that cannot be cloned.
+    ///
+    /// By default, the entire source is kept in memory. For long sources,
+    /// use [`Buffered::max_retained`] or [`Buffered::release_before`].
     fn buffer(self) -> Buffered<Self>
     where
         Self: Sized,
     {
-        Buffered {
-            inner: Arc::new(Mutex::new(BufferedInner {
-                source: self,
-                buffer: Vec::new(),
-            })),
-            time: 0.0,
-        }
+        Buffered::from_parts(self, VecDeque::new())
+    }
+    /// Compare two processing chains fed by the source
+    ///
+    /// Each function builds a chain from a buffered copy of the source.
+    /// The returned [`AbControl`] switches between the chains without clicking,
+    /// and can match their loudness, which is useful for tuning effects by ear.
+    ///
+    /// # Example
+    /// ```
+    /// use hodaun::*;
+    ///
+    /// let (control, switch) = SineWave::new(220.0).ab_switch(
+    ///     |dry| dry.amplify(0.5),
+    ///     |wet| wet.low_pass(500.0),
+    /// );
+    /// control.level_match(true);
+    /// control.toggle();
+    /// assert_eq!(control.selected(), AbChoice::B);
+    /// ```
+    fn ab_switch<A, B>(
+        self,
+        a: impl FnOnce(Buffered<Self>) -> A,
+        b: impl FnOnce(Buffered<Self>) -> B,
+    ) -> (AbControl, AbSwitch<A, B>)
+    where
+        Self: Sized,
+        A: Source,
+        B: Source<Frame = A::Frame>,
+    {
+        // Both chains read the input in lockstep, so little needs to be retained
+        let input = self.buffer().max_retained(1.0);
+        AbSwitch::new(a(input.clone()), b(input))
+    }
+    /// Render the source into a buffer the first time it is played, and play the buffer after that
+    ///
+    /// Only the first `duration` of the source is kept.
+    /// Clones of the returned source share the rendering, so an expensive source
+    /// that is played many times, like a heavily processed loop, only costs memory after the first time.
+    ///
+    /// Rendering happens off the audio thread. See [`Frozen`] for details.
+    /// Use [`Frozen::invalidate_on`] to render the source again when a [`Shared`] parameter it uses changes.
+    fn freeze(self, duration: impl ToDuration) -> Frozen<Self>
+    where
+        Self: Sized + Clone + Send + 'static,
+        Self::Frame: Sync,
+    {
+        Frozen::new(self, duration.to_duration().as_secs_f64())
+    }
+    /// Borrow the source rather than consuming it
+    ///
+    /// This allows a source to be partially consumed by an adapter
+    /// and then used again afterwards.
+    fn by_ref(&mut self) -> &mut Self
+    where
+        Self: Sized,
+    {
+        self
     }
     /// Unroll the source so that its samples are flat
     fn unroll(self, sample_rate: f64) -> Unroll<Self>
@@ -268,49 +829,368 @@ pub trait Source {
             sample_rate,
         }
     }
+    /// Consume the source, returning the number of frames it produced
+    ///
+    /// The source must be finite.
+    fn count(self, sample_rate: f64) -> usize
+    where
+        Self: Sized,
+    {
+        self.fold_frames(sample_rate, 0, |count, _| count + 1)
+    }
+    /// Consume the source, returning the largest absolute amplitude of any channel
+    ///
+    /// The source must be finite.
+    fn peak(self, sample_rate: f64) -> f64
+    where
+        Self: Sized,
+    {
+        self.fold_frames(sample_rate, 0.0, |peak: f64, frame| {
+            (0..Self::Frame::CHANNELS).fold(peak, |peak, i| peak.max(frame.get_channel(i).abs()))
+        })
+    }
+    /// Consume the source, returning the root mean square amplitude of all of its channels
+    ///
+    /// The source must be finite.
+    fn rms(self, sample_rate: f64) -> f64
+    where
+        Self: Sized,
+    {
+        let (sum, count) = self.fold_frames(sample_rate, (0.0, 0), |(sum, count), frame| {
+            let sum = (0..Self::Frame::CHANNELS).fold(sum, |sum: f64, i| {
+                let a = frame.get_channel(i);
+                a.mul_add(a, sum)
+            });
+            (sum, count + Self::Frame::CHANNELS)
+        });
+        if count == 0 {
+            0.0
+        } else {
+            (sum / count as f64).sqrt()
+        }
+    }
+    /// Consume the source, folding every frame into an accumulator
+    ///
+    /// The source must be finite.
+    fn fold_frames<B>(
+        mut self,
+        sample_rate: f64,
+        init: B,
+        mut f: impl FnMut(B, Self::Frame) -> B,
+    ) -> B
+    where
+        Self: Sized,
+    {
+        let mut acc = init;
+        while let Some(frame) = self.next(sample_rate) {
+            acc = f(acc, frame);
+        }
+        acc
+    }
 }
 
-/// A source that produces no samples
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
-pub struct Empty<F>(PhantomData<F>);
-
-impl<F: Frame> Source for Empty<F> {
-    type Frame = F;
-    fn next(&mut self, _sample_rate: f64) -> Option<Self::Frame> {
-        None
-    }
+/// A [`Source`] whose duration is known ahead of time
+///
+/// This allows transitions between sources to be scheduled before
+/// a source actually ends.
+pub trait FiniteSource: Source {
+    /// Get the total duration of the source
+    fn total(&self, sample_rate: f64) -> Duration;
+    /// Get the duration of the source that has not yet been played
+    fn remaining(&self, sample_rate: f64) -> Duration;
 }
 
-pub(crate) type DynamicSource<F> = Box<dyn Source<Frame = F> + Send + 'static>;
+impl<S> Source for &mut S
+where
+    S: Source + ?Sized,
+{
+    type Frame = S::Frame;
+    #[inline(always)]
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        (**self).next(sample_rate)
+    }
+}
 
-/// A source that returns a constant value
-#[derive(Debug, Clone, Copy, PartialEq, PartialOrd)]
-pub struct Constant(pub f64);
+impl<S> FiniteSource for &mut S
+where
+    S: FiniteSource + ?Sized,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        (**self).total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        (**self).remaining(sample_rate)
+    }
+}
 
-impl Source for Constant {
-    type Frame = f64;
+/// A [`Source`] whose playback state can be saved and restored
+///
+/// The state is the position of the source, like the phase of a [`Wave`](crate::Wave)
+/// or how far into a [`Take`] it has played, not its settings.
+/// Restoring a snapshot into a source chain built the same way resumes playback exactly where it was,
+/// which is useful for saving a project and picking it up later.
+///
+/// Combinators implement this trait when the sources they wrap do.
+pub trait StatefulSource: Source {
+    /// Write the playback state to a snapshot
+    fn save_state(&self, state: &mut SourceState);
+    /// Read the playback state from a snapshot
+    ///
+    /// Values are read in the order they were written by [`StatefulSource::save_state`].
+    fn restore_state(&mut self, state: &mut StateReader);
+    /// Take a snapshot of the playback state
+    fn snapshot(&self) -> SourceState {
+        let mut state = SourceState::default();
+        self.save_state(&mut state);
+        state
+    }
+    /// Restore a snapshot of the playback state
+    ///
+    /// Returns `false` if the snapshot does not match the shape of the source chain,
+    /// in which case the source may be partially restored.
+    fn restore(&mut self, state: &SourceState) -> bool {
+        let mut reader = state.reader();
+        self.restore_state(&mut reader);
+        reader.is_complete()
+    }
+}
+
+/// A snapshot of the playback state of a [`StatefulSource`]
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SourceState {
+    values: Vec<f64>,
+}
+
+impl SourceState {
+    /// Append a value to the snapshot
+    pub fn push(&mut self, value: f64) {
+        self.values.push(value);
+    }
+    /// Append an optional value to the snapshot
+    ///
+    /// It can be read back with [`StateReader::read_option`].
+    pub fn push_option(&mut self, value: Option<f64>) {
+        self.push(value.is_some() as u8 as f64);
+        self.push(value.unwrap_or(0.0));
+    }
+    /// Get the values in the snapshot
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+    /// Get a reader for the snapshot's values
+    pub fn reader(&self) -> StateReader<'_> {
+        StateReader {
+            values: &self.values,
+            missing: false,
+        }
+    }
+}
+
+/// Reads values from a [`SourceState`] in order
+#[derive(Debug, Clone)]
+pub struct StateReader<'a> {
+    values: &'a [f64],
+    missing: bool,
+}
+
+impl StateReader<'_> {
+    /// Read the next value
+    ///
+    /// Returns [`None`] if there are no values left.
+    pub fn read(&mut self) -> Option<f64> {
+        if let Some((first, rest)) = self.values.split_first() {
+            self.values = rest;
+            Some(*first)
+        } else {
+            self.missing = true;
+            None
+        }
+    }
+    /// Read the next value into a place, leaving it unchanged if there are no values left
+    pub fn read_into(&mut self, place: &mut f64) {
+        if let Some(value) = self.read() {
+            *place = value;
+        }
+    }
+    /// Read a value written with [`SourceState::push_option`]
+    pub fn read_option(&mut self) -> Option<Option<f64>> {
+        let is_some = self.read()?;
+        let value = self.read()?;
+        Some((is_some != 0.0).then_some(value))
+    }
+    /// Check whether every value has been read and none were missing
+    pub fn is_complete(&self) -> bool {
+        !self.missing && self.values.is_empty()
+    }
+}
+
+impl<S> StatefulSource for &mut S
+where
+    S: StatefulSource + ?Sized,
+{
+    fn save_state(&self, state: &mut SourceState) {
+        (**self).save_state(state)
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        (**self).restore_state(state)
+    }
+}
+
+fn secs(secs: f64) -> Duration {
+    Duration::from_secs_f64(secs.max(0.0))
+}
+
+/// Automation that follows an [`AutomationEnd`] behavior when it ends
+#[derive(Debug, Clone, Copy)]
+struct EndingAutomation<A, F> {
+    automation: A,
+    last: Option<F>,
+    end: AutomationEnd,
+    ended: bool,
+}
+
+impl<A, F> EndingAutomation<A, F> {
+    fn new(automation: A) -> Self {
+        EndingAutomation {
+            automation,
+            last: None,
+            end: AutomationEnd::default(),
+            ended: false,
+        }
+    }
+    fn end(self, end: AutomationEnd) -> Self {
+        EndingAutomation { end, ..self }
+    }
+    fn next(&mut self, sample_rate: f64) -> Option<F>
+    where
+        A: FrameAutomation<F>,
+        F: Frame,
+    {
+        if !self.ended {
+            if let Some(frame) = self.automation.next_frame(sample_rate) {
+                self.last = Some(frame.clone());
+                return Some(frame);
+            }
+            self.ended = true;
+        }
+        match self.end {
+            AutomationEnd::Hold => self.last.clone(),
+            AutomationEnd::Value(value) => Some(F::uniform(value)),
+            AutomationEnd::End => None,
+        }
+    }
+}
+
+/// A source that produces no samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Empty<F>(PhantomData<F>);
+
+impl<F: Frame> Source for Empty<F> {
+    type Frame = F;
+    fn next(&mut self, _sample_rate: f64) -> Option<Self::Frame> {
+        None
+    }
+}
+
+impl<F: Frame> FiniteSource for Empty<F> {
+    fn total(&self, _sample_rate: f64) -> Duration {
+        Duration::ZERO
+    }
+    fn remaining(&self, _sample_rate: f64) -> Duration {
+        Duration::ZERO
+    }
+}
+
+pub(crate) type DynamicSource<F> = Box<dyn Source<Frame = F> + Send + 'static>;
+
+/// A source that returns a constant frame
+///
+/// Any [`Frame`] can be used, so this also works for stereo test signals and DC offsets.
+#[derive(Debug, Clone, Copy, PartialEq, PartialOrd, Default)]
+pub struct Constant<F = f64>(pub F);
+
+impl<F: Frame> Constant<F> {
+    /// Create a new constant source
+    pub fn new(frame: F) -> Self {
+        Constant(frame)
+    }
+}
+
+impl<F: Frame> Source for Constant<F> {
+    type Frame = F;
     fn next(&mut self, _sample_rate: f64) -> Option<Self::Frame> {
-        Some(self.0)
+        Some(self.0.clone())
     }
 }
 
+impl<F: Frame> StatefulSource for Constant<F> {
+    fn save_state(&self, _state: &mut SourceState) {}
+    fn restore_state(&mut self, _state: &mut StateReader) {}
+}
+
 /// Source returned from [`Source::amplify`]
 #[derive(Debug, Clone, Copy)]
-pub struct Amplify<S, A> {
+pub struct Amplify<S, A>
+where
+    S: Source,
+{
     source: S,
-    amp: A,
+    amp: EndingAutomation<A, S::Frame>,
+}
+
+impl<S, A> Amplify<S, A>
+where
+    S: Source,
+{
+    /// Set what happens when the amplitude automation ends
+    ///
+    /// The default is [`AutomationEnd::Hold`]
+    pub fn on_automation_end(self, end: AutomationEnd) -> Self {
+        Amplify {
+            amp: self.amp.end(end),
+            ..self
+        }
+    }
 }
 
 impl<S, A> Source for Amplify<S, A>
 where
     S: Source,
-    A: Automation,
+    A: FrameAutomation<S::Frame>,
 {
     type Frame = S::Frame;
     fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
-        let frame = self.source.next(sample_rate)?;
-        let amp = self.amp.next_value(sample_rate)?;
-        Some(frame.map(|a| a * amp))
+        let mut frame = self.source.next(sample_rate)?;
+        let amp = self.amp.next(sample_rate)?;
+        frame.merge(amp, |a, b| a * b);
+        Some(frame)
+    }
+}
+
+impl<S, A> FiniteSource for Amplify<S, A>
+where
+    S: FiniteSource,
+    A: FrameAutomation<S::Frame>,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+impl<S, A> StatefulSource for Amplify<S, A>
+where
+    S: StatefulSource,
+    A: FrameAutomation<S::Frame>,
+{
+    fn save_state(&self, state: &mut SourceState) {
+        self.source.save_state(state);
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        self.source.restore_state(state);
     }
 }
 
@@ -320,7 +1200,19 @@ pub struct Take<S, R> {
     source: S,
     duration: f64,
     elapsed: f64,
-    release: R,
+    release: EndingAutomation<R, f64>,
+}
+
+impl<S, R> Take<S, R> {
+    /// Set what happens when the release automation ends
+    ///
+    /// The default is [`AutomationEnd::Hold`]
+    pub fn on_automation_end(self, end: AutomationEnd) -> Self {
+        Take {
+            release: self.release.end(end),
+            ..self
+        }
+    }
 }
 
 impl<S, R> Source for Take<S, R>
@@ -334,7 +1226,7 @@ where
             return None;
         }
         let frame = self.source.next(sample_rate)?;
-        let release = self.release.next_value(sample_rate)?;
+        let release = self.release.next(sample_rate)?;
         let amp = if release == 0.0 {
             1.0
         } else {
@@ -346,13 +1238,97 @@ where
     }
 }
 
+impl<S, R> FiniteSource for Take<S, R>
+where
+    S: Source,
+    R: Automation,
+{
+    fn total(&self, _sample_rate: f64) -> Duration {
+        secs(self.duration)
+    }
+    fn remaining(&self, _sample_rate: f64) -> Duration {
+        secs(self.duration - self.elapsed)
+    }
+}
+
+impl<S, R> StatefulSource for Take<S, R>
+where
+    S: StatefulSource,
+    R: Automation,
+{
+    fn save_state(&self, state: &mut SourceState) {
+        state.push(self.elapsed);
+        self.source.save_state(state);
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        state.read_into(&mut self.elapsed);
+        self.source.restore_state(state);
+    }
+}
+
+/// Source returned from [`Source::declick`]
+#[derive(Debug, Clone)]
+pub struct Declick<S: Source> {
+    source: S,
+    fade: f64,
+    time: f64,
+    last: Option<S::Frame>,
+    release: Option<f64>,
+}
+
+impl<S> Source for Declick<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        if self.release.is_none() {
+            if let Some(frame) = self.source.next(sample_rate) {
+                let amp = if self.time < self.fade {
+                    self.time / self.fade
+                } else {
+                    1.0
+                };
+                self.time += 1.0 / sample_rate;
+                let frame = frame.map(|a| a * amp);
+                self.last = Some(frame.clone());
+                return Some(frame);
+            }
+            self.release = Some(0.0);
+        }
+        let release = self.release.as_mut()?;
+        if *release >= self.fade {
+            return None;
+        }
+        let amp = 1.0 - *release / self.fade;
+        *release += 1.0 / sample_rate;
+        self.last.clone().map(|frame| frame.map(|a| a * amp))
+    }
+}
+
+impl<S> FiniteSource for Declick<S>
+where
+    S: FiniteSource,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate) + secs(self.fade)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        match self.release {
+            Some(release) => secs(self.fade - release),
+            None => self.source.remaining(sample_rate) + secs(self.fade),
+        }
+    }
+}
+
 /// Source return from [`Source::chain`]
 #[derive(Debug, Clone, Copy)]
 pub struct Chain<A, B> {
     a: A,
     b: B,
-    b_start: Option<f64>,
-    time: f64,
+    gap: f64,
+    /// How much of the gap has elapsed, or [`None`] if the first source is still playing
+    gap_elapsed: Option<f64>,
 }
 
 impl<A, B> Source for Chain<A, B>
@@ -362,24 +1338,135 @@ where
 {
     type Frame = A::Frame;
     fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
-        let frame = if let Some(a) = self.a.next(sample_rate) {
-            match self.b_start {
-                Some(b_start) if self.time >= b_start => {
-                    let b = self
-                        .b
-                        .next(sample_rate)
-                        .unwrap_or_else(|| Self::Frame::uniform(0.0));
-                    a.add(b)
+        let gap_elapsed = match &mut self.gap_elapsed {
+            Some(elapsed) => elapsed,
+            None => {
+                if let Some(a) = self.a.next(sample_rate) {
+                    return Some(a);
                 }
-                _ => a,
+                self.gap_elapsed.insert(0.0)
             }
-        } else if let Some(b) = self.b.next(sample_rate) {
-            b
+        };
+        if *gap_elapsed < self.gap {
+            *gap_elapsed += 1.0 / sample_rate;
+            return Some(Self::Frame::uniform(0.0));
+        }
+        self.b.next(sample_rate)
+    }
+}
+
+impl<A, B> FiniteSource for Chain<A, B>
+where
+    A: FiniteSource,
+    B: FiniteSource<Frame = A::Frame>,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.a.total(sample_rate) + secs(self.gap) + self.b.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        let gap_left = self.gap - self.gap_elapsed.unwrap_or(0.0);
+        self.a.remaining(sample_rate) + secs(gap_left) + self.b.remaining(sample_rate)
+    }
+}
+
+impl<A, B> StatefulSource for Chain<A, B>
+where
+    A: StatefulSource,
+    B: StatefulSource<Frame = A::Frame>,
+{
+    fn save_state(&self, state: &mut SourceState) {
+        state.push_option(self.gap_elapsed);
+        self.a.save_state(state);
+        self.b.save_state(state);
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        if let Some(gap_elapsed) = state.read_option() {
+            self.gap_elapsed = gap_elapsed;
+        }
+        self.a.restore_state(state);
+        self.b.restore_state(state);
+    }
+}
+
+/// Source returned from [`Source::chain_overlap`]
+#[derive(Debug, Clone, Copy)]
+pub struct ChainOverlap<A, B> {
+    a: A,
+    b: B,
+    overlap: f64,
+    crossfade: bool,
+    a_done: bool,
+    b_started: bool,
+}
+
+impl<A, B> ChainOverlap<A, B> {
+    /// Fade the first source out and the next source in while they overlap
+    ///
+    /// The fade is scheduled from the [`FiniteSource::remaining`] duration of the first source.
+    pub fn crossfade(self) -> Self {
+        ChainOverlap {
+            crossfade: true,
+            ..self
+        }
+    }
+}
+
+impl<A, B> Source for ChainOverlap<A, B>
+where
+    A: FiniteSource,
+    B: Source<Frame = A::Frame>,
+{
+    type Frame = A::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let remaining = self.a.remaining(sample_rate).as_secs_f64();
+        if !self.b_started {
+            self.b_started = self.a_done || remaining <= self.overlap;
+        }
+        let a = if self.a_done {
+            None
         } else {
-            return None;
+            self.a.next(sample_rate)
         };
-        self.time += 1.0 / sample_rate;
-        Some(frame)
+        self.a_done = a.is_none();
+        let b = if self.b_started {
+            self.b.next(sample_rate)
+        } else {
+            None
+        };
+        match (a, b) {
+            (Some(a), Some(b)) if self.crossfade && self.overlap > 0.0 => {
+                let t = (remaining / self.overlap).min(1.0);
+                Some(a.map(|a| a * t).add(b.map(|b| b * (1.0 - t))))
+            }
+            (Some(a), Some(b)) => Some(a.add(b)),
+            (Some(frame), None) => Some(frame),
+            (None, b) if self.b_started => b,
+            (None, _) => {
+                self.b_started = true;
+                self.b.next(sample_rate)
+            }
+        }
+    }
+}
+
+impl<A, B> FiniteSource for ChainOverlap<A, B>
+where
+    A: FiniteSource,
+    B: FiniteSource<Frame = A::Frame>,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        let a = self.a.total(sample_rate);
+        let b = self.b.total(sample_rate);
+        (a + b).saturating_sub(secs(self.overlap).min(a))
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        let a = self.a.remaining(sample_rate);
+        let b = self.b.remaining(sample_rate);
+        if self.b_started {
+            a.max(b)
+        } else {
+            (a + b).saturating_sub(secs(self.overlap).min(a))
+        }
     }
 }
 
@@ -391,18 +1478,33 @@ where
 {
     source: S,
     acc: Option<S::Frame>,
-    freq: F,
+    freq: EndingAutomation<F, f64>,
+}
+
+impl<S, F> LowPass<S, F>
+where
+    S: Source,
+{
+    /// Set what happens when the frequency automation ends
+    ///
+    /// The default is [`AutomationEnd::Hold`]
+    pub fn on_automation_end(self, end: AutomationEnd) -> Self {
+        LowPass {
+            freq: self.freq.end(end),
+            ..self
+        }
+    }
 }
 
 impl<S, F> Source for LowPass<S, F>
 where
     S: Source,
     F: Automation,
-    S::Frame: std::fmt::Debug,
+    S::Frame: core::fmt::Debug,
 {
     type Frame = S::Frame;
     fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
-        let freq = self.freq.next_value(sample_rate)?;
+        let freq = self.freq.next(sample_rate)?;
         let frame = self.source.next(sample_rate)?;
         Some(if let Some(acc) = &mut self.acc {
             let t = (freq / sample_rate).min(1.0);
@@ -415,35 +1517,234 @@ where
     }
 }
 
-/// Source returned from [`Source::map`]
-#[derive(Debug, Clone, Copy)]
-pub struct Map<S, F> {
-    source: S,
-    f: F,
+impl<S, F> FiniteSource for LowPass<S, F>
+where
+    S: FiniteSource,
+    F: Automation,
+    S::Frame: core::fmt::Debug,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
 }
 
-impl<S, F, B> Source for Map<S, F>
+impl<S, F> StatefulSource for LowPass<S, F>
 where
-    S: Source,
-    F: Fn(S::Frame) -> B,
-    B: Frame,
+    S: StatefulSource,
+    F: Automation,
+    S::Frame: core::fmt::Debug,
 {
-    type Frame = B;
-    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
-        self.source.next(sample_rate).map(&self.f)
+    fn save_state(&self, state: &mut SourceState) {
+        for i in 0..S::Frame::CHANNELS {
+            state.push_option(self.acc.as_ref().map(|acc| acc.get_channel(i)));
+        }
+        self.source.save_state(state);
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        for i in 0..S::Frame::CHANNELS {
+            match state.read_option() {
+                Some(Some(amp)) => self
+                    .acc
+                    .get_or_insert_with(|| S::Frame::uniform(0.0))
+                    .set_channel(i, amp),
+                Some(None) => self.acc = None,
+                None => {}
+            }
+        }
+        self.source.restore_state(state);
     }
 }
 
-/// Source returned from [`Source::zip`]
-#[derive(Debug, Clone, Copy)]
-pub struct Zip<A, B, F>
+/// Source returned from [`Source::notch`]
+#[derive(Debug, Clone)]
+pub struct Notch<S, F, Q>
 where
-    A: Source,
-    B: Source,
+    S: Source,
+{
+    source: S,
+    freq: EndingAutomation<F, f64>,
+    q: EndingAutomation<Q, f64>,
+    /// The frequency, q, and sample rate the filter was designed for
+    params: Option<(f64, f64, f64)>,
+    filter: Biquad,
+    state: BiquadState<S::Frame>,
+}
+
+impl<S, F, Q> Notch<S, F, Q>
+where
+    S: Source,
+{
+    /// Set what happens when the frequency or q automation ends
+    ///
+    /// The default is [`AutomationEnd::Hold`]
+    pub fn on_automation_end(self, end: AutomationEnd) -> Self {
+        Notch {
+            freq: self.freq.end(end),
+            q: self.q.end(end),
+            ..self
+        }
+    }
+}
+
+impl<S, F, Q> Source for Notch<S, F, Q>
+where
+    S: Source,
+    F: Automation,
+    Q: Automation,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let freq = self.freq.next(sample_rate)?;
+        let q = self.q.next(sample_rate)?;
+        let frame = self.source.next(sample_rate)?;
+        let params = (freq, q, sample_rate);
+        if self.params != Some(params) {
+            self.filter = Biquad::notch(freq, q, sample_rate);
+            self.params = Some(params);
+        }
+        Some(self.filter.process(&mut self.state, frame))
+    }
+}
+
+impl<S, F, Q> FiniteSource for Notch<S, F, Q>
+where
+    S: FiniteSource,
+    F: Automation,
+    Q: Automation,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+impl<S, F, Q> StatefulSource for Notch<S, F, Q>
+where
+    S: StatefulSource,
+    F: Automation,
+    Q: Automation,
+{
+    fn save_state(&self, state: &mut SourceState) {
+        self.state.save(state);
+        self.source.save_state(state);
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        self.state.restore(state);
+        self.source.restore_state(state);
+    }
+}
+
+/// Source returned from [`Source::map`]
+#[derive(Debug, Clone, Copy)]
+pub struct Map<S, F> {
+    source: S,
+    f: F,
+}
+
+impl<S, F, B> Source for Map<S, F>
+where
+    S: Source,
+    F: Fn(S::Frame) -> B,
+    B: Frame,
+{
+    type Frame = B;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        self.source.next(sample_rate).map(&self.f)
+    }
+}
+
+impl<S, F, B> FiniteSource for Map<S, F>
+where
+    S: FiniteSource,
+    F: Fn(S::Frame) -> B,
+    B: Frame,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+impl<S, F, B> StatefulSource for Map<S, F>
+where
+    S: StatefulSource,
+    F: Fn(S::Frame) -> B,
+    B: Frame,
+{
+    fn save_state(&self, state: &mut SourceState) {
+        self.source.save_state(state);
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        self.source.restore_state(state);
+    }
+}
+
+/// Source returned from [`Source::scan`]
+#[derive(Debug, Clone, Copy)]
+pub struct Scan<S, St, F> {
+    source: S,
+    state: St,
+    f: F,
+}
+
+impl<S, St, F, B> Source for Scan<S, St, F>
+where
+    S: Source,
+    F: FnMut(&mut St, S::Frame, f64) -> Option<B>,
+    B: Frame,
+{
+    type Frame = B;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let frame = self.source.next(sample_rate)?;
+        (self.f)(&mut self.state, frame, sample_rate)
+    }
+}
+
+/// Source returned from [`Source::zip`]
+#[derive(Debug, Clone, Copy)]
+pub struct Zip<A, B, F>
+where
+    A: Source,
+    B: Source,
 {
     a: A,
     b: B,
     f: F,
+    length: ZipLength,
+    a_done: bool,
+    b_done: bool,
+}
+
+/// When a [`Zip`] ends
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum ZipLength {
+    /// End when either source ends
+    #[default]
+    Shortest,
+    /// End when both sources have ended
+    ///
+    /// The source that ends first is padded with silence.
+    Longest,
+}
+
+impl<A, B, F> Zip<A, B, F>
+where
+    A: Source,
+    B: Source,
+{
+    /// Set when the combined source ends
+    ///
+    /// The default is [`ZipLength::Shortest`]
+    pub fn length(self, length: ZipLength) -> Self {
+        Zip { length, ..self }
+    }
 }
 
 impl<A, B, F, C> Source for Zip<A, B, F>
@@ -455,10 +1756,77 @@ where
 {
     type Frame = C;
     fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
-        Some((self.f)(
-            self.a.next(sample_rate)?,
-            self.b.next(sample_rate)?,
-        ))
+        let a = if self.a_done {
+            None
+        } else {
+            self.a.next(sample_rate)
+        };
+        self.a_done = a.is_none();
+        if self.a_done && self.length == ZipLength::Shortest {
+            return None;
+        }
+        let b = if self.b_done {
+            None
+        } else {
+            self.b.next(sample_rate)
+        };
+        self.b_done = b.is_none();
+        match (a, b, self.length) {
+            (Some(a), Some(b), _) => Some((self.f)(a, b)),
+            (None, None, _) | (_, _, ZipLength::Shortest) => None,
+            (a, b, ZipLength::Longest) => Some((self.f)(
+                a.unwrap_or_else(|| A::Frame::uniform(0.0)),
+                b.unwrap_or_else(|| B::Frame::uniform(0.0)),
+            )),
+        }
+    }
+}
+
+impl<A, B, F, C> FiniteSource for Zip<A, B, F>
+where
+    A: FiniteSource,
+    B: FiniteSource,
+    F: Fn(A::Frame, B::Frame) -> C,
+    C: Frame,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        let (a, b) = (self.a.total(sample_rate), self.b.total(sample_rate));
+        match self.length {
+            ZipLength::Shortest => a.min(b),
+            ZipLength::Longest => a.max(b),
+        }
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        let (a, b) = (self.a.remaining(sample_rate), self.b.remaining(sample_rate));
+        match self.length {
+            ZipLength::Shortest => a.min(b),
+            ZipLength::Longest => a.max(b),
+        }
+    }
+}
+
+impl<A, B, F, C> StatefulSource for Zip<A, B, F>
+where
+    A: StatefulSource,
+    B: StatefulSource,
+    F: Fn(A::Frame, B::Frame) -> C,
+    C: Frame,
+{
+    fn save_state(&self, state: &mut SourceState) {
+        state.push(self.a_done as u8 as f64);
+        state.push(self.b_done as u8 as f64);
+        self.a.save_state(state);
+        self.b.save_state(state);
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        if let Some(a_done) = state.read() {
+            self.a_done = a_done != 0.0;
+        }
+        if let Some(b_done) = state.read() {
+            self.b_done = b_done != 0.0;
+        }
+        self.a.restore_state(state);
+        self.b.restore_state(state);
     }
 }
 
@@ -483,132 +1851,754 @@ where
     }
 }
 
-/// Source returned from [`Source::positive`]
-#[derive(Debug, Clone, Copy)]
-pub struct Positive<S> {
-    source: S,
+impl<S, P> FiniteSource for Pan<S, P>
+where
+    S: FiniteSource,
+    P: Automation,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
 }
 
-impl<S> Source for Positive<S>
+impl<S, P> StatefulSource for Pan<S, P>
 where
-    S: Source,
+    S: StatefulSource,
+    P: Automation,
 {
-    type Frame = S::Frame;
-    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
-        self.source
-            .next(sample_rate)
-            .map(|frame| frame.map(|a| (a + 1.0) / 2.0))
+    fn save_state(&self, state: &mut SourceState) {
+        self.source.save_state(state);
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        self.source.restore_state(state);
     }
 }
 
-/// Source returned from [`Source::repeat`]
+/// Source returned from [`Source::haas`]
 #[derive(Debug, Clone)]
-pub struct Repeat<S, P> {
+pub struct Haas<S, D> {
     source: S,
-    count_left: Option<usize>,
-    curr: Vec<S>,
-    period: Option<P>,
-    time: f64,
-    started: bool,
+    delay_ms: D,
+    side: Side,
+    line: DelayLine<f64>,
 }
 
-impl<S, P> Repeat<S, P> {
-    /// Repeat every `period` seconds
-    pub fn every<Q>(self, period: Q) -> Repeat<S, Q>
-    where
-        Self: Sized,
-        Q: Automation,
-    {
-        Repeat {
-            source: self.source,
-            count_left: self.count_left,
-            curr: self.curr,
-            period: Some(period),
-            time: self.time,
-            started: self.started,
-        }
+/// The default maximum delay of a [`Haas`] in seconds
+const DEFAULT_MAX_HAAS: f64 = 0.05;
+
+impl<S, D> Haas<S, D> {
+    /// Set the longest delay the effect can apply
+    ///
+    /// The delay history is allocated for this length.
+    ///
+    /// Default is 50 milliseconds
+    pub fn max_delay(mut self, max: impl ToDuration) -> Self {
+        self.line.set_max_delay(max.to_duration().as_secs_f64());
+        self
+    }
+    /// Allocate the delay history for the sample rate the source will be played at
+    ///
+    /// This allocates, so it is done here rather than while playing.
+    /// The history is allocated for 48 kHz by default.
+    /// At higher sample rates, the longest delay is shortened to what it can hold.
+    pub fn sample_rate(mut self, sample_rate: f64) -> Self {
+        self.line.set_sample_rate(sample_rate);
+        self
     }
 }
 
-impl<S, P> Source for Repeat<S, P>
+impl<S, D> Source for Haas<S, D>
 where
-    S: Source + Clone,
-    P: Automation,
+    S: Source,
+    D: Automation,
 {
-    type Frame = S::Frame;
+    type Frame = Stereo;
     fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
-        let count_left = &mut self.count_left;
-        let mut add_new = || match count_left {
-            Some(count_left) => {
-                if *count_left > 0 {
-                    *count_left -= 1;
-                    true
-                } else {
-                    false
-                }
-            }
-            None => true,
+        let delay = self.delay_ms.next_value(sample_rate)? * 0.001 * sample_rate;
+        let frame = self.source.next(sample_rate)?;
+        let mut channels = [0.0; 2];
+        frame.write_slice(&mut channels);
+        let mut frame = Stereo::new(channels[0], channels[1]);
+        let delayed = match self.side {
+            Side::Left => &mut frame.left,
+            Side::Right => &mut frame.right,
         };
-        if let Some(period) = &mut self.period {
-            let period = period.next_value(sample_rate)?;
-            if self.time >= period {
-                if add_new() {
-                    self.curr.push(self.source.clone());
-                    self.time = 0.0;
-                } else {
-                    return None;
-                }
-            }
-        }
-        if self.curr.is_empty() && (self.period.is_none() || !self.started) {
-            if add_new() {
-                self.curr.push(self.source.clone());
-                self.time = 0.0;
-            } else {
-                return None;
-            }
-        }
-        let mut frame = Self::Frame::uniform(0.0);
-        self.curr.retain_mut(|source| {
-            if let Some(next) = source.next(sample_rate) {
-                frame.merge(next, Frame::add);
-                true
-            } else {
-                false
-            }
-        });
-        self.time += 1.0 / sample_rate;
-        self.started = true;
+        self.line.push(*delayed);
+        *delayed = self.line.read(delay);
         Some(frame)
     }
 }
 
-/// Source returned from [`Source::no_repeat`]
+impl<S, D> FiniteSource for Haas<S, D>
+where
+    S: FiniteSource,
+    D: Automation,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+/// Source returned from [`Source::delay`]
+///
+/// Its [`FiniteSource`] duration includes the echoes after the source ends.
+/// They are estimated from the current time and feedback, as if the source ends at full scale.
 #[derive(Debug, Clone)]
-pub struct NoRepeat<S> {
-    source: Arc<Mutex<S>>,
+pub struct Delay<S: Source, T, B, M> {
+    /// The source, or [`None`] once it has ended
+    source: Option<S>,
+    time: T,
+    feedback: B,
+    mix: M,
+    line: DelayLine<S::Frame>,
+    /// How many frames the echoes have been silent since the source ended
+    silent: usize,
+    /// How many frames have been played
+    played: u64,
+    /// How many frames had been played when the source ended
+    ended_at: Option<u64>,
 }
 
-impl<S> Source for NoRepeat<S>
+/// The amplitude below which a [`Delay`]'s echoes are considered to have died out
+const ECHO_SILENCE: f64 = 1e-4;
+/// The default maximum time of a [`Delay`] in seconds
+const DEFAULT_MAX_DELAY: f64 = 2.0;
+
+impl<S: Source, T, B, M> Delay<S, T, B, M> {
+    /// Set the longest time between echoes
+    ///
+    /// The echo history is allocated for this length.
+    ///
+    /// Default is 2 seconds
+    pub fn max_time(mut self, max: impl ToDuration) -> Self {
+        self.line.set_max_delay(max.to_duration().as_secs_f64());
+        self
+    }
+    /// Allocate the echo history for the sample rate the delay will be played at
+    ///
+    /// This allocates, so it is done here rather than while playing.
+    /// The history is allocated for 48 kHz by default.
+    /// At higher sample rates, the longest time is shortened to what it can hold.
+    pub fn sample_rate(mut self, sample_rate: f64) -> Self {
+        self.line.set_sample_rate(sample_rate);
+        self
+    }
+}
+
+impl<S, T, B, M> Delay<S, T, B, M>
+where
+    S: Source,
+    T: Automation + Clone,
+    B: Automation + Clone,
+{
+    /// Estimate how long the echoes last after the source ends, if it ends at full scale
+    fn tail(&self, sample_rate: f64) -> Duration {
+        let Some(time) = self.time.clone().next_value(sample_rate) else {
+            return Duration::ZERO;
+        };
+        let max = self.line.max_frames(sample_rate) + 1.0;
+        let delay = (time * sample_rate).clamp(1.0, max);
+        let feedback = (self.feedback.clone().next_value(sample_rate)).map_or(0.0, f64::abs);
+        if feedback >= 1.0 {
+            return Duration::MAX;
+        }
+        // Each echo is quieter by the feedback until one is silent,
+        // and then the line must be silent for a whole delay
+        let echoes = if feedback > 0.0 {
+            (ECHO_SILENCE.ln() / feedback.ln()).ceil()
+        } else {
+            0.0
+        };
+        secs((echoes + 2.0) * delay / sample_rate)
+    }
+}
+
+impl<S, T, B, M> Source for Delay<S, T, B, M>
 where
     S: Source,
+    T: Automation,
+    B: Automation,
+    M: Automation,
 {
     type Frame = S::Frame;
     fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
-        self.source.lock().next(sample_rate)
+        let max = self.line.max_frames(sample_rate) + 1.0;
+        let delay = (self.time.next_value(sample_rate)? * sample_rate).clamp(1.0, max);
+        let feedback = self.feedback.next_value(sample_rate)?;
+        let mix = self.mix.next_value(sample_rate)?;
+        let dry = match &mut self.source {
+            Some(source) => source.next(sample_rate),
+            None => None,
+        };
+        let ended = dry.is_none();
+        if ended && self.source.take().is_some() {
+            self.ended_at = Some(self.played);
+        }
+        // The most recent frame in the line is from one frame ago
+        let wet = self.line.read(delay - 1.0);
+        let dry = dry.unwrap_or_else(|| S::Frame::uniform(0.0));
+        if ended {
+            let loud = (0..S::Frame::CHANNELS).any(|i| wet.get_channel(i).abs() > ECHO_SILENCE);
+            self.silent = if loud { 0 } else { self.silent + 1 };
+            // The line must be silent for a whole delay before nothing is left to echo
+            if self.silent > delay as usize {
+                return None;
+            }
+        }
+        let mut fed = wet.clone();
+        fed.merge(dry.clone(), |w, d| d + w * feedback);
+        self.line.push(fed);
+        self.played += 1;
+        let mut frame = dry;
+        frame.merge(wet, |d, w| lerp(d, w, mix));
+        Some(frame)
     }
 }
 
-/// Used to coordinate the dropping of [`Source`]s
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
-pub struct Maintainer<R = f64> {
-    arc: Arc<()>,
-    release_dur: R,
+impl<S, T, B, M> FiniteSource for Delay<S, T, B, M>
+where
+    S: FiniteSource,
+    T: Automation + Clone,
+    B: Automation + Clone,
+    M: Automation,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        let played = secs(self.played as f64 / sample_rate);
+        played.saturating_add(self.remaining(sample_rate))
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        let tail = self.tail(sample_rate);
+        match (&self.source, self.ended_at) {
+            (Some(source), _) => source.remaining(sample_rate).saturating_add(tail),
+            (None, Some(ended_at)) => {
+                tail.saturating_sub(secs((self.played - ended_at) as f64 / sample_rate))
+            }
+            (None, None) => tail,
+        }
+    }
 }
 
-impl<R> Maintainer<R>
+impl<S, T, B, M> StatefulSource for Delay<S, T, B, M>
 where
-    R: Default,
+    S: StatefulSource,
+    T: Automation,
+    B: Automation,
+    M: Automation,
+{
+    fn save_state(&self, state: &mut SourceState) {
+        self.line.save(state);
+        state.push(self.silent as f64);
+        state.push(self.played as f64);
+        state.push_option(self.ended_at.map(|frames| frames as f64));
+        if let Some(source) = &self.source {
+            source.save_state(state);
+        }
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        self.line.restore(state);
+        if let Some(silent) = state.read() {
+            self.silent = silent as usize;
+        }
+        if let Some(played) = state.read() {
+            self.played = played as u64;
+        }
+        if let Some(ended_at) = state.read_option() {
+            self.ended_at = ended_at.map(|frames| frames as u64);
+        }
+        if self.ended_at.is_some() {
+            self.source = None;
+        } else if let Some(source) = &mut self.source {
+            source.restore_state(state);
+        }
+    }
+}
+
+/// Source returned from [`Source::to_mono`]
+#[derive(Debug, Clone, Copy)]
+pub struct ToMono<S> {
+    source: S,
+}
+
+impl<S> Source for ToMono<S>
+where
+    S: Source,
+{
+    type Frame = f64;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        self.source.next(sample_rate).map(|frame| frame.avg())
+    }
+}
+
+impl<S> FiniteSource for ToMono<S>
+where
+    S: FiniteSource,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+impl<S> StatefulSource for ToMono<S>
+where
+    S: StatefulSource,
+{
+    fn save_state(&self, state: &mut SourceState) {
+        self.source.save_state(state);
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        self.source.restore_state(state);
+    }
+}
+
+/// Source returned from [`Source::to_stereo`]
+#[derive(Debug, Clone, Copy)]
+pub struct ToStereo<S> {
+    source: S,
+}
+
+impl<S> Source for ToStereo<S>
+where
+    S: Source,
+{
+    type Frame = Stereo;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let frame = self.source.next(sample_rate)?;
+        let mut channels = [0.0; 2];
+        frame.write_slice(&mut channels);
+        Some(Stereo::new(channels[0], channels[1]))
+    }
+}
+
+impl<S> FiniteSource for ToStereo<S>
+where
+    S: FiniteSource,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+impl<S> StatefulSource for ToStereo<S>
+where
+    S: StatefulSource,
+{
+    fn save_state(&self, state: &mut SourceState) {
+        self.source.save_state(state);
+    }
+    fn restore_state(&mut self, state: &mut StateReader) {
+        self.source.restore_state(state);
+    }
+}
+
+/// Source returned from [`Source::positive`]
+#[derive(Debug, Clone, Copy)]
+pub struct Positive<S> {
+    source: S,
+}
+
+impl<S> Source for Positive<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        self.source
+            .next(sample_rate)
+            .map(|frame| frame.map(|a| (a + 1.0) / 2.0))
+    }
+}
+
+impl<S> FiniteSource for Positive<S>
+where
+    S: FiniteSource,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+/// Source returned from [`Source::repeat`]
+#[derive(Debug, Clone)]
+pub struct Repeat<S, P> {
+    source: S,
+    count_left: Option<usize>,
+    curr: Vec<Repetition<S>>,
+    period: Option<P>,
+    gap: f64,
+    overlap: Overlap,
+    time: f64,
+    idle: f64,
+    started: bool,
+    pattern: Option<Pattern>,
+    step: usize,
+    swing: f64,
+    #[cfg(feature = "noise")]
+    humanize: Humanize,
+}
+
+#[cfg(feature = "noise")]
+#[derive(Debug, Clone)]
+struct Humanize {
+    rng: SmallRng,
+    chance: f64,
+    jitter: f64,
+    velocity: f64,
+}
+
+#[cfg(feature = "noise")]
+impl Default for Humanize {
+    fn default() -> Self {
+        Humanize {
+            rng: crate::gen::time_seeded_rng(),
+            chance: 1.0,
+            jitter: 0.0,
+            velocity: 0.0,
+        }
+    }
+}
+
+/// A repetition of a [`Repeat`]'s source
+///
+/// Finished repetitions are kept so that their slots can be reused instead of dropped while playing.
+#[derive(Debug, Clone)]
+struct Repetition<S> {
+    source: S,
+    delay: f64,
+    amp: f64,
+    /// The step that started the repetition
+    step: usize,
+    playing: bool,
+    /// Whether the delay has passed and the source has started
+    begun: bool,
+}
+
+impl<S: Source> Repetition<S> {
+    /// Mix the next frame of the repetition into a frame, updating whether it is still playing
+    fn mix(&mut self, frame: &mut S::Frame, sample_rate: f64) {
+        if self.delay > 0.0 {
+            self.delay -= 1.0 / sample_rate;
+        } else if let Some(next) = self.source.next(sample_rate) {
+            self.begun = true;
+            frame.merge(next, |a, b| a + b * self.amp);
+        } else {
+            self.playing = false;
+        }
+    }
+}
+
+/// How a [`Repeat`] handles a repetition that starts before the previous one has ended
+///
+/// This can only happen when using [`Repeat::every`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum Overlap {
+    /// Play overlapping repetitions at the same time, summing them
+    #[default]
+    Sum,
+    /// Stop any playing repetitions when a new one starts
+    ///
+    /// A repetition delayed by swing or jitter cuts the others when its delay ends.
+    Cut,
+}
+
+impl<S, P> Repeat<S, P> {
+    /// Repeat every `period` seconds
+    ///
+    /// If the source is longer than the period, overlapping repetitions
+    /// are summed. This can be changed with [`Repeat::overlap`].
+    ///
+    /// The period is automated, so it can follow a [`Tempo`](crate::Tempo)
+    /// using [`Tempo::beats`](crate::Tempo::beats) or [`Tempo::bars`](crate::Tempo::bars).
+    pub fn every<Q>(self, period: Q) -> Repeat<S, Q>
+    where
+        Self: Sized,
+        Q: Automation,
+    {
+        Repeat {
+            source: self.source,
+            count_left: self.count_left,
+            curr: self.curr,
+            period: Some(period),
+            gap: self.gap,
+            overlap: self.overlap,
+            time: self.time,
+            idle: self.idle,
+            started: self.started,
+            pattern: self.pattern,
+            step: self.step,
+            swing: self.swing,
+            #[cfg(feature = "noise")]
+            humanize: self.humanize,
+        }
+    }
+    /// Wait some duration after each repetition ends before starting the next one
+    ///
+    /// This has no effect when used with [`Repeat::every`].
+    pub fn gap(self, gap: impl ToDuration) -> Self {
+        Repeat {
+            gap: gap.to_duration().as_secs_f64(),
+            ..self
+        }
+    }
+    /// Only start repetitions on the hits of a pattern
+    ///
+    /// Each repetition is one step of the pattern, so this is usually used with [`Repeat::every`]
+    /// to play the pattern on a fixed grid. Rests still count towards the total number of repetitions.
+    pub fn pattern(self, pattern: Pattern) -> Self {
+        Repeat {
+            pattern: Some(pattern),
+            step: 0,
+            ..self
+        }
+    }
+    /// Delay every other repetition to give a swing feel
+    ///
+    /// `percent` is how far through each pair of periods the off-beat repetition starts.
+    /// `50.0` is straight timing, about `66.7` is a triplet feel, and the maximum is `75.0`.
+    /// This only has an effect when used with [`Repeat::every`].
+    pub fn swing(self, percent: f64) -> Self {
+        Repeat {
+            swing: percent.clamp(50.0, 75.0) / 100.0,
+            ..self
+        }
+    }
+    /// Set how repetitions that overlap are handled
+    pub fn overlap(self, overlap: Overlap) -> Self {
+        Repeat { overlap, ..self }
+    }
+    /// Get the number of repetitions that have yet to start
+    ///
+    /// Returns [`None`] if the source repeats indefinitely.
+    pub fn repeats_left(&self) -> Option<usize> {
+        self.count_left
+    }
+    /// Get the number of repetitions that are currently playing
+    pub fn playing(&self) -> usize {
+        self.curr.iter().filter(|rep| rep.playing).count()
+    }
+}
+
+#[cfg(feature = "noise")]
+#[cfg_attr(docsrs, doc(cfg(feature = "noise")))]
+impl<S, P> Repeat<S, P> {
+    /// Set the probability in the range [0, 1] that each repetition is played
+    ///
+    /// Repetitions that are not played still count towards the total number of repetitions.
+    pub fn chance(mut self, chance: f64) -> Self {
+        self.humanize.chance = chance.clamp(0.0, 1.0);
+        self
+    }
+    /// Delay the start of each repetition by a random amount up to the given duration
+    pub fn jitter(mut self, jitter: impl ToDuration) -> Self {
+        self.humanize.jitter = jitter.to_duration().as_secs_f64();
+        self
+    }
+    /// Reduce the amplitude of each repetition by a random amount up to the given fraction
+    ///
+    /// For example, a variation of `0.2` will play each repetition at between 80% and 100% amplitude.
+    pub fn velocity_variation(mut self, variation: f64) -> Self {
+        self.humanize.velocity = variation.clamp(0.0, 1.0);
+        self
+    }
+    /// Seed the random number generator used for [`Repeat::chance`],
+    /// [`Repeat::jitter`], and [`Repeat::velocity_variation`]
+    ///
+    /// Repeats with the same seed and settings will always produce the same output.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.humanize.rng = SmallRng::seed_from_u64(seed);
+        self
+    }
+}
+
+impl<S, P> Repeat<S, P>
+where
+    S: Clone,
+{
+    fn start_new(&mut self, period: f64) -> bool {
+        match &mut self.count_left {
+            Some(0) => return false,
+            Some(count_left) => *count_left -= 1,
+            None => {}
+        }
+        self.time = 0.0;
+        let step = self.step;
+        self.step += 1;
+        if let Some(pattern) = &self.pattern {
+            if !pattern.is_hit(step) {
+                return true;
+            }
+        }
+        let swing = if step % 2 == 1 {
+            (self.swing - 0.5) * 2.0 * period
+        } else {
+            0.0
+        };
+        #[cfg(feature = "noise")]
+        let (delay, amp) = {
+            let humanize = &mut self.humanize;
+            if humanize.chance < 1.0 && !humanize.rng.gen_bool(humanize.chance) {
+                return true;
+            }
+            (
+                humanize.jitter * humanize.rng.gen::<f64>(),
+                1.0 - humanize.velocity * humanize.rng.gen::<f64>(),
+            )
+        };
+        #[cfg(not(feature = "noise"))]
+        let (delay, amp) = (0.0, 1.0);
+        let delay = delay + swing;
+        // Reuse a finished repetition so that its source is not dropped while playing
+        if let Some(rep) = self.curr.iter_mut().find(|rep| !rep.playing) {
+            rep.source.clone_from(&self.source);
+            rep.delay = delay;
+            rep.amp = amp;
+            rep.step = step;
+            rep.playing = true;
+            rep.begun = false;
+        } else {
+            self.curr.push(Repetition {
+                source: self.source.clone(),
+                delay,
+                amp,
+                step,
+                playing: true,
+                begun: false,
+            });
+        }
+        self.idle = 0.0;
+        true
+    }
+}
+
+impl<S, P> Repeat<S, P>
+where
+    S: Source,
+{
+    fn is_playing(&self) -> bool {
+        self.curr.iter().any(|rep| rep.playing)
+    }
+    /// Mix the next frame of every playing repetition into a frame
+    fn mix(&mut self, frame: &mut S::Frame, sample_rate: f64) {
+        if self.overlap == Overlap::Cut {
+            // Cut older repetitions when a newer one begins
+            let beginning = (self.curr.iter())
+                .filter(|rep| rep.playing && !rep.begun && rep.delay <= 0.0)
+                .map(|rep| rep.step)
+                .max();
+            if let Some(step) = beginning {
+                for rep in &mut self.curr {
+                    if rep.step < step {
+                        rep.playing = false;
+                    }
+                }
+            }
+        }
+        for rep in &mut self.curr {
+            if rep.playing {
+                rep.mix(frame, sample_rate);
+            }
+        }
+    }
+}
+
+impl<S, P> Source for Repeat<S, P>
+where
+    S: Source + Clone,
+    P: Automation,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        // Whether the last repetition has outlasted the period
+        let mut overtime = false;
+        if let Some(period) = &mut self.period {
+            let period = period.next_value(sample_rate)?;
+            // The last repetition plays to its end, even if it is longer than the period
+            if self.time >= period && !self.start_new(period) {
+                if !self.is_playing() {
+                    return None;
+                }
+                overtime = true;
+            }
+        }
+        if !self.is_playing() && (self.period.is_none() || !self.started) {
+            if self.count_left == Some(0) {
+                return None;
+            }
+            if self.started && self.period.is_none() && self.idle < self.gap {
+                self.idle += 1.0 / sample_rate;
+                return Some(Self::Frame::uniform(0.0));
+            }
+            self.start_new(0.0);
+        }
+        let mut frame = Self::Frame::uniform(0.0);
+        let was_playing = self.is_playing();
+        self.mix(&mut frame, sample_rate);
+        // Start the next repetition on the frame the last one ended instead of a frame later
+        if was_playing
+            && !self.is_playing()
+            && self.period.is_none()
+            && self.gap <= 0.0
+            && self.start_new(0.0)
+        {
+            self.mix(&mut frame, sample_rate);
+        }
+        // End on the frame the last repetition ended instead of playing a silent frame
+        if was_playing
+            && !self.is_playing()
+            && (self.period.is_none() || overtime)
+            && self.count_left == Some(0)
+        {
+            return None;
+        }
+        if !self.is_playing() {
+            self.idle += 1.0 / sample_rate;
+        }
+        self.time += 1.0 / sample_rate;
+        self.started = true;
+        Some(frame)
+    }
+}
+
+/// Source returned from [`Source::no_repeat`]
+#[derive(Debug, Clone)]
+pub struct NoRepeat<S> {
+    source: Arc<Mutex<S>>,
+}
+
+impl<S> Source for NoRepeat<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        self.source.lock().next(sample_rate)
+    }
+}
+
+/// Used to coordinate the dropping of [`Source`]s
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Maintainer<R = f64> {
+    pub(crate) arc: Arc<()>,
+    release_dur: R,
+}
+
+impl<R> Maintainer<R>
+where
+    R: Default,
 {
     /// Create a new maintainer
     pub fn new() -> Self {
@@ -616,209 +2606,1125 @@ where
     }
 }
 
-impl<R> Maintainer<R>
+impl<R> Maintainer<R>
+where
+    R: Automation,
+{
+    /// Create a new maintainer with the given release duration
+    pub fn with_release(release_dur: R) -> Self {
+        Maintainer {
+            arc: Arc::new(()),
+            release_dur,
+        }
+    }
+}
+
+/// An attack-decay-sustain evenlope
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AdsEnvelope<A = f64, D = f64, S = f64> {
+    /// The time after the sound starts before it is at its maximum volume
+    pub attack: A,
+    /// The time between the maximum amplitude and the sustain amplitude
+    pub decay: D,
+    /// The sustain amplitude
+    pub sustain: S,
+}
+
+impl<A, D, S> Default for AdsEnvelope<A, D, S>
+where
+    A: From<f64>,
+    D: From<f64>,
+    S: From<f64>,
+{
+    fn default() -> Self {
+        AdsEnvelope {
+            attack: 0.0.into(),
+            decay: 0.0.into(),
+            sustain: 1.0.into(),
+        }
+    }
+}
+
+impl<A, D, S> AdsEnvelope<A, D, S>
+where
+    A: Automation,
+    D: Automation,
+    S: Automation,
+{
+    /// Create a new ADS envelope
+    pub fn new(attack: A, decay: D, sustain: S) -> Self {
+        Self {
+            attack,
+            decay,
+            sustain,
+        }
+    }
+}
+
+/// Source returned from [`Source::ads`]
+#[derive(Debug, Clone, Copy)]
+pub struct Ads<Src, A, D, S> {
+    source: Src,
+    time: f64,
+    envelope: AdsEnvelope<A, D, S>,
+}
+
+impl<Src, A, D, S> Source for Ads<Src, A, D, S>
+where
+    Src: Source,
+    A: Automation,
+    D: Automation,
+    S: Automation,
+{
+    type Frame = Src::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let frame = self.source.next(sample_rate)?;
+        let attack = self.envelope.attack.next_value(sample_rate)?;
+        let decay = self.envelope.decay.next_value(sample_rate)?;
+        let sustain = self.envelope.sustain.next_value(sample_rate)?;
+        let amp = if self.time < attack {
+            self.time / attack
+        } else {
+            let after_attack = self.time - attack;
+            if after_attack < decay {
+                (1.0 - after_attack / decay) * (1.0 - sustain) + sustain
+            } else {
+                sustain
+            }
+        };
+        self.time += 1.0 / sample_rate;
+        Some(frame.map(|s| s * amp))
+    }
+}
+
+/// Source returned from [`Source::maintained`]
+#[derive(Debug, Clone)]
+pub struct Maintained<S, R> {
+    source: S,
+    arc: Weak<()>,
+    release_dur: R,
+    release_curr: f64,
+}
+
+impl<S, R> Source for Maintained<S, R>
+where
+    S: Source,
+    R: Automation,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let release_dur = self.release_dur.next_value(sample_rate)?;
+        if Weak::strong_count(&self.arc) == 0 {
+            if self.release_curr < release_dur {
+                let amp = 1.0 - self.release_curr / release_dur;
+                let frame = self.source.next(sample_rate)?.map(|s| s * amp);
+                self.release_curr += 1.0 / sample_rate;
+                Some(frame)
+            } else {
+                None
+            }
+        } else {
+            self.source.next(sample_rate)
+        }
+    }
+}
+
+/// Source returned from [`Source::inspect_each`]
+#[derive(Debug, Clone, Copy)]
+pub struct InspectEach<S, F> {
+    source: S,
+    f: F,
+}
+
+impl<S, F> Source for InspectEach<S, F>
+where
+    S: Source,
+    F: FnMut(&S::Frame),
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let frame = self.source.next(sample_rate)?;
+        (self.f)(&frame);
+        Some(frame)
+    }
+}
+
+impl<S, F> FiniteSource for InspectEach<S, F>
+where
+    S: FiniteSource,
+    F: FnMut(&S::Frame),
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+/// A source that is being inspected by a [`SourceInspector`]
+#[derive(Debug, Clone)]
+pub struct InspectedSource<S: Source> {
+    source: S,
+    curr: Shared<Option<S::Frame>>,
+}
+
+/// Allows the inspection of a [`Source`]'s current frame
+#[derive(Debug, Clone)]
+pub struct SourceInspector<F> {
+    curr: Shared<Option<F>>,
+}
+
+impl<S: Source> Source for InspectedSource<S> {
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let frame = self.source.next(sample_rate);
+        self.curr.set(frame.clone());
+        frame
+    }
+}
+
+impl<S: FiniteSource> FiniteSource for InspectedSource<S> {
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+impl<F> SourceInspector<F>
+where
+    F: Frame,
+{
+    /// Read the inspected [`Source`]'s current frame
+    pub fn read(&self) -> Option<F> {
+        self.curr.cloned()
+    }
+}
+
+/// Source returned from [`Source::oversample`]
+#[derive(Debug, Clone)]
+pub struct Oversample<S: Source> {
+    source: S,
+    factor: usize,
+    filters: Vec<(Biquad, BiquadState<S::Frame>)>,
+    sample_rate: f64,
+}
+
+/// The Q values of the second-order sections of an 8th-order Butterworth filter
+const BUTTERWORTH_8_Q: [f64; 4] = [0.5098, 0.6013, 0.9000, 2.5629];
+
+impl<S> Source for Oversample<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        if self.factor == 1 {
+            return self.source.next(sample_rate);
+        }
+        let inner_rate = sample_rate * self.factor as f64;
+        if self.sample_rate != sample_rate {
+            self.sample_rate = sample_rate;
+            let cutoff = 0.45 * sample_rate;
+            let filters = core::mem::take(&mut self.filters);
+            let mut states = filters.into_iter().map(|(_, state)| state);
+            self.filters = BUTTERWORTH_8_Q
+                .iter()
+                .map(|&q| {
+                    let filter = Biquad::low_pass(cutoff, q, inner_rate);
+                    (filter, states.next().unwrap_or_default())
+                })
+                .collect();
+        }
+        let mut output = None;
+        for _ in 0..self.factor {
+            let Some(mut frame) = self.source.next(inner_rate) else {
+                break;
+            };
+            for (filter, state) in &mut self.filters {
+                frame = filter.process(state, frame);
+            }
+            output = Some(frame);
+        }
+        output
+    }
+}
+
+impl<S: FiniteSource> FiniteSource for Oversample<S> {
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+#[cfg(feature = "meter")]
+#[cfg_attr(docsrs, doc(cfg(feature = "meter")))]
+/// A source that is being measured by a [`CpuMeter`]
+#[derive(Debug, Clone)]
+pub struct Metered<S> {
+    source: S,
+    meter: CpuMeter,
+}
+
+#[cfg(feature = "meter")]
+#[cfg_attr(docsrs, doc(cfg(feature = "meter")))]
+/// Measures the CPU time spent by a [`Source`]
+///
+/// Created with [`Source::meter`]
+#[derive(Debug, Clone, Default)]
+pub struct CpuMeter {
+    state: Arc<MeterState>,
+}
+
+#[cfg(feature = "meter")]
+#[derive(Debug, Default)]
+struct MeterState {
+    busy_nanos: AtomicU64,
+    audio_nanos: AtomicU64,
+    frames: AtomicU64,
+}
+
+#[cfg(feature = "meter")]
+impl CpuMeter {
+    /// Get the total time spent producing frames
+    pub fn busy(&self) -> Duration {
+        Duration::from_nanos(self.state.busy_nanos.load(Ordering::Relaxed))
+    }
+    /// Get the total duration of the frames produced
+    pub fn audio_time(&self) -> Duration {
+        Duration::from_nanos(self.state.audio_nanos.load(Ordering::Relaxed))
+    }
+    /// Get the number of frames produced
+    pub fn frames(&self) -> u64 {
+        self.state.frames.load(Ordering::Relaxed)
+    }
+    /// Get the fraction of the real-time budget spent producing frames
+    ///
+    /// A load of `1.0` means producing the frames takes as long as playing them.
+    pub fn load(&self) -> f64 {
+        let audio = self.state.audio_nanos.load(Ordering::Relaxed);
+        if audio == 0 {
+            0.0
+        } else {
+            self.state.busy_nanos.load(Ordering::Relaxed) as f64 / audio as f64
+        }
+    }
+    /// Reset all measurements
+    pub fn reset(&self) {
+        self.state.busy_nanos.store(0, Ordering::Relaxed);
+        self.state.audio_nanos.store(0, Ordering::Relaxed);
+        self.state.frames.store(0, Ordering::Relaxed);
+    }
+}
+
+#[cfg(feature = "meter")]
+impl<S> Source for Metered<S>
+where
+    S: Source,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let start = Instant::now();
+        let frame = self.source.next(sample_rate);
+        let busy = start.elapsed().as_nanos() as u64;
+        let state = &self.meter.state;
+        state.busy_nanos.fetch_add(busy, Ordering::Relaxed);
+        if frame.is_some() {
+            let audio = (1e9 / sample_rate) as u64;
+            state.audio_nanos.fetch_add(audio, Ordering::Relaxed);
+            state.frames.fetch_add(1, Ordering::Relaxed);
+        }
+        frame
+    }
+}
+
+#[cfg(feature = "meter")]
+impl<S: FiniteSource> FiniteSource for Metered<S> {
+    fn total(&self, sample_rate: f64) -> Duration {
+        self.source.total(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.source.remaining(sample_rate)
+    }
+}
+
+#[cfg(feature = "std")]
+/// The default duration of audio buffered by a feeder thread
+const DEFAULT_FEED_BUFFER: f64 = 0.05;
+
+#[cfg(feature = "std")]
+/// Produces a source's frames ahead of time on another thread
+///
+/// The thread is started when the feeder is created.
+/// It waits for the sample rate and buffer duration, which are sent when they are known,
+/// and then sends back the channel it produces frames into.
+struct Feeder<F> {
+    state: FeederState<F>,
+    buffer: f64,
+}
+
+#[cfg(feature = "std")]
+enum FeederState<F> {
+    Starting {
+        handshake: Option<SyncSender<(f64, f64)>>,
+        ready: Receiver<Receiver<Option<F>>>,
+    },
+    Running(Receiver<Option<F>>),
+    Done,
+}
+
+#[cfg(feature = "std")]
+enum Feed<F> {
+    Frame(F),
+    /// The thread has not produced its first frame yet
+    Starting,
+    Empty,
+    Ended,
+}
+
+#[cfg(feature = "std")]
+impl<F> Feeder<F>
+where
+    F: Frame,
+{
+    fn new<S>(mut source: S, buffer: f64) -> Self
+    where
+        S: Source<Frame = F> + Send + 'static,
+    {
+        let (handshake, handshake_recv) = mpsc::sync_channel::<(f64, f64)>(1);
+        let (ready_send, ready) = mpsc::sync_channel(1);
+        let spawned = thread::Builder::new()
+            .name("hodaun feeder".into())
+            .spawn(move || {
+                let Ok((sample_rate, buffer)) = handshake_recv.recv() else {
+                    return;
+                };
+                let (send, recv) = mpsc::sync_channel(((buffer * sample_rate) as usize).max(1));
+                if ready_send.send(recv).is_err() {
+                    return;
+                }
+                loop {
+                    let frame = source.next(sample_rate);
+                    let ended = frame.is_none();
+                    if send.send(frame).is_err() || ended {
+                        break;
+                    }
+                }
+            });
+        Feeder {
+            state: if spawned.is_ok() {
+                FeederState::Starting {
+                    handshake: Some(handshake),
+                    ready,
+                }
+            } else {
+                FeederState::Done
+            },
+            buffer,
+        }
+    }
+    fn set_buffer(&mut self, dur: f64) {
+        self.buffer = dur;
+    }
+    /// Let the thread start producing frames
+    fn start(&mut self, sample_rate: f64) {
+        if let FeederState::Starting { handshake, .. } = &mut self.state {
+            if let Some(handshake) = handshake.take() {
+                let _ = handshake.try_send((sample_rate, self.buffer));
+            }
+        }
+    }
+    fn poll(&mut self, sample_rate: f64) -> Feed<F> {
+        if let FeederState::Starting { ready, .. } = &self.state {
+            let recv = ready.try_recv();
+            self.start(sample_rate);
+            match recv {
+                Ok(recv) => self.state = FeederState::Running(recv),
+                Err(TryRecvError::Empty) => return Feed::Starting,
+                Err(TryRecvError::Disconnected) => self.state = FeederState::Done,
+            }
+        }
+        let FeederState::Running(recv) = &self.state else {
+            return Feed::Ended;
+        };
+        match recv.try_recv() {
+            Ok(Some(frame)) => Feed::Frame(frame),
+            Err(TryRecvError::Empty) => Feed::Empty,
+            Ok(None) | Err(TryRecvError::Disconnected) => {
+                self.state = FeederState::Done;
+                Feed::Ended
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// Source returned from [`Source::prefetch`]
+pub struct Prefetch<S: Source> {
+    feeder: Feeder<S::Frame>,
+}
+
+#[cfg(feature = "std")]
+impl<S> Source for Prefetch<S>
+where
+    S: Source + Send + 'static,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        match self.feeder.poll(sample_rate) {
+            Feed::Frame(frame) => Some(frame),
+            Feed::Starting | Feed::Empty => Some(S::Frame::uniform(0.0)),
+            Feed::Ended => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// Source returned from [`Source::guard`]
+pub struct Guarded<S: Source> {
+    feeder: Feeder<S::Frame>,
+    timeout: f64,
+    waited: f64,
+    watchdog: Watchdog,
+}
+
+#[cfg(feature = "std")]
+impl<S> Guarded<S>
+where
+    S: Source + Send + 'static,
+{
+    /// Set the duration of audio to produce ahead of time
+    ///
+    /// The default is 50 milliseconds.
+    /// This has no effect once the feeder thread has started producing frames.
+    pub fn buffer(mut self, dur: impl ToDuration) -> Self {
+        self.feeder.set_buffer(dur.to_duration().as_secs_f64());
+        self
+    }
+    /// Start producing frames now, at the sample rate the source will be played at
+    ///
+    /// Otherwise, the feeder thread starts producing frames when the source is first played,
+    /// and the source plays silence until the first frames are ready.
+    pub fn sample_rate(mut self, sample_rate: f64) -> Self {
+        self.feeder.start(sample_rate);
+        self
+    }
+}
+
+#[cfg(feature = "std")]
+impl<S> Source for Guarded<S>
+where
+    S: Source + Send + 'static,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        match self.feeder.poll(sample_rate) {
+            Feed::Frame(frame) => {
+                self.waited = 0.0;
+                Some(frame)
+            }
+            Feed::Starting => {
+                // Waiting for the first frame is not an underrun, but it can still stall
+                self.waited += 1.0 / sample_rate;
+                if self.waited >= self.timeout {
+                    self.watchdog.state.stalled.store(true, Ordering::Relaxed);
+                    self.feeder.state = FeederState::Done;
+                    None
+                } else {
+                    Some(S::Frame::uniform(0.0))
+                }
+            }
+            Feed::Empty => {
+                self.watchdog
+                    .state
+                    .underruns
+                    .fetch_add(1, Ordering::Relaxed);
+                self.waited += 1.0 / sample_rate;
+                if self.waited >= self.timeout {
+                    self.watchdog.state.stalled.store(true, Ordering::Relaxed);
+                    self.feeder.state = FeederState::Done;
+                    None
+                } else {
+                    Some(S::Frame::uniform(0.0))
+                }
+            }
+            Feed::Ended => None,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+/// Reports whether a [`Guarded`] source has stalled
+///
+/// Created with [`Source::guard`]
+#[derive(Debug, Clone, Default)]
+pub struct Watchdog {
+    state: Arc<WatchdogState>,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Default)]
+struct WatchdogState {
+    stalled: AtomicBool,
+    underruns: AtomicU64,
+}
+
+#[cfg(feature = "std")]
+impl Watchdog {
+    /// Check if the source stalled and was ended
+    ///
+    /// A stalled source's thread is abandoned. It exits once the source stops blocking.
+    pub fn is_stalled(&self) -> bool {
+        self.state.stalled.load(Ordering::Relaxed)
+    }
+    /// Get the number of frames for which no audio was ready
+    ///
+    /// Silence is played in place of these frames.
+    pub fn underruns(&self) -> u64 {
+        self.state.underruns.load(Ordering::Relaxed)
+    }
+}
+
+/// A source that loops a buffer of frames without any gap
+///
+/// The buffer is rendered once, and clones share it.
+/// If it is played at a different sample rate than it was rendered at,
+/// it is resampled with linear interpolation.
+#[derive(Debug, Clone)]
+pub struct Loop<F> {
+    frames: Arc<[F]>,
+    sample_rate: f64,
+    pos: f64,
+}
+
+impl<F: Frame> Loop<F> {
+    /// Render a source into a loop
+    ///
+    /// The source must be finite.
+    pub fn new(mut source: impl Source<Frame = F>, sample_rate: f64) -> Self {
+        let mut frames = Vec::new();
+        while let Some(frame) = source.next(sample_rate) {
+            frames.push(frame);
+        }
+        Loop::from_frames(frames, sample_rate)
+    }
+    /// Create a loop from frames rendered at the given sample rate
+    pub fn from_frames(frames: impl Into<Arc<[F]>>, sample_rate: f64) -> Self {
+        Loop {
+            frames: frames.into(),
+            sample_rate,
+            pos: 0.0,
+        }
+    }
+    /// Crossfade the end of the loop into its start
+    ///
+    /// This smooths over loops whose end does not line up with their start.
+    /// The loop is shortened by the crossfade duration, which is limited to half the loop.
+    pub fn crossfade(self, dur: impl ToDuration) -> Self {
+        let len = self.frames.len();
+        let fade = ((dur.to_duration().as_secs_f64() * self.sample_rate) as usize).min(len / 2);
+        if fade == 0 {
+            return self;
+        }
+        let looped_len = len - fade;
+        let mut frames = self.frames[..looped_len].to_vec();
+        for (i, frame) in frames[..fade].iter_mut().enumerate() {
+            let t = i as f64 / fade as f64;
+            frame.merge(self.frames[looped_len + i].clone(), |a, b| lerp(b, a, t));
+        }
+        Loop::from_frames(frames, self.sample_rate)
+    }
+    /// Get the duration of one iteration of the loop
+    pub fn loop_duration(&self) -> Duration {
+        secs(self.frames.len() as f64 / self.sample_rate)
+    }
+}
+
+impl<F: Frame> Source for Loop<F> {
+    type Frame = F;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let len = self.frames.len();
+        if len == 0 {
+            return None;
+        }
+        let i = self.pos as usize % len;
+        let t = self.pos.fract();
+        let mut frame = self.frames[i].clone();
+        if t > 0.0 {
+            frame.merge(self.frames[(i + 1) % len].clone(), |a, b| lerp(a, b, t));
+        }
+        self.pos = (self.pos + self.sample_rate / sample_rate) % len as f64;
+        Some(frame)
+    }
+}
+
+/// Source returned from [`Source::buffer`]
+pub struct Buffered<S: Source> {
+    inner: Arc<Mutex<BufferedInner<S>>>,
+    time: f64,
+}
+
+impl<F: Frame> From<Vec<F>> for Buffered<Empty<F>> {
+    fn from(buffer: Vec<F>) -> Self {
+        Buffered::from_parts(Empty(PhantomData), buffer.into())
+    }
+}
+
+struct BufferedInner<S: Source> {
+    source: S,
+    buffer: VecDeque<S::Frame>,
+    /// The number of frames that have been released from the front of the buffer
+    released: usize,
+    max_retained: Option<f64>,
+    /// The sample rate the buffer is played at, or 0 before it is played
+    sample_rate: f64,
+    /// The time before which frames should be released once they are buffered
+    pending_release: Option<f64>,
+}
+
+impl<S: Source> BufferedInner<S> {
+    fn release_before(&mut self, index: usize) {
+        while self.released < index && self.buffer.pop_front().is_some() {
+            self.released += 1;
+        }
+    }
+    fn release_before_time(&mut self, time: f64) {
+        let time = self
+            .pending_release
+            .map_or(time, |pending| pending.max(time));
+        self.pending_release = None;
+        // Before the buffer is played, the sample rate is unknown
+        if self.sample_rate > 0.0 {
+            let index = (time * self.sample_rate) as usize;
+            self.release_before(index);
+            if self.released >= index {
+                return;
+            }
+        }
+        self.pending_release = Some(time);
+    }
+}
+
+impl<S: Source> Buffered<S> {
+    fn from_parts(source: S, buffer: VecDeque<S::Frame>) -> Self {
+        Buffered {
+            inner: Arc::new(Mutex::new(BufferedInner {
+                source,
+                buffer,
+                released: 0,
+                max_retained: None,
+                sample_rate: 0.0,
+                pending_release: None,
+            })),
+            time: 0.0,
+        }
+    }
+    /// Limit the amount of the source that is kept in the buffer
+    ///
+    /// Once the buffer holds more than the given duration, the oldest frames
+    /// are released. This allows long sources, like large files, to be buffered
+    /// without the buffer growing indefinitely.
+    ///
+    /// Clones that fall behind the retained window will end.
+    /// This setting is shared by all clones.
+    pub fn max_retained(self, max: impl ToDuration) -> Self {
+        self.inner.lock().max_retained = Some(max.to_duration().as_secs_f64());
+        self
+    }
+    /// Release all buffered frames before the given time
+    ///
+    /// Clones that have not yet reached the given time will end when
+    /// they try to read a released frame.
+    /// Frames that have not been buffered yet are released once they are.
+    pub fn release_before(&self, time: impl ToDuration) {
+        let time = time.to_duration().as_secs_f64();
+        self.inner.lock().release_before_time(time);
+    }
+}
+
+impl<S> Source for Buffered<S>
 where
-    R: Automation,
+    S: Source,
 {
-    /// Create a new maintainer with the given release duration
-    pub fn with_release(release_dur: R) -> Self {
-        Maintainer {
-            arc: Arc::new(()),
-            release_dur,
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let index = (self.time * sample_rate) as usize;
+        let mut inner = self.inner.lock();
+        inner.sample_rate = sample_rate;
+        let local = index.checked_sub(inner.released)?;
+        while local >= inner.buffer.len() {
+            let frame = inner.source.next(sample_rate)?;
+            inner.buffer.push_back(frame);
+        }
+        self.time += 1.0 / sample_rate;
+        let frame = inner.buffer[local].clone();
+        if let Some(max) = inner.max_retained {
+            let max = (max * sample_rate).ceil() as usize;
+            let end = inner.released + inner.buffer.len();
+            inner.release_before(end.saturating_sub(max));
         }
+        if let Some(time) = inner.pending_release {
+            inner.release_before_time(time);
+        }
+        Some(frame)
     }
 }
 
-/// An attack-decay-sustain evenlope
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct AdsEnvelope<A = f64, D = f64, S = f64> {
-    /// The time after the sound starts before it is at its maximum volume
-    pub attack: A,
-    /// The time between the maximum amplitude and the sustain amplitude
-    pub decay: D,
-    /// The sustain amplitude
-    pub sustain: S,
-}
-
-impl<A, D, S> Default for AdsEnvelope<A, D, S>
+impl<S> FiniteSource for Buffered<S>
 where
-    A: From<f64>,
-    D: From<f64>,
-    S: From<f64>,
+    S: FiniteSource,
 {
-    fn default() -> Self {
-        AdsEnvelope {
-            attack: 0.0.into(),
-            decay: 0.0.into(),
-            sustain: 1.0.into(),
-        }
+    fn total(&self, sample_rate: f64) -> Duration {
+        let inner = self.inner.lock();
+        let buffered = inner.released + inner.buffer.len();
+        secs(buffered as f64 / sample_rate) + inner.source.remaining(sample_rate)
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        self.total(sample_rate).saturating_sub(secs(self.time))
     }
 }
 
-impl<A, D, S> AdsEnvelope<A, D, S>
+impl<S> Clone for Buffered<S>
 where
-    A: Automation,
-    D: Automation,
-    S: Automation,
+    S: Source,
 {
-    /// Create a new ADS envelope
-    pub fn new(attack: A, decay: D, sustain: S) -> Self {
+    fn clone(&self) -> Self {
         Self {
-            attack,
-            decay,
-            sustain,
+            inner: self.inner.clone(),
+            time: self.time,
         }
     }
 }
 
-/// Source returned from [`Source::ads`]
-#[derive(Debug, Clone, Copy)]
-pub struct Ads<Src, A, D, S> {
-    source: Src,
-    time: f64,
-    envelope: AdsEnvelope<A, D, S>,
+/// Source returned from [`Source::freeze`]
+///
+/// The source is never rendered on the audio thread.
+/// Until a rendering is ready, it plays silence without advancing.
+/// With the `std` feature, a background thread shared by all frozen sources renders the source
+/// when it is first played and whenever it is invalidated.
+/// Without it, use [`Frozen::render`] and [`Frozen::refresh`].
+pub struct Frozen<S: Source> {
+    shared: Arc<FrozenShared<S>>,
+    /// The rendering being played
+    frames: Option<Arc<Vec<S::Frame>>>,
+    /// The version of the rendering being played
+    version: u64,
+    /// The parameters passed to [`Frozen::invalidate_on`] and their last seen versions
+    watched: Vec<(ParamVersion, u64)>,
+    pos: usize,
 }
 
-impl<Src, A, D, S> Source for Ads<Src, A, D, S>
+type ChangeCheck = Box<dyn FnMut() -> bool + Send + 'static>;
+type ParamVersion = Arc<dyn Fn() -> u64 + Send + Sync>;
+
+/// The latest rendering and the renderings it replaced
+///
+/// Replaced renderings are kept until no clone is playing them,
+/// so that the audio thread is never the one to free them.
+struct Renderings<F> {
+    current: Option<(f64, Arc<Vec<F>>)>,
+    retired: Vec<Arc<Vec<F>>>,
+}
+
+struct FrozenShared<S: Source> {
+    /// The source and what to check for changes, only locked while rendering
+    control: Mutex<FrozenControl<S>>,
+    /// Only locked briefly to swap in a rendering, and never waited on by the audio thread
+    rendered: Mutex<Renderings<S::Frame>>,
+    /// Incremented each time a rendering is published
+    version: AtomicU64,
+    /// The bits of the sample rate the source is played at, or 0 before it is played
+    play_rate: AtomicU64,
+    invalid: AtomicBool,
+}
+
+struct FrozenControl<S> {
+    source: S,
+    duration: f64,
+    checks: Vec<ChangeCheck>,
+}
+
+impl<S> FrozenShared<S>
 where
-    Src: Source,
-    A: Automation,
-    D: Automation,
-    S: Automation,
+    S: Source + Clone,
 {
-    type Frame = Src::Frame;
-    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
-        let frame = self.source.next(sample_rate)?;
-        let attack = self.envelope.attack.next_value(sample_rate)?;
-        let decay = self.envelope.decay.next_value(sample_rate)?;
-        let sustain = self.envelope.sustain.next_value(sample_rate)?;
-        let amp = if self.time < attack {
-            self.time / attack
-        } else {
-            let after_attack = self.time - attack;
-            if after_attack < decay {
-                (1.0 - after_attack / decay) * (1.0 - sustain) + sustain
-            } else {
-                sustain
+    fn render(&self, sample_rate: f64) {
+        let (mut source, duration) = {
+            let control = self.control.lock();
+            (control.source.clone(), control.duration)
+        };
+        let len = (duration * sample_rate).round() as usize;
+        let frames: Vec<S::Frame> = (0..len).map_while(|_| source.next(sample_rate)).collect();
+        let mut guard = self.rendered.lock();
+        let rendered = &mut *guard;
+        if let Some((_, old)) = rendered.current.replace((sample_rate, Arc::new(frames))) {
+            rendered.retired.push(old);
+        }
+        self.version.fetch_add(1, Ordering::Release);
+    }
+    /// Drop the replaced renderings that no clone is playing anymore
+    fn collect(&self) {
+        let dropped: Vec<_> = {
+            let mut rendered = self.rendered.lock();
+            let (free, kept) =
+                (rendered.retired.drain(..)).partition(|frames| Arc::strong_count(frames) == 1);
+            rendered.retired = kept;
+            free
+        };
+        drop(dropped);
+    }
+    fn refresh(&self) -> bool {
+        let mut changed = self.invalid.swap(false, Ordering::Relaxed);
+        for check in &mut self.control.lock().checks {
+            changed |= check();
+        }
+        let rendered_rate = self.rendered.lock().current.as_ref().map(|(rate, _)| *rate);
+        let play_rate = f64::from_bits(self.play_rate.load(Ordering::Relaxed));
+        let rate = Some(play_rate).filter(|&rate| rate > 0.0).or(rendered_rate);
+        let rendered = match rate {
+            Some(rate) if changed || rendered_rate != Some(rate) => {
+                self.render(rate);
+                true
             }
+            _ => false,
         };
-        self.time += 1.0 / sample_rate;
-        Some(frame.map(|s| s * amp))
+        self.collect();
+        rendered
+    }
+    /// Wake the thread that renders frozen sources
+    fn wake(&self) {
+        #[cfg(feature = "std")]
+        freeze_worker().thread().unpark();
     }
 }
 
-/// Source returned from [`Source::maintained`]
-#[derive(Debug, Clone)]
-pub struct Maintained<S, R> {
-    source: S,
-    arc: Weak<()>,
-    release_dur: R,
-    release_curr: f64,
+/// The renders of a [`Frozen`] that the worker thread can refresh
+#[cfg(feature = "std")]
+trait Refresh: Send + Sync {
+    fn refresh(&self) -> bool;
 }
 
-impl<S, R> Source for Maintained<S, R>
+#[cfg(feature = "std")]
+impl<S> Refresh for FrozenShared<S>
 where
-    S: Source,
-    R: Automation,
+    S: Source + Clone + Send,
+    S::Frame: Send + Sync,
 {
-    type Frame = S::Frame;
-    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
-        let release_dur = self.release_dur.next_value(sample_rate)?;
-        if Weak::strong_count(&self.arc) == 0 {
-            if self.release_curr < release_dur {
-                let amp = 1.0 - self.release_curr / release_dur;
-                let frame = self.source.next(sample_rate)?.map(|s| s * amp);
-                self.release_curr += 1.0 / sample_rate;
-                Some(frame)
-            } else {
-                None
-            }
-        } else {
-            self.source.next(sample_rate)
-        }
+    fn refresh(&self) -> bool {
+        FrozenShared::refresh(self)
     }
 }
 
-/// A source that is being inspected by a [`SourceInspector`]
-#[derive(Debug, Clone)]
-pub struct InspectedSource<S: Source> {
-    source: S,
-    curr: Shared<Option<S::Frame>>,
+/// The background thread shared by all [`Frozen`] sources
+///
+/// It sleeps until a frozen source wakes it, then refreshes every frozen source that is still alive.
+#[cfg(feature = "std")]
+struct FreezeWorker {
+    thread: thread::Thread,
+    frozen: Mutex<Vec<Weak<dyn Refresh>>>,
 }
 
-/// Allows the inspection of a [`Source`]'s current frame
-#[derive(Debug, Clone)]
-pub struct SourceInspector<F> {
-    curr: Shared<Option<F>>,
+#[cfg(feature = "std")]
+impl FreezeWorker {
+    fn thread(&self) -> &thread::Thread {
+        &self.thread
+    }
+    fn register(&self, frozen: Weak<dyn Refresh>) {
+        let mut list = self.frozen.lock();
+        list.retain(|frozen| frozen.strong_count() > 0);
+        list.push(frozen);
+    }
 }
 
-impl<S: Source> Source for InspectedSource<S> {
-    type Frame = S::Frame;
-    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
-        let frame = self.source.next(sample_rate);
-        self.curr.set(frame.clone());
-        frame
-    }
+#[cfg(feature = "std")]
+fn freeze_worker() -> &'static FreezeWorker {
+    static WORKER: std::sync::OnceLock<FreezeWorker> = std::sync::OnceLock::new();
+    WORKER.get_or_init(|| {
+        let thread = thread::Builder::new()
+            .name("hodaun freeze".into())
+            .spawn(|| loop {
+                thread::park();
+                let frozen: Vec<_> = {
+                    let mut list = freeze_worker().frozen.lock();
+                    list.retain(|frozen| frozen.strong_count() > 0);
+                    list.iter().filter_map(Weak::upgrade).collect()
+                };
+                for frozen in frozen {
+                    frozen.refresh();
+                }
+            })
+            .expect("failed to spawn freeze thread");
+        FreezeWorker {
+            thread: thread.thread().clone(),
+            frozen: Mutex::new(Vec::new()),
+        }
+    })
 }
 
-impl<F> SourceInspector<F>
+impl<S> Frozen<S>
 where
-    F: Frame,
+    S: Source + Clone + Send + 'static,
+    S::Frame: Sync,
 {
-    /// Read the inspected [`Source`]'s current frame
-    pub fn read(&self) -> Option<F> {
-        self.curr.cloned()
+    fn new(source: S, duration: f64) -> Self {
+        let shared = Arc::new(FrozenShared {
+            control: Mutex::new(FrozenControl {
+                source,
+                duration,
+                checks: Vec::new(),
+            }),
+            rendered: Mutex::new(Renderings {
+                current: None,
+                retired: Vec::new(),
+            }),
+            version: AtomicU64::new(0),
+            play_rate: AtomicU64::new(0),
+            invalid: AtomicBool::new(false),
+        });
+        #[cfg(feature = "std")]
+        {
+            let weak: Weak<dyn Refresh> = Arc::downgrade(&shared) as _;
+            freeze_worker().register(weak);
+        }
+        Frozen {
+            shared,
+            frames: None,
+            version: 0,
+            watched: Vec::new(),
+            pos: 0,
+        }
     }
 }
 
-/// Source returned from [`Source::buffer`]
-pub struct Buffered<S: Source> {
-    inner: Arc<Mutex<BufferedInner<S>>>,
-    time: f64,
+impl<S> Frozen<S>
+where
+    S: Source + Clone,
+{
+    /// Render the source now on the calling thread
+    ///
+    /// Rendering a long or expensive source can take a while,
+    /// so this is useful for rendering it before adding it to an output.
+    /// The sample rate should be the one it will be played at.
+    pub fn render(self, sample_rate: f64) -> Self {
+        self.shared.render(sample_rate);
+        self
+    }
+    /// Render the source again when a parameter has changed
+    ///
+    /// Setting the parameter wakes the rendering thread,
+    /// which compares it to the value it had when last checked.
+    /// The old rendering keeps playing until the new one is ready,
+    /// and playback continues from the same position in the new one.
+    pub fn invalidate_on<T>(mut self, param: &Shared<T>) -> Self
+    where
+        T: PartialEq + Clone + Send + 'static,
+    {
+        let version_param = param.clone();
+        let version: ParamVersion = Arc::new(move || version_param.version());
+        self.watched.push((version, param.version()));
+        let param = param.clone();
+        let mut last = param.cloned();
+        self.shared.control.lock().checks.push(Box::new(move || {
+            // Reading the value does not change its version
+            let val = param.cloned();
+            let changed = val != last;
+            if changed {
+                last = val;
+            }
+            changed
+        }));
+        self
+    }
+    /// Render the source again
+    ///
+    /// The old rendering keeps playing until the new one is ready.
+    pub fn invalidate(&self) {
+        self.shared.invalid.store(true, Ordering::Relaxed);
+        self.shared.wake();
+    }
+    /// Check for changes and render the source again on the calling thread if needed
+    ///
+    /// Returns whether the source was rendered.
+    /// With the `std` feature, this is done automatically on a background thread.
+    pub fn refresh(&self) -> bool {
+        self.shared.refresh()
+    }
 }
 
-impl<F: Frame> From<Vec<F>> for Buffered<Empty<F>> {
-    fn from(buffer: Vec<F>) -> Self {
-        Self {
-            inner: Arc::new(Mutex::new(BufferedInner {
-                source: Empty(PhantomData),
-                buffer,
-            })),
-            time: 0.0,
+impl<S> Source for Frozen<S>
+where
+    S: Source + Clone,
+{
+    type Frame = S::Frame;
+    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
+        let mut wake = false;
+        let rate_bits = sample_rate.to_bits();
+        if self.shared.play_rate.load(Ordering::Relaxed) != rate_bits {
+            self.shared.play_rate.store(rate_bits, Ordering::Relaxed);
+            wake = true;
+        }
+        for (version, seen) in &mut self.watched {
+            let version = version();
+            if version != *seen {
+                *seen = version;
+                wake = true;
+            }
+        }
+        let version = self.shared.version.load(Ordering::Acquire);
+        if version != self.version {
+            if let Some(rendered) = self.shared.rendered.try_lock() {
+                // The rendering this replaces is still held by the shared state,
+                // so dropping it here never frees it
+                self.frames = (rendered.current.as_ref())
+                    .filter(|(rate, _)| *rate == sample_rate)
+                    .map(|(_, frames)| frames.clone());
+                self.version = version;
+                // Let the worker free the replaced rendering
+                wake = true;
+            }
+        }
+        if wake {
+            self.shared.wake();
         }
+        let Some(frames) = &self.frames else {
+            return Some(S::Frame::uniform(0.0));
+        };
+        let frame = frames.get(self.pos)?.clone();
+        self.pos += 1;
+        Some(frame)
     }
 }
 
-struct BufferedInner<S: Source> {
-    source: S,
-    buffer: Vec<S::Frame>,
-}
-
-impl<S> Source for Buffered<S>
+impl<S> FiniteSource for Frozen<S>
 where
-    S: Source,
+    S: Source + Clone,
 {
-    type Frame = S::Frame;
-    fn next(&mut self, sample_rate: f64) -> Option<Self::Frame> {
-        let index = (self.time * sample_rate) as usize;
-        let mut inner = self.inner.lock();
-        while index >= inner.buffer.len() {
-            let frame = inner.source.next(sample_rate)?;
-            inner.buffer.push(frame);
+    fn total(&self, sample_rate: f64) -> Duration {
+        match &self.frames {
+            Some(frames) => secs(frames.len() as f64 / sample_rate),
+            None => secs(self.shared.control.lock().duration),
         }
-        self.time += 1.0 / sample_rate;
-        Some(inner.buffer[index].clone())
+    }
+    fn remaining(&self, sample_rate: f64) -> Duration {
+        (self.total(sample_rate)).saturating_sub(secs(self.pos as f64 / sample_rate))
     }
 }
 
-impl<S> Clone for Buffered<S>
+impl<S> Clone for Frozen<S>
 where
     S: Source,
 {
     fn clone(&self) -> Self {
         Self {
-            inner: self.inner.clone(),
-            time: self.time,
+            shared: self.shared.clone(),
+            frames: self.frames.clone(),
+            version: self.version,
+            watched: self.watched.clone(),
+            pos: self.pos,
         }
     }
 }
@@ -902,6 +3808,20 @@ where
     }
 }
 
+impl<S, F> FiniteSource for Resample<S, F>
+where
+    S: UnrolledSource + ExactSizeIterator,
+    F: Frame,
+{
+    fn total(&self, sample_rate: f64) -> Duration {
+        secs(self.output_time) + self.remaining(sample_rate)
+    }
+    fn remaining(&self, _sample_rate: f64) -> Duration {
+        let frames = self.source.len() / self.source.channels().max(1);
+        secs(frames as f64 / self.source.sample_rate())
+    }
+}
+
 /// Source returned from [`Source::unroll`]
 pub struct Unroll<S: Source> {
     source: S,
@@ -946,3 +3866,167 @@ where
         self.sample_rate
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::{gen::Impulse, test_util::*, SineWave};
+
+    const RATE: f64 = 48000.0;
+    /// A rate at which the times used below are whole numbers of frames
+    const GRID_RATE: f64 = 1000.0;
+
+    /// Get the peak amplitude of the second half of a finite source, after it has settled
+    fn settled_peak(source: impl Source<Frame = f64>, sample_rate: f64) -> f64 {
+        let frames = collect_frames(source, sample_rate);
+        (frames[frames.len() / 2..].iter()).fold(0.0, |peak, a| peak.max(a.abs()))
+    }
+
+    /// Get the frames at which a source rises above half amplitude
+    fn onsets(source: impl Source<Frame = f64>, sample_rate: f64) -> Vec<usize> {
+        let frames = collect_frames(source, sample_rate);
+        (0..frames.len())
+            .filter(|&i| frames[i] > 0.5 && (i == 0 || frames[i - 1] <= 0.5))
+            .collect()
+    }
+
+    #[test]
+    fn notch_removes_only_its_frequency() {
+        let gain = |freq| settled_peak(SineWave::new(freq).notch(1000.0, 2.0).take(0.5), RATE);
+        assert!(gain(1000.0) < 0.01);
+        assert!(gain(100.0) > 0.95);
+        assert!(gain(5000.0) > 0.95);
+    }
+
+    #[test]
+    fn notch_with_zero_q_stays_finite() {
+        let frames = collect_frames(SineWave::new(1000.0).notch(1000.0, 0.0).take(0.1), RATE);
+        assert!(frames.iter().all(|a| a.is_finite()));
+    }
+
+    #[test]
+    fn delay_echoes_decay_by_the_feedback() {
+        let frames = collect_frames(Impulse::new(0.01).delay(0.1, 0.5, 0.5), GRID_RATE);
+        for (i, &a) in frames.iter().enumerate() {
+            let expected = match i {
+                0 => 0.5,
+                100 => 0.5,
+                200 => 0.25,
+                300 => 0.125,
+                _ if i % 100 == 0 => a,
+                _ => 0.0,
+            };
+            assert!(
+                (a - expected).abs() < 1e-9,
+                "frame {i} is {a}, expected {expected}"
+            );
+        }
+        // The echoes end once they fall below the silence threshold
+        assert!(frames.len() < 2000, "{} frames", frames.len());
+    }
+
+    #[test]
+    fn delay_feedback_reinforces_frequencies_that_fit_its_time() {
+        // 1 ms is one period of 1 kHz and half a period of 500 Hz
+        let gain = |freq| settled_peak(SineWave::new(freq).take(0.5).delay(0.001, 0.5, 1.0), RATE);
+        assert!((gain(1000.0) - 2.0).abs() < 0.01);
+        assert!((gain(500.0) - 2.0 / 3.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn delay_duration_includes_the_echoes() {
+        let delay = Impulse::new(0.01).delay(0.1, 0.5, 0.5);
+        let estimated = delay.total(GRID_RATE).as_secs_f64();
+        let played = collect_frames(delay, GRID_RATE).len() as f64 / GRID_RATE;
+        assert!(
+            estimated >= played && estimated - played < 0.2,
+            "estimated {estimated}, played {played}"
+        );
+    }
+
+    #[test]
+    fn delay_restores_its_echoes() {
+        let delay = || SineWave::new(440.0).take(0.5).delay(0.1, 0.5, 0.5);
+        let mut original = delay();
+        for _ in 0..(0.4 * RATE) as usize {
+            original.next(RATE);
+        }
+        let mut restored = delay();
+        assert!(restored.restore(&original.snapshot()));
+        assert_source_eq(original, restored, RATE, 0.0);
+    }
+
+    #[test]
+    fn repeat_with_gap() {
+        let source = Impulse::new(0.005).repeat(3).gap(0.005);
+        assert_eq!(onsets(source, GRID_RATE), [0, 10, 20]);
+    }
+
+    #[test]
+    fn repeat_every_period() {
+        let source = Impulse::new(0.005).repeat(4).every(0.1);
+        assert_eq!(onsets(source, GRID_RATE), [0, 100, 200, 300]);
+    }
+
+    #[test]
+    fn repeat_swing_delays_off_beats() {
+        // Off-beats start 60% of the way through each pair of periods
+        let source = Impulse::new(0.005).repeat(4).every(0.1).swing(60.0);
+        assert_eq!(onsets(source, GRID_RATE), [0, 120, 200, 320]);
+    }
+
+    #[test]
+    fn repeat_overlap() {
+        let long = Constant(1.0).take(0.15);
+        let sum = collect_frames(long.repeat(3).every(0.1), GRID_RATE);
+        assert_eq!(sum.len(), 350);
+        assert!(sum.iter().any(|&a| a > 1.5));
+        let cut = collect_frames(long.repeat(3).every(0.1).overlap(Overlap::Cut), GRID_RATE);
+        assert!(cut.iter().all(|&a| a <= 1.0));
+        assert_eq!(cut.iter().filter(|&&a| a > 0.0).count(), 350);
+    }
+
+    #[test]
+    fn buffered_clones_share_the_source() {
+        let buffered = StepSource::new(0.0, 1.0).take(0.05).buffer();
+        let first = collect_frames(buffered.clone(), GRID_RATE);
+        assert_eq!(first, (0..50).map(|i| i as f64).collect::<Vec<_>>());
+        assert_source_eq(buffered.clone(), buffered, GRID_RATE, 0.0);
+    }
+
+    #[test]
+    fn buffered_release_ends_clones_that_fall_behind() {
+        let mut ahead = StepSource::new(0.0, 1.0).buffer();
+        let behind = ahead.clone();
+        for _ in 0..20 {
+            ahead.next(GRID_RATE);
+        }
+        ahead.release_before(0.01);
+        assert_eq!(ahead.inner.lock().buffer.len(), 10);
+        assert_eq!(ahead.next(GRID_RATE), Some(20.0));
+        assert_eq!(behind.clone().next(GRID_RATE), None);
+    }
+
+    #[test]
+    fn buffered_release_before_playing_waits_for_the_frames() {
+        let buffered = StepSource::new(0.0, 1.0).buffer();
+        buffered.release_before(0.01);
+        let mut playing = buffered.clone();
+        // The first clone to play buffers the released frames, then they are dropped
+        assert_eq!(playing.next(GRID_RATE), Some(0.0));
+        for _ in 0..10 {
+            playing.next(GRID_RATE);
+        }
+        assert_eq!(buffered.inner.lock().released, 10);
+        assert_eq!(buffered.clone().next(GRID_RATE), None);
+    }
+
+    #[test]
+    fn buffered_max_retained() {
+        let mut buffered = StepSource::new(0.0, 1.0).buffer().max_retained(0.01);
+        for _ in 0..100 {
+            buffered.next(GRID_RATE);
+        }
+        assert!(buffered.inner.lock().buffer.len() <= 10);
+    }
+}
@@ -0,0 +1,283 @@
+use alloc::{boxed::Box, sync::Arc};
+use core::{
+    cell::UnsafeCell,
+    mem::MaybeUninit,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+#[cfg(feature = "std")]
+pub(crate) use parking_lot::{Mutex, MutexGuard};
+
+#[cfg(not(feature = "std"))]
+pub(crate) use spin::{Mutex, MutexGuard};
+
+/// A minimal spin lock for targets without `std`
+///
+/// It has the subset of [`parking_lot::Mutex`]'s API that this crate uses.
+#[cfg(not(feature = "std"))]
+mod spin {
+    use core::{
+        cell::UnsafeCell,
+        fmt, hint,
+        ops::{Deref, DerefMut},
+        sync::atomic::{AtomicBool, Ordering},
+    };
+
+    #[derive(Default)]
+    pub(crate) struct Mutex<T: ?Sized> {
+        locked: AtomicBool,
+        value: UnsafeCell<T>,
+    }
+
+    // SAFETY: access to the value is serialized by the lock
+    unsafe impl<T: ?Sized + Send> Send for Mutex<T> {}
+    unsafe impl<T: ?Sized + Send> Sync for Mutex<T> {}
+
+    impl<T> Mutex<T> {
+        pub(crate) const fn new(value: T) -> Self {
+            Mutex {
+                locked: AtomicBool::new(false),
+                value: UnsafeCell::new(value),
+            }
+        }
+    }
+
+    impl<T: ?Sized> Mutex<T> {
+        pub(crate) fn lock(&self) -> MutexGuard<'_, T> {
+            loop {
+                if let Some(guard) = self.try_lock() {
+                    return guard;
+                }
+                while self.locked.load(Ordering::Relaxed) {
+                    hint::spin_loop();
+                }
+            }
+        }
+        pub(crate) fn try_lock(&self) -> Option<MutexGuard<'_, T>> {
+            (self.locked)
+                .compare_exchange(false, true, Ordering::Acquire, Ordering::Relaxed)
+                .ok()
+                .map(|_| MutexGuard { mutex: self })
+        }
+    }
+
+    impl<T: ?Sized + fmt::Debug> fmt::Debug for Mutex<T> {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            match self.try_lock() {
+                Some(guard) => f.debug_struct("Mutex").field("data", &&*guard).finish(),
+                None => f.write_str("Mutex { <locked> }"),
+            }
+        }
+    }
+
+    pub(crate) struct MutexGuard<'a, T: ?Sized> {
+        mutex: &'a Mutex<T>,
+    }
+
+    impl<T: ?Sized> Deref for MutexGuard<'_, T> {
+        type Target = T;
+        fn deref(&self) -> &T {
+            // SAFETY: the guard holds the lock
+            unsafe { &*self.mutex.value.get() }
+        }
+    }
+
+    impl<T: ?Sized> DerefMut for MutexGuard<'_, T> {
+        fn deref_mut(&mut self) -> &mut T {
+            // SAFETY: the guard holds the lock
+            unsafe { &mut *self.mutex.value.get() }
+        }
+    }
+
+    impl<T: ?Sized> Drop for MutexGuard<'_, T> {
+        fn drop(&mut self) {
+            self.mutex.locked.store(false, Ordering::Release);
+        }
+    }
+}
+
+/// Create a fixed-capacity single-producer single-consumer queue
+///
+/// Pushing and popping never block or allocate, so either end can be used on the audio thread.
+pub(crate) fn ring<T>(capacity: usize) -> (RingSender<T>, RingReceiver<T>) {
+    let capacity = capacity.max(1);
+    let shared = Arc::new(RingShared {
+        capacity,
+        slots: (0..capacity.next_power_of_two())
+            .map(|_| UnsafeCell::new(MaybeUninit::uninit()))
+            .collect(),
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+    });
+    (RingSender(shared.clone()), RingReceiver(shared))
+}
+
+struct RingShared<T> {
+    /// The most values that can be pushed and not popped
+    capacity: usize,
+    /// A power of two number of slots, so that the counters map to the same slots when they wrap around
+    slots: Box<[UnsafeCell<MaybeUninit<T>>]>,
+    /// The number of values that have been popped
+    head: AtomicUsize,
+    /// The number of values that have been pushed
+    tail: AtomicUsize,
+}
+
+// SAFETY: each slot is only accessed by the sender before it is pushed
+// and by the receiver after it is pushed and before it is popped
+unsafe impl<T: Send> Send for RingShared<T> {}
+unsafe impl<T: Send> Sync for RingShared<T> {}
+
+impl<T> RingShared<T> {
+    /// Get the slot a counter refers to
+    fn slot(&self, counter: usize) -> &UnsafeCell<MaybeUninit<T>> {
+        &self.slots[counter & (self.slots.len() - 1)]
+    }
+    fn len(&self) -> usize {
+        let tail = self.tail.load(Ordering::Acquire);
+        tail.wrapping_sub(self.head.load(Ordering::Acquire))
+    }
+}
+
+impl<T> Drop for RingShared<T> {
+    fn drop(&mut self) {
+        let (head, tail) = (*self.head.get_mut(), *self.tail.get_mut());
+        let mask = self.slots.len() - 1;
+        let mut i = head;
+        while i != tail {
+            // SAFETY: slots between the head and tail hold pushed values
+            unsafe { self.slots[i & mask].get_mut().assume_init_drop() };
+            i = i.wrapping_add(1);
+        }
+    }
+}
+
+/// The pushing end of a [`ring`]
+pub(crate) struct RingSender<T>(Arc<RingShared<T>>);
+
+impl<T> RingSender<T> {
+    /// Push a value, returning it if the queue is full
+    pub(crate) fn push(&mut self, value: T) -> Result<(), T> {
+        let shared = &*self.0;
+        let tail = shared.tail.load(Ordering::Relaxed);
+        let head = shared.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) == shared.capacity {
+            return Err(value);
+        }
+        // SAFETY: the slot is not between the head and tail, so the receiver is not reading it
+        unsafe { (*shared.slot(tail).get()).write(value) };
+        shared.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+    /// Get the number of values that have been pushed and not popped
+    pub(crate) fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Get the number of values that have ever been pushed
+    pub(crate) fn pushed(&self) -> usize {
+        self.0.tail.load(Ordering::Relaxed)
+    }
+    /// Check if the [`RingReceiver`] has been dropped
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    pub(crate) fn is_closed(&self) -> bool {
+        Arc::strong_count(&self.0) == 1
+    }
+}
+
+impl<T> core::fmt::Debug for RingSender<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("RingSender")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+/// The popping end of a [`ring`]
+pub(crate) struct RingReceiver<T>(Arc<RingShared<T>>);
+
+impl<T> core::fmt::Debug for RingReceiver<T> {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("RingReceiver")
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl<T> RingReceiver<T> {
+    /// Pop the oldest value
+    pub(crate) fn pop(&mut self) -> Option<T> {
+        let shared = &*self.0;
+        let head = shared.head.load(Ordering::Relaxed);
+        if head == shared.tail.load(Ordering::Acquire) {
+            return None;
+        }
+        // SAFETY: the slot is between the head and tail, so it holds a pushed value
+        // that the sender will not write to until it is popped
+        let value = unsafe { (*shared.slot(head).get()).assume_init_read() };
+        shared.head.store(head.wrapping_add(1), Ordering::Release);
+        Some(value)
+    }
+    /// Get the number of values that have been pushed and not popped
+    pub(crate) fn len(&self) -> usize {
+        self.0.len()
+    }
+    /// Get the number of values that have ever been popped
+    pub(crate) fn popped(&self) -> usize {
+        self.0.head.load(Ordering::Relaxed)
+    }
+    /// Check if the [`RingSender`] has been dropped
+    pub(crate) fn is_closed(&self) -> bool {
+        Arc::strong_count(&self.0) == 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ring_fills_to_capacity() {
+        let (mut send, mut recv) = ring(3);
+        for i in 0..3 {
+            assert_eq!(send.push(i), Ok(()));
+        }
+        assert_eq!(send.push(3), Err(3));
+        assert_eq!(recv.pop(), Some(0));
+        assert_eq!(send.push(3), Ok(()));
+        assert_eq!(send.len(), 3);
+        assert_eq!(
+            [recv.pop(), recv.pop(), recv.pop(), recv.pop()],
+            [Some(1), Some(2), Some(3), None]
+        );
+    }
+
+    #[test]
+    fn ring_counters_wrap_around() {
+        let (mut send, mut recv) = ring(3);
+        // Start the counters just before they overflow
+        let start = usize::MAX - 4;
+        send.0.head.store(start, Ordering::Relaxed);
+        send.0.tail.store(start, Ordering::Relaxed);
+        let mut expected = 0..;
+        for i in 0..20 {
+            assert_eq!(send.push(i * 2), Ok(()));
+            assert_eq!(send.push(i * 2 + 1), Ok(()));
+            assert_eq!(recv.len(), 2);
+            assert_eq!(recv.pop(), expected.next());
+            assert_eq!(recv.pop(), expected.next());
+        }
+        assert_eq!(recv.popped(), start.wrapping_add(40));
+        assert_eq!(recv.pop(), None);
+    }
+
+    #[test]
+    fn ring_drops_unpopped_values() {
+        let value = Arc::new(());
+        let (mut send, recv) = ring(4);
+        for _ in 0..3 {
+            send.push(value.clone()).unwrap();
+        }
+        drop((send, recv));
+        assert_eq!(Arc::strong_count(&value), 1);
+    }
+}
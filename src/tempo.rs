@@ -0,0 +1,195 @@
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use crate::Automation;
+
+/// A shared musical tempo and time signature
+///
+/// Cloning a tempo is cheap, and clones share the same tempo,
+/// so one tempo can act as the transport for every effect and loop that needs it.
+/// Durations created with [`Tempo::beats`] and [`Tempo::bars`] are [`Automation`]s
+/// that follow changes to the tempo while they are used,
+/// so loops and tempo-synced effects stay locked to it.
+///
+/// # Example
+/// ```
+/// use hodaun::*;
+///
+/// let tempo = Tempo::new(120.0);
+/// let mut bar = tempo.bars(1.0);
+/// assert_eq!(bar.next_value(44100.0), Some(2.0));
+/// tempo.set_bpm(60.0);
+/// assert_eq!(bar.next_value(44100.0), Some(4.0));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Tempo {
+    state: Arc<TempoState>,
+}
+
+#[derive(Debug)]
+struct TempoState {
+    bpm: AtomicU64,
+    beats_per_bar: AtomicU64,
+}
+
+impl Tempo {
+    /// Create a new tempo in beats per minute
+    ///
+    /// The time signature defaults to 4 beats per bar.
+    pub fn new(bpm: f64) -> Self {
+        Tempo {
+            state: Arc::new(TempoState {
+                bpm: AtomicU64::new(bpm.to_bits()),
+                beats_per_bar: AtomicU64::new(4),
+            }),
+        }
+    }
+    /// Set the number of beats in a bar
+    pub fn time_signature(self, beats_per_bar: u64) -> Self {
+        self.set_beats_per_bar(beats_per_bar);
+        self
+    }
+    /// Get the tempo in beats per minute
+    pub fn bpm(&self) -> f64 {
+        f64::from_bits(self.state.bpm.load(Ordering::Relaxed))
+    }
+    /// Set the tempo in beats per minute
+    pub fn set_bpm(&self, bpm: f64) {
+        self.state.bpm.store(bpm.to_bits(), Ordering::Relaxed);
+    }
+    /// Get the number of beats in a bar
+    pub fn beats_per_bar(&self) -> u64 {
+        self.state.beats_per_bar.load(Ordering::Relaxed)
+    }
+    /// Set the number of beats in a bar
+    pub fn set_beats_per_bar(&self, beats_per_bar: u64) {
+        (self.state.beats_per_bar).store(beats_per_bar.max(1), Ordering::Relaxed);
+    }
+    /// Get the length of a beat in seconds
+    pub fn beat_secs(&self) -> f64 {
+        60.0 / self.bpm()
+    }
+    /// A duration of some number of beats
+    pub fn beats(&self, beats: f64) -> Beats {
+        Beats {
+            tempo: self.clone(),
+            count: beats,
+            bars: false,
+        }
+    }
+    /// A duration of some number of bars
+    pub fn bars(&self, bars: f64) -> Beats {
+        Beats {
+            tempo: self.clone(),
+            count: bars,
+            bars: true,
+        }
+    }
+    /// The duration of a note value, where a beat is a quarter note
+    ///
+    /// This is useful for tempo-synced delay times.
+    /// For tempo-synced rates, like an LFO's frequency, use [`Beats::rate`].
+    pub fn note(&self, value: NoteValue) -> Beats {
+        self.beats(value.beats())
+    }
+}
+
+/// A musical note value, like a quarter note or a dotted eighth note
+///
+/// # Example
+/// ```
+/// use hodaun::*;
+///
+/// let tempo = Tempo::new(120.0);
+/// let mut dotted_eighth = tempo.note(NoteValue::new(1, 8).dotted());
+/// assert_eq!(dotted_eighth.next_value(44100.0), Some(0.375));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoteValue {
+    /// The length as a fraction of a whole note
+    whole: f64,
+}
+
+impl NoteValue {
+    /// A whole note
+    pub const WHOLE: Self = NoteValue { whole: 1.0 };
+    /// A half note
+    pub const HALF: Self = NoteValue { whole: 0.5 };
+    /// A quarter note
+    pub const QUARTER: Self = NoteValue { whole: 0.25 };
+    /// An eighth note
+    pub const EIGHTH: Self = NoteValue { whole: 0.125 };
+    /// A sixteenth note
+    pub const SIXTEENTH: Self = NoteValue { whole: 0.0625 };
+    /// Create a note value that is a fraction of a whole note, like `1/4` for a quarter note
+    pub fn new(numerator: u32, denominator: u32) -> Self {
+        NoteValue {
+            whole: numerator as f64 / denominator.max(1) as f64,
+        }
+    }
+    /// Make the note value half again as long
+    pub fn dotted(self) -> Self {
+        NoteValue {
+            whole: self.whole * 1.5,
+        }
+    }
+    /// Make the note value two thirds as long, so that three fit in the time of two
+    pub fn triplet(self) -> Self {
+        NoteValue {
+            whole: self.whole * 2.0 / 3.0,
+        }
+    }
+    /// Get the length in beats, where a beat is a quarter note
+    pub fn beats(self) -> f64 {
+        self.whole * 4.0
+    }
+}
+
+/// A musical duration tied to a [`Tempo`]
+///
+/// As an [`Automation`], its value is the duration in seconds at the current tempo.
+/// It can be used anywhere a duration in seconds is automated, like [`Repeat::every`](crate::source::Repeat::every).
+#[derive(Debug, Clone)]
+pub struct Beats {
+    tempo: Tempo,
+    count: f64,
+    bars: bool,
+}
+
+impl Beats {
+    /// Get the duration in seconds at the current tempo
+    pub fn secs(&self) -> f64 {
+        let beats = if self.bars {
+            self.count * self.tempo.beats_per_bar() as f64
+        } else {
+            self.count
+        };
+        beats * self.tempo.beat_secs()
+    }
+    /// Get the rate in Hz that repeats once per this duration
+    ///
+    /// It is useful for syncing an LFO to the tempo,
+    /// for example as the frequency of a [`Wave`](crate::Wave).
+    pub fn rate(self) -> BeatRate {
+        BeatRate(self)
+    }
+}
+
+impl Automation for Beats {
+    fn next_value(&mut self, _sample_rate: f64) -> Option<f64> {
+        Some(self.secs())
+    }
+}
+
+/// A rate tied to a [`Tempo`]
+///
+/// As an [`Automation`], its value is the frequency in Hz at the current tempo.
+/// It is created with [`Beats::rate`].
+#[derive(Debug, Clone)]
+pub struct BeatRate(Beats);
+
+impl Automation for BeatRate {
+    fn next_value(&mut self, _sample_rate: f64) -> Option<f64> {
+        Some(1.0 / self.0.secs())
+    }
+}
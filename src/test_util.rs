@@ -0,0 +1,188 @@
+//! Utilities for testing sources without an audio device
+//!
+//! This module is only available when the `test-util` feature is enabled.
+
+use crate::{Frame, Mono, Source};
+
+/// A source that outputs a ramp, starting at some value and
+/// changing by a fixed step every frame
+///
+/// Because its output is easy to predict, this is useful for
+/// testing how other sources transform their input.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StepSource {
+    value: f64,
+    step: f64,
+}
+
+impl StepSource {
+    /// Create a new step source
+    pub fn new(start: f64, step: f64) -> Self {
+        StepSource { value: start, step }
+    }
+}
+
+impl Source for StepSource {
+    type Frame = Mono;
+    fn next(&mut self, _sample_rate: f64) -> Option<Self::Frame> {
+        let res = self.value;
+        self.value += self.step;
+        Some(res)
+    }
+}
+
+/// Collect all the frames of a finite source
+pub fn collect_frames<S>(mut source: S, sample_rate: f64) -> Vec<S::Frame>
+where
+    S: Source,
+{
+    let mut frames = Vec::new();
+    while let Some(frame) = source.next(sample_rate) {
+        frames.push(frame);
+    }
+    frames
+}
+
+/// Assert that two finite sources produce the same frames
+///
+/// Amplitudes may differ by at most `tolerance`.
+///
+/// # Panics
+/// Panics if the sources differ in length or in any amplitude.
+pub fn assert_source_eq<A, B>(mut a: A, mut b: B, sample_rate: f64, tolerance: f64)
+where
+    A: Source,
+    B: Source<Frame = A::Frame>,
+{
+    let mut i = 0;
+    loop {
+        match (a.next(sample_rate), b.next(sample_rate)) {
+            (Some(a), Some(b)) => assert_frame_eq(&a, &b, tolerance, i),
+            (None, None) => break,
+            (Some(_), None) => panic!("left source is longer than right source ({i} frames)"),
+            (None, Some(_)) => panic!("right source is longer than left source ({i} frames)"),
+        }
+        i += 1;
+    }
+}
+
+fn assert_frame_eq<F: Frame>(a: &F, b: &F, tolerance: f64, index: usize) {
+    for c in 0..F::CHANNELS {
+        let (a, b) = (a.get_channel(c), b.get_channel(c));
+        assert!(
+            (a - b).abs() <= tolerance,
+            "sources differ at frame {index}, channel {c}: {a} != {b}"
+        );
+    }
+}
+
+/// Get the magnitude of each frequency bin of the discrete fourier transform
+/// of the first `frames` frames of a source
+///
+/// Frames are averaged to mono and zero-padded to a power of two length `n`.
+/// Bin `k` corresponds to a frequency of `k * sample_rate / n`.
+/// Only the bins up to the Nyquist frequency are returned.
+pub fn spectrum<S>(mut source: S, sample_rate: f64, frames: usize) -> Vec<f64>
+where
+    S: Source,
+{
+    let mut buf: Vec<(f64, f64)> = (0..frames)
+        .map_while(|_| source.next(sample_rate).map(|frame| (frame.avg(), 0.0)))
+        .collect();
+    let len = buf.len().max(1);
+    let n = len.next_power_of_two();
+    buf.resize(n, (0.0, 0.0));
+    crate::analysis::fft(&mut buf);
+    (buf.iter().take(n / 2 + 1))
+        .map(|(re, im)| (re * re + im * im).sqrt() / len as f64)
+        .collect()
+}
+
+/// Get the frequency with the most energy in the first `frames` frames of a source
+pub fn dominant_frequency<S>(source: S, sample_rate: f64, frames: usize) -> f64
+where
+    S: Source,
+{
+    let spectrum = spectrum(source, sample_rate, frames);
+    let bins = (spectrum.len().max(2) - 1) * 2;
+    let (k, _) =
+        spectrum.iter().enumerate().skip(1).fold(
+            (0, 0.0),
+            |(bk, bm), (k, &m)| if m > bm { (k, m) } else { (bk, bm) },
+        );
+    k as f64 * sample_rate / bins as f64
+}
+
+/// Assert that the frequency with the most energy in the first `frames`
+/// frames of a source is within `tolerance` Hz of `expected`
+///
+/// The frequency resolution is `sample_rate / frames`,
+/// so the tolerance should be at least that large.
+pub fn assert_dominant_frequency<S>(
+    source: S,
+    sample_rate: f64,
+    frames: usize,
+    expected: f64,
+    tolerance: f64,
+) where
+    S: Source,
+{
+    let actual = dominant_frequency(source, sample_rate, frames);
+    assert!(
+        (actual - expected).abs() <= tolerance,
+        "dominant frequency is {actual} Hz, expected {expected} Hz"
+    );
+}
+
+/// Assert that a finite source matches a golden WAV file
+///
+/// Amplitudes may differ by at most `tolerance`.
+///
+/// If the `HODAUN_BLESS` environment variable is set to `1`, the source is written to the file instead
+/// and the assertion passes. Otherwise, the assertion fails if the file does not exist.
+#[cfg(feature = "wav")]
+#[cfg_attr(docsrs, doc(cfg(all(feature = "test-util", feature = "wav"))))]
+pub fn assert_matches_wav<S>(
+    source: S,
+    path: impl AsRef<std::path::Path>,
+    sample_rate: u32,
+    tolerance: f64,
+) where
+    S: Source,
+{
+    use std::{fs::File, io::BufReader};
+
+    use crate::{wav, UnrolledSource};
+
+    let path = path.as_ref();
+    if std::env::var_os("HODAUN_BLESS").is_some_and(|bless| bless == "1") {
+        let file = File::create(path).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+        wav::write_source(std::io::BufWriter::new(file), source, sample_rate)
+            .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+        return;
+    }
+    assert!(
+        path.exists(),
+        "golden file {} does not exist, run with HODAUN_BLESS=1 to create it",
+        path.display()
+    );
+    let file = File::open(path).unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+    let golden = wav::WavSource::new(BufReader::new(file))
+        .unwrap_or_else(|e| panic!("{}: {e}", path.display()));
+    assert_eq!(
+        golden.channels(),
+        <S::Frame as Frame>::CHANNELS,
+        "golden file has a different channel count"
+    );
+    assert_eq!(
+        golden.sample_rate() as u32,
+        sample_rate,
+        "golden file has a different sample rate"
+    );
+    assert_source_eq(
+        source,
+        golden.resample::<S::Frame>(),
+        sample_rate as f64,
+        tolerance,
+    );
+}
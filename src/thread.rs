@@ -0,0 +1,67 @@
+/// A scheduling priority for an audio thread
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub enum ThreadPriority {
+    /// Leave the thread's priority as it is
+    #[default]
+    Default,
+    /// Request realtime scheduling for the thread
+    ///
+    /// This usually requires elevated permissions. If it cannot be granted,
+    /// the thread keeps its normal priority.
+    Realtime,
+}
+
+/// Set the scheduling priority of the current thread
+///
+/// Returns whether the priority was applied.
+/// Realtime scheduling is currently only supported on Unix platforms.
+pub fn set_current_thread_priority(priority: ThreadPriority) -> bool {
+    match priority {
+        ThreadPriority::Default => true,
+        ThreadPriority::Realtime => set_realtime(),
+    }
+}
+
+#[cfg(unix)]
+fn set_realtime() -> bool {
+    // Safety: the thread handle is the current thread's and the parameter is initialized
+    unsafe {
+        let max = libc::sched_get_priority_max(libc::SCHED_FIFO);
+        let min = libc::sched_get_priority_min(libc::SCHED_FIFO);
+        if max < 0 || min < 0 {
+            return false;
+        }
+        let param = libc::sched_param {
+            sched_priority: min + (max - min) * 3 / 4,
+        };
+        libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param) == 0
+    }
+}
+
+#[cfg(not(unix))]
+fn set_realtime() -> bool {
+    false
+}
+
+/// Pin the current thread to a single CPU core
+///
+/// Returns whether the thread was pinned.
+/// This is currently only supported on Linux and Android.
+pub fn pin_current_thread(core: usize) -> bool {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    // Safety: the CPU set is zero-initialized before use and only refers to the current thread
+    unsafe {
+        if core >= libc::CPU_SETSIZE as usize {
+            return false;
+        }
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_ZERO(&mut set);
+        libc::CPU_SET(core, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set) == 0
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = core;
+        false
+    }
+}
@@ -0,0 +1,171 @@
+use alloc::{collections::VecDeque, sync::Arc};
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use crate::{
+    lerp,
+    sync::{ring, Mutex, RingReceiver, RingSender},
+    Automation, Mixer, ToDuration,
+};
+
+/// The most changes that can be scheduled on a [`Timeline`] and not yet reached
+const TIMELINE_CAPACITY: usize = 1024;
+
+/// An [`Automation`] whose changes are scheduled at exact times
+///
+/// Created with [`Mixer::timeline`].
+/// Times are relative to when the mixer started, and changes are applied on the exact mixer frame
+/// they are scheduled for, no matter when the thread that scheduled them runs.
+/// This makes it suitable for mix automation and fades at known times.
+///
+/// Cloning a timeline is cheap, and clones share the same schedule.
+/// It should only be used by sources playing in the mixer it was created from.
+/// Scheduled changes are sent to the playing clones without locking,
+/// and up to 1024 of them can be waiting to be reached.
+#[derive(Debug, Clone)]
+pub struct Timeline {
+    clock: Arc<AtomicU64>,
+    shared: Arc<TimelineShared>,
+    /// The value from the last time this clone was played
+    value: f64,
+}
+
+#[derive(Debug)]
+struct TimelineShared {
+    /// Only locked by threads that schedule changes
+    send: Mutex<RingSender<Change>>,
+    /// Only locked by clones that are playing
+    state: Mutex<TimelineState>,
+    /// The number of changes that have been scheduled and not yet reached or cleared
+    scheduled: AtomicUsize,
+    /// Incremented by [`Timeline::clear`]
+    generation: AtomicU64,
+}
+
+#[derive(Debug)]
+struct TimelineState {
+    recv: RingReceiver<Change>,
+    /// The time and value of the last change that has been reached
+    last: (f64, f64),
+    /// Scheduled changes, sorted by time
+    changes: VecDeque<Change>,
+    /// The clear generation of the changes
+    generation: u64,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Change {
+    time: f64,
+    value: f64,
+    ramp: bool,
+    generation: u64,
+}
+
+impl<F> Mixer<F> {
+    /// Create a [`Timeline`] synchronized to this mixer's frames
+    pub fn timeline(&self, initial: f64) -> Timeline {
+        let (send, recv) = ring(TIMELINE_CAPACITY);
+        Timeline {
+            clock: self.clock.clone(),
+            shared: Arc::new(TimelineShared {
+                send: Mutex::new(send),
+                state: Mutex::new(TimelineState {
+                    recv,
+                    last: (0.0, initial),
+                    changes: VecDeque::with_capacity(TIMELINE_CAPACITY),
+                    generation: 0,
+                }),
+                scheduled: AtomicUsize::new(0),
+                generation: AtomicU64::new(0),
+            }),
+            value: initial,
+        }
+    }
+}
+
+impl Timeline {
+    /// Jump to a value at a time
+    ///
+    /// Returns `false` if 1024 changes are already waiting to be reached, in which case the change is ignored.
+    pub fn set_at(&self, at: impl ToDuration, value: f64) -> bool {
+        self.schedule(at, value, false)
+    }
+    /// Ramp linearly to a value, reaching it at a time
+    ///
+    /// The ramp starts at the previous scheduled change, or when the mixer started if there is none.
+    /// Returns `false` if 1024 changes are already waiting to be reached, in which case the change is ignored.
+    pub fn ramp_at(&self, at: impl ToDuration, value: f64) -> bool {
+        self.schedule(at, value, true)
+    }
+    /// Remove all changes that have not been reached yet
+    pub fn clear(&self) {
+        let _send = self.shared.send.lock();
+        self.shared.generation.fetch_add(1, Ordering::Relaxed);
+    }
+    fn schedule(&self, at: impl ToDuration, value: f64, ramp: bool) -> bool {
+        let shared = &*self.shared;
+        let mut send = shared.send.lock();
+        if shared.scheduled.load(Ordering::Relaxed) >= TIMELINE_CAPACITY {
+            return false;
+        }
+        let change = Change {
+            time: at.to_duration().as_secs_f64(),
+            value,
+            ramp,
+            generation: shared.generation.load(Ordering::Relaxed),
+        };
+        if send.push(change).is_err() {
+            return false;
+        }
+        shared.scheduled.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+}
+
+impl TimelineState {
+    /// Move newly scheduled changes into the sorted queue
+    fn receive(&mut self, shared: &TimelineShared) {
+        let generation = shared.generation.load(Ordering::Relaxed);
+        if generation != self.generation {
+            self.generation = generation;
+            (shared.scheduled).fetch_sub(self.changes.len(), Ordering::Relaxed);
+            self.changes.clear();
+        }
+        while let Some(change) = self.recv.pop() {
+            if change.generation != generation {
+                shared.scheduled.fetch_sub(1, Ordering::Relaxed);
+                continue;
+            }
+            // Changes at the same time apply in the order they were scheduled
+            let i = self.changes.partition_point(|c| c.time <= change.time);
+            self.changes.insert(i, change);
+        }
+    }
+}
+
+impl Automation for Timeline {
+    fn next_value(&mut self, sample_rate: f64) -> Option<f64> {
+        // Another clone is playing on another thread
+        let Some(mut state) = self.shared.state.try_lock() else {
+            return Some(self.value);
+        };
+        state.receive(&self.shared);
+        // The clock is the index of the frame being mixed
+        let now = self.clock.load(Ordering::Relaxed) as f64 / sample_rate;
+        while let Some(change) = state.changes.front().copied() {
+            if change.time > now {
+                break;
+            }
+            state.last = (change.time, change.value);
+            state.changes.pop_front();
+            self.shared.scheduled.fetch_sub(1, Ordering::Relaxed);
+        }
+        let (start, value) = state.last;
+        self.value = match state.changes.front() {
+            Some(next) if next.ramp && next.time > start => {
+                lerp(value, next.value, (now - start) / (next.time - start))
+            }
+            _ => value,
+        };
+        Some(self.value)
+    }
+}